@@ -0,0 +1,175 @@
+//! Reads the variables used at build time from the environment or from an env file,
+//! and rebuilds the program when they change so that their old values aren't kept.
+//!
+//! The env file is `.env` in the directory of the package, or the file given in `COMPILE_ENV_FILE`
+//! (e.g. `.env.production` to build a release channel), unless a variable has its own env file.
+//! The environment takes precedence over them.
+//!
+//! The variables are written as typed constants in `build_env.rs` in the output directory,
+//! which is included by the program, so that an invalid value fails the build.
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// The type of the constant of a variable.
+#[derive(Clone, Copy)]
+// No variable is a number or a boolean yet
+#[expect(dead_code)]
+enum Kind {
+    /// An `Option<&str>`.
+    Text,
+    /// An `Option<u32>`.
+    Integer,
+    /// An `Option<bool>`, the value being `true` or `false`.
+    Boolean,
+}
+
+/// A variable read at build time.
+struct Variable {
+    /// The name of the variable and of its constant.
+    name: &'static str,
+    /// The type of its constant.
+    kind: Kind,
+    /// The env file that it is read from instead of the default one, if any.
+    file: Option<&'static str>,
+}
+
+/// The variables that are available as constants in the program.
+const VARIABLES: [Variable; 1] = [Variable {
+    name: "SENTRY_DSN",
+    kind: Kind::Text,
+    file: None,
+}];
+
+/// Returns the key and the value of a `KEY=value` line of an env file,
+/// without the quotes around the value, or `None` for the comments and the empty lines.
+fn parse_line(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
+    if line.starts_with('#') {
+        return None;
+    }
+    let (key, value) = line
+        .strip_prefix("export ")
+        .unwrap_or(line)
+        .split_once('=')?;
+    let value = value.trim();
+    let unquoted = ['"', '\''].into_iter().find_map(|quote| {
+        value
+            .strip_prefix(quote)
+            .and_then(|value| value.strip_suffix(quote))
+    });
+    Some((key.trim(), unquoted.unwrap_or(value)))
+}
+
+/// Returns the Rust expression of the constant of a variable, e.g. `Some(42)`.
+///
+/// # Panics
+/// Panics if the value doesn't have the type of the variable.
+fn to_expression(variable: &Variable, value: Option<&str>) -> String {
+    let Some(value) = value else {
+        return "None".to_string();
+    };
+    let expression = match variable.kind {
+        Kind::Text => format!("{value:?}"),
+        Kind::Integer => value
+            .parse::<u32>()
+            .unwrap_or_else(|err| {
+                panic!("{} must be a number, found {value:?}: {err}", variable.name)
+            })
+            .to_string(),
+        Kind::Boolean => value
+            .parse::<bool>()
+            .unwrap_or_else(|_| panic!("{} must be true or false, found {value:?}", variable.name))
+            .to_string(),
+    };
+    format!("Some({expression})")
+}
+
+/// Reads an env file and tells Cargo to rebuild the program when it changes.
+/// A missing file is empty unless it is `required`.
+///
+/// # Panics
+/// Panics if a required file can't be read.
+fn read_env_file(path: &str, required: bool) -> String {
+    match fs::read_to_string(path) {
+        Ok(contents) => {
+            println!("cargo::rerun-if-changed={path}");
+            contents
+        }
+        Err(err) if required => panic!("Couldn't read the env file {path:?}: {err}"),
+        Err(_) => String::new(),
+    }
+}
+
+/// Writes the constants of the variables and tells Cargo what the build depends on.
+fn main() {
+    println!("cargo::rerun-if-env-changed=COMPILE_ENV_FILE");
+    let custom_file = env::var("COMPILE_ENV_FILE").ok();
+    // The default env file is optional, but a file given explicitly must exist
+    let default_contents = read_env_file(
+        custom_file.as_deref().unwrap_or(".env"),
+        custom_file.is_some(),
+    );
+
+    let mut constants = String::new();
+    for variable in VARIABLES {
+        println!("cargo::rerun-if-env-changed={}", variable.name);
+        let value = env::var(variable.name).ok().or_else(|| {
+            let contents = variable.file.map(|file| read_env_file(file, true));
+            contents
+                .as_ref()
+                .unwrap_or(&default_contents)
+                .lines()
+                .filter_map(parse_line)
+                .find(|(key, _)| *key == variable.name)
+                .map(|(_, value)| value.to_string())
+        });
+        let kind = match variable.kind {
+            Kind::Text => "&str",
+            Kind::Integer => "u32",
+            Kind::Boolean => "bool",
+        };
+        constants.push_str(&format!(
+            "/// The `{name}` variable when the program was built.\n\
+             pub(crate) const {name}: Option<{kind}> = {expression};\n",
+            name = variable.name,
+            expression = to_expression(&variable, value.as_deref()),
+        ));
+    }
+
+    let out_dir = env::var("OUT_DIR").expect("Cargo sets OUT_DIR");
+    fs::write(Path::new(&out_dir).join("build_env.rs"), constants)
+        .expect("Couldn't write the constants of the variables");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_lines() {
+        assert_eq!(parse_line("KEY=value"), Some(("KEY", "value")));
+        assert_eq!(parse_line("  KEY = value  "), Some(("KEY", "value")));
+        assert_eq!(parse_line("KEY=a=b"), Some(("KEY", "a=b")));
+        assert_eq!(parse_line("export KEY=value"), Some(("KEY", "value")));
+        assert_eq!(
+            parse_line("KEY=\"quoted value\""),
+            Some(("KEY", "quoted value"))
+        );
+        assert_eq!(
+            parse_line("KEY='quoted value'"),
+            Some(("KEY", "quoted value"))
+        );
+        assert_eq!(
+            parse_line("KEY=\"mismatched'"),
+            Some(("KEY", "\"mismatched'"))
+        );
+    }
+
+    #[test]
+    fn ignored_env_lines() {
+        for line in ["", "   ", "# KEY=value", "KEY"] {
+            assert_eq!(parse_line(line), None, "{line:?}");
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Utility functions to set the accent color and the color of the panel on Linux.
+use image::Rgb;
+use log::{debug, info};
+use std::env;
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::palette::to_hex;
+
+/// The accent colors supported by GNOME, with their hue in degrees.
+const GNOME_ACCENT_COLORS: [(&str, f32); 8] = [
+    ("red", 0.0),
+    ("orange", 30.0),
+    ("yellow", 50.0),
+    ("green", 130.0),
+    ("teal", 180.0),
+    ("blue", 215.0),
+    ("purple", 275.0),
+    ("pink", 330.0),
+];
+
+/// The UUID of the Dash to Panel extension of GNOME, whose panel color can be changed.
+const DASH_TO_PANEL_UUID: &str = "dash-to-panel@jderose9.github.com";
+
+/// The settings schema of the Dash to Panel extension.
+const DASH_TO_PANEL_SCHEMA: &str = "org.gnome.shell.extensions.dash-to-panel";
+
+/// Returns the name of the current desktop in lowercase.
+fn get_desktop() -> String {
+    let desktop = env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .to_lowercase();
+    debug!("Current desktop: {:?}", desktop);
+    desktop
+}
+
+/// Sets the accent color of the desktop (KDE Plasma or GNOME).
+///
+/// # Errors
+/// Fails if the command that sets the accent color can't be called or if it fails.
+pub(crate) fn set_accent_color(color: Rgb<u8>) -> Result<(), Box<dyn Error>> {
+    let desktop = get_desktop();
+
+    let output = if desktop.contains("kde") {
+        info!("Setting the KDE accent color to {}", to_hex(color));
+        Command::new("plasma-apply-colorscheme")
+            .args(["--accent-color", &to_hex(color)])
+            .output()?
+    } else {
+        // GNOME only supports a few named accent colors
+        let name = get_nearest_gnome_accent_color(color);
+        info!("Setting the GNOME accent color to {name}");
+        Command::new("gsettings")
+            .args(["set", "org.gnome.desktop.interface", "accent-color", name])
+            .output()?
+    };
+
+    if !output.status.success() {
+        return Err(format!(
+            "Could not set the accent color: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Returns a `gsettings` command that knows the settings of Dash to Panel
+/// (which are in the folder of the extension when it is installed for the user).
+fn get_dash_to_panel_gsettings() -> Command {
+    let mut command = Command::new("gsettings");
+    let user_schemas = dirs::data_dir().map(|dir| {
+        dir.join("gnome-shell/extensions")
+            .join(DASH_TO_PANEL_UUID)
+            .join("schemas")
+    });
+    if let Some(user_schemas) = user_schemas.filter(|dir: &PathBuf| dir.exists()) {
+        command.arg("--schemadir").arg(user_schemas);
+    }
+    command
+}
+
+/// Sets the color of the panel of GNOME with the Dash to Panel extension
+/// (the panels of the other desktops follow their theme).
+///
+/// # Errors
+/// Fails if `gsettings` can't be called or if it fails.
+pub(crate) fn set_panel_color(color: Rgb<u8>) -> Result<(), Box<dyn Error>> {
+    let desktop = get_desktop();
+    let installed = desktop.contains("gnome")
+        && get_dash_to_panel_gsettings()
+            .args(["list-keys", DASH_TO_PANEL_SCHEMA])
+            .output()
+            .is_ok_and(|output| output.status.success());
+    if !installed {
+        debug!("The panel color can only be changed on GNOME with the Dash to Panel extension");
+        return Ok(());
+    }
+
+    info!("Setting the Dash to Panel color to {}", to_hex(color));
+    for (key, value) in [
+        ("trans-use-custom-bg", "true".to_string()),
+        ("trans-bg-color", to_hex(color)),
+    ] {
+        let output = get_dash_to_panel_gsettings()
+            .args(["set", DASH_TO_PANEL_SCHEMA, key, &value])
+            .output()?;
+        if !output.status.success() {
+            return Err(format!(
+                "Could not set the panel color: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Returns the GNOME accent color that has the nearest hue,
+/// or `slate` if the color is almost gray.
+fn get_nearest_gnome_accent_color(color: Rgb<u8>) -> &'static str {
+    let [r, g, b] = color.0.map(|value| f32::from(value) / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    if max == 0.0 || delta / max < 0.15 {
+        return "slate";
+    }
+
+    let hue = if (max - r).abs() < f32::EPSILON {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if (max - g).abs() < f32::EPSILON {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+
+    GNOME_ACCENT_COLORS
+        .iter()
+        .min_by(|a, b| hue_distance(a.1, hue).total_cmp(&hue_distance(b.1, hue)))
+        .map_or("blue", |(name, _)| name)
+}
+
+/// Returns the distance between two hues, on the color wheel.
+fn hue_distance(a: f32, b: f32) -> f32 {
+    let distance = (a - b).abs() % 360.0;
+    distance.min(360.0 - distance)
+}
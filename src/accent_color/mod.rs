@@ -0,0 +1,13 @@
+//! Utility functions to set the accent color and the color of the panel of the operating system.
+
+#[cfg(target_os = "linux")]
+mod linux;
+
+#[cfg(target_os = "linux")]
+pub(crate) use linux::{set_accent_color, set_panel_color};
+
+#[cfg(target_os = "windows")]
+mod windows;
+
+#[cfg(target_os = "windows")]
+pub(crate) use windows::{set_accent_color, set_panel_color};
@@ -0,0 +1,85 @@
+//! Utility functions to set the accent color and the color of the taskbar on Windows.
+use image::Rgb;
+use log::info;
+use std::error::Error;
+use std::process::Command;
+
+use crate::palette::to_hex;
+
+/// Sets a `REG_DWORD` value in the registry.
+///
+/// # Errors
+/// Fails if `reg` can't be called or if it fails.
+fn set_registry_dword(key: &str, name: &str, value: u32) -> Result<(), Box<dyn Error>> {
+    let output = Command::new("reg")
+        .args([
+            "add",
+            key,
+            "/v",
+            name,
+            "/t",
+            "REG_DWORD",
+            "/d",
+            &value.to_string(),
+            "/f",
+        ])
+        .output()?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Could not set registry value {name}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Sets the accent color of Windows.
+///
+/// # Errors
+/// Fails if the registry values can't be set.
+pub(crate) fn set_accent_color(color: Rgb<u8>) -> Result<(), Box<dyn Error>> {
+    info!("Setting the accent color to {}", to_hex(color));
+
+    // The registry stores the colors as 0xAABBGGRR
+    let abgr = 0xFF00_0000
+        | (u32::from(color[2]) << 16)
+        | (u32::from(color[1]) << 8)
+        | u32::from(color[0]);
+    // ...except ColorizationColor, which is 0xAARRGGBB
+    let argb = 0xC400_0000
+        | (u32::from(color[0]) << 16)
+        | (u32::from(color[1]) << 8)
+        | u32::from(color[2]);
+
+    set_registry_dword(
+        r"HKCU\Software\Microsoft\Windows\CurrentVersion\Explorer\Accent",
+        "AccentColorMenu",
+        abgr,
+    )?;
+    set_registry_dword(r"HKCU\Software\Microsoft\Windows\DWM", "AccentColor", abgr)?;
+    set_registry_dword(
+        r"HKCU\Software\Microsoft\Windows\DWM",
+        "ColorizationColor",
+        argb,
+    )?;
+
+    Ok(())
+}
+
+/// Sets the color of the taskbar. Windows only shows the accent color on the taskbar,
+/// so the accent color is changed too.
+///
+/// # Errors
+/// Fails if the registry values can't be set.
+pub(crate) fn set_panel_color(color: Rgb<u8>) -> Result<(), Box<dyn Error>> {
+    set_accent_color(color)?;
+    info!("Showing the accent color on the taskbar");
+    set_registry_dword(
+        r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+        "ColorPrevalence",
+        1,
+    )
+}
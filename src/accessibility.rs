@@ -0,0 +1,77 @@
+//! The description of the current wallpaper for the users of a screen reader: it is written
+//! in a text file, and it can be read aloud when the wallpaper changes.
+use log::debug;
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+use crate::i18n::tr;
+use crate::image_structs::Image;
+use crate::notification::{get_body, get_title};
+use crate::paths::Paths;
+
+/// Returns the text that describes the image: its description, its author and its source.
+fn get_text(image: &dyn Image) -> String {
+    let mut text = get_body(image);
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    text.push_str(&tr!("source", source = image.get_source()));
+    text.push('\n');
+    text
+}
+
+/// Writes the description of the new wallpaper in `current_wallpaper.txt` in the data directory.
+///
+/// # Errors
+/// Fails if the file can't be written.
+pub(crate) fn write_description(image: &dyn Image) -> Result<(), Box<dyn Error>> {
+    let path = Paths::current_wallpaper_file()?;
+    debug!("Writing the description of the wallpaper in {path:?}");
+    fs::write(path, get_text(image))?;
+    Ok(())
+}
+
+/// Returns the command that reads a text aloud with speech-dispatcher, which is also used by Orca.
+#[cfg(target_os = "linux")]
+fn get_speak_command(text: &str) -> Command {
+    let mut command = Command::new("spd-say");
+    command.args(["--", text]);
+    command
+}
+
+/// Returns the command that reads a text aloud with the speech synthesizer of Windows,
+/// which is also used by Narrator.
+#[cfg(target_os = "windows")]
+fn get_speak_command(text: &str) -> Command {
+    let mut command = Command::new("powershell");
+    command
+        .args([
+            "-NoProfile",
+            "-Command",
+            "Add-Type -AssemblyName System.Speech; \
+             (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak($env:SPOKEN_TEXT)",
+        ])
+        .env("SPOKEN_TEXT", text);
+    command
+}
+
+/// Returns the command that reads a text aloud with the speech synthesizer of macOS.
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn get_speak_command(text: &str) -> Command {
+    let mut command = Command::new("say");
+    command.args(["--", text]);
+    command
+}
+
+/// Reads the description of the new wallpaper aloud. The speech isn't waited for.
+///
+/// # Errors
+/// Fails if the speech synthesizer can't be started.
+pub(crate) fn speak_description(image: &dyn Image) -> Result<(), Box<dyn Error>> {
+    let text = format!("{}. {}", get_title(), get_body(image));
+    get_speak_command(&text)
+        .spawn()
+        .map_err(|err| format!("Could not start the speech synthesizer: {err}"))?;
+    Ok(())
+}
@@ -1,90 +1,308 @@
 //! Utility functions to register the wallpaper changer as a scheduled task on Linux.
-use log::info;
+use log::{debug, warn};
 use std::error::Error;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use crate::Paths;
+use super::{systemd, Schedule, TaskStatus};
+use crate::i18n::tr;
+use crate::{catch_up, Paths};
 
-/// Registers the given `script_path` as a scheduled task on Linux.
+/// Returns the command that runs the script with the given profile.
+fn get_command(script_path: &Path, profile: Option<&str>) -> String {
+    match profile {
+        Some(profile) => format!("{} --profile {profile}", script_path.to_string_lossy()),
+        None => script_path.to_string_lossy().to_string(),
+    }
+}
+
+/// Registers the given `script_path` as a scheduled task on Linux, with the given profile
+/// and schedule: a systemd user timer, or a cron job if systemd isn't used.
+///
+/// Running the script when the session is unlocked isn't supported on Linux.
 ///
 /// # Errors
-/// Fails if the crontab file can't be accessed or edited.
-pub(crate) fn register_task(script_path: &Path) -> Result<(), Box<dyn Error>> {
-    // Get the current user's crontab
+/// Fails if the timer can't be enabled, if the crontab file can't be accessed or edited,
+/// or in system mode if systemd isn't used (cron has no jobs for all the users).
+pub(crate) fn register_task(
+    script_path: &Path,
+    profile: Option<&str>,
+    schedule: &Schedule,
+) -> Result<(), Box<dyn Error>> {
+    if schedule.on_unlock {
+        warn!("Running the script when the session is unlocked isn't supported on Linux");
+    }
+    if systemd::is_available() {
+        return systemd::register_task(script_path, profile, schedule);
+    }
+    if Paths::is_system() {
+        return Err("The script can only be registered for all the users with systemd".into());
+    }
+    register_cron_task(script_path, profile, schedule)
+}
 
-    use log::info;
+/// Unregisters the given `script_path` with the given profile as a scheduled task on Linux,
+/// both as a systemd user timer and as a cron job.
+///
+/// # Errors
+/// Fails if the timer can't be disabled or if the crontab file can't be accessed or edited.
+pub(crate) fn unregister_task(
+    script_path: &Path,
+    profile: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    if systemd::is_available() {
+        systemd::unregister_task(profile)?;
+        if Paths::is_system() {
+            return Ok(());
+        }
+        // The script may also have been registered as a cron job by an older version
+        if let Err(err) = unregister_cron_task(script_path, profile) {
+            debug!("Couldn't check the crontab: {err}");
+        }
+        return Ok(());
+    }
+    unregister_cron_task(script_path, profile)
+}
+
+/// Returns the scheduled task that runs the given `script_path` (or an older copy of it)
+/// with the given profile: the systemd user timer, or the cron job if there is no timer.
+/// Returns `None` if the script isn't registered.
+///
+/// # Errors
+/// Fails if the units or the crontab can't be read.
+pub(crate) fn get_task_status(
+    script_path: &Path,
+    profile: Option<&str>,
+) -> Result<Option<TaskStatus>, Box<dyn Error>> {
+    if let Some(status) = systemd::get_task_status(profile)? {
+        return Ok(Some(status));
+    }
+    if Paths::is_system() {
+        return Ok(None);
+    }
+
+    let cron_content = read_crontab()?;
+    let lines = cron_content
+        .lines()
+        .filter_map(parse_cron_line)
+        .filter_map(|(schedule, command)| {
+            let path = get_script_path(command, script_path, profile)?;
+            Some((schedule, command, path))
+        })
+        .collect::<Vec<_>>();
+    let Some((_, command, path)) = lines.first() else {
+        return Ok(None);
+    };
+    Ok(Some(TaskStatus {
+        kind: "cron job",
+        schedule: lines
+            .iter()
+            .map(|(schedule, _, _)| *schedule)
+            .collect::<Vec<_>>()
+            .join(", "),
+        command: (*command).to_string(),
+        script_path: PathBuf::from(path),
+    }))
+}
+
+/// Returns the content of the current user's crontab (empty if the user has no crontab).
+///
+/// # Errors
+/// Fails if `crontab` can't be called.
+fn read_crontab() -> Result<String, Box<dyn Error>> {
     let cron_result = Command::new("crontab").arg("-l").output()?;
-    let mut cron_content: String = if cron_result.status.success() {
+    Ok(if cron_result.status.success() {
         String::from_utf8_lossy(&cron_result.stdout).to_string()
     } else {
         String::new()
-    };
-
-    // Ensure the script is not already registered
-    if cron_content.contains(&*script_path.to_string_lossy()) {
-        info!("The script is already registered as a cron job.");
-        return Ok(());
-    }
-
-    // Register the script to run every 5 minutes
-    cron_content.push_str(&format!("*/5 * * * * {}\n", script_path.to_string_lossy()));
+    })
+}
 
+/// Replaces the content of the current user's crontab.
+///
+/// # Errors
+/// Fails if the temporary file can't be written or if `crontab` can't be called.
+fn write_crontab(cron_content: &str) -> Result<(), Box<dyn Error>> {
     // Create a temporary file
-    let cron_file = Paths::crontab_temp_file();
+    let cron_file = Paths::crontab_temp_file()?;
     if let Some(parent) = cron_file.parent() {
         fs::create_dir_all(parent)?;
     }
 
     fs::write(&cron_file, cron_content)?;
-    Command::new("crontab").arg(cron_file).output()?;
+    Command::new("crontab").arg(&cron_file).output()?;
 
     fs::remove_file(cron_file)?;
+    Ok(())
+}
+
+/// Splits a line of a crontab into its schedule (e.g. `*/5 * * * *` or `@reboot`) and its command.
+/// Returns `None` for comments, empty lines and environment variables.
+fn parse_cron_line(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    if line.starts_with('@') {
+        let (schedule, command) = line.split_once(char::is_whitespace)?;
+        return Some((schedule, command.trim()));
+    }
+
+    // Minute, hour, day of month, month and day of week
+    let mut command = line;
+    for _ in 0..5 {
+        command = command.trim_start().split_once(char::is_whitespace)?.1;
+    }
+    let schedule = line[..line.len() - command.len()].trim();
+    Some((schedule, command.trim()))
+}
 
-    info!("Script added to crontab");
+/// Returns the path of the executable if the command runs the script with the given profile,
+/// wherever the executable is (it may have been moved since it was registered).
+fn get_script_path<'a>(
+    command: &'a str,
+    script_path: &Path,
+    profile: Option<&str>,
+) -> Option<&'a str> {
+    let command = command
+        .rsplit_once(" catch-up ")
+        .map_or(command, |(command, _)| command);
+    let path = match profile {
+        Some(profile) => command.strip_suffix(&format!(" --profile {profile}"))?,
+        None if command.contains(" --profile ") => return None,
+        None => command,
+    };
+    (Path::new(path).file_name() == script_path.file_name()).then_some(path)
+}
+
+/// Returns the lines of the crontab that run the command with the given schedule.
+///
+/// cron doesn't run the jobs missed while the computer was off or asleep, so a frequent `catch-up`
+/// line changes the wallpaper when a change was missed.
+/// The `@reboot` line runs the command when cron starts, which is the closest thing to a logon.
+fn get_cron_lines(command: &str, schedule: &Schedule) -> Vec<String> {
+    let interval = if schedule.every < 60 {
+        format!("*/{} * * * *", schedule.every)
+    } else {
+        format!("0 */{} * * *", schedule.every / 60)
+    };
+    let mut lines = vec![format!("{interval} {command}")];
+    let check_interval = catch_up::CHECK_INTERVAL.as_secs() / 60;
+    if u64::from(schedule.every) > check_interval {
+        lines.push(format!(
+            "*/{check_interval} * * * * {command} catch-up {}m",
+            schedule.every
+        ));
+    }
+    if schedule.at_logon {
+        lines.push(format!("@reboot {command}"));
+    }
+    lines
+}
+
+/// Registers the given `script_path` as a cron job, with the given profile and schedule.
+/// The previous schedule of the profile is replaced, even if it ran the script from another path.
+///
+/// # Errors
+/// Fails if the crontab file can't be accessed or edited.
+fn register_cron_task(
+    script_path: &Path,
+    profile: Option<&str>,
+    schedule: &Schedule,
+) -> Result<(), Box<dyn Error>> {
+    if schedule.on_resume {
+        warn!(
+            "cron can't run the script when the computer resumes from sleep, \
+             a missed change will be caught up a few minutes later"
+        );
+    }
+
+    // Replace the previous lines of this profile
+    let cron_content = read_crontab()?;
+    let mut lines = Vec::new();
+    for line in cron_content.lines() {
+        match parse_cron_line(line)
+            .and_then(|(_, command)| get_script_path(command, script_path, profile))
+        {
+            Some(path) if Path::new(path) != script_path => {
+                println!("{}", tr!("register-updated", path = path));
+            }
+            Some(_) => {}
+            None => lines.push(line.to_string()),
+        }
+    }
+    lines.extend(get_cron_lines(&get_command(script_path, profile), schedule));
+    write_crontab(&(lines.join("\n") + "\n"))?;
+
+    println!("{}", tr!("register-cron"));
 
     Ok(())
 }
 
-/// Unregisters the given `script_path` as a scheduled task on Linux.
+/// Unregisters the given `script_path` with the given profile as a cron job,
+/// even if it was registered from another path.
 ///
 /// # Errors
 /// Fails if the crontab file can't be accessed or edited.
-pub(crate) fn unregister_task(script_path: &Path) -> Result<(), Box<dyn Error>> {
-    // Get the current user's crontab
-    let cron_result = Command::new("crontab").arg("-l").output()?;
-    let mut cron_content: String = if cron_result.status.success() {
-        String::from_utf8_lossy(&cron_result.stdout).to_string()
-    } else {
-        String::new()
+fn unregister_cron_task(script_path: &Path, profile: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let cron_content = read_crontab()?;
+    let is_registered = |line: &str| {
+        parse_cron_line(line)
+            .is_some_and(|(_, command)| get_script_path(command, script_path, profile).is_some())
     };
 
-    // Ensure the script is registered
-    if !cron_content.contains(&*script_path.to_string_lossy()) {
-        info!("The script is not registered as a cron job.");
+    // Ensure the script is registered with this profile
+    if !cron_content.lines().any(is_registered) {
+        println!("{}", tr!("schedule-not-registered"));
         return Ok(());
     }
 
     // Remove the script from the crontab
-    cron_content = cron_content
+    let lines = cron_content
         .lines()
-        .filter(|line| !line.contains(&*script_path.to_string_lossy()))
-        .collect::<Vec<&str>>()
-        .join("\n");
+        .filter(|line| !is_registered(line))
+        .collect::<Vec<&str>>();
+    write_crontab(&(lines.join("\n") + "\n"))?;
 
-    // Create a temporary file
-    let cron_file = Paths::crontab_temp_file();
-    if let Some(parent) = cron_file.parent() {
-        fs::create_dir_all(parent)?;
-    }
+    println!("{}", tr!("unregister-cron"));
 
-    fs::write(&cron_file, cron_content)?;
-    Command::new("crontab").arg(cron_file).output()?;
+    Ok(())
+}
 
-    fs::remove_file(cron_file)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    info!("Script added to crontab");
+    #[test]
+    fn cron_lines() {
+        assert_eq!(
+            parse_cron_line("*/5 * * * * /usr/bin/wallpaper-changer-rs --profile work"),
+            Some((
+                "*/5 * * * *",
+                "/usr/bin/wallpaper-changer-rs --profile work"
+            ))
+        );
+        assert_eq!(
+            parse_cron_line("  0  9 * * 1-5   command argument  "),
+            Some(("0  9 * * 1-5", "command argument"))
+        );
+        assert_eq!(
+            parse_cron_line("@reboot  command"),
+            Some(("@reboot", "command"))
+        );
+    }
 
-    Ok(())
+    #[test]
+    fn ignored_cron_lines() {
+        for line in [
+            "",
+            "   ",
+            "# */5 * * * * command",
+            "PATH=/usr/bin",
+            "* * * * *",
+        ] {
+            assert_eq!(parse_cron_line(line), None, "{line:?}");
+        }
+    }
 }
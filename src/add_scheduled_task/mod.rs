@@ -1,13 +1,104 @@
 //! Utility functions to register the wallpaper changer as a scheduled task.
+use std::error::Error;
+use std::path::PathBuf;
 
 #[cfg(target_os = "windows")]
 mod windows;
 
 #[cfg(target_os = "windows")]
-pub(crate) use windows::{register_task, unregister_task};
+pub(crate) use windows::{get_task_status, register_task, unregister_task};
 
 #[cfg(target_os = "linux")]
 mod linux;
 
 #[cfg(target_os = "linux")]
-pub(crate) use linux::{register_task, unregister_task};
+mod systemd;
+
+#[cfg(target_os = "linux")]
+pub(crate) use linux::{get_task_status, register_task, unregister_task};
+
+/// A registered scheduled task, as shown by the `schedule status` command.
+pub(crate) struct TaskStatus {
+    /// What runs the script (e.g. "systemd timer").
+    pub(crate) kind: &'static str,
+    /// When the script runs, in the syntax of the scheduler.
+    pub(crate) schedule: String,
+    /// The command line that is run.
+    pub(crate) command: String,
+    /// The executable that is run, which may be an old path if the executable was moved.
+    pub(crate) script_path: PathBuf,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// When the scheduled task runs.
+pub(crate) struct Schedule {
+    /// The interval between two runs, in minutes (5 by default).
+    pub(crate) every: u32,
+    /// Run when the user logs on.
+    pub(crate) at_logon: bool,
+    /// Run when the session is unlocked.
+    pub(crate) on_unlock: bool,
+    /// Run when the computer resumes from sleep.
+    pub(crate) on_resume: bool,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            every: 5,
+            at_logon: false,
+            on_unlock: false,
+            on_resume: false,
+        }
+    }
+}
+
+impl Schedule {
+    /// Parses the options of the `register` command:
+    /// `--every 30m` (or `2h`), `--at-logon`, `--on-unlock` and `--on-resume`.
+    ///
+    /// # Errors
+    /// Fails if an option is unknown or if the interval is invalid.
+    pub(crate) fn from_args(args: &[String]) -> Result<Self, Box<dyn Error>> {
+        let mut schedule = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--every" => {
+                    let interval = args.next().ok_or("The --every option needs an interval")?;
+                    schedule.every = parse_interval(interval)?;
+                }
+                "--at-logon" => schedule.at_logon = true,
+                "--on-unlock" => schedule.on_unlock = true,
+                "--on-resume" => schedule.on_resume = true,
+                _ => return Err(format!("Unknown option {arg:?}").into()),
+            }
+        }
+        Ok(schedule)
+    }
+}
+
+/// Parses an interval in minutes (`30m` or `30`) or in hours (`2h`) and returns it in minutes.
+/// It must divide an hour or a day so that it can be expressed in all the schedulers.
+///
+/// # Errors
+/// Fails if the interval is malformed or doesn't divide an hour or a day.
+pub(crate) fn parse_interval(interval: &str) -> Result<u32, Box<dyn Error>> {
+    let minutes = if let Some(hours) = interval.strip_suffix('h') {
+        hours.parse::<u32>()? * 60
+    } else {
+        interval.strip_suffix('m').unwrap_or(interval).parse()?
+    };
+    let valid = match minutes {
+        1..=59 => 60 % minutes == 0,
+        60..=1440 => minutes % 60 == 0 && 24 % (minutes / 60) == 0,
+        _ => false,
+    };
+    if !valid {
+        return Err(format!(
+            "The interval must divide an hour (e.g. 5m, 30m) or a day (e.g. 2h, 24h); found {interval:?}"
+        )
+        .into());
+    }
+    Ok(minutes)
+}
@@ -0,0 +1,235 @@
+//! Utility functions to register the wallpaper changer as a systemd user timer on Linux.
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use super::{Schedule, TaskStatus};
+use crate::i18n::tr;
+use crate::Paths;
+
+/// Returns `true` if the system was booted with systemd.
+pub(crate) fn is_available() -> bool {
+    Path::new("/run/systemd/system").exists()
+}
+
+/// Returns the name of the units of the given profile, without extension.
+fn get_unit_name(profile: Option<&str>) -> String {
+    match profile {
+        Some(profile) => format!("wallpaper-changer-{profile}"),
+        None => "wallpaper-changer".to_string(),
+    }
+}
+
+/// Returns the directory of the systemd user units, or of the user units of all the users
+/// in system mode.
+///
+/// # Errors
+/// Fails if the config directory can't be determined.
+fn get_units_dir() -> Result<PathBuf, Box<dyn Error>> {
+    if Paths::is_system() {
+        return Ok(PathBuf::from("/etc/systemd/user"));
+    }
+    Ok(dirs::config_dir()
+        .ok_or("Could not find the config directory")?
+        .join("systemd")
+        .join("user"))
+}
+
+/// Runs `systemctl --user` (or `systemctl --global` in system mode) with the given arguments.
+///
+/// # Errors
+/// Fails if `systemctl` can't be called or if it fails.
+fn systemctl(args: &[&str]) -> Result<(), Box<dyn Error>> {
+    let scope = if Paths::is_system() {
+        "--global"
+    } else {
+        "--user"
+    };
+    let output = Command::new("systemctl")
+        .arg(scope)
+        .args(args)
+        .output()
+        .map_err(|err| format!("Could not call systemctl: {err}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "systemctl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Returns the `[Timer]` section of the timer that runs the script with the given schedule.
+///
+/// The calendar timer also runs the script after a resume, since the time of a run has passed
+/// during the sleep.
+fn get_timer_section(schedule: &Schedule) -> String {
+    let calendar = if schedule.every < 60 {
+        format!("*:0/{}", schedule.every)
+    } else {
+        format!("0/{}:00", schedule.every / 60)
+    };
+    let mut section = format!("[Timer]\nOnCalendar={calendar}\nPersistent=true\n");
+    if schedule.at_logon {
+        // The user manager starts when the user logs on
+        section.push_str("OnStartupSec=0\n");
+    }
+    section
+}
+
+/// Returns the value of a key in the content of a unit file.
+fn get_unit_value<'a>(unit: &'a str, key: &str) -> Option<&'a str> {
+    unit.lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+        .map(str::trim)
+}
+
+/// Returns the timer of the given profile, or `None` if there is no timer.
+///
+/// # Errors
+/// Fails if the units can't be read.
+pub(crate) fn get_task_status(profile: Option<&str>) -> Result<Option<TaskStatus>, Box<dyn Error>> {
+    let unit_name = get_unit_name(profile);
+    let units_dir = get_units_dir()?;
+    let timer_path = units_dir.join(format!("{unit_name}.timer"));
+    if !timer_path.exists() {
+        return Ok(None);
+    }
+
+    let timer = fs::read_to_string(timer_path)?;
+    let service = fs::read_to_string(units_dir.join(format!("{unit_name}.service")))?;
+    let mut schedule = get_unit_value(&timer, "OnCalendar")
+        .unwrap_or_default()
+        .to_string();
+    if get_unit_value(&timer, "OnStartupSec").is_some() {
+        schedule.push_str(", at logon");
+    }
+    let command = get_unit_value(&service, "ExecStart").unwrap_or_default();
+    // The path of the executable is quoted
+    let script_path = command
+        .strip_prefix('"')
+        .and_then(|command| command.split_once('"'))
+        .map_or(command, |(path, _)| path);
+
+    Ok(Some(TaskStatus {
+        kind: "systemd timer",
+        schedule,
+        command: command.to_string(),
+        script_path: PathBuf::from(script_path),
+    }))
+}
+
+/// Writes a service and a timer that run the script with the given schedule and enables the timer.
+/// The previous timer of the profile is replaced, even if it ran the script from another path.
+///
+/// # Errors
+/// Fails if the units can't be written or enabled.
+pub(crate) fn register_task(
+    script_path: &Path,
+    profile: Option<&str>,
+    schedule: &Schedule,
+) -> Result<(), Box<dyn Error>> {
+    if let Ok(Some(status)) = get_task_status(profile) {
+        if status.script_path != script_path {
+            println!(
+                "{}",
+                tr!("register-updated", path = status.script_path.display())
+            );
+        }
+    }
+
+    let unit_name = get_unit_name(profile);
+    let units_dir = get_units_dir()?;
+    fs::create_dir_all(&units_dir)?;
+
+    let mut command = format!("\"{}\"", script_path.to_string_lossy());
+    if Paths::is_system() {
+        command.push_str(" --system");
+    }
+    if let Some(profile) = profile {
+        command.push_str(&format!(" --profile {profile}"));
+    }
+    fs::write(
+        units_dir.join(format!("{unit_name}.service")),
+        format!(
+            "[Unit]\n\
+             Description=Change the wallpaper\n\
+             After=graphical-session.target\n\
+             \n\
+             [Service]\n\
+             Type=oneshot\n\
+             ExecStart={command}\n"
+        ),
+    )?;
+    fs::write(
+        units_dir.join(format!("{unit_name}.timer")),
+        format!(
+            "[Unit]\n\
+             Description=Change the wallpaper\n\
+             \n\
+             {}\
+             \n\
+             [Install]\n\
+             WantedBy=timers.target\n",
+            get_timer_section(schedule)
+        ),
+    )?;
+
+    if Paths::is_system() {
+        // The timer can't be started in the sessions that are already open
+        systemctl(&["enable", &format!("{unit_name}.timer")])?;
+        println!(
+            "{}",
+            tr!(
+                "register-systemd-system",
+                timer = format!("{unit_name}.timer")
+            )
+        );
+        return Ok(());
+    }
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["enable", "--now", &format!("{unit_name}.timer")])?;
+    println!(
+        "{}",
+        tr!("register-systemd", timer = format!("{unit_name}.timer"))
+    );
+
+    Ok(())
+}
+
+/// Disables the timer of the given profile and removes its units.
+///
+/// # Errors
+/// Fails if the timer can't be disabled or if the units can't be removed.
+pub(crate) fn unregister_task(profile: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let unit_name = get_unit_name(profile);
+    let units_dir = get_units_dir()?;
+    let timer_path = units_dir.join(format!("{unit_name}.timer"));
+    if !timer_path.exists() {
+        println!("{}", tr!("schedule-not-registered"));
+        return Ok(());
+    }
+
+    if Paths::is_system() {
+        systemctl(&["disable", &format!("{unit_name}.timer")])?;
+    } else {
+        systemctl(&["disable", "--now", &format!("{unit_name}.timer")])?;
+    }
+    fs::remove_file(timer_path)?;
+    let service_path = units_dir.join(format!("{unit_name}.service"));
+    if service_path.exists() {
+        fs::remove_file(service_path)?;
+    }
+    if !Paths::is_system() {
+        systemctl(&["daemon-reload"])?;
+    }
+    println!(
+        "{}",
+        tr!("unregister-systemd", timer = format!("{unit_name}.timer"))
+    );
+
+    Ok(())
+}
@@ -1,92 +1,289 @@
 //! Utility functions to register the wallpaper changer as a scheduled task on Windows.
-use log::info;
+use chrono::Local;
+use std::env;
 use std::error::Error;
+use std::fmt::Write;
+use std::fs;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-#[cfg(target_os = "windows")]
-/// Registers the given `script_path` as a scheduled task on Windows.
-///
-/// # Errors
-/// Fails if `schtasks` can't be called.
-pub(crate) fn register_task(script_path: &Path) -> Result<(), Box<dyn Error>> {
-    let task_name = "wallpaper-changer-rs";
+use super::{Schedule, TaskStatus};
+use crate::i18n::tr;
+use crate::slideshow::escape_xml;
+use crate::Paths;
 
-    // Check if the task is already registered
-    let status = Command::new("schtasks")
-        .args(&["/Query", "/TN", task_name])
-        .status()?;
+/// The event of the system log written when the computer resumes from sleep.
+const RESUME_EVENT_QUERY: &str =
+    "*[System[Provider[@Name='Microsoft-Windows-Power-Troubleshooter'] and EventID=1]]";
 
-    if status.success() {
-        info!("Task '{task_name}' is already registered.");
-        return Ok(());
+/// The group of all the users (`BUILTIN\Users`), which runs the task in system mode.
+const USERS_GROUP_SID: &str = "S-1-5-32-545";
+
+/// Returns the name of the task of the given profile
+/// (the task of all the users has its own name).
+fn get_task_name(profile: Option<&str>) -> String {
+    let name = if Paths::is_system() {
+        "wallpaper-changer-rs-system"
+    } else {
+        "wallpaper-changer-rs"
+    };
+    match profile {
+        Some(profile) => format!("{name}-{profile}"),
+        None => name.to_string(),
     }
+}
 
-    // Create a task in Task Scheduler to run every 5 minutes
-    let output = Command::new("schtasks")
-        .args(&[
-            "/Create",
-            "/SC",
-            "MINUTE",
-            "/MO",
-            "5",
-            "/TN",
-            task_name,
-            "/TR",
-            &script_path.to_string_lossy(),
-        ])
-        .output()?;
+/// Runs `schtasks` with the given arguments.
+///
+/// # Errors
+/// Fails if `schtasks` can't be called or if it fails.
+fn schtasks(args: &[&str]) -> Result<(), Box<dyn Error>> {
+    let output = Command::new("schtasks").args(args).output()?;
 
     if !output.status.success() {
         return Err(Box::new(io::Error::new(
             io::ErrorKind::Other,
             format!(
-                "Failed to create task: {}",
+                "schtasks {} failed: {}",
+                args.first().unwrap_or(&""),
                 String::from_utf8_lossy(&output.stderr)
             ),
         )));
     }
 
-    info!("Task '{task_name}' created successfully.");
-
     Ok(())
 }
 
-#[cfg(target_os = "windows")]
-/// Unregisters the given `script_path` as a scheduled task on Windows.
+/// Returns `true` if the task exists.
 ///
 /// # Errors
 /// Fails if `schtasks` can't be called.
-pub(crate) fn unregister_task(script_path: &Path) -> Result<(), Box<dyn Error>> {
-    let task_name = "wallpaper-changer-rs";
-
-    // Check if the task is already registered
-    let status = Command::new("schtasks")
-        .args(&["/Query", "/TN", task_name])
-        .status()?;
+fn task_exists(task_name: &str) -> Result<bool, Box<dyn Error>> {
+    Ok(Command::new("schtasks")
+        .args(["/Query", "/TN", task_name])
+        .output()?
+        .status
+        .success())
+}
 
-    if !status.success() {
-        info!("Task '{task_name}' is not registered.");
-        return Ok(());
-    }
+/// Returns the content of an element of a task definition, with the XML entities decoded.
+fn get_xml_element(xml: &str, tag: &str) -> Option<String> {
+    let start = xml.find(&format!("<{tag}>"))? + tag.len() + 2;
+    let end = start + xml[start..].find(&format!("</{tag}>"))?;
+    Some(
+        xml[start..end]
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&amp;", "&"),
+    )
+}
 
-    // Delete the task from Task Scheduler
+/// Returns the task of the given profile, or `None` if there is no task.
+///
+/// # Errors
+/// Fails if `schtasks` can't be called.
+pub(crate) fn get_task_status(
+    _script_path: &Path,
+    profile: Option<&str>,
+) -> Result<Option<TaskStatus>, Box<dyn Error>> {
+    let task_name = get_task_name(profile);
     let output = Command::new("schtasks")
-        .args(&["/Delete", "/TN", task_name, "/F"])
+        .args(["/Query", "/TN", &task_name, "/XML"])
         .output()?;
-
     if !output.status.success() {
-        return Err(Box::new(io::Error::new(
-            io::ErrorKind::Other,
-            format!(
-                "Failed to delete task: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ),
-        )));
+        return Ok(None);
     }
 
-    info!("Task '{task_name}' deleted successfully.");
+    let xml = String::from_utf8_lossy(&output.stdout);
+    let mut schedule = get_xml_element(&xml, "Interval").unwrap_or_default();
+    for (tag, name) in [
+        ("LogonTrigger", "at logon"),
+        ("SessionStateChangeTrigger", "on unlock"),
+        ("EventTrigger", "on resume"),
+    ] {
+        if xml.contains(&format!("<{tag}")) {
+            let _ = write!(schedule, ", {name}");
+        }
+    }
+
+    let program = get_xml_element(&xml, "Command").unwrap_or_default();
+    let arguments = get_xml_element(&xml, "Arguments").unwrap_or_default();
+    // The tasks registered by older versions run the executable in a headless console host
+    let (script_path, command) = match arguments.strip_prefix("--headless ") {
+        Some(command) if program == "conhost.exe" => {
+            let script_path = command
+                .strip_prefix('"')
+                .and_then(|command| command.split_once('"'))
+                .map_or(command, |(path, _)| path);
+            (script_path.to_string(), command.to_string())
+        }
+        _ => (program.clone(), format!("\"{program}\" {arguments}")),
+    };
+
+    Ok(Some(TaskStatus {
+        kind: "scheduled task",
+        schedule,
+        command: command.trim().to_string(),
+        script_path: PathBuf::from(script_path),
+    }))
+}
+
+/// Returns the definition of a task that runs the script with the given profile and schedule.
+///
+/// The executable is run directly: it has no console window, so no window flashes on each run.
+/// The task also runs on battery, catches up the runs missed while the computer was off
+/// and doesn't wake the computer. In system mode, it runs for all the users.
+fn get_task_xml(script_path: &Path, profile: Option<&str>, schedule: &Schedule) -> String {
+    let user = escape_xml(&format!(
+        "{}\\{}",
+        env::var("USERDOMAIN").unwrap_or_default(),
+        env::var("USERNAME").unwrap_or_default()
+    ));
+    // The triggers without a user run for any user
+    let (user_id, principal) = if Paths::is_system() {
+        (
+            String::new(),
+            format!("<GroupId>{USERS_GROUP_SID}</GroupId>"),
+        )
+    } else {
+        (
+            format!("\n      <UserId>{user}</UserId>"),
+            format!("<UserId>{user}</UserId>\n      <LogonType>InteractiveToken</LogonType>"),
+        )
+    };
+    let mut arguments = String::new();
+    if Paths::is_system() {
+        arguments.push_str(" --system");
+    }
+    if let Some(profile) = profile {
+        let _ = write!(arguments, " --profile {profile}");
+    }
+    let working_directory = script_path.parent().unwrap_or(script_path);
+
+    let mut triggers = format!(
+        "    <TimeTrigger>\n      <StartBoundary>{}</StartBoundary>\n      \
+         <Repetition>\n        <Interval>PT{}M</Interval>\n      </Repetition>\n    \
+         </TimeTrigger>\n",
+        Local::now().format("%Y-%m-%dT%H:%M:00"),
+        schedule.every,
+    );
+    if schedule.at_logon {
+        let _ = write!(
+            triggers,
+            "    <LogonTrigger>{user_id}\n    </LogonTrigger>\n"
+        );
+    }
+    if schedule.on_unlock {
+        let _ = write!(
+            triggers,
+            "    <SessionStateChangeTrigger>{user_id}\n      \
+             <StateChange>SessionUnlock</StateChange>\n    </SessionStateChangeTrigger>\n"
+        );
+    }
+    if schedule.on_resume {
+        let _ = write!(
+            triggers,
+            "    <EventTrigger>\n      <Subscription>{}</Subscription>\n    </EventTrigger>\n",
+            escape_xml(&format!(
+                "<QueryList><Query Id=\"0\" Path=\"System\">\
+                 <Select Path=\"System\">{RESUME_EVENT_QUERY}</Select></Query></QueryList>"
+            )),
+        );
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-16\"?>\n\
+         <Task version=\"1.2\" xmlns=\"http://schemas.microsoft.com/windows/2004/02/mit/task\">\n  \
+         <RegistrationInfo>\n    <Description>Change the wallpaper</Description>\n  \
+         </RegistrationInfo>\n  \
+         <Triggers>\n{triggers}  </Triggers>\n  \
+         <Principals>\n    <Principal id=\"Author\">\n      {principal}\n      \
+         <RunLevel>LeastPrivilege</RunLevel>\n    \
+         </Principal>\n  </Principals>\n  \
+         <Settings>\n    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>\n    \
+         <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>\n    \
+         <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>\n    \
+         <StartWhenAvailable>true</StartWhenAvailable>\n    \
+         <RunOnlyIfNetworkAvailable>false</RunOnlyIfNetworkAvailable>\n    \
+         <WakeToRun>false</WakeToRun>\n    <Hidden>true</Hidden>\n    \
+         <ExecutionTimeLimit>PT10M</ExecutionTimeLimit>\n  </Settings>\n  \
+         <Actions Context=\"Author\">\n    <Exec>\n      <Command>{}</Command>\n      \
+         <Arguments>{}</Arguments>\n      <WorkingDirectory>{}</WorkingDirectory>\n    \
+         </Exec>\n  </Actions>\n</Task>\n",
+        escape_xml(&script_path.to_string_lossy()),
+        escape_xml(arguments.trim_start()),
+        escape_xml(&working_directory.to_string_lossy()),
+    )
+}
+
+#[cfg(target_os = "windows")]
+/// Registers the given `script_path` as a scheduled task on Windows, with the given profile
+/// and schedule. The previous task of the profile is replaced,
+/// even if it ran the script from another path.
+///
+/// # Errors
+/// Fails if the task definition can't be written or if `schtasks` can't be called.
+pub(crate) fn register_task(
+    script_path: &Path,
+    profile: Option<&str>,
+    schedule: &Schedule,
+) -> Result<(), Box<dyn Error>> {
+    if let Ok(Some(status)) = get_task_status(script_path, profile) {
+        if status.script_path != script_path {
+            println!(
+                "{}",
+                tr!("register-updated", path = status.script_path.display())
+            );
+        }
+    }
+
+    let task_name = get_task_name(profile);
+
+    // schtasks reads the definition from a UTF-16 file
+    let xml_path = Paths::temp_dir()?.join("task.xml");
+    let mut data = vec![0xFF, 0xFE];
+    for unit in get_task_xml(script_path, profile, schedule).encode_utf16() {
+        data.extend_from_slice(&unit.to_le_bytes());
+    }
+    fs::write(&xml_path, data)?;
+
+    let result = schtasks(&[
+        "/Create",
+        "/F",
+        "/TN",
+        &task_name,
+        "/XML",
+        &xml_path.to_string_lossy(),
+    ]);
+    fs::remove_file(&xml_path)?;
+    result?;
+    println!("{}", tr!("register-windows", task = task_name));
+
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+/// Unregisters the given `script_path` with the given profile as a scheduled task on Windows.
+///
+/// # Errors
+/// Fails if `schtasks` can't be called.
+pub(crate) fn unregister_task(
+    _script_path: &Path,
+    profile: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let task_name = get_task_name(profile);
+
+    // Check if the task is registered
+    if !task_exists(&task_name)? {
+        println!("{}", tr!("schedule-not-registered"));
+        return Ok(());
+    }
+
+    // Delete the task from Task Scheduler
+    schtasks(&["/Delete", "/TN", &task_name, "/F"])?;
+    println!("{}", tr!("unregister-windows", task = task_name));
 
     Ok(())
 }
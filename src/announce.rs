@@ -0,0 +1,189 @@
+//! Announcements of the wallpaper changes with a webhook or an MQTT message,
+//! e.g. for Home Assistant dashboards or e-ink displays that mirror the wallpaper.
+use chrono::Utc;
+use log::{error, info, warn};
+use serde_json::json;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::process;
+use std::thread;
+use std::time::Duration;
+use url::Url;
+
+use crate::config::AnnounceConfig;
+use crate::http;
+use crate::image_structs::Image;
+
+/// The number of times an announcement is sent before giving up.
+const ANNOUNCE_ATTEMPTS: u32 = 2;
+
+/// The time to wait before sending an announcement again.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// The maximum duration of a request to the webhook or the MQTT broker.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The default port of the MQTT brokers.
+const MQTT_PORT: u16 = 1883;
+
+/// Sends an announcement, again after [`RETRY_DELAY`] if it fails.
+///
+/// # Errors
+/// Fails if the announcement couldn't be sent after [`ANNOUNCE_ATTEMPTS`] attempts.
+fn send_with_retries(
+    name: &str,
+    send: impl Fn() -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut attempt = 1;
+    loop {
+        match send() {
+            Ok(()) => return Ok(()),
+            Err(err) if attempt < ANNOUNCE_ATTEMPTS => {
+                warn!("Couldn't send the {name}, trying again: {err}");
+                thread::sleep(RETRY_DELAY);
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Posts the JSON payload to the webhook.
+///
+/// # Errors
+/// Fails if the request fails or if the webhook answers with an error.
+fn post_webhook(url: &str, payload: &str) -> Result<(), Box<dyn Error>> {
+    http::post(url)
+        .header("Content-Type", "application/json")
+        .config()
+        .timeout_global(Some(TIMEOUT))
+        .build()
+        .send(payload)?;
+    Ok(())
+}
+
+/// Makes an MQTT packet of the given type and flags (the first byte) with its body.
+#[expect(clippy::cast_possible_truncation)]
+fn make_mqtt_packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    // The remaining length is encoded with 7 bits per byte
+    let mut length = body.len();
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+    packet.extend(body);
+    packet
+}
+
+/// Appends a string prefixed with its length to an MQTT packet.
+///
+/// # Errors
+/// Fails if the string is longer than 65535 bytes.
+fn push_mqtt_string(body: &mut Vec<u8>, string: &str) -> Result<(), Box<dyn Error>> {
+    body.extend(u16::try_from(string.len())?.to_be_bytes());
+    body.extend(string.as_bytes());
+    Ok(())
+}
+
+/// Publishes the payload on the topic of an `mqtt://[user:password@]host[:port]/topic` URL
+/// (MQTT 3.1.1 with QoS 0).
+///
+/// # Errors
+/// Fails if the URL is invalid, if the broker can't be reached or if it refuses the connection.
+fn publish_mqtt(url: &str, payload: &str, retain: bool) -> Result<(), Box<dyn Error>> {
+    let url = Url::parse(url)?;
+    if url.scheme() != "mqtt" {
+        return Err(format!("The MQTT URL must start with mqtt://, found {url}").into());
+    }
+    let host = url.host_str().ok_or("The MQTT URL has no host")?;
+    let topic = url.path().trim_start_matches('/');
+    if topic.is_empty() {
+        return Err("The MQTT URL has no topic".into());
+    }
+
+    let mut stream = TcpStream::connect((host, url.port().unwrap_or(MQTT_PORT)))?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    // CONNECT with a clean session
+    let mut body = Vec::new();
+    push_mqtt_string(&mut body, "MQTT")?;
+    body.push(4);
+    let mut flags = 0x02;
+    if !url.username().is_empty() {
+        flags |= 0x80;
+    }
+    if url.password().is_some() {
+        flags |= 0x40;
+    }
+    body.push(flags);
+    body.extend(60_u16.to_be_bytes());
+    let client_id = format!("wallpaper-changer-rs-{}", process::id());
+    push_mqtt_string(&mut body, &client_id)?;
+    if !url.username().is_empty() {
+        push_mqtt_string(&mut body, url.username())?;
+    }
+    if let Some(password) = url.password() {
+        push_mqtt_string(&mut body, password)?;
+    }
+    stream.write_all(&make_mqtt_packet(0x10, &body))?;
+
+    // CONNACK
+    let mut connack = [0; 4];
+    stream.read_exact(&mut connack)?;
+    if connack[0] != 0x20 || connack[3] != 0 {
+        return Err(format!(
+            "The MQTT broker refused the connection (return code {})",
+            connack[3]
+        )
+        .into());
+    }
+
+    // PUBLISH, then DISCONNECT
+    let mut body = Vec::new();
+    push_mqtt_string(&mut body, topic)?;
+    body.extend(payload.as_bytes());
+    stream.write_all(&make_mqtt_packet(0x30 | u8::from(retain), &body))?;
+    stream.write_all(&make_mqtt_packet(0xE0, &[]))?;
+    Ok(())
+}
+
+/// Announces the new wallpaper with the webhook and the MQTT topic of the config.
+pub(crate) fn announce_change(config: &AnnounceConfig, image: &dyn Image, wallpaper: &Path) {
+    let payload = json!({
+        "wallpaper": wallpaper.to_string_lossy(),
+        "image": image.get_path().to_string_lossy(),
+        "source": image.get_source(),
+        "description": image.get_description(),
+        "author": image.get_author(),
+        "changed_at": Utc::now(),
+    })
+    .to_string();
+
+    if !config.webhook_url.is_empty() {
+        info!("Announcing the new wallpaper to the webhook");
+        let result = send_with_retries("webhook", || post_webhook(&config.webhook_url, &payload));
+        if let Err(err) = result {
+            error!("Couldn't send the webhook: {err}");
+        }
+    }
+    if !config.mqtt_url.is_empty() {
+        info!("Announcing the new wallpaper on MQTT");
+        let result = send_with_retries("MQTT message", || {
+            publish_mqtt(&config.mqtt_url, &payload, config.mqtt_retain)
+        });
+        if let Err(err) = result {
+            error!("Couldn't publish the MQTT message: {err}");
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! The composition of a given image for a given screen size without a desktop
+//! (e.g. in a CI job or a digital signage pipeline): the image is resized and labeled
+//! like a wallpaper, but it isn't set as the background and the history isn't changed.
+use image::ImageFormat;
+use log::info;
+use std::error::Error;
+use std::path::Path;
+
+use crate::compose;
+use crate::config::Config;
+use crate::image_structs::LocalImage;
+use crate::screen_size::Monitor;
+
+/// Parses a screen size written as `WIDTHxHEIGHT` (e.g. `2560x1440`).
+///
+/// # Errors
+/// Fails if the size is malformed or empty.
+pub(crate) fn parse_size(size: &str) -> Result<(u32, u32), Box<dyn Error>> {
+    let invalid = || format!("Invalid size {size:?}, expected WIDTHxHEIGHT (e.g. 2560x1440)");
+    let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+    let width: u32 = width.parse().map_err(|_| invalid())?;
+    let height: u32 = height.parse().map_err(|_| invalid())?;
+    if width == 0 || height == 0 {
+        return Err(invalid().into());
+    }
+    Ok((width, height))
+}
+
+/// Resizes the image for a screen of the given size, applies the filters, writes its description
+/// and the widgets on it and saves it in the output file, in the format of its extension.
+///
+/// # Errors
+/// Fails if the extension of the output isn't a supported format
+/// or if the image can't be opened, labeled or saved.
+pub(crate) fn compose_file(
+    config: &Config,
+    input: &Path,
+    (width, height): (u32, u32),
+    output: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let output_format = match ImageFormat::from_path(output) {
+        Ok(format @ (ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP)) => format,
+        _ => {
+            return Err(format!(
+                "Unsupported output format {output:?}, use a .jpg, .png or .webp file"
+            )
+            .into())
+        }
+    };
+
+    let mut image = LocalImage::from(input.to_path_buf());
+    image.label_template.clone_from(&config.label_template);
+    // A single monitor, without the dark theme of the desktop
+    let monitor = Monitor {
+        x: 0,
+        y: 0,
+        width,
+        height,
+        primary: true,
+        scale: 1.0,
+    };
+    info!("Composing {input:?} for a {width}x{height} screen");
+    compose(config, &image, &[monitor], false, output, output_format)?;
+    Ok(())
+}
@@ -0,0 +1,117 @@
+//! Utility functions to read the events of a local `.ics` calendar.
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Debug)]
+/// An event of a calendar.
+pub(crate) struct Event {
+    pub(crate) start: DateTime<Local>,
+    pub(crate) all_day: bool,
+    pub(crate) summary: String,
+}
+
+/// Returns the next `count` events (that start after `now`) of an `.ics` file.
+///
+/// Recurring events are only taken into account at their first occurrence.
+///
+/// # Errors
+/// Fails if the file can't be read.
+pub(crate) fn get_next_events(
+    path: &Path,
+    now: DateTime<Local>,
+    count: usize,
+) -> Result<Vec<Event>, Box<dyn Error>> {
+    let contents = fs::read_to_string(path)?;
+    let mut events = parse_events(&contents)
+        .into_iter()
+        .filter(|event| {
+            if event.all_day {
+                event.start.date_naive() >= now.date_naive()
+            } else {
+                event.start >= now
+            }
+        })
+        .collect::<Vec<_>>();
+    events.sort_by_key(|event| event.start);
+    events.truncate(count);
+    Ok(events)
+}
+
+/// Parses the events of an `.ics` file, ignoring the malformed ones.
+fn parse_events(contents: &str) -> Vec<Event> {
+    // Lines that start with a space or a tab continue the previous line
+    let mut lines: Vec<String> = Vec::new();
+    for line in contents.lines() {
+        if let Some(continuation) = line.strip_prefix([' ', '\t']) {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(continuation);
+                continue;
+            }
+        }
+        lines.push(line.to_string());
+    }
+
+    let mut events = Vec::new();
+    let mut start = None;
+    let mut summary = String::new();
+    let mut in_event = false;
+    for line in &lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        // Remove the parameters (e.g. "DTSTART;TZID=Europe/Paris")
+        let name = name.split(';').next().unwrap_or_default();
+        match (name, value) {
+            ("BEGIN", "VEVENT") => {
+                in_event = true;
+                start = None;
+                summary.clear();
+            }
+            ("END", "VEVENT") => {
+                if let Some((start, all_day)) = start.take() {
+                    events.push(Event {
+                        start,
+                        all_day,
+                        summary: summary.clone(),
+                    });
+                }
+                in_event = false;
+            }
+            ("DTSTART", _) if in_event => start = parse_date(value),
+            ("SUMMARY", _) if in_event => summary = unescape(value),
+            _ => {}
+        }
+    }
+    events
+}
+
+/// Parses an iCalendar date (`19700101`), local date-time (`19700101T000000`)
+/// or UTC date-time (`19700101T000000Z`). Returns the date and `true` if it is a whole day.
+fn parse_date(value: &str) -> Option<(DateTime<Local>, bool)> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        let date = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some((Utc.from_utc_datetime(&date).with_timezone(&Local), false));
+    }
+    if let Ok(date) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        return Some((Local.from_local_datetime(&date).earliest()?, false));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+    Some((
+        Local
+            .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+            .earliest()?,
+        true,
+    ))
+}
+
+/// Removes the iCalendar escaping from a text value.
+fn unescape(value: &str) -> String {
+    value
+        .replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
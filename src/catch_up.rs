@@ -0,0 +1,44 @@
+//! The time of the last wallpaper change, used to change the wallpaper as soon as possible
+//! when a change was missed (e.g. while the computer was off or asleep), like anacron.
+use chrono::{DateTime, Local};
+use log::debug;
+use std::error::Error;
+use std::fs;
+use std::time::Duration;
+
+use crate::paths::Paths;
+
+/// The interval between two checks of the scheduler that runs the catch-up.
+/// A change is overdue only after this delay so that it doesn't run with the normal change.
+pub(crate) const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Stores the current time as the time of the last wallpaper change.
+///
+/// # Errors
+/// Fails if the file can't be written.
+pub(crate) fn record_change() -> Result<(), Box<dyn Error>> {
+    fs::write(Paths::last_change_file()?, Local::now().to_rfc3339())?;
+    Ok(())
+}
+
+/// Returns the time elapsed since the last wallpaper change,
+/// or `None` if the wallpaper has never been changed.
+fn get_time_since_last_change() -> Option<Duration> {
+    let content = fs::read_to_string(Paths::last_change_file().ok()?).ok()?;
+    let last_change = DateTime::parse_from_rfc3339(content.trim())
+        .inspect_err(|err| debug!("Invalid time of the last change: {err}"))
+        .ok()?;
+    // The time may go back when the clock is adjusted
+    (Local::now() - last_change.with_timezone(&Local))
+        .to_std()
+        .ok()
+}
+
+/// Returns `true` if the last wallpaper change is older than the interval between two changes
+/// (with a margin of [`CHECK_INTERVAL`]).
+pub(crate) fn is_overdue(interval: Duration) -> bool {
+    match get_time_since_last_change() {
+        Some(elapsed) => elapsed >= interval + CHECK_INTERVAL,
+        None => true,
+    }
+}
@@ -0,0 +1,146 @@
+//! The circuit breaker of the image APIs (Unsplash, the lfnewtab API or the `api_url` of the config):
+//! an API that fails several times in a row (e.g. when it is down or when it answers garbage)
+//! isn't contacted for a while, and for longer each time it fails again, instead of slowing down
+//! every run. Its state is stored in the database, so it is shared by the daemon and the other runs.
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+use crate::database::Database;
+use crate::image_list::RateLimitError;
+
+/// The number of consecutive failures after which an API isn't contacted anymore.
+const MAX_FAILURES: u32 = 3;
+
+/// How long an API isn't contacted after [`MAX_FAILURES`] failures.
+/// It is doubled after each new failure, up to [`MAX_BENCH_DURATION`].
+const BENCH_DURATION: TimeDelta = TimeDelta::minutes(30);
+
+/// The longest time an API isn't contacted.
+const MAX_BENCH_DURATION: TimeDelta = TimeDelta::days(1);
+
+/// The failures of an API, stored in the state of the database.
+#[derive(Default, Deserialize, Serialize)]
+struct ProviderState {
+    /// The number of consecutive failures.
+    failures: u32,
+    /// When the API can be contacted again.
+    reopen_at: Option<DateTime<Utc>>,
+    /// The last error of the API.
+    last_error: String,
+}
+
+#[derive(Debug)]
+/// An error returned when an API isn't contacted because it failed too many times.
+pub(crate) struct ProviderBenchedError {
+    provider: String,
+    failures: u32,
+    until: DateTime<Utc>,
+    last_error: String,
+}
+
+impl fmt::Display for ProviderBenchedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} failed {} times in a row, it won't be contacted until {} (last error: {})",
+            self.provider,
+            self.failures,
+            self.until.with_timezone(&Local).format("%H:%M"),
+            self.last_error
+        )
+    }
+}
+
+impl Error for ProviderBenchedError {}
+
+/// Returns the key of the state of an API in the database.
+fn get_key(provider: &str) -> String {
+    format!("provider_state:{provider}")
+}
+
+/// Reads the failures of an API, or returns an empty state if it hasn't failed.
+///
+/// # Errors
+/// Fails if the database can't be read.
+fn read(database: &Database, provider: &str) -> Result<ProviderState, Box<dyn Error>> {
+    Ok(database
+        .get_state(&get_key(provider))?
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default())
+}
+
+/// Checks that an API can be contacted.
+///
+/// # Errors
+/// Fails with a [`ProviderBenchedError`] if the API failed too many times recently,
+/// or if the database can't be read.
+pub(crate) fn check(provider: &str) -> Result<(), Box<dyn Error>> {
+    let state = read(&Database::open()?, provider)?;
+    match state.reopen_at {
+        Some(until) if until > Utc::now() => Err(Box::new(ProviderBenchedError {
+            provider: provider.to_string(),
+            failures: state.failures,
+            until,
+            last_error: state.last_error,
+        })),
+        Some(_) => {
+            info!("Trying {provider} again after {} failures", state.failures);
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Records the result of a request to an API, and stops contacting it if it failed too many times.
+/// The rate limits aren't failures, they have their own cool-down.
+pub(crate) fn record<T>(provider: &str, result: &Result<T, Box<dyn Error>>) {
+    if let Err(err) = try_record(provider, result) {
+        warn!("Couldn't store the state of {provider}: {err}");
+    }
+}
+
+/// Records the result of a request to an API, see [`record`].
+///
+/// # Errors
+/// Fails if the database can't be read or written to.
+fn try_record<T>(provider: &str, result: &Result<T, Box<dyn Error>>) -> Result<(), Box<dyn Error>> {
+    let database = Database::open()?;
+    let key = get_key(provider);
+    let mut state = read(&database, provider)?;
+    match result {
+        Ok(_) => {
+            if state.failures > 0 {
+                info!("{provider} works again after {} failures", state.failures);
+                database.set_state(&key, None)?;
+            }
+            return Ok(());
+        }
+        Err(err) if err.is::<RateLimitError>() => return Ok(()),
+        Err(err) => {
+            state.failures += 1;
+            state.last_error = err.to_string();
+        }
+    }
+    if state.failures >= MAX_FAILURES {
+        let exponent = (state.failures - MAX_FAILURES).min(10);
+        let duration = (BENCH_DURATION * (1 << exponent)).min(MAX_BENCH_DURATION);
+        let until = Utc::now() + duration;
+        warn!(
+            "Disabling {provider} until {} after {} consecutive failures, last error: {}",
+            until.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+            state.failures,
+            state.last_error
+        );
+        state.reopen_at = Some(until);
+    } else {
+        debug!(
+            "{provider} failed {} times in a row: {}",
+            state.failures, state.last_error
+        );
+    }
+    database.set_state(&key, Some(&serde_json::to_string(&state)?))?;
+    Ok(())
+}
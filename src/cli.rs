@@ -0,0 +1,564 @@
+//! The commands of the program, used by its binary.
+use log::{debug, error, info};
+use std::env;
+use std::error::Error;
+use std::path::{self, Path, PathBuf};
+use std::time::Duration;
+
+use crate::add_scheduled_task::{
+    get_task_status, parse_interval, register_task, unregister_task, Schedule,
+};
+use crate::config::Config;
+use crate::database::{Database, ImageList};
+use crate::i18n::tr;
+use crate::image_structs::Image;
+use crate::paths::Paths;
+use crate::{batch, catch_up, change_wallpaper, content_filter, daemon, favorites_backup, feed};
+use crate::{gallery, get_last_wallpaper, image_list, last_run};
+use crate::{logging, notification, offline, pause, pin, provenance, settings_bundle, skip_rules};
+
+#[cfg(feature = "tray")]
+use crate::tray;
+
+/// Sets the profile given with `--profile` in the arguments and removes it from them.
+/// The profile must be set before any path is used.
+///
+/// # Errors
+/// Fails if the profile name is missing or invalid.
+fn set_profile(args: &mut Vec<String>) -> Result<(), Box<dyn Error>> {
+    if let Some(index) = args.iter().position(|arg| arg == "--profile") {
+        let profile = args
+            .get(index + 1)
+            .ok_or("The --profile argument needs a profile name")?;
+        Paths::set_profile(profile)?;
+        args.drain(index..=index + 1);
+    }
+    Ok(())
+}
+
+/// Uses the system-wide config if `--system` is in the arguments and removes it from them.
+/// It must be called before any path is used.
+fn set_system_mode(args: &mut Vec<String>) {
+    if let Some(index) = args.iter().position(|arg| arg == "--system") {
+        Paths::set_system();
+        args.remove(index);
+    }
+}
+
+/// Returns `true` if the errors can be reported to Sentry. The telemetry is disabled
+/// by the `--no-telemetry` argument (which is removed from the arguments),
+/// the `WALLPAPER_CHANGER_NO_SENTRY` environment variable or `telemetry = false` in the config.
+fn is_telemetry_enabled(args: &mut Vec<String>) -> bool {
+    if let Some(index) = args.iter().position(|arg| arg == "--no-telemetry") {
+        args.remove(index);
+        return false;
+    }
+    if env::var_os("WALLPAPER_CHANGER_NO_SENTRY").is_some_and(|value| !value.is_empty()) {
+        return false;
+    }
+    Config::read_unvalidated().is_none_or(|config| config.telemetry)
+}
+
+/// Removes the `-v`, `-vv`, `-q`... arguments and returns the number of `v` minus the number of `q`.
+fn take_verbosity(args: &mut Vec<String>) -> i8 {
+    let mut verbosity: i8 = 0;
+    args.retain(|arg| {
+        let Some(letters) = arg.strip_prefix('-').filter(|letters| !letters.is_empty()) else {
+            return true;
+        };
+        let count = i8::try_from(letters.len()).unwrap_or(i8::MAX);
+        if letters.chars().all(|letter| letter == 'v') {
+            verbosity = verbosity.saturating_add(count);
+        } else if letters.chars().all(|letter| letter == 'q') {
+            verbosity = verbosity.saturating_sub(count);
+        } else {
+            return true;
+        }
+        false
+    });
+    verbosity
+}
+
+/// Sets up the logs with the `[logging]` section of the config and the `-v` and `-q` arguments,
+/// which are removed from the arguments.
+///
+/// # Errors
+/// Fails if the logs are already set up.
+fn init_logging(args: &mut Vec<String>) -> Result<(), Box<dyn Error>> {
+    let verbosity = take_verbosity(args);
+    let config = Config::read_unvalidated()
+        .map(|config| config.logging)
+        .unwrap_or_default();
+    logging::init(&config, verbosity)
+}
+
+/// Removes `--seed` and the seed that follows it from the arguments and returns the seed.
+///
+/// # Errors
+/// Fails if the seed is missing or isn't a number.
+fn take_seed(args: &mut Vec<String>) -> Result<Option<u64>, Box<dyn Error>> {
+    let Some(index) = args.iter().position(|arg| arg == "--seed") else {
+        return Ok(None);
+    };
+    let seed = args
+        .get(index + 1)
+        .ok_or("The --seed argument needs a number")?
+        .parse()?;
+    args.drain(index..=index + 1);
+    Ok(Some(seed))
+}
+
+/// Runs the program with its arguments (without the name of the program): sets the mode,
+/// the profile and the logs, then runs the command.
+///
+/// `start_telemetry` is called unless the telemetry is disabled, and what it returns
+/// (e.g. the guard of Sentry) is kept until the command ends.
+///
+/// # Errors
+/// Fails if the arguments are invalid, if the logs can't be set up or if the command fails.
+pub fn run_program<T>(
+    mut args: Vec<String>,
+    start_telemetry: impl FnOnce() -> T,
+) -> Result<(), Box<dyn Error>> {
+    // The system mode and the profile must be set before any path is used
+    set_system_mode(&mut args);
+    set_profile(&mut args)?;
+
+    init_logging(&mut args)?;
+
+    // The --no-telemetry argument is removed even if the telemetry isn't compiled in
+    let _telemetry = is_telemetry_enabled(&mut args).then(start_telemetry);
+
+    run_command(&args)
+}
+
+/// Runs the command given in the arguments (without the profile),
+/// or changes the wallpaper if there is none.
+///
+/// # Errors
+/// Fails if the arguments are invalid or if the command fails.
+pub fn run_command(args: &[String]) -> Result<(), Box<dyn Error>> {
+    // The seed makes the choice of the images reproducible
+    let mut args = args.to_vec();
+    let seed = take_seed(&mut args)?;
+    let command = args.first().map(String::as_str);
+
+    // if the first argument is register, register a scheduled task
+    if command == Some("register") {
+        debug!("Found register argument, registering scheduled task");
+        let schedule = Schedule::from_args(&args[1..])?;
+        return register_task(&env::current_exe()?, Paths::profile(), &schedule);
+    }
+
+    // if the arguments are schedule status, show the scheduled task
+    if command == Some("schedule") {
+        debug!("Found schedule argument, showing the scheduled task");
+        return match args.get(1).map(String::as_str) {
+            Some("status") => show_schedule_status(&env::current_exe()?),
+            _ => Err("Usage: schedule status".into()),
+        };
+    }
+
+    // if the first argument is status, show the result of the last run
+    if command == Some("status") {
+        debug!("Found status argument, showing the status of the last run");
+        return last_run::show_status();
+    }
+
+    // if the first argument is whatis, show where a wallpaper comes from
+    if command == Some("whatis") {
+        debug!("Found whatis argument, reading the provenance of the wallpaper");
+        return what_is(args.get(1).map(PathBuf::from));
+    }
+
+    // if the first argument is history, show the last wallpapers
+    if command == Some("history") {
+        debug!("Found history argument, showing the last wallpapers");
+        let count = args.get(1).map_or(Ok(10), |count| count.parse())?;
+        return show_history(count);
+    }
+
+    // if the first argument is gallery, make an HTML page of the last wallpapers
+    if command == Some("gallery") {
+        debug!("Found gallery argument, writing the gallery of the history");
+        return gallery::write(args.get(1).map(Path::new));
+    }
+
+    // if the first argument is favorite or block, keep the image or never show it again
+    if command == Some("favorite") {
+        debug!("Found favorite argument, editing the favorites");
+        return edit_image_list(ImageList::Favorites, &args[1..]);
+    }
+    if command == Some("block") {
+        debug!("Found block argument, editing the blocklist");
+        return edit_image_list(ImageList::Blocklist, &args[1..]);
+    }
+
+    // if the first argument is export-settings or import-settings, move the settings to another computer
+    if let Some(action @ ("export-settings" | "import-settings")) = command {
+        debug!("Found {action} argument, moving the settings");
+        let file = Path::new(args.get(1).ok_or_else(|| format!("Usage: {action} FILE"))?);
+        return match action {
+            "export-settings" => settings_bundle::export(file),
+            _ => settings_bundle::import(file),
+        };
+    }
+
+    // if the first argument is --show-filtered, list the images hidden by the content filter
+    if command == Some("--show-filtered") {
+        debug!("Found --show-filtered argument, listing the filtered images");
+        return content_filter::show_filtered();
+    }
+
+    // if the first argument is pause, stop changing the wallpaper until resume or for some time
+    if command == Some("pause") {
+        debug!("Found pause argument, pausing the wallpaper changes");
+        return pause::pause(&args[1..]);
+    }
+
+    // if the first argument is resume, change the wallpaper again
+    if command == Some("resume") {
+        debug!("Found resume argument, resuming the wallpaper changes");
+        return pause::resume();
+    }
+
+    // if the first argument is pin, keep the current wallpaper until unpin or for some time
+    if command == Some("pin") {
+        debug!("Found pin argument, pinning the current wallpaper");
+        return pin::pin(&args[1..]);
+    }
+
+    // if the first argument is unpin, change the wallpaper again
+    if command == Some("unpin") {
+        debug!("Found unpin argument, unpinning the wallpaper");
+        return pin::unpin();
+    }
+
+    // if the first argument is unregister, unregister a scheduled task
+    if command == Some("unregister") {
+        debug!("Found unregister argument, unregistering scheduled task");
+        return unregister_task(&env::current_exe()?, Paths::profile());
+    }
+
+    // if the first argument is compose, label an image without setting it as the background
+    if command == Some("compose") {
+        debug!("Found compose argument, composing an image without a desktop");
+        return compose_image(&args[1..]);
+    }
+
+    // on Linux
+    #[cfg(target_os = "linux")]
+    set_dbus_address();
+
+    // if the first argument is tray, show an icon in the system tray
+    #[cfg(feature = "tray")]
+    if command == Some("tray") {
+        debug!("Found tray argument, showing the tray icon");
+        return run_tray(args.get(1).map(String::as_str));
+    }
+
+    // if the first argument is export-feed, compose the next wallpapers in a folder
+    if command == Some("export-feed") {
+        debug!("Found export-feed argument, exporting the next wallpapers");
+        return export_feed(&args[1..], seed);
+    }
+
+    // if the first argument is prefetch, download many online images before going offline
+    if command == Some("prefetch") {
+        debug!("Found prefetch argument, downloading a batch of online images");
+        return prefetch(&args[1..]);
+    }
+
+    // if the first argument is daemon, stay in the background
+    if command == Some("daemon") {
+        debug!("Found daemon argument, starting the daemon");
+        return daemon::run();
+    }
+
+    change(&args, seed)
+}
+
+/// Sets the address of the D-Bus session bus of the current user, which isn't set in the scheduled tasks.
+#[cfg(target_os = "linux")]
+fn set_dbus_address() {
+    extern "C" {
+        fn getuid() -> u32;
+    }
+    let uid = unsafe { getuid() };
+    debug!("uid is {}", uid);
+    unsafe {
+        env::set_var(
+            "DBUS_SESSION_BUS_ADDRESS",
+            format!("unix:path=/run/user/{uid}/bus"),
+        );
+    }
+    debug!(
+        "Environment variable DBUS_SESSION_BUS_ADDRESS is set to {:?}",
+        env::var("DBUS_SESSION_BUS_ADDRESS")
+    );
+}
+
+/// Shows an icon in the system tray, or adds it to or removes it from the programs
+/// started at logon with the `--enable-autostart` or `--disable-autostart` option.
+///
+/// # Errors
+/// Fails if the option is invalid or if the tray icon or the autostart entry can't be set up.
+#[cfg(feature = "tray")]
+fn run_tray(option: Option<&str>) -> Result<(), Box<dyn Error>> {
+    match option {
+        None => tray::run(),
+        Some("--enable-autostart") => tray::set_autostart(&env::current_exe()?, true),
+        Some("--disable-autostart") => tray::set_autostart(&env::current_exe()?, false),
+        Some(_) => Err("Usage: tray [--enable-autostart | --disable-autostart]".into()),
+    }
+}
+
+/// Downloads a batch of online images that are shown before new ones are downloaded,
+/// 100 of them unless another number is given with `--count`.
+///
+/// # Errors
+/// Fails if the arguments are invalid, if the config can't be loaded or if no image can be downloaded.
+fn prefetch(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let count = match args {
+        [] => 100,
+        [option, count] if option == "--count" => count.parse()?,
+        _ => return Err("Usage: prefetch [--count N]".into()),
+    };
+    offline::prefetch(&Config::load()?, count)
+}
+
+/// Composes the next wallpapers in the folder given in the arguments,
+/// 10 of them unless another number is given with `--count`.
+///
+/// # Errors
+/// Fails if the arguments are invalid, if the config can't be loaded or if the export fails.
+fn export_feed(args: &[String], seed: Option<u64>) -> Result<(), Box<dyn Error>> {
+    const USAGE: &str = "Usage: export-feed DIR [--count N]";
+    let (dir, count) = match args {
+        [dir] => (dir, 10),
+        [dir, option, count] | [option, count, dir] if option == "--count" => (dir, count.parse()?),
+        _ => return Err(USAGE.into()),
+    };
+    let mut config = Config::load()?;
+    config.seed = seed.or(config.seed);
+    feed::export(&config, Path::new(dir), count)
+}
+
+/// Composes the image given with `--input` for a screen of the size given with `--size`
+/// and saves it in the file given with `--output`, without changing the wallpaper.
+///
+/// # Errors
+/// Fails if the arguments are invalid, if the config can't be loaded or if the composition fails.
+fn compose_image(args: &[String]) -> Result<(), Box<dyn Error>> {
+    const USAGE: &str = "Usage: compose --input IMAGE --size WIDTHxHEIGHT --output FILE";
+    let (mut input, mut size, mut output) = (None, None, None);
+    let mut args = args.iter();
+    while let Some(option) = args.next() {
+        let value = args.next().ok_or(USAGE)?;
+        match option.as_str() {
+            "--input" => input = Some(value),
+            "--size" => size = Some(value),
+            "--output" => output = Some(value),
+            _ => return Err(USAGE.into()),
+        }
+    }
+    let (Some(input), Some(size), Some(output)) = (input, size, output) else {
+        return Err(USAGE.into());
+    };
+    let config = Config::load()?;
+    batch::compose_file(
+        &config,
+        Path::new(input),
+        batch::parse_size(size)?,
+        Path::new(output),
+    )?;
+    println!("{}", tr!("compose-saved", path = output));
+    Ok(())
+}
+
+/// Changes the wallpaper (see [`try_change`]) and records the result of the run,
+/// so that the failures of the scheduled task can be noticed.
+///
+/// # Errors
+/// Fails if the arguments are invalid, if the config can't be loaded or if the change fails.
+fn change(args: &[String], seed: Option<u64>) -> Result<(), Box<dyn Error>> {
+    match try_change(args, seed) {
+        Ok(wallpaper) => {
+            last_run::record_success(wallpaper.as_deref());
+            Ok(())
+        }
+        Err(err) => {
+            last_run::record_failure(err.as_ref());
+            Err(err)
+        }
+    }
+}
+
+/// Changes the wallpaper with the given seed, unless the changes are paused, the wallpaper
+/// is pinned, a skip rule matches or (with `catch-up`) no change was missed.
+/// Returns the image set as the wallpaper, or `None` if it wasn't changed.
+///
+/// # Errors
+/// Fails if the arguments are invalid, if the config can't be loaded or if the change fails.
+fn try_change(args: &[String], seed: Option<u64>) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    let command = args.first().map(String::as_str);
+
+    // Don't change the wallpaper during a pause (e.g. a presentation)
+    if pause::is_paused() {
+        info!("Not changing the wallpaper because the changes are paused");
+        return Ok(None);
+    }
+
+    // Keep the pinned wallpaper
+    if pin::is_pinned() {
+        info!("Not changing the wallpaper because it is pinned");
+        return Ok(None);
+    }
+
+    // if the first argument is catch-up, only change the wallpaper if a change was missed
+    if command == Some("catch-up") {
+        let interval = parse_interval(args.get(1).ok_or("Usage: catch-up INTERVAL")?)?;
+        if !catch_up::is_overdue(Duration::from_secs(u64::from(interval) * 60)) {
+            debug!("The wallpaper was changed recently, nothing to catch up");
+            return Ok(None);
+        }
+        info!("A wallpaper change was missed, changing the wallpaper now");
+    }
+
+    // Load configuration
+    let mut config = Config::load()?;
+    config.seed = seed.or(config.seed);
+
+    // Don't change the wallpaper in some conditions (e.g. during a presentation)
+    if let Some(rule) = skip_rules::get_matching_rule(&config) {
+        info!("Not changing the wallpaper because of the skip rule {rule:?}");
+        return Ok(None);
+    }
+
+    // Load image data
+    let mut image_data = image_list::ImageData::load()?;
+
+    let images = change_wallpaper(&config, &mut image_data)?;
+
+    // Keep the button of the notification working for a while
+    notification::wait_for_actions();
+
+    Ok(images.first().map(|image| image.get_path()))
+}
+
+/// Shows whether the given `script_path` is registered as a scheduled task with the current profile,
+/// when it runs and which command is run.
+///
+/// # Errors
+/// Fails if the scheduled task can't be read.
+fn show_schedule_status(script_path: &Path) -> Result<(), Box<dyn Error>> {
+    let Some(status) = get_task_status(script_path, Paths::profile())? else {
+        println!("{}", tr!("schedule-not-registered"));
+        return Ok(());
+    };
+    println!("{}", tr!("schedule-registered", kind = status.kind));
+    println!("{}", tr!("schedule-schedule", schedule = status.schedule));
+    println!("{}", tr!("schedule-command", command = status.command));
+    if status.script_path != script_path {
+        println!(
+            "{}",
+            tr!(
+                "schedule-moved",
+                old = status.script_path.display(),
+                new = script_path.display()
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Shows where a wallpaper (by default the most recent one) comes from.
+///
+/// # Errors
+/// Fails if there is no wallpaper or if its metadata can't be read.
+fn what_is(path: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
+    let path = match path {
+        Some(path) => path,
+        None => get_last_wallpaper()?,
+    };
+
+    let fields = provenance::read(&path)?;
+    println!("{}", path.display());
+    if fields.is_empty() {
+        println!("{}", tr!("whatis-no-provenance"));
+    }
+    for (keyword, value) in fields {
+        println!("{keyword}: {value}");
+    }
+    Ok(())
+}
+
+/// Shows the last images shown as the wallpaper, the most recent first.
+///
+/// # Errors
+/// Fails if the history can't be read.
+fn show_history(count: u32) -> Result<(), Box<dyn Error>> {
+    let entries = Database::open()?.get_history(count)?;
+    if entries.is_empty() {
+        println!("{}", tr!("history-empty"));
+    }
+    for entry in entries {
+        println!(
+            "{}  {}",
+            entry
+                .shown_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M"),
+            entry.source
+        );
+        if !entry.description.is_empty() {
+            println!("    {}", entry.description.replace('\n', " / "));
+        }
+    }
+    Ok(())
+}
+
+/// Adds an image (by default the current wallpaper) to the favorites or to the blocklist,
+/// or removes it if the first argument is `--remove`.
+///
+/// # Errors
+/// Fails if there is no current wallpaper, if the list can't be changed
+/// or if the config of the favorites folder can't be loaded.
+fn edit_image_list(list: ImageList, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let remove = args.first().is_some_and(|arg| arg == "--remove");
+    let database = Database::open()?;
+    let (path, source) = match args.get(usize::from(remove)) {
+        Some(path) => {
+            let path = path::absolute(path)?;
+            let source = path.to_string_lossy().to_string();
+            (path, source)
+        }
+        None => {
+            let entry = database
+                .get_history(1)?
+                .pop()
+                .ok_or("No wallpaper has been shown yet")?;
+            (entry.path, entry.source)
+        }
+    };
+    if remove {
+        let id = if database.remove_from_list(list, &path)? {
+            format!("list-removed-{}", list.name())
+        } else {
+            format!("list-missing-{}", list.name())
+        };
+        println!("{}", tr!(&id, path = path.display()));
+    } else {
+        database.add_to_list(list, &path, &source)?;
+        let id = format!("list-added-{}", list.name());
+        println!("{}", tr!(&id, path = path.display()));
+        // Keep a copy of the favorites outside of the cache (e.g. in a synced folder)
+        if matches!(list, ImageList::Favorites) {
+            let config = Config::load()?;
+            if let Err(err) = favorites_backup::backup(&config, &path, &source) {
+                error!("Couldn't copy the favorite: {err}");
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,59 +1,1139 @@
 //! Utility functions to manage the config.
-use log::debug;
-use serde::Deserialize;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, Local, NaiveTime, Utc};
+use log::{debug, error, info, warn, LevelFilter};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use crate::http;
+use crate::location::get_location;
 use crate::paths::Paths;
+use crate::skip_rules;
+use crate::solar::TimeOfDay;
+use crate::theme::is_dark_mode;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// A coordinate of a text, from the top left corner of the image.
+pub(crate) enum Coordinate {
+    /// A number of pixels (`120`).
+    Pixels(i32),
+    /// A percentage of the free space around the text (`50%`).
+    Percentage(f32),
+}
+
+impl Coordinate {
+    /// Parses a coordinate in pixels (`120`) or in percentage of the free space (`50%`).
+    fn parse(coordinate: &str) -> Option<Self> {
+        let coordinate = coordinate.trim();
+        if let Some(percentage) = coordinate.strip_suffix('%') {
+            Some(Self::Percentage(percentage.trim().parse().ok()?))
+        } else {
+            Some(Self::Pixels(coordinate.parse().ok()?))
+        }
+    }
+
+    /// Returns the coordinate in pixels, given the free space around the text.
+    #[expect(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    pub(crate) fn resolve(self, free_space: i32) -> i32 {
+        match self {
+            Self::Pixels(pixels) => pixels,
+            Self::Percentage(percentage) => (free_space as f32 * percentage / 100.0).round() as i32,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
+/// Where a text is written on the wallpaper.
+pub(crate) enum LabelPosition {
+    /// At the center of the image (`center`).
+    Center,
+    /// In the top left corner (`top_left`).
+    TopLeft,
+    /// In the top right corner (`top_right`).
+    TopRight,
+    /// In the bottom left corner (`bottom_left`).
+    BottomLeft,
+    /// In the bottom right corner (`bottom_right`).
+    BottomRight,
+    /// The text isn't written (`none`).
+    Hidden,
+    /// `x,y` coordinates in pixels (`120,80`) or percentages (`50%,100%`).
+    Coordinates(Coordinate, Coordinate),
+}
+
+impl TryFrom<String> for LabelPosition {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Ok(match value.as_str() {
+            "center" => Self::Center,
+            "top_left" => Self::TopLeft,
+            "top_right" => Self::TopRight,
+            "bottom_left" => Self::BottomLeft,
+            "bottom_right" => Self::BottomRight,
+            "none" => Self::Hidden,
+            _ => {
+                let coordinates = value.split_once(',').and_then(|(x, y)| {
+                    Some(Self::Coordinates(
+                        Coordinate::parse(x)?,
+                        Coordinate::parse(y)?,
+                    ))
+                });
+                coordinates.ok_or_else(|| {
+                    format!(
+                        "the position must be one of center, top_left, top_right, bottom_left, \
+                         bottom_right, none or x,y coordinates (120,80 or 50%,100%); \
+                         found '{value}'"
+                    )
+                })?
+            }
+        })
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+/// A term used to search the online images.
+pub(crate) struct SearchTerm {
+    pub(crate) term: String,
+    /// The hours when the term can be chosen, like `19:00-06:00`, empty to choose it at any time.
+    #[serde(default)]
+    pub(crate) hours: String,
+}
+
+impl SearchTerm {
+    /// Returns `true` if the term can be chosen at the given time.
+    pub(crate) fn is_active(&self, time: NaiveTime) -> bool {
+        self.hours.is_empty()
+            || skip_rules::parse_time_range(&self.hours)
+                .is_some_and(|range| skip_rules::is_between(time, range))
+    }
+}
+
+/// Deserializes the search terms: a list of terms or of `{ term, hours }` tables,
+/// or a comma-separated string like in the older configs.
+///
+/// # Errors
+/// Fails if the value isn't one of these forms.
+fn deserialize_search_terms<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<SearchTerm>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    /// The two accepted forms of a search term.
+    enum Item {
+        /// A term that can be chosen at any time.
+        Term(String),
+        /// A term with its hours.
+        SearchTerm(SearchTerm),
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    /// The two accepted forms of the list.
+    enum List {
+        /// A comma-separated string.
+        String(String),
+        /// A list of terms.
+        List(Vec<Item>),
+    }
+
+    let items = match List::deserialize(deserializer)? {
+        List::String(string) => string
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|term| Item::Term(term.to_string()))
+            .collect(),
+        List::List(list) => list,
+    };
+    Ok(items
+        .into_iter()
+        .map(|item| match item {
+            Item::Term(term) => SearchTerm {
+                term,
+                hours: String::new(),
+            },
+            Item::SearchTerm(search_term) => search_term,
+        })
+        .collect())
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+/// Sources that replace the normal ones in some conditions (empty values are ignored).
+pub(crate) struct SourceOverride {
+    pub(crate) pictures_folder: PathBuf,
+    #[serde(deserialize_with = "deserialize_search_terms")]
+    pub(crate) search_terms: Vec<SearchTerm>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+/// A template filled with the colors of the wallpaper.
+pub(crate) struct PaletteTemplate {
+    /// The path of the template file.
+    pub(crate) template: String,
+    /// The path where the filled template is written.
+    pub(crate) output: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+/// The configuration of the clock widget.
+pub(crate) struct ClockConfig {
+    pub(crate) font_size: u32,
+    /// The `strftime` format of the time.
+    pub(crate) format: String,
+    pub(crate) position: LabelPosition,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            font_size: 96,
+            format: "%H:%M".to_string(),
+            position: LabelPosition::BottomRight,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+/// The configuration of the date widget.
+pub(crate) struct DateConfig {
+    pub(crate) font_size: u32,
+    /// The `strftime` format of the date, the date is written in French if it is empty.
+    pub(crate) format: String,
+    pub(crate) position: LabelPosition,
+}
+
+impl Default for DateConfig {
+    fn default() -> Self {
+        Self {
+            font_size: 48,
+            format: String::new(),
+            position: LabelPosition::BottomLeft,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+/// The configuration of the calendar widget.
+pub(crate) struct CalendarConfig {
+    /// The `strftime` format of the events that last a whole day.
+    pub(crate) all_day_format: String,
+    /// The number of events that are shown.
+    pub(crate) events: usize,
+    pub(crate) font_size: u32,
+    /// The `strftime` format of the other events.
+    pub(crate) format: String,
+    /// The path of the `.ics` file.
+    pub(crate) ics_file: String,
+    pub(crate) position: LabelPosition,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self {
+            all_day_format: "%d/%m".to_string(),
+            events: 3,
+            font_size: 28,
+            format: "%d/%m %H:%M".to_string(),
+            ics_file: String::new(),
+            position: LabelPosition::Center,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+/// The configuration of the system information widget
+/// (hostname, uptime, disk usage and IP address).
+pub(crate) struct SystemInfoConfig {
+    pub(crate) font_size: u32,
+    pub(crate) position: LabelPosition,
+}
+
+impl Default for SystemInfoConfig {
+    fn default() -> Self {
+        Self {
+            font_size: 20,
+            position: LabelPosition::TopLeft,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+/// The configuration of the cross-fade from the previous wallpaper to the new one (Windows only).
+pub(crate) struct CrossFadeConfig {
+    /// The number of blended frames shown between the two wallpapers.
+    pub(crate) frames: u32,
+    /// The duration of the cross-fade, in milliseconds.
+    pub(crate) duration_ms: u32,
+}
+
+impl Default for CrossFadeConfig {
+    fn default() -> Self {
+        Self {
+            frames: 8,
+            duration_ms: 600,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+/// A part of each screen that is darkened or blurred so that the desktop icons or the taskbar
+/// stay readable whatever the photo. The position and the size are percentages of the screen.
+pub(crate) struct DimRegion {
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    pub(crate) width: f32,
+    pub(crate) height: f32,
+    /// How much the region is darkened, between 0 (not at all) and 1 (black).
+    pub(crate) darken: f32,
+    /// The radius of the blur of the region, 0 to disable it.
+    pub(crate) blur: f32,
+}
+
+impl Default for DimRegion {
+    fn default() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            width: 25.0,
+            height: 100.0,
+            darken: 0.4,
+            blur: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+/// The configuration of the looped video with a slow pan and zoom over the photo.
+pub(crate) struct KenBurnsConfig {
+    /// The duration of the video, in seconds.
+    pub(crate) duration: u32,
+    /// The number of frames per second.
+    pub(crate) fps: u32,
+    /// The directory where the video is saved (e.g. a folder watched by Wallpaper Engine),
+    /// the temporary directory if it is empty.
+    pub(crate) output_dir: String,
+    /// How much the image is enlarged at the middle of the video.
+    pub(crate) zoom: f32,
+}
+
+impl Default for KenBurnsConfig {
+    fn default() -> Self {
+        Self {
+            duration: 20,
+            fps: 25,
+            output_dir: String::new(),
+            zoom: 1.2,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+/// The configuration of the slideshow that cross-fades between several wallpapers
+/// (GNOME and Cinnamon only).
+pub(crate) struct SlideshowConfig {
+    /// The number of images in the slideshow.
+    pub(crate) images: usize,
+    /// The time each image is shown, in seconds.
+    pub(crate) duration: u32,
+    /// The duration of the cross-fade between two images, in seconds.
+    pub(crate) transition: u32,
+}
+
+impl Default for SlideshowConfig {
+    fn default() -> Self {
+        Self {
+            images: 5,
+            duration: 300,
+            transition: 5,
+        }
+    }
+}
+
+impl SlideshowConfig {
+    /// Returns the time it takes to show all the images of the slideshow.
+    pub(crate) fn total_duration(&self) -> Duration {
+        let images = u64::try_from(self.images).unwrap_or(1);
+        Duration::from_secs((u64::from(self.duration) + u64::from(self.transition)) * images)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+/// Where the wallpaper changes are announced (e.g. for Home Assistant or an e-ink display).
+pub(crate) struct AnnounceConfig {
+    /// A URL where a JSON description of the new wallpaper is posted.
+    pub(crate) webhook_url: String,
+    /// An `mqtt://[user:password@]host[:port]/topic` URL where the description is published.
+    pub(crate) mqtt_url: String,
+    /// Keep the last message on the MQTT broker for the clients that subscribe later.
+    pub(crate) mqtt_retain: bool,
+}
+
+impl Default for AnnounceConfig {
+    fn default() -> Self {
+        Self {
+            webhook_url: String::new(),
+            mqtt_url: String::new(),
+            mqtt_retain: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+/// Where the logs are written and how many messages are kept.
+pub(crate) struct LoggingConfig {
+    /// The level of the messages shown in the console (`off`, `error`, `warn`, `info`, `debug`
+    /// or `trace`), changed by the `-v` and `-q` arguments.
+    pub(crate) console_level: LevelFilter,
+    /// Show the messages in the console as JSON objects (one per line) instead of plain text.
+    pub(crate) console_json: bool,
+    /// Write the logs to a file every day (it can be disabled on flash storage).
+    pub(crate) file: bool,
+    /// The level of the messages written to the log files.
+    pub(crate) file_level: LevelFilter,
+    /// The number of days the log files are kept.
+    pub(crate) retention_days: u64,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            console_level: LevelFilter::Info,
+            console_json: false,
+            file: true,
+            file_level: LevelFilter::Debug,
+            retention_days: 7,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+/// The commands run when the wallpaper changes, with environment variables describing it.
+pub(crate) struct HooksConfig {
+    /// The commands run before the new wallpaper is set.
+    pub(crate) pre_change: Vec<String>,
+    /// The commands run after the new wallpaper is set.
+    pub(crate) post_change: Vec<String>,
+}
+
+/// The default config file, with a comment for each option.
+/// `{pictures_folder}` is replaced by the default pictures folder.
+const DEFAULT_CONFIG: &str = include_str!("default_config.toml");
+
+/// Replaces the options of a config with the options of the policy, in the nested tables too
+/// (the lists are replaced entirely). Returns the names of the options of the config that changed.
+fn apply_policy(config: &mut toml::Table, policy: toml::Table, prefix: &str) -> Vec<String> {
+    let mut overridden = Vec::new();
+    for (key, value) in policy {
+        let name = format!("{prefix}{key}");
+        let value = match (config.remove(&key), value) {
+            (Some(toml::Value::Table(mut table)), toml::Value::Table(policy_table)) => {
+                overridden.extend(apply_policy(&mut table, policy_table, &format!("{name}.")));
+                toml::Value::Table(table)
+            }
+            (current, value) => {
+                if current.is_some_and(|current| current != value) {
+                    overridden.push(name);
+                }
+                value
+            }
+        };
+        config.insert(key, value);
+    }
+    overridden
+}
+
+/// The default description of the local images.
+pub(crate) const DEFAULT_LABEL_TEMPLATE: &str = "{filename}\n{date}";
 
 #[derive(Clone, Debug, Deserialize)]
 #[serde(default)]
 /// The configuration of the program.
-pub(crate) struct Config {
+pub struct Config {
+    /// Announce the wallpaper changes with a webhook or an MQTT message.
+    pub(crate) announce: Option<AnnounceConfig>,
     pub(crate) api_key: String,
+    /// The URL of an API compatible with the Unsplash API used instead of it
+    /// (e.g. a local mock server to test the online images without the network).
+    pub(crate) api_url: String,
+    /// Add the images that can't be decoded to the blocklist, so that they aren't chosen again.
+    pub(crate) block_unreadable_images: bool,
+    pub(crate) blur_radius: f32,
+    /// A PEM file with the certificate authorities trusted for HTTPS, instead of the default ones.
+    pub(crate) ca_bundle: String,
+    /// The URL of the HTTP proxy, `HTTPS_PROXY` or `HTTP_PROXY` is used if it is empty.
+    pub(crate) proxy: String,
+    pub(crate) calendar: Option<CalendarConfig>,
+    pub(crate) clock: Option<ClockConfig>,
+    pub(crate) color_filter: String,
+    /// Fade from the previous wallpaper to the new one instead of changing it abruptly.
+    pub(crate) cross_fade: Option<CrossFadeConfig>,
+    pub(crate) dark_brightness: i32,
+    pub(crate) dark_contrast: f32,
+    pub(crate) dark_pictures_folder: PathBuf,
+    #[serde(deserialize_with = "deserialize_search_terms")]
+    pub(crate) dark_search_terms: Vec<SearchTerm>,
+    pub(crate) date: Option<DateConfig>,
+    /// The parts of each screen that are darkened or blurred (e.g. behind the icons or the taskbar).
+    pub(crate) dim_regions: Vec<DimRegion>,
+    /// A folder (e.g. a synced folder) or an rclone remote (`remote:path`) where the favorites
+    /// are copied, empty to keep them only in the cache.
+    pub(crate) favorites_dir: String,
+    /// The images of the folders whose name contains one of these words (case-insensitively)
+    /// are never chosen, e.g. `private` or `work-docs`.
+    pub(crate) filtered_folders: Vec<String>,
+    pub(crate) fit_mode: String,
+    /// How often the images of some subfolders of the pictures folder are chosen
+    /// compared to the other images (which have a weight of 1).
+    pub(crate) folder_weights: BTreeMap<String, f64>,
+    /// The name of an installed font used to write the text, e.g. `Noto Sans`.
+    pub(crate) font_family: String,
+    /// The path of a font file used to write the text, preferred over `font_family`.
+    pub(crate) font_path: String,
     pub(crate) font_size: u32,
+    /// A healthchecks.io-style URL pinged after every run (followed by `/fail` after a failure).
+    pub(crate) healthcheck_url: String,
+    pub(crate) hooks: HooksConfig,
     pub(crate) images_per_download: u32,
-    pub(crate) label_position: String,
-    pub(crate) pictures_folder: String,
-    pub(crate) search_terms: String,
+    /// Make a video wallpaper with a slow pan and zoom over the photo (needs `ffmpeg`).
+    pub(crate) ken_burns: Option<KenBurnsConfig>,
+    /// What is drawn behind the text: `none` (only a shadow), `box` or `blur`.
+    pub(crate) label_background: String,
+    /// The color of the text (`#RRGGBB`), or `auto` to use black or white depending on the background.
+    pub(crate) label_color: String,
+    /// The space between the text and the borders of the screen, in logical pixels.
+    pub(crate) label_margin: u32,
+    /// The index of the monitor where the text is written when spanning, instead of the primary monitor.
+    pub(crate) label_monitor: Option<usize>,
+    pub(crate) label_opacity: f32,
+    pub(crate) label_outline_color: String,
+    /// Where the description is written, `none` to hide it.
+    pub(crate) label_position: LabelPosition,
+    /// The description of the local images, with the `{filename}`, `{date}`, `{camera}`,
+    /// `{location}`, `{place}` and `{caption}` fields. Lines with only missing fields are removed.
+    /// `{filename}` is replaced by the contents of `photo.jpg.txt` or `photo.md` if one exists.
+    pub(crate) label_template: String,
+    /// The language of the messages and of the dates: `auto` (the language of the system),
+    /// `en` or `fr`.
+    pub(crate) language: String,
+    pub(crate) latitude: Option<f64>,
+    pub(crate) logging: LoggingConfig,
+    pub(crate) longitude: Option<f64>,
+    /// The age in days after which the downloaded pictures are deleted, `0` to keep them.
+    pub(crate) max_cache_age_days: u32,
+    /// The maximum size of the downloaded pictures in megabytes, `0` for no limit.
+    pub(crate) max_cache_size_mb: u64,
+    /// The maximum number of pixels of a decoded image in millions, `0` for no limit.
+    /// The larger JPEG images are downscaled while they are decoded and the other ones are skipped.
+    pub(crate) max_decoded_megapixels: u32,
+    /// The maximum download speed of the images in kilobits per second, `0` for no limit.
+    pub(crate) max_download_kbps: u32,
+    /// The number of last shown images that aren't chosen again during `no_repeat_hours`,
+    /// `0` to allow repeats.
+    pub(crate) no_repeat_count: u32,
+    /// How long the last shown images aren't chosen again, in hours.
+    pub(crate) no_repeat_hours: u32,
+    /// Show a desktop notification with the description of the new wallpaper
+    /// and a button to block it.
+    pub(crate) notifications: bool,
+    /// The probability of choosing an online image instead of a local one,
+    /// `0.0` to never use the network.
+    pub(crate) online_probability: f64,
+    /// The format of the generated wallpaper: `png`, `jpeg` or `webp` (lossless).
+    pub(crate) output_format: String,
+    /// The quality of the generated wallpaper in JPEG format, between 1 and 100.
+    pub(crate) output_quality: u8,
+    pub(crate) palette: bool,
+    pub(crate) palette_reload_command: String,
+    pub(crate) palette_templates: Vec<PaletteTemplate>,
+    pub(crate) pictures_folder: PathBuf,
+    /// Choose and compose the next wallpaper at the end of each run,
+    /// so that the next run only has to set it as the background.
+    pub(crate) prefetch_next: bool,
+    /// How often the local images with 1 to 5 stars are chosen compared to the unrated ones
+    /// (which have a weight of 1), empty to ignore the ratings.
+    pub(crate) rating_weights: Vec<f64>,
+    /// A TOML file with the ratings of the local images, used instead of their EXIF ratings.
+    pub(crate) ratings_file: String,
+    /// Find the city where the photos were taken from their GPS coordinates (without Internet).
+    pub(crate) reverse_geocoding: bool,
+    /// The terms used to search the online images, `random` for any image.
+    /// A term can be chosen only at some hours, e.g. `{ term = "city night", hours = "19:00-06:00" }`.
+    #[serde(deserialize_with = "deserialize_search_terms")]
+    pub(crate) search_terms: Vec<SearchTerm>,
+    /// The seed of the random choices, to choose the same images again (e.g. when debugging),
+    /// a random one if it isn't set.
+    pub(crate) seed: Option<u64>,
+    pub(crate) set_accent_color: bool,
+    /// Give a muted shade of the wallpaper to the panel (GNOME with Dash to Panel)
+    /// or to the taskbar (Windows, where the accent color is changed too).
+    pub(crate) set_panel_color: bool,
+    /// Show the online images of a batch in a random order (each one once before any repeats)
+    /// instead of the order of Unsplash.
+    pub(crate) shuffle_online_images: bool,
+    /// Don't change the wallpaper when one of these conditions is met: `fullscreen_app`,
+    /// `on_battery`, `metered_connection` or `between HH:MM-HH:MM`.
+    pub(crate) skip_when: Vec<String>,
+    /// Cross-fade between several wallpapers instead of changing them abruptly.
+    pub(crate) slideshow: Option<SlideshowConfig>,
+    pub(crate) smart_crop: bool,
+    pub(crate) span_monitors: bool,
+    /// Read the description of the new wallpaper aloud with the speech synthesizer of the system.
+    pub(crate) speak_description: bool,
+    pub(crate) system_info: Option<SystemInfoConfig>,
+    /// Report the errors to the developers with Sentry.
+    pub(crate) telemetry: bool,
+    /// Sources used on a date (`12-25`), a weekday (`monday`), a month (`december`)
+    /// or a season (`winter`).
+    pub(crate) themes: BTreeMap<String, SourceOverride>,
+    /// Sources used at `sunrise`, during the `day`, at `sunset` and at `night`.
+    pub(crate) time_of_day: BTreeMap<String, SourceOverride>,
+    pub(crate) tint_color: String,
+    pub(crate) tint_opacity: f32,
     pub(crate) use_unsplash: bool,
+    /// Download online images when the connection is metered (e.g. when tethering).
+    pub(crate) use_metered_connection: bool,
+    pub(crate) vignette: f32,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            announce: None,
             api_key: String::new(),
+            api_url: String::new(),
+            block_unreadable_images: true,
+            blur_radius: 0.0,
+            ca_bundle: String::new(),
+            proxy: String::new(),
+            calendar: None,
+            clock: None,
+            color_filter: "none".to_string(),
+            cross_fade: None,
+            dark_brightness: -20,
+            dark_contrast: -10.0,
+            dark_pictures_folder: PathBuf::new(),
+            dark_search_terms: Vec::new(),
+            date: None,
+            dim_regions: Vec::new(),
+            favorites_dir: String::new(),
+            filtered_folders: Vec::new(),
+            fit_mode: "fill".to_string(),
+            folder_weights: BTreeMap::new(),
+            font_family: String::new(),
+            font_path: String::new(),
             font_size: 28,
+            healthcheck_url: String::new(),
+            hooks: HooksConfig::default(),
             images_per_download: 10,
-            label_position: "top_right".to_string(),
-            pictures_folder: dirs::picture_dir()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string(),
-            search_terms: String::new(),
+            ken_burns: None,
+            label_background: "none".to_string(),
+            label_color: "#FFFFFF".to_string(),
+            label_margin: 10,
+            label_monitor: None,
+            label_opacity: 1.0,
+            label_outline_color: "#000000".to_string(),
+            label_position: LabelPosition::TopRight,
+            label_template: DEFAULT_LABEL_TEMPLATE.to_string(),
+            language: "auto".to_string(),
+            latitude: None,
+            logging: LoggingConfig::default(),
+            longitude: None,
+            max_cache_age_days: 30,
+            max_cache_size_mb: 500,
+            max_decoded_megapixels: 100,
+            max_download_kbps: 0,
+            no_repeat_count: 20,
+            no_repeat_hours: 24,
+            notifications: false,
+            online_probability: 0.5,
+            output_format: "png".to_string(),
+            output_quality: 92,
+            palette: false,
+            palette_reload_command: String::new(),
+            palette_templates: Vec::new(),
+            pictures_folder: dirs::picture_dir().unwrap_or_default(),
+            prefetch_next: true,
+            rating_weights: Vec::new(),
+            ratings_file: String::new(),
+            reverse_geocoding: false,
+            search_terms: Vec::new(),
+            seed: None,
+            set_accent_color: false,
+            set_panel_color: false,
+            shuffle_online_images: false,
+            skip_when: Vec::new(),
+            slideshow: None,
+            smart_crop: true,
+            span_monitors: false,
+            speak_description: false,
+            system_info: None,
+            telemetry: true,
+            themes: BTreeMap::new(),
+            time_of_day: BTreeMap::new(),
+            tint_color: String::new(),
+            tint_opacity: 0.3,
             use_unsplash: true,
+            use_metered_connection: false,
+            vignette: 0.0,
         }
     }
 }
 
+/// Returns the meteorological season of a date: `winter`, `spring`, `summer` or `autumn`.
+/// The seasons are reversed in the southern hemisphere (with a negative latitude).
+pub(crate) fn get_season(date: DateTime<Local>, latitude: Option<f64>) -> &'static str {
+    // Meteorological seasons of the northern hemisphere, starting in January
+    const SEASONS: [&str; 12] = [
+        "winter", "winter", "spring", "spring", "spring", "summer", "summer", "summer", "autumn",
+        "autumn", "autumn", "winter",
+    ];
+
+    let month = date.month0() as usize;
+    if latitude.is_some_and(|latitude| latitude < 0.0) {
+        SEASONS[(month + 6) % 12]
+    } else {
+        SEASONS[month]
+    }
+}
+
 impl Config {
-    /// Loads the config from the `config.toml` file.
+    /// Returns the config with the pictures folder and search terms of the override
+    /// replacing the normal ones if they are set.
+    pub(crate) fn with_override(&self, sources: &SourceOverride) -> Self {
+        let mut config = self.clone();
+        if !sources.pictures_folder.as_os_str().is_empty() {
+            config.pictures_folder.clone_from(&sources.pictures_folder);
+        }
+        if !sources.search_terms.is_empty() {
+            config.search_terms.clone_from(&sources.search_terms);
+        }
+        config
+    }
+
+    /// Returns the most specific theme that matches the given date, with its name.
+    ///
+    /// A date (`12-25`) is more specific than a month (`december`),
+    /// which is more specific than a weekday (`monday`), which is more specific than a season (`winter`).
+    pub(crate) fn get_theme(&self, date: DateTime<Local>) -> Option<(&str, &SourceOverride)> {
+        const MONTHS: [&str; 12] = [
+            "january",
+            "february",
+            "march",
+            "april",
+            "may",
+            "june",
+            "july",
+            "august",
+            "september",
+            "october",
+            "november",
+            "december",
+        ];
+        const WEEKDAYS: [&str; 7] = [
+            "monday",
+            "tuesday",
+            "wednesday",
+            "thursday",
+            "friday",
+            "saturday",
+            "sunday",
+        ];
+        let month = date.month0() as usize;
+        let date_key = date.format("%m-%d").to_string();
+        [
+            date_key.as_str(),
+            MONTHS[month],
+            WEEKDAYS[date.weekday().num_days_from_monday() as usize],
+            get_season(date, self.latitude),
+        ]
+        .into_iter()
+        .find_map(|name| self.themes.get_key_value(name))
+        .map(|(name, sources)| (name.as_str(), sources))
+    }
+
+    /// Returns the config to use now: the sources of the current theme, of the current time of day
+    /// and of the dark mode replace the normal ones if they are set.
+    pub(crate) fn resolve(&self) -> Self {
+        let mut config = self.clone();
+
+        if let Some((name, sources)) = self.get_theme(Local::now()) {
+            debug!("Using theme {:?}", name);
+            config = config.with_override(sources);
+        }
+
+        if !self.time_of_day.is_empty() {
+            if let Some(location) = get_location(self) {
+                let time_of_day = TimeOfDay::at(Utc::now(), location);
+                debug!("Time of day: {:?}", time_of_day);
+                if let Some(sources) = self.time_of_day.get(time_of_day.name()) {
+                    config = config.with_override(sources);
+                }
+            }
+        }
+
+        if is_dark_mode() {
+            config = config.with_override(&SourceOverride {
+                pictures_folder: self.dark_pictures_folder.clone(),
+                search_terms: self.dark_search_terms.clone(),
+            });
+        }
+
+        config
+    }
+
+    /// Checks that the options with a fixed set of values have one of them.
     ///
     /// # Errors
-    /// Fails if the config directory can't be determined or if the file is malformed or can't be read.
-    pub(crate) fn load() -> Result<Self, Box<dyn Error>> {
-        let config_path = Paths::config_file();
-        debug!("Config path: {:?}", config_path);
+    /// Fails with a message that lists the valid values of the first invalid option.
+    pub(crate) fn validate(&self) -> Result<(), Box<dyn Error>> {
+        let options: [(&str, &str, &[&str]); 5] = [
+            (
+                "color_filter",
+                &self.color_filter,
+                &["none", "grayscale", "sepia"],
+            ),
+            (
+                "fit_mode",
+                &self.fit_mode,
+                &["fill", "fit", "stretch", "center", "blur_fill"],
+            ),
+            (
+                "label_background",
+                &self.label_background,
+                &["none", "box", "blur"],
+            ),
+            ("language", &self.language, &["auto", "en", "fr"]),
+            (
+                "output_format",
+                &self.output_format,
+                &["png", "jpeg", "jpg", "webp"],
+            ),
+        ];
+        for (name, value, valid_values) in options {
+            if !valid_values.contains(&value) {
+                return Err(format!(
+                    "{name} must be one of {}; found '{value}'",
+                    valid_values.join(", ")
+                )
+                .into());
+            }
+        }
+        if let Some(rule) = self
+            .skip_when
+            .iter()
+            .find(|rule| !skip_rules::is_valid_rule(rule))
+        {
+            return Err(format!(
+                "skip_when must only contain fullscreen_app, on_battery, metered_connection \
+                 or \"between HH:MM-HH:MM\"; found '{rule}'"
+            )
+            .into());
+        }
+        if let Some(search_term) = self
+            .search_terms
+            .iter()
+            .chain(&self.dark_search_terms)
+            .chain(
+                self.themes
+                    .values()
+                    .chain(self.time_of_day.values())
+                    .flat_map(|sources| &sources.search_terms),
+            )
+            .find(|search_term| {
+                !search_term.hours.is_empty()
+                    && skip_rules::parse_time_range(&search_term.hours).is_none()
+            })
+        {
+            return Err(format!(
+                "The hours of the search term '{}' must be like \"HH:MM-HH:MM\"; found '{}'",
+                search_term.term, search_term.hours
+            )
+            .into());
+        }
+        if !self.rating_weights.is_empty() && self.rating_weights.len() != 5 {
+            return Err("rating_weights must contain the weights of 1 to 5 stars".into());
+        }
+        if let Some(weight) = self
+            .rating_weights
+            .iter()
+            .chain(self.folder_weights.values())
+            .find(|weight| !(weight.is_finite() && **weight >= 0.0))
+        {
+            return Err(format!("The weights must be positive numbers; found {weight}").into());
+        }
+        if let Some((name, format)) = self.find_invalid_date_format() {
+            return Err(format!(
+                "{name} must be a strftime format like \"%H:%M\"; found '{format}'"
+            )
+            .into());
+        }
+        for region in &self.dim_regions {
+            let percentages = [region.x, region.y, region.width, region.height];
+            if !percentages
+                .iter()
+                .all(|value| (0.0..=100.0).contains(value))
+            {
+                return Err(format!(
+                    "The position and the size of the dim_regions must be percentages \
+                     between 0 and 100; found {percentages:?}"
+                )
+                .into());
+            }
+            if !(0.0..=1.0).contains(&region.darken)
+                || !(region.blur.is_finite() && region.blur >= 0.0)
+            {
+                return Err(format!(
+                    "The darken value of the dim_regions must be between 0.0 and 1.0 \
+                     and their blur must be positive; found {} and {}",
+                    region.darken, region.blur
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
 
-        if !config_path.exists() {
-            debug!("Config file not found, using default values");
-            return Ok(Self::default());
+    /// Returns the name and the value of the first `strftime` format of the widgets
+    /// that chrono can't use (it would panic when the widget is drawn).
+    fn find_invalid_date_format(&self) -> Option<(&'static str, &str)> {
+        let formats = [
+            (
+                "clock.format",
+                self.clock.as_ref().map(|clock| &clock.format),
+            ),
+            ("date.format", self.date.as_ref().map(|date| &date.format)),
+            (
+                "calendar.format",
+                self.calendar.as_ref().map(|calendar| &calendar.format),
+            ),
+            (
+                "calendar.all_day_format",
+                self.calendar
+                    .as_ref()
+                    .map(|calendar| &calendar.all_day_format),
+            ),
+        ];
+        formats.into_iter().find_map(|(name, format)| {
+            let format = format?;
+            StrftimeItems::new(format)
+                .any(|item| item == Item::Error)
+                .then_some((name, format.as_str()))
+        })
+    }
+
+    /// Writes the default config with comments in a file, so that the options can be discovered.
+    ///
+    /// # Errors
+    /// Fails if the file can't be written.
+    fn write_default(path: &Path) -> Result<(), Box<dyn Error>> {
+        let pictures_folder = toml::Value::String(
+            Self::default()
+                .pictures_folder
+                .to_string_lossy()
+                .to_string(),
+        );
+        fs::write(
+            path,
+            DEFAULT_CONFIG.replace("{pictures_folder}", &pictures_folder.to_string()),
+        )?;
+        Ok(())
+    }
+
+    /// Parses the content of a config file and applies the policy file of the administrators
+    /// over it. Returns the config and the options of the file that were overridden by the policy.
+    ///
+    /// # Errors
+    /// Fails if the config or the policy file is malformed or if the policy file can't be read.
+    fn parse(contents: &str) -> Result<(Self, Vec<String>), Box<dyn Error>> {
+        let mut table: toml::Table = toml::from_str(contents)?;
+        let policy_path = Paths::policy_file();
+        let mut overridden = Vec::new();
+        if policy_path.exists() {
+            let policy: toml::Table = toml::from_str(&fs::read_to_string(&policy_path)?)
+                .map_err(|err| format!("Invalid policy file {policy_path:?}: {err}"))?;
+            overridden = apply_policy(&mut table, policy, "");
         }
-        debug!("Loading config");
-        let config_contents = fs::read_to_string(config_path)?;
+        Ok((toml::Value::Table(table).try_into()?, overridden))
+    }
+
+    /// Reads the config file without validating it, for the options that are needed
+    /// before the logs and the error reporting are set up (the policy is applied,
+    /// even if there is no config file). Returns `None` if it can't be read.
+    pub(crate) fn read_unvalidated() -> Option<Self> {
+        let contents = fs::read_to_string(Paths::config_file().ok()?).unwrap_or_default();
+        Self::parse(&contents).ok().map(|(config, _)| config)
+    }
+
+    /// Loads the config from the `config.toml` file, with the options of the policy file
+    /// of the administrators, which can't be changed in it.
+    ///
+    /// # Errors
+    /// Fails if the config directory can't be determined, if the file can't be read
+    /// or if it (or the policy file) is malformed or has invalid values.
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let config_path = Paths::config_file()?;
+        debug!("Config path: {:?}", config_path);
+
+        let config_contents = if config_path.exists() {
+            debug!("Loading config");
+            fs::read_to_string(config_path)?
+        } else if Paths::is_system() {
+            // The system-wide config is written by the administrators
+            warn!("The system-wide config {config_path:?} doesn't exist, using the default config");
+            String::new()
+        } else {
+            info!("Config file not found, writing the default config in {config_path:?}");
+            if let Err(err) = Self::write_default(config_path) {
+                error!("Couldn't write the default config: {err}");
+            }
+            String::new()
+        };
         debug!("Config length: {}", config_contents.len());
-        let config = toml::from_str(&config_contents)?;
+        let (config, overridden) = Self::parse(&config_contents)?;
+        for option in overridden {
+            warn!("The {option:?} option is overridden by the policy of the administrators");
+        }
         debug!("Config loaded: {:?}", config);
+        config.validate()?;
+        http::configure(&config)?;
         Ok(config)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use toml::Value;
+
+    use super::*;
+
+    /// Returns the terms and the hours of the search terms of a source override.
+    fn parse_search_terms(source: &str) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        Ok(toml::from_str::<SourceOverride>(source)?
+            .search_terms
+            .into_iter()
+            .map(|search_term| (search_term.term, search_term.hours))
+            .collect())
+    }
+
+    #[test]
+    fn label_position_keywords() {
+        assert_eq!(
+            LabelPosition::try_from("top_left".to_string()),
+            Ok(LabelPosition::TopLeft)
+        );
+        assert_eq!(
+            LabelPosition::try_from("none".to_string()),
+            Ok(LabelPosition::Hidden)
+        );
+    }
+
+    #[test]
+    fn label_position_coordinates() {
+        assert_eq!(
+            LabelPosition::try_from("120, 50%".to_string()),
+            Ok(LabelPosition::Coordinates(
+                Coordinate::Pixels(120),
+                Coordinate::Percentage(50.0)
+            ))
+        );
+    }
+
+    #[test]
+    fn invalid_label_positions() {
+        for position in ["middle", "120", "10,abc", "%,10"] {
+            assert!(
+                LabelPosition::try_from(position.to_string()).is_err(),
+                "{position}"
+            );
+        }
+    }
+
+    #[test]
+    fn search_terms_string() {
+        assert_eq!(
+            parse_search_terms("search_terms = \"nature, , city \"").unwrap(),
+            [
+                ("nature".to_string(), String::new()),
+                ("city".to_string(), String::new())
+            ]
+        );
+    }
+
+    #[test]
+    fn search_terms_list() {
+        assert_eq!(
+            parse_search_terms(
+                "search_terms = [\"nature\", { term = \"stars\", hours = \"19:00-06:00\" }]"
+            )
+            .unwrap(),
+            [
+                ("nature".to_string(), String::new()),
+                ("stars".to_string(), "19:00-06:00".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_search_terms() {
+        assert!(parse_search_terms("search_terms = 42").is_err());
+        assert!(parse_search_terms("search_terms = [{ hours = \"19:00-06:00\" }]").is_err());
+    }
+
+    #[test]
+    fn policy_overrides_nested_options() {
+        let mut config: toml::Table = toml::from_str(
+            "notifications = true\n\
+             online_probability = 0.5\n\
+             [clock]\n\
+             format = \"%H:%M\"\n\
+             size = 40",
+        )
+        .unwrap();
+        let policy: toml::Table = toml::from_str(
+            "notifications = true\n\
+             telemetry = false\n\
+             [clock]\n\
+             format = \"%I:%M\"",
+        )
+        .unwrap();
+        let overridden = apply_policy(&mut config, policy, "");
+        assert_eq!(overridden, ["clock.format"]);
+        assert_eq!(config["online_probability"].as_float(), Some(0.5));
+        assert_eq!(config["telemetry"].as_bool(), Some(false));
+        assert_eq!(config["clock"]["format"].as_str(), Some("%I:%M"));
+        assert_eq!(config["clock"]["size"].as_integer(), Some(40));
+    }
+
+    #[test]
+    fn policy_replaces_lists() {
+        let mut config: toml::Table =
+            toml::from_str("search_terms = [\"nature\", \"city\"]").unwrap();
+        let policy: toml::Table = toml::from_str("search_terms = [\"office\"]").unwrap();
+        assert_eq!(apply_policy(&mut config, policy, ""), ["search_terms"]);
+        let search_terms = config["search_terms"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(Value::as_str)
+            .collect::<Vec<_>>();
+        assert_eq!(search_terms, [Some("office")]);
+    }
+}
@@ -0,0 +1,54 @@
+//! The content filter of the local images: the images of the folders whose name contains
+//! one of the `filtered_folders` of the config (e.g. `private` or `work-docs`) are never chosen,
+//! so that they can't appear by accident on a shared screen.
+use std::error::Error;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::i18n::tr;
+use crate::image_list::get_images;
+use crate::local_index::get_pictures_folders;
+
+/// Returns the name of the folder of the image that matches the content filter,
+/// or `None` if the image can be chosen. Only the folders inside the pictures folder are checked.
+pub(crate) fn get_filtered_folder(
+    config: &Config,
+    pictures_dir: &Path,
+    path: &Path,
+) -> Option<String> {
+    let folders = path.strip_prefix(pictures_dir).ok()?.parent()?;
+    folders
+        .iter()
+        .map(|folder| folder.to_string_lossy())
+        .find(|folder| {
+            let folder = folder.to_lowercase();
+            config
+                .filtered_folders
+                .iter()
+                .any(|term| !term.is_empty() && folder.contains(&term.to_lowercase()))
+        })
+        .map(|folder| folder.to_string())
+}
+
+/// Prints the images of the pictures folders that are never chosen because of the content filter,
+/// with the folder that matched.
+///
+/// # Errors
+/// Fails if the config can't be loaded or if a pictures folder can't be indexed.
+pub(crate) fn show_filtered() -> Result<(), Box<dyn Error>> {
+    let config = Config::load()?;
+    let mut count: usize = 0;
+    for pictures_dir in get_pictures_folders(&config) {
+        for path in get_images(&pictures_dir)? {
+            if let Some(folder) = get_filtered_folder(&config, &pictures_dir, &path) {
+                println!(
+                    "{}",
+                    tr!("filtered-image", path = path.display(), folder = folder)
+                );
+                count += 1;
+            }
+        }
+    }
+    println!("{}", tr!("filtered-count", count = count));
+    Ok(())
+}
@@ -0,0 +1,87 @@
+//! The D-Bus service that controls the daemon on Linux.
+use std::error::Error;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+use zbus::blocking::{connection, Connection};
+use zbus::{fdo, interface};
+
+use super::{get_current, send, Request};
+use crate::paths::Paths;
+
+/// The well-known name of the service.
+const BUS_NAME: &str = "io.github.lfavole.WallpaperChanger";
+
+/// The path of the object that receives the calls.
+const OBJECT_PATH: &str = "/io/github/lfavole/WallpaperChanger";
+
+/// The connection to the session bus, which answers the calls as long as it is kept.
+pub(crate) type Server = Connection;
+
+/// Returns the well-known name of the service of the current profile.
+fn get_bus_name() -> String {
+    match Paths::profile() {
+        // The elements of a bus name can't start with a digit
+        Some(profile) => format!("{BUS_NAME}.profile_{}", profile.replace('-', "_")),
+        None => BUS_NAME.to_string(),
+    }
+}
+
+/// The object that forwards the calls to the daemon.
+struct Controller {
+    /// Sends the requests to the daemon.
+    sender: Sender<Request>,
+}
+
+#[interface(name = "io.github.lfavole.WallpaperChanger")]
+impl Controller {
+    /// Changes the wallpaper now.
+    fn next(&self) -> fdo::Result<()> {
+        send(&self.sender, Request::Next).map_err(fdo::Error::Failed)
+    }
+
+    /// Goes back to the previous wallpaper.
+    fn previous(&self) -> fdo::Result<()> {
+        send(&self.sender, Request::Previous).map_err(fdo::Error::Failed)
+    }
+
+    /// Stops changing the wallpaper periodically.
+    fn pause(&self) -> fdo::Result<()> {
+        send(&self.sender, Request::Pause).map_err(fdo::Error::Failed)
+    }
+
+    /// Changes the wallpaper periodically again.
+    fn resume(&self) -> fdo::Result<()> {
+        send(&self.sender, Request::Resume).map_err(fdo::Error::Failed)
+    }
+
+    /// Changes the interval between two wallpaper changes, in seconds.
+    fn set_interval(&self, seconds: u32) -> fdo::Result<()> {
+        if seconds == 0 {
+            return Err(fdo::Error::InvalidArgs(
+                "The interval must be positive".to_string(),
+            ));
+        }
+        send(
+            &self.sender,
+            Request::SetInterval(Duration::from_secs(seconds.into())),
+        )
+        .map_err(fdo::Error::Failed)
+    }
+
+    /// Returns the sources of the current images (paths or URLs).
+    fn current(&self) -> fdo::Result<Vec<String>> {
+        get_current(&self.sender).map_err(fdo::Error::Failed)
+    }
+}
+
+/// Registers the D-Bus service on the session bus.
+///
+/// # Errors
+/// Fails if the session bus can't be reached or if the name is already taken
+/// (e.g. by another daemon).
+pub(super) fn serve(sender: Sender<Request>) -> Result<Server, Box<dyn Error>> {
+    Ok(connection::Builder::session()?
+        .name(get_bus_name())?
+        .serve_at(OBJECT_PATH, Controller { sender })?
+        .build()?)
+}
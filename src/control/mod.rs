@@ -0,0 +1,68 @@
+//! The control interface of the daemon, which lets desktop widgets and keyboard shortcuts drive it:
+//! a D-Bus service on Linux and a named pipe on Windows.
+use std::error::Error;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+#[cfg(target_os = "linux")]
+mod linux;
+
+#[cfg(target_os = "linux")]
+use linux as platform;
+
+#[cfg(target_os = "windows")]
+mod windows;
+
+#[cfg(target_os = "windows")]
+use windows as platform;
+
+pub(crate) use platform::Server;
+
+/// A request sent to the daemon through the control interface.
+pub(crate) enum Request {
+    /// Change the wallpaper now.
+    Next,
+    /// Go back to the previous wallpaper.
+    Previous,
+    /// Stop changing the wallpaper periodically.
+    Pause,
+    /// Change the wallpaper periodically again.
+    Resume,
+    /// Change the interval between two wallpaper changes.
+    SetInterval(Duration),
+    /// Send the sources of the current images (paths or URLs).
+    Current(Sender<Vec<String>>),
+}
+
+/// Starts the control interface. The requests are sent to the returned receiver
+/// as long as the returned server is kept.
+///
+/// # Errors
+/// Fails if the D-Bus name can't be acquired or if the named pipe can't be created.
+pub(crate) fn start() -> Result<(Server, Receiver<Request>), Box<dyn Error>> {
+    let (sender, receiver) = mpsc::channel();
+    let server = platform::serve(sender)?;
+    Ok((server, receiver))
+}
+
+/// Sends a request to the daemon.
+///
+/// # Errors
+/// Fails if the daemon has stopped.
+fn send(sender: &Sender<Request>, request: Request) -> Result<(), String> {
+    sender
+        .send(request)
+        .map_err(|_| "The daemon has stopped".to_string())
+}
+
+/// Asks the daemon for the sources of the current images and waits for the answer.
+///
+/// # Errors
+/// Fails if the daemon has stopped.
+fn get_current(sender: &Sender<Request>) -> Result<Vec<String>, String> {
+    let (reply_sender, reply_receiver) = mpsc::channel();
+    send(sender, Request::Current(reply_sender))?;
+    reply_receiver
+        .recv()
+        .map_err(|_| "The daemon has stopped".to_string())
+}
@@ -0,0 +1,172 @@
+//! The named pipe that controls the daemon on Windows.
+//!
+//! Each client sends one command (`Next`, `Previous`, `Pause`, `Resume`, `SetInterval 600`
+//! or `Current`) and receives `OK`, the current images (one per line) or an error.
+use log::debug;
+use std::error::Error;
+use std::io;
+use std::ptr;
+use std::sync::mpsc::Sender;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::{get_current, send, Request};
+use crate::paths::Paths;
+
+/// The thread that answers the clients of the pipe.
+pub(crate) type Server = JoinHandle<()>;
+
+/// The pipe can be read and written.
+const PIPE_ACCESS_DUPLEX: u32 = 0x3;
+/// The data is written as messages.
+const PIPE_TYPE_MESSAGE: u32 = 0x4;
+/// The data is read as messages.
+const PIPE_READMODE_MESSAGE: u32 = 0x2;
+/// Only the local clients can connect.
+const PIPE_REJECT_REMOTE_CLIENTS: u32 = 0x8;
+/// The client connected before the call to `ConnectNamedPipe`.
+const ERROR_PIPE_CONNECTED: i32 = 535;
+/// The handle returned when the pipe can't be created.
+const INVALID_HANDLE_VALUE: isize = -1;
+/// The size of the buffers of the pipe, more than enough for a command.
+const BUFFER_SIZE: u32 = 4096;
+
+extern "system" {
+    fn CreateNamedPipeW(
+        lpName: *const u16,
+        dwOpenMode: u32,
+        dwPipeMode: u32,
+        nMaxInstances: u32,
+        nOutBufferSize: u32,
+        nInBufferSize: u32,
+        nDefaultTimeOut: u32,
+        lpSecurityAttributes: *const u8,
+    ) -> isize;
+    fn ConnectNamedPipe(hNamedPipe: isize, lpOverlapped: *mut u8) -> i32;
+    fn DisconnectNamedPipe(hNamedPipe: isize) -> i32;
+    fn FlushFileBuffers(hFile: isize) -> i32;
+    fn ReadFile(
+        hFile: isize,
+        lpBuffer: *mut u8,
+        nNumberOfBytesToRead: u32,
+        lpNumberOfBytesRead: *mut u32,
+        lpOverlapped: *mut u8,
+    ) -> i32;
+    fn WriteFile(
+        hFile: isize,
+        lpBuffer: *const u8,
+        nNumberOfBytesToWrite: u32,
+        lpNumberOfBytesWritten: *mut u32,
+        lpOverlapped: *mut u8,
+    ) -> i32;
+}
+
+/// Returns the name of the pipe of the current profile.
+fn get_pipe_name() -> String {
+    match Paths::profile() {
+        Some(profile) => format!(r"\\.\pipe\wallpaper-changer-rs-{profile}"),
+        None => r"\\.\pipe\wallpaper-changer-rs".to_string(),
+    }
+}
+
+/// Runs a command received through the pipe and returns the answer.
+fn run_command(sender: &Sender<Request>, command: &str) -> String {
+    let command = command.trim();
+    let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+    let result = match name {
+        "Next" => send(sender, Request::Next).map(|()| "OK".to_string()),
+        "Previous" => send(sender, Request::Previous).map(|()| "OK".to_string()),
+        "Pause" => send(sender, Request::Pause).map(|()| "OK".to_string()),
+        "Resume" => send(sender, Request::Resume).map(|()| "OK".to_string()),
+        "SetInterval" => match argument.trim().parse::<u64>() {
+            Ok(seconds) if seconds > 0 => {
+                send(sender, Request::SetInterval(Duration::from_secs(seconds)))
+                    .map(|()| "OK".to_string())
+            }
+            _ => Err("The interval must be a positive number of seconds".to_string()),
+        },
+        "Current" => get_current(sender).map(|sources| sources.join("\n")),
+        _ => Err(format!("Unknown command {name:?}")),
+    };
+    result.unwrap_or_else(|err| format!("Error: {err}"))
+}
+
+/// Reads a command from the connected client and writes the answer.
+///
+/// # Errors
+/// Fails if the pipe can't be read or written.
+fn answer_client(pipe: isize, sender: &Sender<Request>) -> Result<(), io::Error> {
+    let mut buffer = [0; BUFFER_SIZE as usize];
+    let mut read = 0;
+    if unsafe {
+        ReadFile(
+            pipe,
+            buffer.as_mut_ptr(),
+            BUFFER_SIZE,
+            &mut read,
+            ptr::null_mut(),
+        )
+    } == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+
+    let command = String::from_utf8_lossy(&buffer[..read as usize]);
+    let answer = run_command(sender, &command) + "\n";
+    let length = u32::try_from(answer.len()).map_err(io::Error::other)?;
+    let mut written = 0;
+    if unsafe { WriteFile(pipe, answer.as_ptr(), length, &mut written, ptr::null_mut()) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // Wait for the client to read the answer before disconnecting it
+    unsafe {
+        FlushFileBuffers(pipe);
+    }
+    Ok(())
+}
+
+/// Creates the named pipe and answers its clients in a background thread.
+///
+/// # Errors
+/// Fails if the pipe can't be created (e.g. if another daemon uses it).
+pub(super) fn serve(sender: Sender<Request>) -> Result<Server, Box<dyn Error>> {
+    let name = get_pipe_name()
+        .encode_utf16()
+        .chain([0])
+        .collect::<Vec<_>>();
+    let pipe = unsafe {
+        CreateNamedPipeW(
+            name.as_ptr(),
+            PIPE_ACCESS_DUPLEX,
+            PIPE_TYPE_MESSAGE | PIPE_READMODE_MESSAGE | PIPE_REJECT_REMOTE_CLIENTS,
+            1,
+            BUFFER_SIZE,
+            BUFFER_SIZE,
+            0,
+            ptr::null(),
+        )
+    };
+    if pipe == INVALID_HANDLE_VALUE {
+        return Err(Box::new(io::Error::last_os_error()));
+    }
+
+    // The same instance of the pipe is reused for each client
+    Ok(thread::spawn(move || loop {
+        let connected = unsafe { ConnectNamedPipe(pipe, ptr::null_mut()) } != 0
+            || io::Error::last_os_error().raw_os_error() == Some(ERROR_PIPE_CONNECTED);
+        if connected {
+            if let Err(err) = answer_client(pipe, &sender) {
+                debug!("Couldn't answer the client of the pipe: {err}");
+            }
+        } else {
+            debug!(
+                "Couldn't wait for a client of the pipe: {}",
+                io::Error::last_os_error()
+            );
+            thread::sleep(Duration::from_secs(1));
+        }
+        unsafe {
+            DisconnectNamedPipe(pipe);
+        }
+    }))
+}
@@ -0,0 +1,85 @@
+//! A short cross-fade from the previous wallpaper to the new one, made of blended frames
+//! that are set as the background one after the other.
+use image::imageops::{self, FilterType};
+use image::RgbImage;
+use log::{debug, info};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::config::CrossFadeConfig;
+use crate::image_structs::open_image;
+use crate::paths::Paths;
+use crate::set_background::{self, BackgroundStyle};
+
+/// Whether the background can be changed quickly enough to show the frames
+/// (on Linux, `gsettings` is too slow and Cinnamon already fades the backgrounds).
+pub(crate) const SUPPORTED: bool = cfg!(target_os = "windows");
+
+/// Saves the frames between the two images in the directory and returns their paths.
+///
+/// # Errors
+/// Fails if a frame can't be saved.
+#[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn render_frames(
+    previous: &RgbImage,
+    next: &RgbImage,
+    count: u32,
+    frames_dir: &Path,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    fs::create_dir_all(frames_dir)?;
+    let mut frames = Vec::new();
+    for index in 1..=count {
+        #[expect(clippy::cast_precision_loss)]
+        let progress = index as f32 / (count + 1) as f32;
+        let mut frame = next.clone();
+        for (pixel, previous_pixel) in frame.pixels_mut().zip(previous.pixels()) {
+            for (value, previous_value) in pixel.0.iter_mut().zip(previous_pixel.0) {
+                *value = (f32::from(previous_value) * (1.0 - progress)
+                    + f32::from(*value) * progress)
+                    .round() as u8;
+            }
+        }
+        // Some desktops don't read a file again if its path hasn't changed
+        let path = frames_dir.join(format!("frame_{index:02}.jpg"));
+        frame.save(&path)?;
+        frames.push(path);
+    }
+    Ok(frames)
+}
+
+/// Shows a cross-fade from the previous wallpaper to the new one by setting the blended frames
+/// as the background. The new wallpaper must be set afterwards.
+///
+/// # Errors
+/// Fails if a wallpaper can't be opened or if a frame can't be saved or set as the background.
+pub(crate) fn play(
+    previous: &Path,
+    next: &Path,
+    config: &CrossFadeConfig,
+    style: BackgroundStyle,
+) -> Result<(), Box<dyn Error>> {
+    if config.frames == 0 {
+        return Ok(());
+    }
+    let next = open_image(next)?.to_rgb8();
+    let mut previous = open_image(previous)?.to_rgb8();
+    if previous.dimensions() != next.dimensions() {
+        debug!("Resizing the previous wallpaper to the size of the new one");
+        previous = imageops::resize(&previous, next.width(), next.height(), FilterType::Triangle);
+    }
+
+    // The frames are rendered first so that they are shown at a regular pace
+    let frames_dir = Paths::temp_dir()?.join("cross_fade");
+    let frames = render_frames(&previous, &next, config.frames, &frames_dir)?;
+    info!("Showing a cross-fade of {} frames", frames.len());
+    let delay = Duration::from_millis(config.duration_ms.into()) / (config.frames + 1);
+    for frame in &frames {
+        set_background::set_background(frame, style)?;
+        thread::sleep(delay);
+    }
+    fs::remove_dir_all(frames_dir)?;
+    Ok(())
+}
@@ -0,0 +1,373 @@
+//! A resident mode that changes the wallpaper periodically
+//! and regenerates it when the display configuration or the config changes.
+//! It can be controlled through D-Bus on Linux and a named pipe on Windows.
+use log::{debug, error, info};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::error::Error;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::{Config, SlideshowConfig};
+use crate::control::{self, Request};
+use crate::image_list::ImageData;
+use crate::image_structs::Image;
+use crate::last_run;
+use crate::local_index::PicturesWatcher;
+use crate::paths::Paths;
+use crate::pause;
+use crate::pin;
+use crate::screen_size::get_monitor_geometry;
+use crate::skip_rules;
+use crate::{change_wallpaper, set_wallpaper};
+
+/// The interval between two wallpaper changes.
+const CHANGE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// The interval between two checks of the display configuration.
+const DISPLAY_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The maximum time spent waiting for a control request before checking the timer and the config.
+const TICK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The number of previous wallpapers that can be shown again with the `Previous` request.
+const HISTORY_LENGTH: usize = 10;
+
+/// Set when the `SIGHUP` signal is received, to reload the config.
+static RELOAD_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Handles the `SIGHUP` signal.
+#[cfg(target_os = "linux")]
+extern "C" fn on_sighup(_signal: i32) {
+    RELOAD_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Reloads the config when the `SIGHUP` signal is received.
+#[cfg(target_os = "linux")]
+fn handle_sighup() {
+    /// The number of the `SIGHUP` signal.
+    const SIGHUP: i32 = 1;
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    }
+    unsafe {
+        signal(SIGHUP, on_sighup);
+    }
+}
+
+/// Returns `true` if a reload of the config was requested with `SIGHUP` since the last call.
+fn take_reload_request() -> bool {
+    RELOAD_REQUESTED.swap(false, Ordering::Relaxed)
+}
+
+/// Watches the config file. The events are sent to the returned receiver
+/// as long as the returned watcher is kept.
+///
+/// # Errors
+/// Fails if the directory of the config file can't be watched.
+fn watch_config() -> Result<(RecommendedWatcher, Receiver<notify::Result<Event>>), Box<dyn Error>> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    // Editors often replace the file instead of writing in it, so its directory is watched
+    let directory = Paths::config_file()?
+        .parent()
+        .ok_or("The config file has no parent directory")?;
+    watcher.watch(directory, RecursiveMode::NonRecursive)?;
+    Ok((watcher, receiver))
+}
+
+/// Returns `true` if the config file was modified since the last call.
+fn config_changed(receiver: &Receiver<notify::Result<Event>>) -> bool {
+    // All the pending events are read so that a change is only seen once
+    receiver
+        .try_iter()
+        .filter_map(Result::ok)
+        .filter(|event| {
+            !event.kind.is_access()
+                && event
+                    .paths
+                    .iter()
+                    .any(|path| Paths::config_file().is_ok_and(|config_file| path == config_file))
+        })
+        .count()
+        > 0
+}
+
+/// Starts watching the pictures folders of the config to find the new pictures immediately.
+fn watch_pictures(config: &Config) -> Option<PicturesWatcher> {
+    PicturesWatcher::start(config)
+        .inspect_err(|err| error!("Couldn't watch the pictures folders: {err}"))
+        .ok()
+}
+
+/// Waits for a request of the control interface until the next tick.
+fn wait_for_request(receiver: Option<&Receiver<Request>>) -> Option<Request> {
+    match receiver.map(|receiver| receiver.recv_timeout(TICK_INTERVAL)) {
+        Some(Ok(request)) => Some(request),
+        Some(Err(RecvTimeoutError::Timeout)) => None,
+        // The control interface isn't available
+        None | Some(Err(RecvTimeoutError::Disconnected)) => {
+            thread::sleep(TICK_INTERVAL);
+            None
+        }
+    }
+}
+
+/// Returns the interval between two wallpaper changes.
+fn get_change_interval(config: &Config) -> Duration {
+    config
+        .slideshow
+        .as_ref()
+        .map_or(CHANGE_INTERVAL, SlideshowConfig::total_duration)
+}
+
+/// Regenerates the wallpaper with the same images.
+fn regenerate(config: &Config, images: &[Box<dyn Image>], image_data: &ImageData) {
+    if images.is_empty() {
+        return;
+    }
+    match set_wallpaper(config, images) {
+        Ok(output_paths) => {
+            if let Err(err) = image_data.delete_old_images(&output_paths) {
+                error!("Error: {err}");
+            }
+        }
+        Err(err) => error!("Error: {err}"),
+    }
+}
+
+/// The wallpapers shown by the daemon.
+struct Wallpapers {
+    /// The images of the current wallpaper.
+    current: Vec<Box<dyn Image>>,
+    /// The images of the previous wallpapers, the most recent last.
+    history: Vec<Vec<Box<dyn Image>>>,
+}
+
+impl Wallpapers {
+    /// Sets new images as the wallpaper and keeps the previous ones in the history.
+    fn change(&mut self, config: &Config, image_data: &mut ImageData) {
+        let images = match change_wallpaper(config, image_data) {
+            Ok(images) => images,
+            Err(err) => {
+                error!("Error: {err}");
+                last_run::record_failure(err.as_ref());
+                return;
+            }
+        };
+        last_run::record_success(images.first().map(|image| image.get_path()).as_deref());
+        let previous_images = mem::replace(&mut self.current, images);
+        if previous_images.is_empty() {
+            return;
+        }
+        self.history.push(previous_images);
+        if self.history.len() > HISTORY_LENGTH {
+            self.history.remove(0);
+        }
+    }
+
+    /// Sets the previous images as the wallpaper again.
+    fn go_back(&mut self, config: &Config, image_data: &ImageData) {
+        let Some(images) = self.history.pop() else {
+            info!("There is no previous wallpaper");
+            return;
+        };
+        self.current = images;
+        regenerate(config, &self.current, image_data);
+    }
+}
+
+/// When the daemon changes the wallpaper periodically.
+struct Timer {
+    /// The time of the last change. The system time is used because a monotonic clock
+    /// doesn't advance while the computer is asleep on all the platforms.
+    last_change: SystemTime,
+    /// Whether the changes are paused.
+    paused: bool,
+    /// The interval set through the control interface, which replaces the one of the config.
+    interval_override: Option<Duration>,
+}
+
+impl Timer {
+    /// Returns `true` if the wallpaper must be changed.
+    fn is_due(&self, change_interval: Duration) -> bool {
+        !self.paused
+            && self
+                .last_change
+                .elapsed()
+                .is_ok_and(|elapsed| elapsed >= self.interval_override.unwrap_or(change_interval))
+    }
+}
+
+/// Handles a request of the control interface.
+fn handle_request(
+    request: Request,
+    config: &Config,
+    image_data: &mut ImageData,
+    wallpapers: &mut Wallpapers,
+    timer: &mut Timer,
+) {
+    match request {
+        Request::Next => {
+            debug!("Changing the wallpaper on request");
+            wallpapers.change(config, image_data);
+            timer.last_change = SystemTime::now();
+        }
+        Request::Previous => {
+            debug!("Going back to the previous wallpaper");
+            wallpapers.go_back(config, image_data);
+            timer.last_change = SystemTime::now();
+        }
+        Request::Pause => {
+            info!("Wallpaper changes paused");
+            timer.paused = true;
+        }
+        Request::Resume => {
+            info!("Wallpaper changes resumed");
+            timer.paused = false;
+            timer.last_change = SystemTime::now();
+        }
+        Request::SetInterval(interval) => {
+            info!(
+                "Changing the wallpaper every {} seconds",
+                interval.as_secs()
+            );
+            timer.interval_override = Some(interval);
+        }
+        Request::Current(reply) => {
+            let sources = wallpapers.current.iter().map(|image| image.get_source());
+            let _ = reply.send(sources.collect());
+        }
+    }
+}
+
+/// Runs the daemon: changes the wallpaper every [`CHANGE_INTERVAL`] (or at the end of the slideshow)
+/// and regenerates the current one at the new size when a monitor is added, removed or resized.
+///
+/// The config is reloaded and the wallpaper regenerated when the config file is modified
+/// or when the `SIGHUP` signal is received.
+///
+/// The requests of the control interface (next, previous, pause...) are handled as soon as they arrive.
+/// The index of the local images is updated as soon as the pictures folders change.
+///
+/// # Errors
+/// Fails if the config or the image data can't be loaded.
+pub(crate) fn run() -> Result<(), Box<dyn Error>> {
+    let mut config = Config::load()?;
+    let mut image_data = ImageData::load()?;
+    let mut change_interval = get_change_interval(&config);
+
+    #[cfg(target_os = "linux")]
+    handle_sighup();
+    let config_watcher = match watch_config() {
+        Ok(config_watcher) => Some(config_watcher),
+        Err(err) => {
+            error!("Couldn't watch the config file, it won't be reloaded automatically: {err}");
+            None
+        }
+    };
+
+    let mut pictures_watcher = watch_pictures(&config);
+
+    let control = match control::start() {
+        Ok(control) => Some(control),
+        Err(err) => {
+            error!("Couldn't start the control interface: {err}");
+            None
+        }
+    };
+
+    info!("Daemon started");
+
+    let mut monitors = get_monitor_geometry();
+    let mut last_display_check = Instant::now();
+    let mut wallpapers = Wallpapers {
+        current: Vec::new(),
+        history: Vec::new(),
+    };
+    wallpapers.change(&config, &mut image_data);
+    let mut timer = Timer {
+        last_change: SystemTime::now(),
+        paused: false,
+        interval_override: None,
+    };
+
+    loop {
+        if let Some(request) = wait_for_request(control.as_ref().map(|(_, receiver)| receiver)) {
+            handle_request(
+                request,
+                &config,
+                &mut image_data,
+                &mut wallpapers,
+                &mut timer,
+            );
+            continue;
+        }
+
+        if let Some(pictures_watcher) = &pictures_watcher {
+            pictures_watcher.apply_changes();
+        }
+
+        if timer.is_due(change_interval) {
+            if pause::is_paused() {
+                debug!("Not changing the wallpaper because the changes are paused");
+                timer.last_change = SystemTime::now();
+                continue;
+            }
+            if pin::is_pinned() {
+                debug!("Not changing the wallpaper because it is pinned");
+                timer.last_change = SystemTime::now();
+                continue;
+            }
+            if let Some(rule) = skip_rules::get_matching_rule(&config) {
+                info!("Not changing the wallpaper because of the skip rule {rule:?}");
+                timer.last_change = SystemTime::now();
+                continue;
+            }
+            debug!("Changing the wallpaper");
+            wallpapers.change(&config, &mut image_data);
+            timer.last_change = SystemTime::now();
+            monitors = get_monitor_geometry();
+            last_display_check = Instant::now();
+            continue;
+        }
+
+        let file_changed = config_watcher
+            .as_ref()
+            .is_some_and(|(_, receiver)| config_changed(receiver));
+        if take_reload_request() || file_changed {
+            info!("Reloading the config");
+            match Config::load() {
+                Ok(new_config) => {
+                    config = new_config;
+                    change_interval = get_change_interval(&config);
+                    pictures_watcher = watch_pictures(&config);
+                }
+                Err(err) => {
+                    error!("Couldn't reload the config, keeping the previous one: {err}");
+                    continue;
+                }
+            }
+            // Apply the new settings to the current images
+            regenerate(&config, &wallpapers.current, &image_data);
+            continue;
+        }
+
+        if last_display_check.elapsed() < DISPLAY_POLL_INTERVAL {
+            continue;
+        }
+        last_display_check = Instant::now();
+        let new_monitors = get_monitor_geometry();
+        if new_monitors == monitors {
+            continue;
+        }
+        info!("The display configuration has changed, regenerating the wallpaper");
+        debug!("New monitors: {:?}", new_monitors);
+        monitors = new_monitors;
+
+        // Regenerate the wallpaper with the same images at the new size
+        regenerate(&config, &wallpapers.current, &image_data);
+    }
+}
@@ -0,0 +1,718 @@
+//! The database where the state of the program is stored: the online images,
+//! the index of the local images, the history of the wallpapers, the favorites, the blocklist
+//! and the checksums of the downloaded images.
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::image_list::ImageData;
+use crate::image_structs::{Image, OnlineImage};
+use crate::local_index::DirectoryScan;
+use crate::paths::Paths;
+
+/// The version of the schema of the database, stored in `PRAGMA user_version`.
+const SCHEMA_VERSION: u32 = 1;
+
+/// The changes of the schema, the one at index `i` upgrades the database from version `i` to `i + 1`.
+const MIGRATIONS: [&str; SCHEMA_VERSION as usize] = [
+    // 1: the first schema
+    "
+    CREATE TABLE online_images (
+        position INTEGER PRIMARY KEY,
+        id TEXT NOT NULL,
+        url TEXT NOT NULL,
+        date TEXT,
+        description TEXT NOT NULL,
+        photographer TEXT NOT NULL
+    );
+    CREATE TABLE state (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE local_folders (
+        folder TEXT PRIMARY KEY,
+        indexed_at TEXT NOT NULL
+    );
+    CREATE TABLE local_dirs (
+        folder TEXT NOT NULL REFERENCES local_folders (folder) ON DELETE CASCADE,
+        dir TEXT NOT NULL,
+        modified TEXT NOT NULL,
+        PRIMARY KEY (folder, dir)
+    );
+    CREATE TABLE local_files (
+        folder TEXT NOT NULL,
+        dir TEXT NOT NULL,
+        name TEXT NOT NULL,
+        PRIMARY KEY (folder, dir, name),
+        FOREIGN KEY (folder, dir) REFERENCES local_dirs (folder, dir) ON DELETE CASCADE
+    );
+    CREATE TABLE history (
+        id INTEGER PRIMARY KEY,
+        path TEXT NOT NULL,
+        source TEXT NOT NULL,
+        description TEXT NOT NULL,
+        shown_at TEXT NOT NULL
+    );
+    CREATE INDEX history_path ON history (path, shown_at);
+    CREATE TABLE favorites (
+        path TEXT PRIMARY KEY,
+        source TEXT NOT NULL,
+        added_at TEXT NOT NULL
+    );
+    CREATE TABLE blocklist (
+        path TEXT PRIMARY KEY,
+        source TEXT NOT NULL,
+        added_at TEXT NOT NULL
+    );
+    CREATE TABLE downloaded_files (
+        path TEXT PRIMARY KEY,
+        checksum TEXT NOT NULL
+    );
+    CREATE INDEX downloaded_files_checksum ON downloaded_files (checksum);
+",
+];
+
+/// How long to wait for another instance (e.g. the daemon) that is writing to the database.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A list of images chosen by the user.
+#[derive(Clone, Copy)]
+pub(crate) enum ImageList {
+    /// The images that are kept in the cache.
+    Favorites,
+    /// The images that are never chosen.
+    Blocklist,
+}
+
+impl ImageList {
+    /// Returns the name of the list, which is also the name of its table.
+    pub(crate) const fn name(self) -> &'static str {
+        match self {
+            Self::Favorites => "favorites",
+            Self::Blocklist => "blocklist",
+        }
+    }
+}
+
+/// An image that was shown as the wallpaper.
+#[derive(Deserialize, Serialize)]
+pub(crate) struct HistoryEntry {
+    pub(crate) path: PathBuf,
+    /// Where the image comes from (its path or its URL).
+    pub(crate) source: String,
+    pub(crate) description: String,
+    pub(crate) shown_at: DateTime<Utc>,
+}
+
+/// An image of a list.
+#[derive(Deserialize, Serialize)]
+pub(crate) struct ListEntry {
+    pub(crate) path: PathBuf,
+    /// Where the image comes from (its path or its URL).
+    pub(crate) source: String,
+    pub(crate) added_at: DateTime<Utc>,
+}
+
+/// Returns the key of a pictures folder in the index: its canonical path, so that a folder written
+/// in different ways (e.g. with a trailing separator, with `/` instead of `\` or through a link)
+/// has a single index.
+fn get_folder_key(folder: &Path) -> String {
+    fs::canonicalize(folder)
+        .unwrap_or_else(|_| folder.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+/// A connection to the database.
+pub(crate) struct Database {
+    connection: Connection,
+}
+
+impl Database {
+    /// Opens the database, creating or upgrading it if needed.
+    ///
+    /// # Errors
+    /// Fails if the database can't be opened, if it can't be upgraded
+    /// or if it was created by a newer version of the program.
+    pub(crate) fn open() -> Result<Self, Box<dyn Error>> {
+        let connection = Connection::open(Paths::database_file()?)?;
+        // The scheduled task, the daemon and the tray icon can use the database at the same time
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "foreign_keys", true)?;
+        let mut database = Self { connection };
+        database.migrate()?;
+        database.move_pictures_paths()?;
+        Ok(database)
+    }
+
+    /// Updates the paths of the downloaded pictures in the lists, in the history and in the checksums
+    /// if their directory has moved (e.g. to the cache directory).
+    ///
+    /// # Errors
+    /// Fails if the database can't be written to.
+    fn move_pictures_paths(&mut self) -> Result<(), Box<dyn Error>> {
+        let new_dir = Paths::downloaded_pictures_dir()?
+            .to_string_lossy()
+            .to_string();
+        let old_dir = match self.get_state("pictures_dir")? {
+            Some(old_dir) => old_dir,
+            None => Paths::legacy_base_dir()?
+                .join("pictures")
+                .to_string_lossy()
+                .to_string(),
+        };
+        if old_dir == new_dir {
+            return Ok(());
+        }
+        info!("Moving the paths of the downloaded pictures from {old_dir:?} to {new_dir:?}");
+        let transaction = self
+            .connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        for table in ["favorites", "blocklist", "history", "downloaded_files"] {
+            transaction.execute(
+                &format!(
+                    "UPDATE OR REPLACE {table} SET path = ?2 || substr(path, length(?1) + 1)
+                     WHERE substr(path, 1, length(?1)) = ?1"
+                ),
+                [&old_dir, &new_dir],
+            )?;
+        }
+        transaction.execute(
+            "INSERT OR REPLACE INTO state (key, value) VALUES ('pictures_dir', ?1)",
+            [&new_dir],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Upgrades the schema of the database to the current version.
+    ///
+    /// # Errors
+    /// Fails if the database was created by a newer version of the program or if a migration fails.
+    fn migrate(&mut self) -> Result<(), Box<dyn Error>> {
+        let transaction = self
+            .connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let version: u32 =
+            transaction.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            return Err(format!(
+                "The database {:?} was created by a newer version of the program",
+                Paths::database_file()?
+            )
+            .into());
+        }
+        for (new_version, migration) in (1..=SCHEMA_VERSION).zip(MIGRATIONS) {
+            if new_version > version {
+                info!("Upgrading the database to version {new_version}");
+                transaction.execute_batch(migration)?;
+            }
+        }
+        transaction.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        transaction.commit()?;
+
+        if version == 0 {
+            self.import_legacy_files()?;
+        }
+        Ok(())
+    }
+
+    /// Imports the image data file used before the database (or its backup), then deletes them
+    /// and the pictures lists. The files that can't be imported are renamed
+    /// to `image_data.json.imported-failed` and `image_data.json.bak.imported-failed`.
+    ///
+    /// # Errors
+    /// Fails if the image data can't be stored or if the old files can't be deleted or renamed.
+    fn import_legacy_files(&mut self) -> Result<(), Box<dyn Error>> {
+        let data_path = Paths::base_dir()?.join("image_data.json");
+        let legacy_paths = [data_path.with_extension("json.bak"), data_path.clone()];
+        if legacy_paths.iter().any(|path| path.exists()) {
+            info!("Importing the image data from {data_path:?}");
+            match ImageData::read_legacy_file(&data_path) {
+                Ok(image_data) => {
+                    self.store_image_data(&image_data)?;
+                    for path in legacy_paths.iter().filter(|path| path.exists()) {
+                        fs::remove_file(path)?;
+                    }
+                }
+                Err(err) => {
+                    // The files are kept so that the state can be recovered by hand
+                    warn!("Couldn't import the image data, keeping its files: {err}");
+                    for path in legacy_paths.iter().filter(|path| path.exists()) {
+                        let mut failed_path = path.clone().into_os_string();
+                        failed_path.push(".imported-failed");
+                        fs::rename(path, failed_path)?;
+                    }
+                }
+            }
+        }
+        // The pictures lists are rebuilt when they are needed
+        let path_cache_dir = Paths::base_dir()?.join("path_cache");
+        if path_cache_dir.exists() {
+            debug!("Removing the old pictures lists in {path_cache_dir:?}");
+            fs::remove_dir_all(path_cache_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the value of a key of the state, or `None` if it isn't set.
+    ///
+    /// # Errors
+    /// Fails if the database can't be read.
+    pub(crate) fn get_state(&self, key: &str) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(self
+            .connection
+            .query_row("SELECT value FROM state WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()?)
+    }
+
+    /// Sets the value of a key of the state, or removes it if the value is `None`.
+    ///
+    /// # Errors
+    /// Fails if the database can't be written to.
+    pub(crate) fn set_state(&self, key: &str, value: Option<&str>) -> Result<(), Box<dyn Error>> {
+        match value {
+            Some(value) => self.connection.execute(
+                "INSERT OR REPLACE INTO state (key, value) VALUES (?1, ?2)",
+                params![key, value],
+            )?,
+            None => self
+                .connection
+                .execute("DELETE FROM state WHERE key = ?1", [key])?,
+        };
+        Ok(())
+    }
+
+    /// Loads the online images and their state.
+    ///
+    /// # Errors
+    /// Fails if the database can't be read or if a value is malformed.
+    pub(crate) fn load_image_data(&self) -> Result<ImageData, Box<dyn Error>> {
+        let mut statement = self.connection.prepare(
+            "SELECT id, url, date, description, photographer FROM online_images ORDER BY position",
+        )?;
+        let urls = statement
+            .query_map([], |row| {
+                Ok(OnlineImage {
+                    id: row.get(0)?,
+                    url: row.get(1)?,
+                    date: row.get(2)?,
+                    description: row.get(3)?,
+                    photographer: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ImageData {
+            urls,
+            current_index: self
+                .get_state("current_index")?
+                .map_or(Ok(0), |value| value.parse())?,
+            order: self.get_state("order")?.map_or(Ok(Vec::new()), |value| {
+                value.split(',').map(str::parse).collect()
+            })?,
+            needs_downloading: self
+                .get_state("needs_downloading")?
+                .map_or(Ok(false), |value| value.parse())?,
+            cool_down_until: self
+                .get_state("cool_down_until")?
+                .map(|value| DateTime::parse_from_rfc3339(&value).map(|date| date.to_utc()))
+                .transpose()?,
+        })
+    }
+
+    /// Replaces the online images and their state.
+    ///
+    /// # Errors
+    /// Fails if the database can't be written to.
+    pub(crate) fn store_image_data(
+        &mut self,
+        image_data: &ImageData,
+    ) -> Result<(), Box<dyn Error>> {
+        let transaction = self
+            .connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        transaction.execute("DELETE FROM online_images", [])?;
+        // The positions are given in the order of the insertions
+        for image in &image_data.urls {
+            transaction.execute(
+                "INSERT INTO online_images (id, url, date, description, photographer)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    image.id,
+                    image.url,
+                    image.date,
+                    image.description,
+                    image.photographer
+                ],
+            )?;
+        }
+        let state = [
+            ("current_index", Some(image_data.current_index.to_string())),
+            (
+                "order",
+                Some(
+                    image_data
+                        .order
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(","),
+                )
+                .filter(|order| !order.is_empty()),
+            ),
+            (
+                "needs_downloading",
+                Some(image_data.needs_downloading.to_string()),
+            ),
+            (
+                "cool_down_until",
+                image_data.cool_down_until.map(|date| date.to_rfc3339()),
+            ),
+        ];
+        for (key, value) in state {
+            match value {
+                Some(value) => transaction.execute(
+                    "INSERT OR REPLACE INTO state (key, value) VALUES (?1, ?2)",
+                    params![key, value],
+                )?,
+                None => transaction.execute("DELETE FROM state WHERE key = ?1", [key])?,
+            };
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Returns when a pictures folder was fully indexed and the indexed directories
+    /// (relative to it) with their modification time, or `None` if the folder hasn't been indexed yet.
+    ///
+    /// # Errors
+    /// Fails if the database can't be read.
+    pub(crate) fn get_local_dirs(
+        &self,
+        folder: &Path,
+    ) -> Result<Option<(DateTime<Utc>, Vec<(PathBuf, DateTime<Utc>)>)>, Box<dyn Error>> {
+        let folder = get_folder_key(folder);
+        let Some(indexed_at) = self
+            .connection
+            .query_row(
+                "SELECT indexed_at FROM local_folders WHERE folder = ?1",
+                [&folder],
+                |row| row.get(0),
+            )
+            .optional()?
+        else {
+            return Ok(None);
+        };
+        let mut statement = self
+            .connection
+            .prepare("SELECT dir, modified FROM local_dirs WHERE folder = ?1")?;
+        let dirs = statement
+            .query_map([&folder], |row| {
+                Ok((PathBuf::from(row.get::<_, String>(0)?), row.get(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some((indexed_at, dirs)))
+    }
+
+    /// Returns the local images indexed in a pictures folder, relative to it.
+    ///
+    /// # Errors
+    /// Fails if the database can't be read.
+    pub(crate) fn get_local_files(&self, folder: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT dir, name FROM local_files WHERE folder = ?1")?;
+        let images = statement
+            .query_map([get_folder_key(folder)], |row| {
+                Ok(PathBuf::from(row.get::<_, String>(0)?).join(row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(images)
+    }
+
+    /// Updates the index of a pictures folder with the directories that were listed again
+    /// and the ones that were removed. With `full`, the previous index is replaced.
+    ///
+    /// # Errors
+    /// Fails if the database can't be written to.
+    pub(crate) fn update_local_index(
+        &mut self,
+        folder: &Path,
+        full: bool,
+        scans: &[DirectoryScan],
+        removed_dirs: &[PathBuf],
+    ) -> Result<(), Box<dyn Error>> {
+        let folder = get_folder_key(folder);
+        let transaction = self
+            .connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        if full {
+            // The directories and the files of the folder are deleted with it
+            transaction.execute("DELETE FROM local_folders WHERE folder = ?1", [&folder])?;
+            transaction.execute(
+                "INSERT INTO local_folders (folder, indexed_at) VALUES (?1, ?2)",
+                params![folder, Utc::now()],
+            )?;
+        }
+        {
+            let mut remove_dir =
+                transaction.prepare("DELETE FROM local_dirs WHERE folder = ?1 AND dir = ?2")?;
+            let mut add_dir = transaction
+                .prepare("INSERT INTO local_dirs (folder, dir, modified) VALUES (?1, ?2, ?3)")?;
+            let mut add_file = transaction
+                .prepare("INSERT INTO local_files (folder, dir, name) VALUES (?1, ?2, ?3)")?;
+            for dir in removed_dirs {
+                remove_dir.execute(params![folder, dir.to_string_lossy()])?;
+            }
+            for scan in scans {
+                let dir = scan.dir.to_string_lossy();
+                remove_dir.execute(params![folder, dir])?;
+                add_dir.execute(params![folder, dir, scan.modified])?;
+                for name in &scan.files {
+                    add_file.execute(params![folder, dir, name])?;
+                }
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Adds the images of a new wallpaper to the history.
+    ///
+    /// # Errors
+    /// Fails if the database can't be written to.
+    pub(crate) fn add_to_history(
+        &mut self,
+        images: &[Box<dyn Image>],
+    ) -> Result<(), Box<dyn Error>> {
+        let shown_at = Utc::now();
+        let transaction = self
+            .connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        for image in images {
+            transaction.execute(
+                "INSERT INTO history (path, source, description, shown_at) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    image.get_path().to_string_lossy(),
+                    image.get_source(),
+                    image.get_description(),
+                    shown_at
+                ],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Returns the last images shown as the wallpaper, the most recent first.
+    ///
+    /// # Errors
+    /// Fails if the database can't be read.
+    pub(crate) fn get_history(&self, count: u32) -> Result<Vec<HistoryEntry>, Box<dyn Error>> {
+        let mut statement = self.connection.prepare(
+            "SELECT path, source, description, shown_at FROM history
+             ORDER BY shown_at DESC, id DESC LIMIT ?1",
+        )?;
+        let entries = statement
+            .query_map([count], |row| {
+                Ok(HistoryEntry {
+                    path: PathBuf::from(row.get::<_, String>(0)?),
+                    source: row.get(1)?,
+                    description: row.get(2)?,
+                    shown_at: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+
+    /// Returns the paths of the last `count` images shown since the given time.
+    ///
+    /// # Errors
+    /// Fails if the database can't be read.
+    pub(crate) fn get_recently_shown(
+        &self,
+        count: u32,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let mut statement = self.connection.prepare(
+            "SELECT path FROM history WHERE shown_at >= ?2
+             ORDER BY shown_at DESC, id DESC LIMIT ?1",
+        )?;
+        let paths = statement
+            .query_map(params![count, since], |row| row.get::<_, String>(0))?
+            .map(|path| path.map(PathBuf::from))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(paths)
+    }
+
+    /// Adds an image to a list.
+    ///
+    /// # Errors
+    /// Fails if the database can't be written to.
+    pub(crate) fn add_to_list(
+        &self,
+        list: ImageList,
+        path: &Path,
+        source: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        self.connection.execute(
+            &format!(
+                "INSERT OR REPLACE INTO {} (path, source, added_at) VALUES (?1, ?2, ?3)",
+                list.name()
+            ),
+            params![path.to_string_lossy(), source, Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    /// Removes an image from a list. Returns `false` if it wasn't in the list.
+    ///
+    /// # Errors
+    /// Fails if the database can't be written to.
+    pub(crate) fn remove_from_list(
+        &self,
+        list: ImageList,
+        path: &Path,
+    ) -> Result<bool, Box<dyn Error>> {
+        let removed = self.connection.execute(
+            &format!("DELETE FROM {} WHERE path = ?1", list.name()),
+            [path.to_string_lossy()],
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// Returns the paths of the images in a list.
+    ///
+    /// # Errors
+    /// Fails if the database can't be read.
+    pub(crate) fn get_list(&self, list: ImageList) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let mut statement = self
+            .connection
+            .prepare(&format!("SELECT path FROM {}", list.name()))?;
+        let paths = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .map(|path| path.map(PathBuf::from))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(paths)
+    }
+
+    /// Records the checksum of a downloaded image.
+    ///
+    /// # Errors
+    /// Fails if the database can't be written to.
+    pub(crate) fn add_downloaded_file(
+        &self,
+        path: &Path,
+        checksum: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO downloaded_files (path, checksum) VALUES (?1, ?2)",
+            params![path.to_string_lossy(), checksum],
+        )?;
+        Ok(())
+    }
+
+    /// Returns the downloaded images that have a checksum.
+    ///
+    /// # Errors
+    /// Fails if the database can't be read.
+    pub(crate) fn get_downloaded_files(
+        &self,
+        checksum: &str,
+    ) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT path FROM downloaded_files WHERE checksum = ?1")?;
+        let paths = statement
+            .query_map([checksum], |row| row.get::<_, String>(0))?
+            .map(|path| path.map(PathBuf::from))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(paths)
+    }
+
+    /// Forgets the checksum of a downloaded image that was removed.
+    ///
+    /// # Errors
+    /// Fails if the database can't be written to.
+    pub(crate) fn remove_downloaded_file(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        self.connection.execute(
+            "DELETE FROM downloaded_files WHERE path = ?1",
+            [path.to_string_lossy()],
+        )?;
+        Ok(())
+    }
+
+    /// Returns the images of a list with the time they were added.
+    ///
+    /// # Errors
+    /// Fails if the database can't be read.
+    pub(crate) fn get_list_entries(
+        &self,
+        list: ImageList,
+    ) -> Result<Vec<ListEntry>, Box<dyn Error>> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT path, source, added_at FROM {} ORDER BY added_at",
+            list.name()
+        ))?;
+        let entries = statement
+            .query_map([], |row| {
+                Ok(ListEntry {
+                    path: PathBuf::from(row.get::<_, String>(0)?),
+                    source: row.get(1)?,
+                    added_at: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+
+    /// Adds imported images to the lists and to the history, in a single transaction.
+    /// The history entries that are already in the database are skipped.
+    ///
+    /// # Errors
+    /// Fails if the database can't be written to.
+    pub(crate) fn import_settings(
+        &mut self,
+        lists: &[(ImageList, Vec<ListEntry>)],
+        history: &[HistoryEntry],
+    ) -> Result<(), Box<dyn Error>> {
+        let transaction = self
+            .connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        for (list, entries) in lists {
+            for entry in entries {
+                transaction.execute(
+                    &format!(
+                        "INSERT OR REPLACE INTO {} (path, source, added_at) VALUES (?1, ?2, ?3)",
+                        list.name()
+                    ),
+                    params![entry.path.to_string_lossy(), entry.source, entry.added_at],
+                )?;
+            }
+        }
+        for entry in history {
+            transaction.execute(
+                "INSERT INTO history (path, source, description, shown_at)
+                 SELECT ?1, ?2, ?3, ?4
+                 WHERE NOT EXISTS (SELECT 1 FROM history WHERE path = ?1 AND shown_at = ?4)",
+                params![
+                    entry.path.to_string_lossy(),
+                    entry.source,
+                    entry.description,
+                    entry.shown_at
+                ],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
@@ -2,30 +2,38 @@ use chrono::DateTime;
 use chrono::Datelike;
 use chrono::Local;
 
-/// Format a date in French.
-pub(crate) fn format_date_in_french(date: DateTime<Local>) -> String {
-    let days = [
-        "dimanche", "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi",
-    ];
-    let months = [
-        "janvier",
-        "février",
-        "mars",
-        "avril",
-        "mai",
-        "juin",
-        "juillet",
-        "août",
-        "septembre",
-        "octobre",
-        "novembre",
-        "décembre",
-    ];
+use crate::i18n;
 
-    let day_of_week = days[date.weekday().num_days_from_sunday() as usize];
+/// Format a date in the language of the messages.
+pub(crate) fn format_date(date: DateTime<Local>) -> String {
     let day = date.day();
-    let month = months[(date.month() - 1) as usize];
     let year = date.year();
 
-    format!("{day_of_week} {day} {month} {year}")
+    if i18n::get_language() == "fr" {
+        let days = [
+            "dimanche", "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi",
+        ];
+        let months = [
+            "janvier",
+            "février",
+            "mars",
+            "avril",
+            "mai",
+            "juin",
+            "juillet",
+            "août",
+            "septembre",
+            "octobre",
+            "novembre",
+            "décembre",
+        ];
+        let day_of_week = days[date.weekday().num_days_from_sunday() as usize];
+        let month = months[(date.month() - 1) as usize];
+        return format!("{day_of_week} {day} {month} {year}");
+    }
+
+    // The names of the days and months of chrono are in English
+    let day_of_week = date.format("%A");
+    let month = date.format("%B");
+    format!("{day_of_week}, {month} {day}, {year}")
 }
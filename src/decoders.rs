@@ -0,0 +1,475 @@
+//! Decoders for the image formats that the `image` crate can't read:
+//! the RAW files of the cameras (from their embedded JPEG preview),
+//! HEIC (with the `heic` feature), JPEG XL (with the `jxl` feature)
+//! and the videos, from which a random frame is taken (with the `video` feature).
+//! The huge JPEG images can also be downscaled while they are decoded.
+//!
+//! AVIF images are read by the `image` crate itself with the `avif` feature.
+#[cfg(feature = "jxl")]
+use image::ImageDecoder;
+use image::{DynamicImage, GrayImage, RgbImage};
+use jpeg_decoder::PixelFormat;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+#[cfg(feature = "video")]
+use crate::image_structs::{get_image_dimensions, open_image};
+#[cfg(feature = "video")]
+use crate::video_frames;
+
+/// The extensions of the images that can be read in addition to the default ones.
+pub(crate) const EXTENSIONS: &[&str] = &[
+    "arw",
+    "cr2",
+    "dng",
+    "nef",
+    #[cfg(feature = "avif")]
+    "avif",
+    #[cfg(feature = "heic")]
+    "heic",
+    #[cfg(feature = "heic")]
+    "heif",
+    #[cfg(feature = "jxl")]
+    "jxl",
+    #[cfg(feature = "video")]
+    "avi",
+    #[cfg(feature = "video")]
+    "m4v",
+    #[cfg(feature = "video")]
+    "mkv",
+    #[cfg(feature = "video")]
+    "mov",
+    #[cfg(feature = "video")]
+    "mp4",
+    #[cfg(feature = "video")]
+    "webm",
+];
+
+/// Returns the lowercase extension of a file.
+fn get_extension(path: &Path) -> String {
+    path.extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase()
+}
+
+/// Decodes an image that the `image` crate can't read, already rotated according to its metadata.
+///
+/// Returns `None` if the image must be read by the `image` crate.
+pub(crate) fn open(path: &Path) -> Option<Result<DynamicImage, Box<dyn Error>>> {
+    match get_extension(path).as_str() {
+        "arw" | "cr2" | "dng" | "nef" => Some(raw::open(path)),
+        #[cfg(feature = "heic")]
+        "heic" | "heif" => Some(heic::open(path)),
+        #[cfg(feature = "jxl")]
+        "jxl" => Some(open_jxl(path)),
+        #[cfg(feature = "video")]
+        "avi" | "m4v" | "mkv" | "mov" | "mp4" | "webm" => {
+            Some(video_frames::get_random_frame(path).and_then(|frame| open_image(&frame)))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the dimensions of an image that the `image` crate can't read, without decoding it.
+///
+/// Returns `None` if the image must be read by the `image` crate.
+pub(crate) fn get_dimensions(path: &Path) -> Option<Result<(u32, u32), Box<dyn Error>>> {
+    match get_extension(path).as_str() {
+        "arw" | "cr2" | "dng" | "nef" => Some(raw::get_dimensions(path)),
+        #[cfg(feature = "heic")]
+        "heic" | "heif" => Some(heic::get_dimensions(path)),
+        #[cfg(feature = "jxl")]
+        "jxl" => Some(get_jxl_dimensions(path)),
+        #[cfg(feature = "video")]
+        "avi" | "m4v" | "mkv" | "mov" | "mp4" | "webm" => {
+            Some(video_frames::get_any_frame(path).and_then(|frame| get_image_dimensions(&frame)))
+        }
+        _ => None,
+    }
+}
+
+/// Decodes a JPEG XL image.
+///
+/// # Errors
+/// Fails if the file can't be opened or decoded.
+#[cfg(feature = "jxl")]
+fn open_jxl(path: &Path) -> Result<DynamicImage, Box<dyn Error>> {
+    let decoder = jxl_oxide::integration::JxlDecoder::new(File::open(path)?)?;
+    Ok(DynamicImage::from_decoder(decoder)?)
+}
+
+/// Returns the dimensions of a JPEG XL image.
+///
+/// # Errors
+/// Fails if the file can't be opened or if its header is invalid.
+#[cfg(feature = "jxl")]
+fn get_jxl_dimensions(path: &Path) -> Result<(u32, u32), Box<dyn Error>> {
+    let decoder = jxl_oxide::integration::JxlDecoder::new(File::open(path)?)?;
+    Ok(decoder.dimensions())
+}
+
+/// Decodes a JPEG image downscaled by the given factor, without decoding it at its full size:
+/// it is scaled by 1/2, 1/4 or 1/8 (the smallest scale that is at least the requested size)
+/// during the inverse DCT. The image isn't rotated according to its metadata.
+///
+/// # Errors
+/// Fails if the file can't be opened or decoded or if its pixel format isn't supported.
+pub(crate) fn open_scaled_jpeg(path: &Path, factor: f64) -> Result<DynamicImage, Box<dyn Error>> {
+    let mut decoder = jpeg_decoder::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.read_info()?;
+    let info = decoder.info().ok_or("The JPEG header couldn't be read")?;
+    #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let scale = |size: u16| {
+        (f64::from(size) * factor)
+            .ceil()
+            .clamp(1.0, f64::from(u16::MAX)) as u16
+    };
+    let (width, height) = decoder.scale(scale(info.width), scale(info.height))?;
+    let pixels = decoder.decode()?;
+
+    let (width, height) = (u32::from(width), u32::from(height));
+    let image = match info.pixel_format {
+        PixelFormat::RGB24 => {
+            RgbImage::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8)
+        }
+        PixelFormat::L8 => GrayImage::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8),
+        pixel_format => {
+            return Err(
+                format!("Unsupported pixel format {pixel_format:?} for a downscaled JPEG").into(),
+            )
+        }
+    };
+    Ok(image.ok_or("The decoded JPEG image doesn't have the expected size")?)
+}
+
+/// Reading of the JPEG previews embedded in the RAW files of the cameras
+/// (Sony ARW, Canon CR2, DNG and Nikon NEF), which are all based on TIFF.
+mod raw {
+    use image::metadata::Orientation;
+    use image::{DynamicImage, ImageFormat, ImageReader};
+    use std::cmp::Reverse;
+    use std::collections::HashSet;
+    use std::error::Error;
+    use std::fs::File;
+    use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom};
+    use std::path::Path;
+
+    /// The TIFF tags that are used to find the previews.
+    const COMPRESSION: u16 = 0x103;
+    const STRIP_OFFSETS: u16 = 0x111;
+    const ORIENTATION: u16 = 0x112;
+    const STRIP_BYTE_COUNTS: u16 = 0x117;
+    const SUB_IFDS: u16 = 0x14a;
+    const JPEG_OFFSET: u16 = 0x201;
+    const JPEG_LENGTH: u16 = 0x202;
+
+    /// The maximum number of IFDs (and of values of an entry) that are read,
+    /// to stop on malformed files.
+    const MAX_IFDS: usize = 32;
+
+    /// The maximum size of a preview, to avoid reading garbage on malformed files.
+    const MAX_PREVIEW_LENGTH: u32 = 64 * 1024 * 1024;
+
+    /// An entry of an IFD, with its value or the offset of its values.
+    struct Entry {
+        tag: u16,
+        kind: u16,
+        count: u32,
+        value: [u8; 4],
+    }
+
+    /// A TIFF file that is read with the byte order of its header.
+    struct Tiff {
+        reader: BufReader<File>,
+        little_endian: bool,
+    }
+
+    impl Tiff {
+        /// Opens a TIFF file and returns it with the offset of its first IFD.
+        ///
+        /// # Errors
+        /// Fails if the file can't be read or if it isn't based on TIFF.
+        fn open(path: &Path) -> Result<(Self, u32), Box<dyn Error>> {
+            let mut reader = BufReader::new(File::open(path)?);
+            let mut byte_order = [0; 2];
+            reader.read_exact(&mut byte_order)?;
+            let little_endian = match &byte_order {
+                b"II" => true,
+                b"MM" => false,
+                _ => return Err("The RAW file isn't based on TIFF".into()),
+            };
+            let mut tiff = Self {
+                reader,
+                little_endian,
+            };
+            tiff.read_u16()?;
+            let first_ifd = tiff.read_u32()?;
+            Ok((tiff, first_ifd))
+        }
+
+        /// Converts 2 bytes with the byte order of the file.
+        fn to_u16(&self, bytes: [u8; 2]) -> u16 {
+            if self.little_endian {
+                u16::from_le_bytes(bytes)
+            } else {
+                u16::from_be_bytes(bytes)
+            }
+        }
+
+        /// Converts 4 bytes with the byte order of the file.
+        fn to_u32(&self, bytes: [u8; 4]) -> u32 {
+            if self.little_endian {
+                u32::from_le_bytes(bytes)
+            } else {
+                u32::from_be_bytes(bytes)
+            }
+        }
+
+        /// Reads a 16-bit integer.
+        fn read_u16(&mut self) -> io::Result<u16> {
+            let mut bytes = [0; 2];
+            self.reader.read_exact(&mut bytes)?;
+            Ok(self.to_u16(bytes))
+        }
+
+        /// Reads a 32-bit integer.
+        fn read_u32(&mut self) -> io::Result<u32> {
+            let mut bytes = [0; 4];
+            self.reader.read_exact(&mut bytes)?;
+            Ok(self.to_u32(bytes))
+        }
+
+        /// Reads the entries of the IFD at the given offset and the offset of the next IFD.
+        fn read_ifd(&mut self, offset: u32) -> io::Result<(Vec<Entry>, u32)> {
+            self.reader.seek(SeekFrom::Start(offset.into()))?;
+            let count = self.read_u16()?;
+            let mut entries = Vec::with_capacity(count.into());
+            for _ in 0..count {
+                let tag = self.read_u16()?;
+                let kind = self.read_u16()?;
+                let count = self.read_u32()?;
+                let mut value = [0; 4];
+                self.reader.read_exact(&mut value)?;
+                entries.push(Entry {
+                    tag,
+                    kind,
+                    count,
+                    value,
+                });
+            }
+            Ok((entries, self.read_u32()?))
+        }
+
+        /// Reads the integer values of an entry (an empty list for the other types).
+        fn read_values(&mut self, entry: &Entry) -> io::Result<Vec<u32>> {
+            let size = match entry.kind {
+                // SHORT
+                3 => 2,
+                // LONG and IFD
+                4 | 13 => 4,
+                _ => return Ok(Vec::new()),
+            };
+            let length = (entry.count as usize).min(MAX_IFDS) * size;
+            let bytes = if length <= 4 {
+                entry.value[..length].to_vec()
+            } else {
+                self.reader
+                    .seek(SeekFrom::Start(self.to_u32(entry.value).into()))?;
+                let mut bytes = vec![0; length];
+                self.reader.read_exact(&mut bytes)?;
+                bytes
+            };
+            Ok(bytes
+                .chunks_exact(size)
+                .map(|chunk| match chunk {
+                    [first, second] => u32::from(self.to_u16([*first, *second])),
+                    [first, second, third, fourth] => {
+                        self.to_u32([*first, *second, *third, *fourth])
+                    }
+                    _ => unreachable!(),
+                })
+                .collect())
+        }
+
+        /// Reads a part of the file.
+        fn read_at(&mut self, offset: u32, length: u32) -> io::Result<Vec<u8>> {
+            self.reader.seek(SeekFrom::Start(offset.into()))?;
+            let mut bytes = vec![0; length as usize];
+            self.reader.read_exact(&mut bytes)?;
+            Ok(bytes)
+        }
+    }
+
+    /// Returns the offsets and lengths of the JPEG previews of a RAW file, from the largest
+    /// to the smallest, and the orientation of the photo.
+    ///
+    /// # Errors
+    /// Fails if the file can't be read or if it isn't based on TIFF.
+    fn find_previews(
+        tiff: &mut Tiff,
+        first_ifd: u32,
+    ) -> Result<(Vec<(u32, u32)>, Orientation), Box<dyn Error>> {
+        let mut previews = Vec::new();
+        let mut orientation = None;
+        let mut visited = HashSet::new();
+        let mut queue = vec![first_ifd];
+        while let Some(offset) = queue.pop() {
+            if offset == 0 || visited.len() == MAX_IFDS || !visited.insert(offset) {
+                continue;
+            }
+            let (entries, next_ifd) = tiff.read_ifd(offset)?;
+            queue.push(next_ifd);
+
+            let mut values = |tag| {
+                entries
+                    .iter()
+                    .find(|entry| entry.tag == tag)
+                    .map_or_else(|| Ok(Vec::new()), |entry| tiff.read_values(entry))
+            };
+            // The orientation of the photo is in the first IFD
+            if offset == first_ifd {
+                orientation = values(ORIENTATION)?
+                    .first()
+                    .and_then(|value| u8::try_from(*value).ok())
+                    .and_then(Orientation::from_exif);
+            }
+            // Previews stored as a JPEG stream
+            if let ([preview_offset], [length]) = (
+                values(JPEG_OFFSET)?.as_slice(),
+                values(JPEG_LENGTH)?.as_slice(),
+            ) {
+                previews.push((*preview_offset, *length));
+            }
+            // Previews stored in a single JPEG-compressed strip
+            if matches!(values(COMPRESSION)?.as_slice(), [6 | 7]) {
+                if let ([preview_offset], [length]) = (
+                    values(STRIP_OFFSETS)?.as_slice(),
+                    values(STRIP_BYTE_COUNTS)?.as_slice(),
+                ) {
+                    previews.push((*preview_offset, *length));
+                }
+            }
+            queue.extend(values(SUB_IFDS)?);
+        }
+        previews.retain(|(_, length)| *length > 0 && *length <= MAX_PREVIEW_LENGTH);
+        previews.sort_by_key(|(_, length)| Reverse(*length));
+        previews.dedup();
+        Ok((previews, orientation.unwrap_or(Orientation::NoTransforms)))
+    }
+
+    /// Returns the largest JPEG preview of a RAW file that can be read with the given function,
+    /// and the orientation of the photo.
+    ///
+    /// # Errors
+    /// Fails if the file can't be read or if it has no readable JPEG preview.
+    fn read_preview<T>(
+        path: &Path,
+        read: impl Fn(&[u8]) -> Result<T, Box<dyn Error>>,
+    ) -> Result<(T, Orientation), Box<dyn Error>> {
+        let (mut tiff, first_ifd) = Tiff::open(path)?;
+        let (previews, orientation) = find_previews(&mut tiff, first_ifd)?;
+        for (offset, length) in previews {
+            let Ok(data) = tiff.read_at(offset, length) else {
+                continue;
+            };
+            // Skip the raw data, which may also be JPEG-compressed, with a lossless JPEG
+            // that can't be read
+            if !data.starts_with(&[0xff, 0xd8]) {
+                continue;
+            }
+            if let Ok(result) = read(&data) {
+                return Ok((result, orientation));
+            }
+        }
+        Err("The RAW file has no readable JPEG preview".into())
+    }
+
+    /// Decodes the largest JPEG preview of a RAW file, rotated according to the RAW metadata.
+    ///
+    /// # Errors
+    /// Fails if the file can't be read or if it has no readable JPEG preview.
+    pub(super) fn open(path: &Path) -> Result<DynamicImage, Box<dyn Error>> {
+        let (mut image, orientation) = read_preview(path, |data| {
+            Ok(image::load_from_memory_with_format(
+                data,
+                ImageFormat::Jpeg,
+            )?)
+        })?;
+        image.apply_orientation(orientation);
+        Ok(image)
+    }
+
+    /// Returns the dimensions of the largest JPEG preview of a RAW file once rotated.
+    ///
+    /// # Errors
+    /// Fails if the file can't be read or if it has no readable JPEG preview.
+    pub(super) fn get_dimensions(path: &Path) -> Result<(u32, u32), Box<dyn Error>> {
+        let ((width, height), orientation) = read_preview(path, |data| {
+            Ok(ImageReader::with_format(Cursor::new(data), ImageFormat::Jpeg).into_dimensions()?)
+        })?;
+        Ok(match orientation {
+            Orientation::Rotate90
+            | Orientation::Rotate270
+            | Orientation::Rotate90FlipH
+            | Orientation::Rotate270FlipH => (height, width),
+            _ => (width, height),
+        })
+    }
+}
+
+#[cfg(feature = "heic")]
+/// Decoding of the HEIC images with `libheif`.
+mod heic {
+    use image::{DynamicImage, RgbImage};
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+    use std::error::Error;
+    use std::path::Path;
+
+    /// Opens a HEIC file.
+    ///
+    /// # Errors
+    /// Fails if the file can't be read or if it isn't a valid HEIC file.
+    fn read_context(path: &Path) -> Result<HeifContext<'static>, Box<dyn Error>> {
+        let path = path
+            .to_str()
+            .ok_or("The path of the image is not valid UTF-8")?;
+        Ok(HeifContext::read_from_file(path)?)
+    }
+
+    /// Decodes the primary image of a HEIC file.
+    ///
+    /// # Errors
+    /// Fails if the file can't be read or decoded.
+    pub(super) fn open(path: &Path) -> Result<DynamicImage, Box<dyn Error>> {
+        let context = read_context(path)?;
+        let handle = context.primary_image_handle()?;
+        let image = LibHeif::new().decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)?;
+        let plane = image
+            .planes()
+            .interleaved
+            .ok_or("The HEIC image has no interleaved plane")?;
+
+        // The rows of the plane may be padded
+        let row_length = plane.width as usize * 3;
+        let mut pixels = Vec::with_capacity(row_length * plane.height as usize);
+        for row in plane.data.chunks(plane.stride).take(plane.height as usize) {
+            pixels.extend_from_slice(&row[..row_length]);
+        }
+        let buffer = RgbImage::from_raw(plane.width, plane.height, pixels)
+            .ok_or("The HEIC image has an invalid size")?;
+        Ok(DynamicImage::ImageRgb8(buffer))
+    }
+
+    /// Returns the dimensions of the primary image of a HEIC file.
+    ///
+    /// # Errors
+    /// Fails if the file can't be read.
+    pub(super) fn get_dimensions(path: &Path) -> Result<(u32, u32), Box<dyn Error>> {
+        let context = read_context(path)?;
+        let handle = context.primary_image_handle()?;
+        Ok((handle.width(), handle.height()))
+    }
+}
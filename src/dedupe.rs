@@ -0,0 +1,59 @@
+//! The deduplication of the downloaded images: the same photo can be returned under different
+//! Unsplash IDs or by different providers, so the checksum of each downloaded file is stored
+//! and a file that is identical to an existing one becomes a hard link to it instead of a copy.
+use log::{debug, info};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use crate::database::Database;
+
+/// Returns the SHA-256 checksum of a file, in hexadecimal.
+///
+/// # Errors
+/// Fails if the file can't be read.
+fn get_checksum(path: &Path) -> Result<String, Box<dyn Error>> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Moves a downloaded file to its destination, or links the destination to an identical file
+/// that was already downloaded and removes the downloaded file.
+///
+/// # Errors
+/// Fails if the downloaded file can't be read or moved, or if the database can't be written to.
+pub(crate) fn store(downloaded: &Path, path: &Path) -> Result<(), Box<dyn Error>> {
+    let checksum = get_checksum(downloaded)?;
+    let database = Database::open()?;
+    let mut linked = false;
+    for existing in database.get_downloaded_files(&checksum)? {
+        if existing == path {
+            continue;
+        }
+        if !existing.exists() {
+            // The file was removed from the cache
+            database.remove_downloaded_file(&existing)?;
+            continue;
+        }
+        match fs::hard_link(&existing, path) {
+            Ok(()) => {
+                info!(
+                    "{path:?} is identical to {existing:?}, linking it instead of storing a copy"
+                );
+                fs::remove_file(downloaded)?;
+                linked = true;
+                break;
+            }
+            // e.g. on a file system without hard links
+            Err(err) => debug!("Couldn't link {path:?} to {existing:?}: {err}"),
+        }
+    }
+    if !linked {
+        fs::rename(downloaded, path)?;
+    }
+    database.add_downloaded_file(path, &checksum)?;
+    Ok(())
+}
@@ -0,0 +1,171 @@
+//! Metadata read from the EXIF tags of the local images.
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use exif::{Context, Exif, In, Reader, Tag, Value};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// The `XPTitle` tag written by Windows, which isn't known by the `exif` crate.
+const XP_TITLE: Tag = Tag(Context::Tiff, 0x9c9b);
+
+/// The `Rating` tag (0 to 5 stars) written by Windows and the photo managers,
+/// which isn't known by the `exif` crate.
+const RATING: Tag = Tag(Context::Tiff, 0x4746);
+
+/// The cities used for the reverse geocoding, as `name,country,latitude,longitude` lines.
+const CITIES: &str = include_str!("cities.csv");
+
+#[derive(Clone, Debug, Default)]
+/// The interesting EXIF metadata of an image.
+pub(crate) struct ExifData {
+    /// The date when the photo was taken.
+    pub(crate) date: Option<DateTime<Local>>,
+    /// The camera model, prefixed by its make if needed.
+    pub(crate) camera: Option<String>,
+    /// The caption of the photo.
+    pub(crate) caption: Option<String>,
+    /// The GPS coordinates `(latitude, longitude)` where the photo was taken.
+    pub(crate) location: Option<(f64, f64)>,
+    /// The number of stars of the photo, `0` if it isn't rated.
+    pub(crate) rating: Option<u8>,
+}
+
+impl ExifData {
+    /// Reads the EXIF metadata of an image.
+    ///
+    /// # Errors
+    /// Fails if the file can't be opened or if it doesn't contain EXIF metadata.
+    pub(crate) fn read(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let file = File::open(path)?;
+        let exif = Reader::new().read_from_container(&mut BufReader::new(file))?;
+
+        let date = get_ascii(&exif, Tag::DateTimeOriginal)
+            .or_else(|| get_ascii(&exif, Tag::DateTime))
+            .and_then(|date| NaiveDateTime::parse_from_str(&date, "%Y:%m:%d %H:%M:%S").ok())
+            .and_then(|date| Local.from_local_datetime(&date).earliest());
+
+        let make = get_ascii(&exif, Tag::Make);
+        let camera = match (make, get_ascii(&exif, Tag::Model)) {
+            // The model often already contains the make ("Canon EOS 5D")
+            (Some(make), Some(model))
+                if !model.to_lowercase().starts_with(&make.to_lowercase()) =>
+            {
+                Some(format!("{make} {model}"))
+            }
+            (make, model) => model.or(make),
+        };
+
+        let caption = get_ascii(&exif, Tag::ImageDescription).or_else(|| get_xp_title(&exif));
+
+        let location = get_coordinate(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, 'S').zip(
+            get_coordinate(&exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, 'W'),
+        );
+
+        let rating = exif
+            .get_field(RATING, In::PRIMARY)
+            .and_then(|field| field.value.get_uint(0))
+            .and_then(|rating| u8::try_from(rating).ok());
+
+        Ok(Self {
+            date,
+            camera,
+            caption,
+            location,
+            rating,
+        })
+    }
+}
+
+/// Returns the trimmed text of an ASCII tag, or `None` if it is missing or empty.
+fn get_ascii(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => values
+            .first()
+            .map(|value| String::from_utf8_lossy(value).trim().to_string())
+            .filter(|value| !value.is_empty()),
+        _ => None,
+    }
+}
+
+/// Returns the `XPTitle` tag, which is stored as UTF-16 bytes.
+fn get_xp_title(exif: &Exif) -> Option<String> {
+    let Value::Byte(bytes) = &exif.get_field(XP_TITLE, In::PRIMARY)?.value else {
+        return None;
+    };
+    let chars: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    Some(
+        String::from_utf16_lossy(&chars)
+            .trim_end_matches('\0')
+            .trim()
+            .to_string(),
+    )
+    .filter(|title| !title.is_empty())
+}
+
+/// Returns a GPS coordinate in decimal degrees, negative when its reference
+/// is `negative_ref` (south or west).
+fn get_coordinate(exif: &Exif, tag: Tag, ref_tag: Tag, negative_ref: char) -> Option<f64> {
+    let Value::Rational(parts) = &exif.get_field(tag, In::PRIMARY)?.value else {
+        return None;
+    };
+    // Degrees, minutes and seconds
+    let degrees = parts
+        .iter()
+        .zip([1.0, 60.0, 3600.0])
+        .map(|(part, divisor)| part.to_f64() / divisor)
+        .sum::<f64>();
+    if !degrees.is_finite() {
+        return None;
+    }
+    let negative =
+        get_ascii(exif, ref_tag).is_some_and(|reference| reference.starts_with(negative_ref));
+    Some(if negative { -degrees } else { degrees })
+}
+
+/// Formats GPS coordinates like `48.8566°N, 2.3522°E`.
+pub(crate) fn format_location((latitude, longitude): (f64, f64)) -> String {
+    format!(
+        "{:.4}°{}, {:.4}°{}",
+        latitude.abs(),
+        if latitude < 0.0 { 'S' } else { 'N' },
+        longitude.abs(),
+        if longitude < 0.0 { 'W' } else { 'E' },
+    )
+}
+
+/// The maximum distance between a photo and a city for the city name to be used, in kilometers.
+const MAX_CITY_DISTANCE: f64 = 150.0;
+
+/// Returns the name of the bundled city that is the closest to the given coordinates,
+/// like `Paris, France`, or `None` if no city is close enough.
+pub(crate) fn reverse_geocode((latitude, longitude): (f64, f64)) -> Option<String> {
+    CITIES
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split(',');
+            let name = parts.next()?;
+            let country = parts.next()?;
+            let city_latitude: f64 = parts.next()?.parse().ok()?;
+            let city_longitude: f64 = parts.next()?.parse().ok()?;
+            let distance =
+                great_circle_distance((latitude, longitude), (city_latitude, city_longitude));
+            Some((distance, name, country))
+        })
+        .filter(|(distance, _, _)| *distance <= MAX_CITY_DISTANCE)
+        .min_by(|first, second| first.0.total_cmp(&second.0))
+        .map(|(_, name, country)| format!("{name}, {country}"))
+}
+
+/// Returns the great-circle distance between two points, in kilometers.
+fn great_circle_distance(first: (f64, f64), second: (f64, f64)) -> f64 {
+    const EARTH_RADIUS: f64 = 6371.0;
+    let (latitude1, longitude1) = (first.0.to_radians(), first.1.to_radians());
+    let (latitude2, longitude2) = (second.0.to_radians(), second.1.to_radians());
+    let haversine = ((latitude2 - latitude1) / 2.0).sin().powi(2)
+        + latitude1.cos() * latitude2.cos() * ((longitude2 - longitude1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * haversine.sqrt().asin()
+}
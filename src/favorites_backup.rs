@@ -0,0 +1,99 @@
+//! The copies of the favorites in the `favorites_dir` of the config (e.g. a synced folder
+//! or an rclone remote), since the online images are deleted from the cache after some time.
+use log::{debug, info};
+use std::error::Error;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::Config;
+use crate::paths::Paths;
+use crate::provenance;
+
+/// Returns `true` if the destination is an rclone remote (`remote:path`)
+/// rather than a folder (a Windows drive like `C:` is a folder).
+fn is_rclone_remote(destination: &str) -> bool {
+    destination
+        .split_once(':')
+        .is_some_and(|(remote, _)| remote.len() > 1 && !remote.contains(['/', '\\']))
+}
+
+/// Returns a path in the folder with the name of the image that isn't used by another file.
+fn get_free_path(dir: &Path, image: &Path) -> PathBuf {
+    let stem = image.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = image.extension().unwrap_or_default().to_string_lossy();
+    let mut path = dir.join(image.file_name().unwrap_or_default());
+    let mut index = 1;
+    while path.exists() {
+        path = dir.join(format!("{stem}-{index}.{extension}"));
+        index += 1;
+    }
+    path
+}
+
+/// Copies an image to a folder, keeping its modification date. The source of the image
+/// (e.g. the URL of an online image) is written in the copy. Returns the path of the copy.
+///
+/// # Errors
+/// Fails if the folder can't be created or if the image can't be copied.
+fn copy_to_dir(image: &Path, source: &str, dir: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+    let destination = dir.join(image.file_name().unwrap_or_default());
+    if destination.exists() && fs::read(&destination)? == fs::read(image)? {
+        debug!("{image:?} is already in {dir:?}");
+        return Ok(destination);
+    }
+    let destination = get_free_path(dir, image);
+    fs::copy(image, &destination)?;
+    if source != image.to_string_lossy() {
+        provenance::write_source(&destination, source, None)?;
+    }
+    File::options()
+        .append(true)
+        .open(&destination)?
+        .set_modified(fs::metadata(image)?.modified()?)?;
+    Ok(destination)
+}
+
+/// Copies an image to an rclone remote.
+///
+/// # Errors
+/// Fails if `rclone` can't be called or if the copy fails.
+fn copy_to_remote(image: &Path, remote: &str) -> Result<(), Box<dyn Error>> {
+    let result = Command::new("rclone")
+        .arg("copy")
+        .arg(image)
+        .arg(remote)
+        .output()
+        .map_err(|err| format!("Could not copy the favorite using rclone: {err}"))?;
+    if !result.status.success() {
+        return Err(format!(
+            "Could not copy the favorite to {remote}: {}",
+            String::from_utf8_lossy(&result.stderr)
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Copies a new favorite to the `favorites_dir` of the config, if it is set.
+///
+/// # Errors
+/// Fails if the image can't be copied.
+pub(crate) fn backup(config: &Config, image: &Path, source: &str) -> Result<(), Box<dyn Error>> {
+    if config.favorites_dir.is_empty() {
+        return Ok(());
+    }
+    if is_rclone_remote(&config.favorites_dir) {
+        // The copy with the source is sent, then deleted
+        let copy = copy_to_dir(image, source, &Paths::temp_dir()?.join("favorites"))?;
+        let result = copy_to_remote(&copy, &config.favorites_dir);
+        fs::remove_file(copy)?;
+        result?;
+        info!("Copied {image:?} to {}", config.favorites_dir);
+    } else {
+        let destination = copy_to_dir(image, source, &Paths::expand_user(&config.favorites_dir))?;
+        info!("Copied {image:?} to {destination:?}");
+    }
+    Ok(())
+}
@@ -0,0 +1,85 @@
+//! The feed of the upcoming wallpapers: several wallpapers composed ahead of time in a folder
+//! that a screensaver or a digital photo frame can watch.
+use log::{debug, error, info};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::i18n::tr;
+use crate::image_list::{self, ImageData};
+use crate::screen_size::get_monitors;
+use crate::{compose_background, get_rng, resolve_config, UnreadableImageError, MAX_ATTEMPTS};
+
+/// The start of the names of the wallpapers of the feed.
+const FEED_PREFIX: &str = "wallpaper_";
+
+/// Removes the wallpapers of the previous feed from the folder (the other files are kept).
+///
+/// # Errors
+/// Fails if the folder can't be read or if a wallpaper can't be deleted.
+fn clear_feed(dir: &Path) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file()
+            && path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(FEED_PREFIX))
+        {
+            debug!("Removing the previous wallpaper {path:?}");
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Composes `count` wallpapers with the images that would be chosen next and saves them
+/// in the folder as `wallpaper_001.jpg`, `wallpaper_002.jpg`... in their order.
+/// The wallpaper and the history aren't changed.
+///
+/// # Errors
+/// Fails if the folder can't be written, if not enough images can be chosen
+/// or if an image can't be composed.
+pub(crate) fn export(config: &Config, dir: &Path, count: u32) -> Result<(), Box<dyn Error>> {
+    let config = &resolve_config(config);
+    let mut rng = get_rng(config);
+    let mut image_data = ImageData::load()?;
+    let monitors = get_monitors();
+
+    fs::create_dir_all(dir)?;
+    clear_feed(dir)?;
+
+    let mut used_images: Vec<PathBuf> = Vec::new();
+    let mut attempts = 0;
+    let mut index = 1;
+    while index <= count {
+        let image = image_list::select_random_image(config, &mut image_data, &mut rng)?;
+        let image_path = image.get_path();
+        // Choose another image if it is already in the feed (unless there are too few images)
+        if used_images.contains(&image_path) && attempts < MAX_ATTEMPTS {
+            attempts += 1;
+            continue;
+        }
+        let output_path = match compose_background(config, image.as_ref(), &monitors) {
+            Ok((output_path, _)) => output_path,
+            Err(err) if err.is::<UnreadableImageError>() && attempts < MAX_ATTEMPTS => {
+                error!("{err}, choosing another image");
+                attempts += 1;
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        let feed_path = dir
+            .join(format!("{FEED_PREFIX}{index:03}"))
+            .with_extension(output_path.extension().unwrap_or_default());
+        info!("Saving {image_path:?} in {feed_path:?}");
+        fs::copy(&output_path, &feed_path)?;
+
+        used_images.push(image_path);
+        attempts = 0;
+        index += 1;
+    }
+
+    println!("{}", tr!("feed-saved", count = count, dir = dir.display()));
+    Ok(())
+}
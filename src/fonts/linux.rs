@@ -0,0 +1,59 @@
+//! Utility functions to find the fonts on Linux.
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Returns the path of the font that `fontconfig` uses for the given pattern.
+///
+/// # Errors
+/// Fails if `fc-match` can't be called or if no font matches.
+fn match_font(pattern: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let output = Command::new("fc-match")
+        .args(["--format", "%{file}", pattern])
+        .output()
+        .map_err(|err| format!("Could not find the font {pattern} using fc-match: {err}"))?;
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || path.is_empty() {
+        return Err(format!("No font matches {pattern}").into());
+    }
+    Ok(PathBuf::from(path))
+}
+
+/// Returns the path of the font of the given family.
+///
+/// # Errors
+/// Fails if `fc-match` can't be called or if the font isn't installed.
+pub(crate) fn find_font(family: &str) -> Result<PathBuf, Box<dyn Error>> {
+    // fontconfig returns another font (e.g. the default one) when the family isn't installed
+    let output = Command::new("fc-list")
+        .args([family, "family"])
+        .output()
+        .map_err(|err| format!("Could not list the fonts using fc-list: {err}"))?;
+    let installed = String::from_utf8_lossy(&output.stdout).lines().any(|line| {
+        line.split(',')
+            .any(|name| name.trim().eq_ignore_ascii_case(family))
+    });
+    if !installed {
+        return Err(format!("The {family} font is not installed").into());
+    }
+    match_font(family)
+}
+
+/// Returns the paths of the fonts used when a glyph is missing:
+/// the default sans-serif font and the Japanese, Chinese and Korean fonts.
+pub(crate) fn get_fallback_fonts() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for pattern in [
+        "sans-serif",
+        "sans-serif:lang=ja",
+        "sans-serif:lang=zh-cn",
+        "sans-serif:lang=ko",
+    ] {
+        if let Ok(path) = match_font(pattern) {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    paths
+}
@@ -0,0 +1,162 @@
+//! Fonts used to write text on the wallpaper, with fallbacks for the missing glyphs.
+use ab_glyph::{Font, FontArc, PxScale};
+use image::{Rgba, RgbaImage};
+use imageproc::drawing::{draw_text_mut, text_size};
+use log::{debug, error};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::ptr;
+
+use crate::config::Config;
+use crate::paths::Paths;
+
+#[cfg(target_os = "linux")]
+mod linux;
+
+#[cfg(target_os = "windows")]
+mod windows;
+
+/// The family of the default font, loaded from the system.
+const DEFAULT_FONT_FAMILY: &str = "Montserrat";
+
+/// A list of fonts: each character is drawn with the first font that has a glyph for it.
+pub(crate) struct FontStack {
+    fonts: Vec<FontArc>,
+}
+
+impl FontStack {
+    /// Loads the font of the configuration (if any), the installed Montserrat font
+    /// and the fallback fonts of the system (including CJK fonts).
+    ///
+    /// # Errors
+    /// Fails if no font is available.
+    pub(crate) fn load(config: &Config) -> Result<Self, Box<dyn Error>> {
+        let mut fonts = Vec::new();
+
+        if !config.font_path.is_empty() {
+            let path = Paths::expand_user(&config.font_path);
+            match load_font_file(&path) {
+                Ok(font) => fonts.push(font),
+                Err(err) => error!("Couldn't load the font {path:?}: {err}"),
+            }
+        }
+
+        if !config.font_family.is_empty() {
+            match load_system_font(&config.font_family) {
+                Ok(font) => fonts.push(font),
+                Err(err) => error!("Couldn't load the {} font: {err}", config.font_family),
+            }
+        }
+
+        match load_system_font(DEFAULT_FONT_FAMILY) {
+            Ok(font) => fonts.push(font),
+            Err(err) => debug!("Couldn't load the {DEFAULT_FONT_FAMILY} font: {err}"),
+        }
+
+        #[cfg(target_os = "linux")]
+        let fallback_paths = linux::get_fallback_fonts();
+        #[cfg(target_os = "windows")]
+        let fallback_paths = windows::get_fallback_fonts();
+
+        for path in fallback_paths {
+            match load_font_file(&path) {
+                Ok(font) => {
+                    debug!("Loaded the fallback font {path:?}");
+                    fonts.push(font);
+                }
+                Err(err) => debug!("Couldn't load the fallback font {path:?}: {err}"),
+            }
+        }
+
+        if fonts.is_empty() {
+            return Err("No font could be loaded".into());
+        }
+
+        Ok(Self { fonts })
+    }
+
+    /// Returns the first font that has a glyph for the character,
+    /// or the first font if none of them has one.
+    fn font_for(&self, character: char) -> &FontArc {
+        self.fonts
+            .iter()
+            .find(|font| font.glyph_id(character).0 != 0)
+            .unwrap_or(&self.fonts[0])
+    }
+
+    /// Splits the text into runs of characters that are drawn with the same font.
+    fn runs<'a>(&self, text: &'a str) -> Vec<(&FontArc, &'a str)> {
+        let mut runs = Vec::new();
+        let mut start = 0;
+        let mut current: Option<&FontArc> = None;
+        for (index, character) in text.char_indices() {
+            // Spaces are drawn with the font of the previous characters
+            if character.is_whitespace() && current.is_some() {
+                continue;
+            }
+            let font = self.font_for(character);
+            if let Some(current_font) = current {
+                if !ptr::eq(current_font, font) {
+                    runs.push((current_font, &text[start..index]));
+                    start = index;
+                }
+            }
+            current = Some(font);
+        }
+        if let Some(font) = current {
+            runs.push((font, &text[start..]));
+        }
+        runs
+    }
+
+    /// Returns the size of a line of text.
+    pub(crate) fn text_size(&self, scale: PxScale, text: &str) -> (u32, u32) {
+        self.runs(text)
+            .into_iter()
+            .map(|(font, run)| text_size(scale, font, run))
+            .fold((0, 0), |(width, height), (run_width, run_height)| {
+                (width + run_width, height.max(run_height))
+            })
+    }
+
+    /// Draws a line of text on an image, using the fallback fonts for the missing glyphs.
+    #[expect(clippy::cast_possible_wrap)]
+    pub(crate) fn draw_text_mut(
+        &self,
+        image: &mut RgbaImage,
+        color: Rgba<u8>,
+        x: i32,
+        y: i32,
+        scale: PxScale,
+        text: &str,
+    ) {
+        let mut x = x;
+        for (font, run) in self.runs(text) {
+            draw_text_mut(image, color, x, y, scale, font, run);
+            x += text_size(scale, font, run).0 as i32;
+        }
+    }
+}
+
+/// Loads an installed font from its family name.
+///
+/// # Errors
+/// Fails if the font isn't installed or if it can't be loaded.
+fn load_system_font(family: &str) -> Result<FontArc, Box<dyn Error>> {
+    #[cfg(target_os = "linux")]
+    let path = linux::find_font(family)?;
+    #[cfg(target_os = "windows")]
+    let path = windows::find_font(family)?;
+
+    debug!("Using {path:?} for the {family} font");
+    load_font_file(&path)
+}
+
+/// Loads a font file (`.ttf`, `.otf` or the first font of a `.ttc` collection).
+///
+/// # Errors
+/// Fails if the file can't be read or if it isn't a valid font.
+fn load_font_file(path: &Path) -> Result<FontArc, Box<dyn Error>> {
+    Ok(FontArc::try_from_vec(fs::read(path)?)?)
+}
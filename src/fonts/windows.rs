@@ -0,0 +1,61 @@
+//! Utility functions to find the fonts on Windows.
+use std::env;
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// The fonts used when a glyph is missing: Segoe UI, then the Japanese, Chinese and Korean fonts.
+const FALLBACK_FONTS: [&str; 5] = [
+    "segoeui.ttf",
+    "YuGothB.ttc",
+    "msyh.ttc",
+    "malgun.ttf",
+    "seguisym.ttf",
+];
+
+/// Returns the fonts folder of Windows.
+fn get_fonts_dir() -> PathBuf {
+    PathBuf::from(env::var("WINDIR").unwrap_or_else(|_| r"C:\Windows".to_string())).join("Fonts")
+}
+
+/// Returns the path of the font of the given family, using the list of installed fonts in the registry.
+///
+/// # Errors
+/// Fails if `reg` can't be called or if the font isn't installed.
+pub(crate) fn find_font(family: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let family = family.to_lowercase();
+    // The fonts installed for the current user are in HKCU
+    for key in [
+        r"HKCU\Software\Microsoft\Windows NT\CurrentVersion\Fonts",
+        r"HKLM\Software\Microsoft\Windows NT\CurrentVersion\Fonts",
+    ] {
+        let Ok(output) = Command::new("reg").args(["query", key]).output() else {
+            continue;
+        };
+
+        // The output contains lines like "Arial (TrueType)    REG_SZ    arial.ttf"
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let file = stdout.lines().find_map(|line| {
+            let (name, file) = line.split_once("REG_SZ")?;
+            let name = name.trim().to_lowercase();
+            (name == family || name.starts_with(&format!("{family} ("))).then_some(file.trim())
+        });
+
+        if let Some(file) = file {
+            // The value is a full path for the fonts of the current user
+            return Ok(get_fonts_dir().join(file));
+        }
+    }
+
+    Err(format!("The {family} font is not installed").into())
+}
+
+/// Returns the paths of the fonts used when a glyph is missing.
+pub(crate) fn get_fallback_fonts() -> Vec<PathBuf> {
+    let fonts_dir = get_fonts_dir();
+    FALLBACK_FONTS
+        .iter()
+        .map(|file| fonts_dir.join(file))
+        .filter(|path| path.exists())
+        .collect()
+}
@@ -0,0 +1,186 @@
+//! A static HTML gallery of the last wallpapers, to review them and choose
+//! the images to add to the favorites or to the blocklist.
+use chrono::Local;
+use image::imageops::FilterType;
+use log::{debug, warn};
+use std::env;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{self, Path, PathBuf};
+use url::Url;
+
+use crate::database::{Database, HistoryEntry};
+use crate::i18n::tr;
+use crate::image_structs::open_image;
+use crate::paths::Paths;
+use crate::slideshow::escape_xml;
+
+/// The number of wallpapers of the history that are shown in the gallery.
+const MAX_ENTRIES: u32 = 500;
+
+/// The maximum size of the thumbnails.
+const THUMBNAIL_SIZE: (u32, u32) = (320, 200);
+
+/// The head of the gallery, with its style and the script that copies the commands.
+const HEADER: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Wallpaper gallery</title>
+<style>
+body { font-family: sans-serif; margin: 2em; background: #222; color: #eee; }
+a { color: #9cf; }
+.gallery { display: grid; grid-template-columns: repeat(auto-fill, minmax(340px, 1fr)); gap: 1.5em; }
+.entry { background: #333; padding: 10px; border-radius: 6px; }
+.entry img { display: block; max-width: 100%; margin: auto; }
+.missing { height: 200px; display: flex; align-items: center; justify-content: center; color: #999; }
+.description { white-space: pre-line; }
+.date { color: #aaa; font-size: 0.9em; }
+button { cursor: pointer; }
+</style>
+<script>
+function copyCommand(button) {
+  navigator.clipboard.writeText(button.dataset.command).then(() => {
+    button.textContent = "Command copied";
+  });
+}
+</script>
+</head>
+<body>
+"#;
+
+/// Escapes the characters that have a special meaning in HTML, including in the attributes.
+fn escape_html(text: &str) -> String {
+    escape_xml(text).replace('"', "&quot;")
+}
+
+/// Returns the URL of a file, or `None` if its path isn't absolute.
+fn get_file_url(path: &Path) -> Option<String> {
+    Url::from_file_path(path).ok().map(String::from)
+}
+
+/// Returns the link to the source of an image: its URL, or its file if it is a local image.
+fn get_source_url(source: &str) -> Option<String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        Some(source.to_string())
+    } else {
+        get_file_url(Path::new(source))
+    }
+}
+
+/// Returns the thumbnail of an image, making it if it doesn't exist yet,
+/// or `None` if the image isn't available anymore (e.g. an online image deleted from the cache).
+fn get_thumbnail(path: &Path) -> Option<PathBuf> {
+    if !path.is_file() {
+        return None;
+    }
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    let thumbnail_path = Paths::gallery_thumbnails_dir()
+        .ok()?
+        .join(format!("{:016x}.jpg", hasher.finish()));
+    if thumbnail_path.exists() {
+        return Some(thumbnail_path);
+    }
+
+    debug!("Making the thumbnail of {path:?}");
+    let result = open_image(path).and_then(|image| {
+        let thumbnail = image.resize(THUMBNAIL_SIZE.0, THUMBNAIL_SIZE.1, FilterType::Triangle);
+        Ok(thumbnail.to_rgb8().save(&thumbnail_path)?)
+    });
+    match result {
+        Ok(()) => Some(thumbnail_path),
+        Err(err) => {
+            warn!("Couldn't make the thumbnail of {path:?}: {err}");
+            None
+        }
+    }
+}
+
+/// Returns the HTML of a wallpaper of the history, with buttons that copy the commands
+/// that add the image to the favorites or to the blocklist.
+fn get_entry_html(entry: &HistoryEntry, program: &str) -> String {
+    let mut html = String::from("<div class=\"entry\">\n");
+    match get_thumbnail(&entry.path).and_then(|thumbnail| get_file_url(&thumbnail)) {
+        Some(url) => {
+            let _ = writeln!(html, "<img src=\"{}\" alt=\"\">", escape_html(&url));
+        }
+        None => {
+            html.push_str("<div class=\"missing\">The image isn't in the cache anymore</div>\n");
+        }
+    }
+
+    let _ = writeln!(
+        html,
+        "<p class=\"date\">{}</p>",
+        entry
+            .shown_at
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M")
+    );
+    if !entry.description.is_empty() {
+        let _ = writeln!(
+            html,
+            "<p class=\"description\">{}</p>",
+            escape_html(&entry.description)
+        );
+    }
+    let source = escape_html(&entry.source);
+    match get_source_url(&entry.source) {
+        Some(url) => {
+            let _ = writeln!(
+                html,
+                "<p><a href=\"{}\">{source}</a></p>",
+                escape_html(&url)
+            );
+        }
+        None => {
+            let _ = writeln!(html, "<p>{source}</p>");
+        }
+    }
+
+    let path = entry.path.to_string_lossy();
+    for (command, label) in [("favorite", "Favorite"), ("block", "Block")] {
+        let _ = writeln!(
+            html,
+            "<button data-command=\"{}\" onclick=\"copyCommand(this)\">{label}</button>",
+            escape_html(&format!("{program} {command} \"{path}\""))
+        );
+    }
+    html.push_str("</div>\n");
+    html
+}
+
+/// Writes the gallery of the last wallpapers of the history in the given file,
+/// or in `gallery.html` in the cache directory.
+///
+/// # Errors
+/// Fails if the history can't be read or if the gallery can't be written.
+pub(crate) fn write(path: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let path = match path {
+        Some(path) => path::absolute(path)?,
+        None => Paths::gallery_file()?.to_path_buf(),
+    };
+    let entries = Database::open()?.get_history(MAX_ENTRIES)?;
+    let program = env::current_exe()?.to_string_lossy().to_string();
+
+    let mut html = String::from(HEADER);
+    let _ = writeln!(
+        html,
+        "<h1>Wallpaper gallery</h1>\n<p>{} wallpapers, generated on {}. \
+         The buttons copy the command that adds the image to the favorites or to the blocklist.</p>",
+        entries.len(),
+        Local::now().format("%Y-%m-%d %H:%M")
+    );
+    html.push_str("<div class=\"gallery\">\n");
+    for entry in &entries {
+        html.push_str(&get_entry_html(entry, &program));
+    }
+    html.push_str("</div>\n</body>\n</html>\n");
+
+    fs::write(&path, html)?;
+    println!("{}", tr!("gallery-saved", path = path.display()));
+    Ok(())
+}
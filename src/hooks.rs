@@ -0,0 +1,70 @@
+//! Commands run before and after the wallpaper changes (e.g. to restart a compositor
+//! or to update conky), with environment variables describing the new wallpaper.
+use log::{error, info};
+use std::path::Path;
+
+use crate::config::Config;
+use crate::image_structs::{open_image, Image};
+use crate::palette::{run_shell_command, Palette};
+
+/// Returns the environment variables describing the image of the new wallpaper.
+fn get_image_variables(image: &dyn Image) -> Vec<(String, String)> {
+    vec![
+        (
+            "WALLPAPER_IMAGE".to_string(),
+            image.get_path().to_string_lossy().to_string(),
+        ),
+        ("WALLPAPER_SOURCE".to_string(), image.get_source()),
+        ("WALLPAPER_DESCRIPTION".to_string(), image.get_description()),
+        (
+            "WALLPAPER_AUTHOR".to_string(),
+            image.get_author().unwrap_or_default(),
+        ),
+    ]
+}
+
+/// Runs the hooks one after the other. A hook that fails doesn't prevent the next ones from running.
+fn run_hooks(kind: &str, hooks: &[String], variables: &[(String, String)]) {
+    for hook in hooks {
+        info!("Running the {kind} hook {hook:?}");
+        if let Err(err) = run_shell_command(hook, variables) {
+            error!("Couldn't run the {kind} hook: {err}");
+        }
+    }
+}
+
+/// Runs the `pre_change` hooks before the image is set as the wallpaper.
+pub(crate) fn run_pre_change(config: &Config, image: &dyn Image) {
+    if config.hooks.pre_change.is_empty() {
+        return;
+    }
+    let variables = get_image_variables(image);
+    run_hooks("pre_change", &config.hooks.pre_change, &variables);
+}
+
+/// Runs the `post_change` hooks after the image is set as the wallpaper,
+/// with the path of the generated wallpaper and its colors.
+pub(crate) fn run_post_change(config: &Config, image: &dyn Image, wallpaper: &Path) {
+    if config.hooks.post_change.is_empty() {
+        return;
+    }
+    let mut variables = get_image_variables(image);
+    variables.push((
+        "WALLPAPER_PATH".to_string(),
+        wallpaper.to_string_lossy().to_string(),
+    ));
+    match open_image(wallpaper) {
+        Ok(img) => {
+            let palette = Palette::from_image(&img, wallpaper);
+            variables.extend(
+                palette
+                    .special
+                    .into_iter()
+                    .chain(palette.colors)
+                    .map(|(name, color)| (format!("WALLPAPER_{}", name.to_uppercase()), color)),
+            );
+        }
+        Err(err) => error!("Couldn't read the colors of the wallpaper: {err}"),
+    }
+    run_hooks("post_change", &config.hooks.post_change, &variables);
+}
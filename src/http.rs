@@ -0,0 +1,197 @@
+//! The HTTP client used for the online images, the geolocation and the webhooks,
+//! with the proxy, the certificate authorities and the download speed limit of the config.
+use log::debug;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::RwLock;
+use std::thread;
+use std::time::{Duration, Instant};
+use ureq::tls::{parse_pem, PemItem, RootCerts, TlsConfig};
+use ureq::typestate::{WithBody, WithoutBody};
+use ureq::{Agent, Proxy, RequestBuilder};
+use url::Url;
+
+use crate::config::Config;
+use crate::paths::Paths;
+
+/// The maximum time to connect to a server, including the TLS handshake.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The maximum duration of a request, from the connection to the end of the response.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The maximum duration of an image download, which can be slowed down by the download speed limit.
+/// An interrupted download is resumed on the next attempt.
+pub(crate) const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// The agents used for the requests, set by [`configure`].
+static AGENTS: RwLock<Option<Agents>> = RwLock::new(None);
+
+/// The maximum download speed in kilobits per second, `0` for no limit, set by [`configure`].
+static MAX_DOWNLOAD_KBPS: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Clone)]
+/// The agents used for the requests.
+struct Agents {
+    /// The agent that uses the proxy, if there is one.
+    proxied: Agent,
+    /// The agent used for the hosts of `NO_PROXY`.
+    direct: Agent,
+    /// The hosts (and their subdomains) that are accessed without the proxy.
+    no_proxy: Vec<String>,
+}
+
+impl Agents {
+    /// Returns the agent used to access the URL.
+    fn get_agent(&self, url: &str) -> &Agent {
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_lowercase))
+            .unwrap_or_default();
+        let bypass = self
+            .no_proxy
+            .iter()
+            .any(|entry| entry == "*" || host == *entry || host.ends_with(&format!(".{entry}")));
+        if bypass {
+            &self.direct
+        } else {
+            &self.proxied
+        }
+    }
+}
+
+/// Returns the value of an environment variable, in uppercase or lowercase, if it isn't empty.
+fn get_env(name: &str) -> Option<String> {
+    env::var(name)
+        .or_else(|_| env::var(name.to_lowercase()))
+        .ok()
+        .filter(|value| !value.is_empty())
+}
+
+/// Reads the certificates of a PEM file.
+///
+/// # Errors
+/// Fails if the file can't be read or if it has no certificate.
+fn read_certificates(path: &str) -> Result<TlsConfig, Box<dyn Error>> {
+    let pem = fs::read(Paths::expand_user(path))?;
+    let certificates = parse_pem(&pem)
+        .filter_map(|item| match item {
+            Ok(PemItem::Certificate(certificate)) => Some(certificate.to_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if certificates.is_empty() {
+        return Err(format!("No certificate found in {path:?}").into());
+    }
+    debug!("Loaded {} certificates from {path:?}", certificates.len());
+    Ok(TlsConfig::builder()
+        .root_certs(RootCerts::new_with_certs(&certificates))
+        .build())
+}
+
+/// Makes the agents used for the requests with the `proxy` and `ca_bundle` of the config
+/// and sets the download speed limit.
+///
+/// The requests time out after [`REQUEST_TIMEOUT`].
+///
+/// Without a proxy in the config, the `HTTPS_PROXY` and `HTTP_PROXY` environment variables are used.
+/// The hosts of `NO_PROXY` are always accessed directly.
+///
+/// # Errors
+/// Fails if the proxy URL is invalid or if the certificates can't be read.
+pub(crate) fn configure(config: &Config) -> Result<(), Box<dyn Error>> {
+    let proxy = if config.proxy.is_empty() {
+        get_env("HTTPS_PROXY").or_else(|| get_env("HTTP_PROXY"))
+    } else {
+        Some(config.proxy.clone())
+    };
+    let proxy = proxy.map(|proxy| Proxy::new(&proxy)).transpose()?;
+    let tls_config = if config.ca_bundle.is_empty() {
+        TlsConfig::default()
+    } else {
+        read_certificates(&config.ca_bundle)?
+    };
+
+    let make_agent = |proxy: Option<Proxy>| -> Agent {
+        Agent::config_builder()
+            .proxy(proxy)
+            .tls_config(tls_config.clone())
+            .timeout_connect(Some(CONNECT_TIMEOUT))
+            .timeout_global(Some(REQUEST_TIMEOUT))
+            .build()
+            .into()
+    };
+    let agents = Agents {
+        proxied: make_agent(proxy),
+        direct: make_agent(None),
+        no_proxy: get_env("NO_PROXY")
+            .unwrap_or_default()
+            .split(',')
+            .map(|entry| entry.trim().trim_start_matches('.').to_lowercase())
+            .filter(|entry| !entry.is_empty())
+            .collect(),
+    };
+    if let Ok(mut current) = AGENTS.write() {
+        *current = Some(agents);
+    }
+    MAX_DOWNLOAD_KBPS.store(config.max_download_kbps, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Starts a GET request with the agent configured by [`configure`].
+pub(crate) fn get(url: &str) -> RequestBuilder<WithoutBody> {
+    let agents = AGENTS.read().ok().and_then(|agents| agents.clone());
+    match agents {
+        Some(agents) => agents.get_agent(url).get(url),
+        // The default agent uses the proxy of the environment
+        None => ureq::get(url),
+    }
+}
+
+/// Starts a POST request with the agent configured by [`configure`].
+pub(crate) fn post(url: &str) -> RequestBuilder<WithBody> {
+    let agents = AGENTS.read().ok().and_then(|agents| agents.clone());
+    match agents {
+        Some(agents) => agents.get_agent(url).post(url),
+        None => ureq::post(url),
+    }
+}
+
+/// A reader that waits between the reads to stay under the download speed limit.
+pub(crate) struct Throttled<R> {
+    inner: R,
+    start: Instant,
+    read_bytes: u64,
+}
+
+impl<R: Read> Read for Throttled<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let max_kbps = MAX_DOWNLOAD_KBPS.load(Ordering::Relaxed);
+        if max_kbps == 0 {
+            return self.inner.read(buf);
+        }
+        let bytes_per_second = u64::from(max_kbps) * 1000 / 8;
+        // Read at most a tenth of a second of data at once so that the speed stays even
+        let length = usize::try_from(bytes_per_second / 10)
+            .map_or(buf.len(), |length| length.max(1).min(buf.len()));
+        let count = self.inner.read(&mut buf[..length])?;
+        self.read_bytes += count as u64;
+        let expected = Duration::from_millis(self.read_bytes * 1000 / bytes_per_second);
+        if let Some(delay) = expected.checked_sub(self.start.elapsed()) {
+            thread::sleep(delay);
+        }
+        Ok(count)
+    }
+}
+
+/// Limits the speed of a download to the `max_download_kbps` of the config.
+pub(crate) fn throttle<R: Read>(reader: R) -> Throttled<R> {
+    Throttled {
+        inner: reader,
+        start: Instant::now(),
+        read_bytes: 0,
+    }
+}
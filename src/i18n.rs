@@ -0,0 +1,210 @@
+//! The translations of the messages shown to the user (the output of the commands,
+//! the notifications and the dates), chosen with the `language` option of the config
+//! or the locale of the system.
+//!
+//! The catalogs in `locales` use the Fluent syntax (`message-id = Text with { $variable }`)
+//! and are parsed once, the first time a message is translated. The logs stay in English.
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use log::warn;
+use std::env;
+use std::sync::OnceLock;
+use unic_langid::LanguageIdentifier;
+
+use crate::config::Config;
+
+/// The catalogs of the supported languages, the first one being used for the missing messages.
+const CATALOGS: [(&str, &str); 2] = [
+    ("en", include_str!("locales/en.ftl")),
+    ("fr", include_str!("locales/fr.ftl")),
+];
+
+/// The language of the messages, once it is known.
+static LANGUAGE: OnceLock<&'static str> = OnceLock::new();
+
+/// The parsed catalogs, in the same order as [`CATALOGS`].
+static BUNDLES: OnceLock<Vec<(&'static str, FluentBundle<FluentResource>)>> = OnceLock::new();
+
+/// Returns the translation of a message with the values of its variables,
+/// e.g. `tr!("feed-saved", count = 10, dir = dir.display())`.
+macro_rules! tr {
+    ($id:expr $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::i18n::translate($id, &[$((stringify!($name), $value.to_string())),*])
+    };
+}
+
+pub(crate) use tr;
+
+/// Returns the supported language of a locale (e.g. `fr` for `fr_FR.UTF-8` or `fr-FR`).
+fn find_language(locale: &str) -> Option<&'static str> {
+    let code = locale.split(['_', '-', '.', '@']).next()?.to_lowercase();
+    CATALOGS
+        .iter()
+        .map(|(language, _)| *language)
+        .find(|language| *language == code)
+}
+
+/// Returns the locale chosen in the settings of Windows (e.g. `fr-FR`).
+#[cfg(target_os = "windows")]
+fn get_system_locale() -> Option<String> {
+    use std::process::Command;
+
+    let output = Command::new("reg")
+        .args([
+            "query",
+            r"HKCU\Control Panel\International",
+            "/v",
+            "LocaleName",
+        ])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("LocaleName"))
+        .and_then(|line| line.split_whitespace().last())
+        .map(str::to_string)
+}
+
+/// Returns the locale of the system when it isn't in the environment variables.
+#[cfg(not(target_os = "windows"))]
+const fn get_system_locale() -> Option<String> {
+    None
+}
+
+/// Returns the language of the `language` option of the config, or the language of the locale
+/// of the system (English if it isn't supported) if it is `auto`.
+fn resolve_language(language: &str) -> &'static str {
+    if let Some(language) = find_language(language) {
+        return language;
+    }
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+        .or_else(get_system_locale)
+        .and_then(|locale| find_language(&locale))
+        .unwrap_or(CATALOGS[0].0)
+}
+
+/// Sets the language of the messages with the `language` option of the config,
+/// unless a message was already translated.
+pub(crate) fn set_language(language: &str) {
+    let _ = LANGUAGE.set(resolve_language(language));
+}
+
+/// Returns the language of the messages, read from the config the first time.
+pub(crate) fn get_language() -> &'static str {
+    LANGUAGE.get_or_init(|| {
+        resolve_language(
+            &Config::read_unvalidated()
+                .map(|config| config.language)
+                .unwrap_or_default(),
+        )
+    })
+}
+
+/// Parses the catalog of a language. The invalid messages are skipped.
+fn make_bundle(language: &str, catalog: &str) -> FluentBundle<FluentResource> {
+    let resource =
+        FluentResource::try_new(catalog.to_string()).unwrap_or_else(|(resource, errors)| {
+            warn!("Invalid messages in the {language} catalog: {errors:?}");
+            resource
+        });
+    let mut bundle = FluentBundle::new_concurrent(vec![language
+        .parse::<LanguageIdentifier>()
+        .unwrap_or_default()]);
+    // The Unicode isolation marks around the variables are visible in some terminals
+    bundle.set_use_isolating(false);
+    if let Err(errors) = bundle.add_resource(resource) {
+        warn!("Duplicate messages in the {language} catalog: {errors:?}");
+    }
+    bundle
+}
+
+/// Returns the parsed catalogs, parsing them the first time.
+fn get_bundles() -> &'static [(&'static str, FluentBundle<FluentResource>)] {
+    BUNDLES.get_or_init(|| {
+        CATALOGS
+            .iter()
+            .map(|(language, catalog)| (*language, make_bundle(language, catalog)))
+            .collect()
+    })
+}
+
+/// Returns a message of the catalog of a language with the values of its variables,
+/// or `None` if it isn't translated.
+fn format_message(language: &str, id: &str, args: &FluentArgs) -> Option<String> {
+    let (_, bundle) = get_bundles().iter().find(|(code, _)| *code == language)?;
+    let pattern = bundle.get_message(id)?.value()?;
+    let mut errors = Vec::new();
+    let message = bundle.format_pattern(pattern, Some(args), &mut errors);
+    if !errors.is_empty() {
+        warn!("Couldn't format the message {id:?} in {language}: {errors:?}");
+    }
+    Some(message.into_owned())
+}
+
+/// Returns the translation of a message with the values of its variables (see [`tr`]).
+/// The message is in English if it isn't translated, and it is its identifier
+/// (with a warning) if it doesn't exist.
+pub(crate) fn translate(id: &str, variables: &[(&str, String)]) -> String {
+    let mut args = FluentArgs::new();
+    for (name, value) in variables {
+        args.set(*name, value.as_str());
+    }
+    format_message(get_language(), id, &args)
+        .or_else(|| format_message(CATALOGS[0].0, id, &args))
+        .unwrap_or_else(|| {
+            warn!("The message {id:?} doesn't exist");
+            id.to_string()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+
+    /// Returns the identifiers of the messages used with [`tr`] in the files of a folder.
+    fn find_used_ids(dir: &Path, ids: &mut Vec<String>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                find_used_ids(&path, ids);
+            } else if path.extension().is_some_and(|extension| extension == "rs") {
+                let content = fs::read_to_string(&path).unwrap();
+                ids.extend(content.match_indices("tr!(").filter_map(|(index, _)| {
+                    // Skip the other macros like `include_str!`
+                    if content[..index]
+                        .ends_with(|previous: char| previous.is_alphanumeric() || previous == '_')
+                    {
+                        return None;
+                    }
+                    let call = content[index + "tr!(".len()..].trim_start();
+                    let id = call.strip_prefix('"')?.split('"').next()?;
+                    Some(id.to_string())
+                }));
+            }
+        }
+    }
+
+    #[test]
+    fn catalogs_are_valid() {
+        for (language, catalog) in CATALOGS {
+            let result = FluentResource::try_new(catalog.to_string());
+            assert!(result.is_ok(), "invalid {language} catalog");
+        }
+    }
+
+    #[test]
+    fn used_messages_exist() {
+        let mut ids = Vec::new();
+        find_used_ids(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src"), &mut ids);
+        assert!(!ids.is_empty());
+        let english = &get_bundles()[0].1;
+        for id in ids {
+            assert!(english.has_message(&id), "missing English message {id:?}");
+        }
+    }
+}
@@ -1,126 +1,216 @@
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use std::cmp::Reverse;
 use std::error::Error;
+use std::fmt;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 
 use log::debug;
 use log::info;
-use rand::seq::IteratorRandom;
+use log::warn;
+use rand::seq::{IteratorRandom, SliceRandom};
 use rand::Rng;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use serde_json::Value;
 
+use crate::circuit_breaker;
+use crate::database::{Database, ImageList};
+use crate::http;
 use crate::image_structs::is_image;
 use crate::image_structs::Image;
 use crate::image_structs::LocalImage;
 use crate::image_structs::OnlineImage;
+use crate::image_structs::UnsplashPhoto;
+use crate::local_index;
+use crate::local_terms;
 use crate::paths::Paths;
 use super::Config;
 use super::NoImagesError;
 
 
+/// The number of old background images that are kept to be reused.
+const MAX_CACHED_BACKGROUNDS: usize = 10;
+
+/// The proxy of the Unsplash API used without an API key.
+const PROXY_API_URL: &str = "https://lfnewtab.vercel.app/unsplash/";
+
+/// The Unsplash API, used with an API key.
+const UNSPLASH_API_URL: &str = "https://api.unsplash.com/";
+
+/// The time to wait when Unsplash limits the requests without saying for how long
+/// (its limits are reset every hour).
+const DEFAULT_COOL_DOWN: Duration = Duration::from_secs(60 * 60);
+
+/// The number of characters of an unexpected response of the API that are logged.
+const MAX_SNIPPET_LENGTH: usize = 500;
+
+/// A response of the API. The photos are decoded one by one, see [`UnsplashPhoto`].
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PhotosResponse {
+    /// The random photos.
+    Photos(Vec<Value>),
+    /// The results of a search (returned by some APIs of `api_url`).
+    SearchResults { results: Vec<Value> },
+    /// The errors, e.g. an invalid API key.
+    Errors { errors: Vec<String> },
+}
+
+#[derive(Debug)]
+/// An error returned when Unsplash limits the requests.
+pub(crate) struct RateLimitError {
+    /// When the requests can be made again.
+    pub(crate) until: DateTime<Utc>,
+}
+
+impl fmt::Display for RateLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "The Unsplash rate limit was reached, no images will be downloaded until {}",
+            self.until.with_timezone(&Local).format("%H:%M")
+        )
+    }
+}
+
+impl Error for RateLimitError {}
+
 // Imports are OK here
-#[derive(Clone, Default, Deserialize, Serialize)]
+#[derive(Clone, Default, Deserialize)]
 #[serde(default)]
-/// Data for the online images stored on disk.
+/// Data for the online images stored in the database.
 pub(crate) struct ImageData {
     pub(crate) urls: Vec<OnlineImage>,
+    /// The position of the next image in the order of the images.
     pub(crate) current_index: usize,
+    /// The indexes of the images in a random order, or empty to show them in the order of `urls`.
+    pub(crate) order: Vec<usize>,
     pub(crate) needs_downloading: bool,
+    /// No images are downloaded until this time because Unsplash limits the requests.
+    pub(crate) cool_down_until: Option<DateTime<Utc>>,
 }
 
 impl ImageData {
-    /// Loads the image data from its file.
+    /// Reads the image data from the JSON file used before the database,
+    /// or from its backup if it is malformed.
     ///
     /// # Errors
-    /// Fails if the image data directory can't be determined
-    /// or if the file is malformed.
-    pub(crate) fn load() -> Result<Self, Box<dyn Error>> {
-        let data_path = Paths::image_data_path();
-        debug!("Loading image data from {:?}", data_path);
-
-        let ret = if data_path.exists() {
-            let image_data = serde_json::from_reader(fs::File::open(data_path)?)?;
-            debug!("Image data loaded");
-            Ok(image_data)
-        } else {
-            debug!("Image data file not found, using default values");
-            Ok(Self::default())
+    /// Fails if the file and its backup can't be read or are malformed.
+    pub(crate) fn read_legacy_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let read = |path: &Path| -> Result<Self, Box<dyn Error>> {
+            Ok(serde_json::from_reader(fs::File::open(path)?)?)
         };
-        if let Ok(ref data) = ret {
-            info!(
-                "Loaded {} images from the cache, current index is {}",
-                data.urls.len(),
-                data.current_index
-            );
-        }
-        ret
+        read(path).or_else(|err| {
+            let backup_path = path.with_extension("json.bak");
+            warn!("Couldn't read the image data ({err}), using the backup {backup_path:?}");
+            read(&backup_path)
+        })
+    }
+
+    /// Loads the image data from the database.
+    ///
+    /// # Errors
+    /// Fails if the database can't be opened or read.
+    pub(crate) fn load() -> Result<Self, Box<dyn Error>> {
+        debug!("Loading image data from the database");
+        let data = Database::open()?.load_image_data()?;
+        info!(
+            "Loaded {} images from the cache, current index is {}",
+            data.urls.len(),
+            data.current_index
+        );
+        Ok(data)
     }
 
-    /// Saves the image data to its file.
+    /// Saves the image data to the database.
     ///
     /// # Errors
-    /// Fails if the file can't be written to.
+    /// Fails if the database can't be opened or written to.
     pub(crate) fn store(&self) -> Result<(), Box<dyn Error>> {
-        debug!("Storing image data to {:?}", Paths::image_data_path());
-        Ok(serde_json::to_writer(
-            fs::File::create(Paths::image_data_path())?,
-            self,
-        )?)
+        debug!("Storing image data to the database");
+        Database::open()?.store_image_data(self)
     }
 
-    /// Deletes all the images in this [`ImageData`].
+    /// Shuffles the images if `shuffle` is `true` and they aren't shuffled yet,
+    /// or shows them in their order otherwise.
+    pub(crate) fn update_order(&mut self, shuffle: bool, rng: &mut impl Rng) {
+        if !shuffle {
+            self.order.clear();
+        } else if self.order.len() != self.urls.len() {
+            let mut order = (0..self.urls.len()).collect::<Vec<_>>();
+            order.shuffle(rng);
+            self.order = order;
+        }
+    }
+
+    /// Returns the index in `urls` of the image at the given position of the order.
+    pub(crate) fn get_image_index(&self, position: usize) -> usize {
+        self.order.get(position).copied().unwrap_or(position)
+    }
+
+    /// Returns the paths of the images of the batch that weren't shown yet.
+    pub(crate) fn get_unshown_paths(&self) -> Vec<PathBuf> {
+        (self.current_index..self.urls.len())
+            .filter_map(|position| self.urls.get(self.get_image_index(position)))
+            .map(OnlineImage::get_path)
+            .collect()
+    }
+
+    /// Deletes all the images in this [`ImageData`] except the favorites.
     ///
     /// # Errors
-    /// Fails if an image or the data file can't be deleted.
+    /// Fails if the favorites can't be read or if an image can't be deleted.
     pub(crate) fn clear(&mut self) -> Result<(), Box<dyn Error>> {
+        let favorites = Database::open()?.get_list(ImageList::Favorites)?;
         for image in &self.urls {
             let path = image.get_path();
-            if path.exists() {
+            if favorites.contains(&path) {
+                debug!("Keeping favorite image {:?}", path);
+            } else if path.exists() {
                 debug!("Removing image {:?}", path);
                 fs::remove_file(path)?;
             } else {
                 debug!("Image {:?} not found", path);
             }
         }
-        // Remove the file
-        let data_path = Paths::image_data_path();
-        if data_path.exists() {
-            debug!("Removing image data file {:?}", data_path);
-            fs::remove_file(data_path)?;
-        } else {
-            debug!("Image data file {:?} not found", data_path);
-        }
         Ok(())
     }
 
-    /// Downloads all the images in this [`ImageData`].
+    /// Downloads all the images in this [`ImageData`] with the given wallpaper size.
     ///
     /// # Errors
     /// Fails if an image can't be downloaded.
-    pub(crate) fn download_all_images(&self) -> Result<(), Box<dyn Error>> {
+    pub(crate) fn download_all_images(
+        &self,
+        wallpaper_size: (u32, u32),
+    ) -> Result<(), Box<dyn Error>> {
         for image in &self.urls {
-            image.download()?;
+            image.download(wallpaper_size)?;
         }
         Ok(())
     }
 
-    /// Deletes all the old online images and background images.
+    /// Deletes all the old online images and all the background images
+    /// except the current ones and the [`MAX_CACHED_BACKGROUNDS`] most recent ones.
     ///
     /// # Errors
     /// Fails if an image can't be deleted.
     pub(crate) fn delete_old_images(
         &self,
-        current_background: &Path,
+        current_backgrounds: &[PathBuf],
     ) -> Result<(), Box<dyn Error>> {
-        let image_paths = self
+        // The partial downloads are kept so that they can be resumed
+        let mut image_paths = self
             .urls
             .iter()
-            .map(super::image_structs::Image::get_path)
+            .flat_map(|image| [image.get_path(), image.get_part_path()])
             .collect::<Vec<_>>();
+        image_paths.extend(Database::open()?.get_list(ImageList::Favorites)?);
         debug!("Found {} images to keep", image_paths.len());
         let mut removed_images: usize = 0;
-        for entry in fs::read_dir(Paths::downloaded_pictures_dir())? {
+        for entry in fs::read_dir(Paths::downloaded_pictures_dir()?)? {
             let path = entry?.path();
             if path.is_file() && image_paths.iter().all(|image_path| path != *image_path) {
                 debug!("Removing old image {:?}", path);
@@ -130,40 +220,116 @@ impl ImageData {
                 debug!("Keeping image {:?}", path);
             }
         }
-        for entry in fs::read_dir(Paths::temp_dir())? {
-            let path = entry?.path();
-            if path.is_file() && path != current_background {
-                debug!("Removing old background image {:?}", path);
-                fs::remove_file(path)?;
-                removed_images += 1;
-            }
+        // Keep the most recent backgrounds so that they can be reused
+        let mut backgrounds = fs::read_dir(Paths::temp_dir()?)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        backgrounds
+            .retain(|path| path.is_file() && is_image(path) && !current_backgrounds.contains(path));
+        backgrounds.sort_by_key(|path| {
+            Reverse(
+                fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok(),
+            )
+        });
+        for path in backgrounds.into_iter().skip(MAX_CACHED_BACKGROUNDS) {
+            debug!("Removing old background image {:?}", path);
+            fs::remove_file(path)?;
+            removed_images += 1;
         }
         info!("Removed {} old images", removed_images);
         Ok(())
     }
 }
 
-/// Downloads pictures from Unsplash.
+/// Deletes the downloaded pictures that are older than `max_cache_age_days`,
+/// then the oldest ones until they take less than `max_cache_size_mb`.
+/// The given images (e.g. the current wallpapers) and the favorites are never deleted.
+///
+/// # Errors
+/// Fails if the pictures folder or the favorites can't be read or if a picture can't be deleted.
+pub(crate) fn enforce_cache_limits(
+    config: &Config,
+    kept_images: &[PathBuf],
+) -> Result<(), Box<dyn Error>> {
+    let favorites = Database::open()?.get_list(ImageList::Favorites)?;
+    let mut pictures = Vec::new();
+    for entry in fs::read_dir(Paths::downloaded_pictures_dir()?)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            pictures.push((entry.path(), metadata.len(), metadata.modified()?));
+        }
+    }
+    pictures.sort_by_key(|(_, _, modified)| *modified);
+
+    let max_age = Duration::from_secs(u64::from(config.max_cache_age_days) * 24 * 60 * 60);
+    let max_size = config.max_cache_size_mb.saturating_mul(1024 * 1024);
+    let mut total_size = pictures.iter().map(|(_, size, _)| size).sum::<u64>();
+    let now = SystemTime::now();
+    let mut removed_images: usize = 0;
+    for (path, size, modified) in pictures {
+        let too_old = config.max_cache_age_days > 0
+            && now.duration_since(modified).unwrap_or_default() > max_age;
+        let too_large = config.max_cache_size_mb > 0 && total_size > max_size;
+        // The pictures are sorted from the oldest, so the next ones are within the limits too
+        if !too_old && !too_large {
+            break;
+        }
+        if kept_images.contains(&path) || favorites.contains(&path) {
+            continue;
+        }
+        debug!("Removing cached image {:?}", path);
+        fs::remove_file(path)?;
+        total_size -= size;
+        removed_images += 1;
+    }
+    if removed_images > 0 {
+        info!("Removed {removed_images} cached images to stay within the limits");
+    }
+    Ok(())
+}
+
+/// Downloads pictures from Unsplash, or from the `api_url` of the config if it is set.
 ///
 /// # Errors
-/// Fails if the Unsplash API endpoint can't be contacted or if its response can't be decoded.
-#[expect(clippy::missing_panics_doc)]
-pub(crate) fn download_pictures(config: &Config) -> Result<Vec<OnlineImage>, Box<dyn Error>> {
-    #[expect(clippy::unwrap_used)]
-    let mut url = url::Url::parse(if config.api_key.is_empty() {
+/// Fails if the API URL is invalid, if the API is disabled after too many failures,
+/// if the API endpoint can't be contacted or if its response can't be decoded.
+pub(crate) fn download_pictures(
+    config: &Config,
+    rng: &mut impl Rng,
+) -> Result<Vec<OnlineImage>, Box<dyn Error>> {
+    let api_url = if !config.api_url.is_empty() {
+        debug!("Using the API at {:?}", config.api_url);
+        config.api_url.as_str()
+    } else if config.api_key.is_empty() {
         debug!("No API key found, using the lfnewtab API");
-        "https://lfnewtab.vercel.app/unsplash/"
+        PROXY_API_URL
     } else {
         debug!("Using the Unsplash API");
-        "https://api.unsplash.com/"
-    })
-    .unwrap();
+        UNSPLASH_API_URL
+    };
+    let mut url = url::Url::parse(api_url)?;
+    // An API that keeps failing isn't contacted for a while
+    let provider = url.host_str().unwrap_or(api_url).to_string();
+    circuit_breaker::check(&provider)?;
+    // The endpoints are relative to the API URL
+    if !url.path().ends_with('/') {
+        url.set_path(&(url.path().to_string() + "/"));
+    }
 
+    // The terms with hours are chosen only during them
+    let now = Local::now().time();
     let search_term = config
         .search_terms
-        .split(',')
-        .choose(&mut rand::rng())
-        .unwrap_or_default();
+        .iter()
+        .filter(|search_term| search_term.is_active(now))
+        .choose(rng)
+        .map_or(String::new(), |search_term| {
+            local_terms::expand(config, &search_term.term)
+        });
+    let search_term = search_term.as_str();
 
     if search_term.is_empty() || search_term == "random" {
         debug!("Search term is {:?}, getting random images", search_term);
@@ -178,49 +344,132 @@ pub(crate) fn download_pictures(config: &Config) -> Result<Vec<OnlineImage>, Box
             .append_pair("count", config.images_per_download.to_string().as_str());
     }
 
+    if config.span_monitors {
+        debug!("Spanning across monitors, getting landscape images only");
+        url.query_pairs_mut()
+            .append_pair("orientation", "landscape");
+    }
+
     if !config.api_key.is_empty() {
         url.query_pairs_mut()
             .append_pair("client_id", &config.api_key);
     }
 
-    let response = ureq::get(url.as_str()).call()?;
-    let response: Value = serde_json::from_reader(response.into_body().as_reader())?;
+    let result = request_pictures(&url);
+    circuit_breaker::record(&provider, &result);
+    result
+}
 
-    let image_urls = if response.is_array() {
-        response.as_array()
-    } else {
-        response["results"].as_array()
+/// Gets the pictures from an endpoint of the API.
+///
+/// # Errors
+/// Fails with a [`RateLimitError`] if the API limits the requests, or if the endpoint
+/// can't be contacted or its response can't be decoded.
+fn request_pictures(url: &url::Url) -> Result<Vec<OnlineImage>, Box<dyn Error>> {
+    let response = http::get(url.as_str())
+        .config()
+        .http_status_as_error(false)
+        .build()
+        .call()?;
+    let status = response.status();
+    let remaining = response
+        .headers()
+        .get("x-ratelimit-remaining")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    // Unsplash answers 403 when the limit is reached, other services answer 429
+    if status.as_u16() == 429 || (status.as_u16() == 403 && remaining.as_deref() == Some("0")) {
+        let retry_after = response
+            .headers()
+            .get("retry-after")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map_or(DEFAULT_COOL_DOWN, Duration::from_secs);
+        return Err(Box::new(RateLimitError {
+            until: Utc::now() + TimeDelta::from_std(retry_after)?,
+        }));
+    }
+    if !status.is_success() {
+        let body = response.into_body().read_to_string().unwrap_or_default();
+        debug!("Response of the API: {}", get_snippet(&body));
+        let details = if let Ok(PhotosResponse::Errors { errors }) = serde_json::from_str(&body) {
+            format!(" ({})", errors.join(", "))
+        } else {
+            String::new()
+        };
+        return Err(format!("Unsplash answered with the HTTP status {status}{details}").into());
+    }
+    if remaining.as_deref() == Some("0") {
+        debug!("No more requests can be made to Unsplash this hour");
+    }
+    let body = response.into_body().read_to_string()?;
+    let photos = match serde_json::from_str(&body) {
+        Ok(PhotosResponse::Photos(photos) | PhotosResponse::SearchResults { results: photos }) => {
+            photos
+        }
+        Ok(PhotosResponse::Errors { errors }) => {
+            return Err(format!("The API returned an error: {}", errors.join(", ")).into());
+        }
+        Err(err) => {
+            debug!("Unexpected response of the API: {}", get_snippet(&body));
+            return Err(format!("The response of the API can't be decoded: {err}").into());
+        }
+    };
+
+    // An image with an unexpected schema is skipped instead of the whole response
+    let count = photos.len();
+    let mut image_urls = Vec::new();
+    for photo in photos {
+        match UnsplashPhoto::deserialize(&photo) {
+            Ok(photo) => image_urls.push(OnlineImage::from(photo)),
+            Err(err) => {
+                warn!("Skipping an image with an unexpected schema: {err}");
+                debug!("Unexpected image: {}", get_snippet(&photo.to_string()));
+            }
+        }
+    }
+    if image_urls.is_empty() && count > 0 {
+        return Err(
+            format!("None of the {count} images returned by the API could be decoded").into(),
+        );
     }
-    .ok_or("Error parsing response")?
-    .iter()
-    .map(OnlineImage::from)
-    .collect::<Vec<_>>();
     debug!("Downloaded {} images", image_urls.len());
 
     Ok(image_urls)
 }
 
-/// Selects a random image, downloads it and returns it.
+/// Returns the beginning of a raw response of the API, to log it.
+fn get_snippet(raw: &str) -> &str {
+    raw.char_indices()
+        .nth(MAX_SNIPPET_LENGTH)
+        .map_or(raw, |(end, _)| &raw[..end])
+}
+
+/// Selects a random image with the given random number generator, downloads it and returns it.
 ///
 /// # Errors
 /// Fails if the local or web images can't be obtained or downloaded.
 pub(crate) fn select_random_image(
     config: &Config,
     image_data: &mut ImageData,
+    rng: &mut impl Rng,
 ) -> Result<Box<dyn Image>, Box<dyn Error>> {
-    let mut rng = rand::rng();
-
     // Randomly decide between a local or online image
-    let use_local_image = rng.random::<bool>();
+    let use_local_image = !rng.random_bool(config.online_probability.clamp(0.0, 1.0));
 
     if use_local_image {
-        if let Ok(ret) = LocalImage::get(config, image_data) {
+        if let Ok(ret) = LocalImage::get(config, image_data, rng) {
             return Ok(ret);
         }
     }
 
     if !use_local_image {
-        if let Ok(ret) = OnlineImage::get(config, image_data) {
+        match OnlineImage::get(config, image_data, rng) {
+            Ok(ret) => return Ok(ret),
+            Err(err) => info!("Using a local image because no online image is available: {err}"),
+        }
+        // Fall back to the local images (e.g. when Unsplash limits the requests)
+        if let Ok(ret) = LocalImage::get(config, image_data, rng) {
             return Ok(ret);
         }
     }
@@ -229,56 +478,34 @@ pub(crate) fn select_random_image(
     Err(Box::new(NoImagesError))
 }
 
-/// Returns all the images in a directory and in its subdirectories, without using a cache.
+/// Returns the images that must not be chosen again yet:
+/// the last `no_repeat_count` images shown in the last `no_repeat_hours` hours.
 ///
 /// # Errors
-/// Fails if a directory can't be read.
-pub(crate) fn get_images_no_cache(pictures_dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
-    let mut images = Vec::new();
-    for entry in fs::read_dir(pictures_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_dir() {
-            let mut other_images = get_images_no_cache(&path)?;
-            images.append(&mut other_images);
-        } else if path.is_file() && is_image(&path) {
-            images.push(path);
-        }
+/// Fails if the history can't be read.
+pub(crate) fn get_recent_images(
+    database: &Database,
+    config: &Config,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    if config.no_repeat_count == 0 || config.no_repeat_hours == 0 {
+        return Ok(Vec::new());
     }
-    Ok(images)
+    let since = Utc::now() - TimeDelta::hours(i64::from(config.no_repeat_hours));
+    database.get_recently_shown(config.no_repeat_count, since)
 }
 
-/// Returns all the images in a directory and in its subdirectories.
+/// Returns all the images in a directory and in its subdirectories,
+/// from the index of the local images which is updated first.
 ///
 /// # Errors
-/// Fails if the cache directory can't be found or created or if a directory can't be read.
+/// Fails if the database can't be opened or if a directory can't be read.
 pub(crate) fn get_images(pictures_dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
-    let cache_path = Paths::get_path_cache_file_path(pictures_dir);
-    // if the change time of the folder is newer than the cache file, regenerate the cache
-    // otherwise, read the cache file and return the paths
-    if let Ok(metadata) = fs::metadata(pictures_dir) {
-        if let Ok(cache_metadata) = fs::metadata(&cache_path) {
-            if metadata.modified()? <= cache_metadata.modified()? {
-                let cache_file = fs::File::open(&cache_path)?;
-                let paths: Vec<String> = serde_json::from_reader(cache_file)?;
-                let images = paths
-                    .iter()
-                    .map(|path| pictures_dir.join(path))
-                    .collect::<Vec<_>>();
-                return Ok(images);
-            }
-        }
-    }
-
-    let images = get_images_no_cache(pictures_dir)?;
-
-    // Write the paths to the cache file, but only the part after the pictures_dir
-    let cache_file = fs::File::create(&cache_path)?;
-    let paths = images
+    let mut database = Database::open()?;
+    local_index::update(&mut database, pictures_dir)?;
+    let images = database
+        .get_local_files(pictures_dir)?
         .iter()
-        .map(|path| path.strip_prefix(pictures_dir).unwrap().to_string_lossy())
-        .collect::<Vec<_>>();
-    serde_json::to_writer(cache_file, &paths)?;
-
+        .map(|path| pictures_dir.join(path))
+        .collect();
     Ok(images)
 }
@@ -1,45 +1,71 @@
 use chrono::Local;
 use chrono::{DateTime, Utc};
+use image::metadata::Orientation;
 use image::DynamicImage;
-use image::GenericImageView;
 use image::ImageDecoder;
+use image::ImageFormat;
 use image::ImageReader;
 use log::debug;
 use log::error;
 use log::info;
+use log::warn;
 use rand::seq::IteratorRandom;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 use std::error::Error;
 use std::ffi::OsStr;
-use std::fs::{metadata, File};
+use std::fs::{metadata, read_to_string, remove_file, OpenOptions};
 use std::io::copy;
 use std::path::Path;
 use std::path::PathBuf;
 use url::Url;
 
-use crate::date_format::format_date_in_french;
-use crate::get_screen_size;
+use crate::circuit_breaker::ProviderBenchedError;
+use crate::config::DEFAULT_LABEL_TEMPLATE;
+use crate::content_filter::get_filtered_folder;
+use crate::database::{Database, ImageList};
+use crate::date_format::format_date;
+use crate::decoders;
+use crate::dedupe;
+use crate::exif_data::{format_location, reverse_geocode, ExifData};
+use crate::http;
 use crate::image_list::download_pictures;
 use crate::image_list::get_images;
+use crate::image_list::get_recent_images;
 use crate::image_list::ImageData;
+use crate::image_list::RateLimitError;
 use crate::paths::Paths;
+use crate::screen_size::get_wallpaper_size;
+use crate::timing;
+use crate::weights::Weights;
 use crate::Config;
 use crate::NoImagesError;
 
 /// An image that has a path and a description.
 pub(crate) trait Image {
-    /// Returns a random image.
+    /// Returns a random image chosen with the given random number generator.
     ///
     /// # Errors
     /// It depends on the implementation but it fails if no image can be found.
-    fn get(config: &Config, image_data: &mut ImageData) -> Result<Box<Self>, Box<dyn Error>>
+    fn get(
+        config: &Config,
+        image_data: &mut ImageData,
+        rng: &mut impl Rng,
+    ) -> Result<Box<Self>, Box<dyn Error>>
     where
         Self: Sized;
     /// Returns the path of the image.
     fn get_path(&self) -> PathBuf;
     /// Returns the description of the image.
     fn get_description(&self) -> String;
+    /// Returns where the image comes from (its path or its URL).
+    fn get_source(&self) -> String {
+        self.get_path().to_string_lossy().to_string()
+    }
+    /// Returns the author of the image, if it is known.
+    fn get_author(&self) -> Option<String> {
+        None
+    }
 }
 
 #[derive(Clone)]
@@ -47,35 +73,82 @@ pub(crate) trait Image {
 pub(crate) struct LocalImage {
     pub(crate) path: PathBuf,
     pub(crate) date: Option<DateTime<Local>>,
+    pub(crate) exif: ExifData,
+    /// The contents of the caption file next to the image (`photo.jpg.txt` or `photo.md`).
+    pub(crate) sidecar_caption: Option<String>,
+    /// The name of the place where the photo was taken, if reverse geocoding is enabled.
+    pub(crate) place: Option<String>,
+    /// The template of the description, see [`Config::label_template`].
+    pub(crate) label_template: String,
 }
 
 impl Image for LocalImage {
     #[expect(clippy::unwrap_in_result)]
-    fn get(config: &Config, _image_data: &mut ImageData) -> Result<Box<Self>, Box<dyn Error>> {
+    fn get(
+        config: &Config,
+        _image_data: &mut ImageData,
+        rng: &mut impl Rng,
+    ) -> Result<Box<Self>, Box<dyn Error>> {
         info!("Getting local images");
 
-        // Get the path to the Pictures directory
-        let pictures_dir = Path::new(&config.pictures_folder);
-
-        let local_images = get_images(pictures_dir)?;
+        let pictures_dir = Paths::expand_user(&config.pictures_folder.to_string_lossy());
+        let database = Database::open()?;
+        let blocked_images = database.get_list(ImageList::Blocklist)?;
+        let mut local_images = get_images(&pictures_dir)?
+            .into_iter()
+            .filter(|path| {
+                !blocked_images.contains(path)
+                    && get_filtered_folder(config, &pictures_dir, path).is_none()
+            })
+            .collect::<Vec<_>>();
         debug!("Found {} local images", local_images.len());
 
         if local_images.is_empty() {
             return Err(Box::new(NoImagesError));
         }
 
-        let mut rng = rand::rng();
+        // Don't show the same images too often, unless all of them were shown recently
+        let recent_images = get_recent_images(&database, config)?;
+        if local_images
+            .iter()
+            .any(|path| !recent_images.contains(path))
+        {
+            local_images.retain(|path| !recent_images.contains(path));
+        }
+
+        let wallpaper_size = get_wallpaper_size(config);
+        let weights = Weights::new(config, &pictures_dir);
 
         for _ in 0..10000 {
             // Select a random local image
             #[expect(clippy::unwrap_used)]
-            let image_path = local_images.iter().choose(&mut rng).unwrap().clone();
-            if is_too_vertical(&image_path) {
-                debug!("Skipping {image_path:?} because it's too vertical");
+            let image_path = local_images.iter().choose(rng).unwrap().clone();
+            // The images with a higher rating or in a preferred folder are kept more often
+            if !weights.accept(&image_path, rng) {
                 continue;
             }
+            // Only the header is read here, the pixels are decoded for the selected image only
+            match get_image_dimensions(&image_path) {
+                Ok(dimensions) => {
+                    debug!("Image dimensions: {:?}", dimensions);
+                    if is_too_vertical(dimensions, wallpaper_size) {
+                        debug!("Skipping {image_path:?} because it's too vertical");
+                        continue;
+                    }
+                    if config.span_monitors && is_too_narrow(dimensions, wallpaper_size) {
+                        debug!("Skipping {image_path:?} because it's too narrow to span all the monitors");
+                        continue;
+                    }
+                    // Only the JPEG images can be downscaled while they are decoded
+                    if exceeds_decoded_size(config, dimensions) && !is_jpeg(&image_path) {
+                        info!("Skipping {image_path:?} because it has more pixels than max_decoded_megapixels");
+                        continue;
+                    }
+                }
+                Err(err) => debug!("Couldn't get the dimensions of {image_path:?}: {err}"),
+            }
             info!("Selecting {image_path:?}");
-            return Ok(Box::new(Self::from(image_path)));
+            return Ok(Box::new(Self::with_config(image_path, config)));
         }
 
         Err(Box::new(NoImagesError))
@@ -86,16 +159,49 @@ impl Image for LocalImage {
     }
 
     fn get_description(&self) -> String {
-        // Get the filename and the current date
-        let filename = self
-            .get_path()
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
-        let date = self.date.map(format_date_in_french).unwrap_or_default();
+        // Get the caption (or the filename) and the current date
+        let filename = self.sidecar_caption.clone().unwrap_or_else(|| {
+            self.get_path()
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        });
+        let date = self.date.map(format_date).unwrap_or_default();
+
+        let description = self
+            .label_template
+            .replace("{filename}", &filename)
+            .replace("{date}", &date)
+            .replace("{camera}", self.exif.camera.as_deref().unwrap_or_default())
+            .replace(
+                "{location}",
+                &self.exif.location.map(format_location).unwrap_or_default(),
+            )
+            .replace("{place}", self.place.as_deref().unwrap_or_default())
+            .replace(
+                "{caption}",
+                self.exif.caption.as_deref().unwrap_or_default(),
+            );
+
+        // Remove the lines of the missing fields
+        description
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
 
-        format!("{filename}\n{date}")
+impl LocalImage {
+    /// Returns the local image with the description and the place given by the config.
+    pub(crate) fn with_config(path: PathBuf, config: &Config) -> Self {
+        let mut image = Self::from(path);
+        image.label_template.clone_from(&config.label_template);
+        if config.reverse_geocoding {
+            image.place = image.exif.location.and_then(reverse_geocode);
+        }
+        image
     }
 }
 
@@ -140,23 +246,40 @@ impl From<PathBuf> for LocalImage {
             date_format = Some("%Y%m%d");
         }
 
-        let date: Option<DateTime<Local>> = if let Some(format) = date_format {
-            debug!("Parsing date with format: {}", format);
-            DateTime::parse_from_str(&filename, format)
-                .ok()
-                .map(DateTime::<Local>::from)
-        } else {
-            None
-        }
-        .or_else(|| {
-            debug!("Getting file metadata");
-            metadata(&path)
-                .and_then(|metadata| metadata.modified())
-                .ok()
-                .map(DateTime::<Local>::from)
+        let exif = ExifData::read(&path).unwrap_or_else(|err| {
+            debug!("Couldn't read the EXIF metadata of {path:?}: {err}");
+            ExifData::default()
         });
 
-        Self { path, date }
+        // The date when the photo was taken is more reliable than the filename
+        let date: Option<DateTime<Local>> = exif
+            .date
+            .or_else(|| {
+                date_format.and_then(|format| {
+                    debug!("Parsing date with format: {}", format);
+                    DateTime::parse_from_str(&filename, format)
+                        .ok()
+                        .map(DateTime::<Local>::from)
+                })
+            })
+            .or_else(|| {
+                debug!("Getting file metadata");
+                metadata(&path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .map(DateTime::<Local>::from)
+            });
+
+        let sidecar_caption = read_sidecar_caption(&path);
+
+        Self {
+            path,
+            date,
+            exif,
+            sidecar_caption,
+            place: None,
+            label_template: DEFAULT_LABEL_TEMPLATE.to_string(),
+        }
     }
 }
 
@@ -170,16 +293,33 @@ pub(crate) struct OnlineImage {
     pub(crate) date: Option<DateTime<Utc>>,
     #[serde(default)]
     pub(crate) description: String,
+    #[serde(default)]
+    pub(crate) photographer: String,
 }
 
 impl Image for OnlineImage {
-    fn get(config: &Config, image_data: &mut ImageData) -> Result<Box<Self>, Box<dyn Error>> {
+    fn get(
+        config: &Config,
+        image_data: &mut ImageData,
+        rng: &mut impl Rng,
+    ) -> Result<Box<Self>, Box<dyn Error>> {
         info!("Getting online images");
         // Check if we need to download new images
-        if image_data.needs_downloading || image_data.current_index >= image_data.urls.len() {
+        let cool_down_until = image_data
+            .cool_down_until
+            .filter(|cool_down_until| *cool_down_until > Utc::now());
+        let needs_downloading =
+            image_data.needs_downloading || image_data.current_index >= image_data.urls.len();
+        if let Some(until) = cool_down_until.filter(|_| needs_downloading) {
+            // Don't make the rate limiting last longer, reuse the previous images if there are some
+            if image_data.urls.is_empty() {
+                return Err(Box::new(RateLimitError { until }));
+            }
+            debug!("Reusing the previous online images until {until}");
+        } else if needs_downloading {
             info!("Downloading pictures from Unsplash");
             // Download random pictures from Unsplash
-            match download_pictures(config) {
+            match download_pictures(config, rng) {
                 Ok(image_urls) => {
                     // Clear the old images
                     image_data.clear()?;
@@ -193,70 +333,141 @@ impl Image for OnlineImage {
                     image_data.store()?;
                 }
                 Err(err) => {
-                    error!("Error: {err}");
+                    if let Some(rate_limit) = err.downcast_ref::<RateLimitError>() {
+                        warn!("{rate_limit}");
+                        image_data.cool_down_until = Some(rate_limit.until);
+                    } else if err.is::<ProviderBenchedError>() {
+                        warn!("{err}");
+                    } else {
+                        error!("Error: {err}");
+                    }
                     image_data.needs_downloading = true;
                     image_data.store()?;
+                    if image_data.urls.is_empty() {
+                        return Err(err);
+                    }
                 }
             }
         }
 
         if image_data.current_index >= image_data.urls.len() {
             image_data.current_index = 0;
+            // The images are shuffled again each time the batch is reused
+            image_data.order.clear();
         }
+        image_data.update_order(config.shuffle_online_images, rng);
+
+        // Skip the blocked images, and the recently shown ones if possible
+        let database = Database::open()?;
+        let blocked_images = database.get_list(ImageList::Blocklist)?;
+        let recent_images = get_recent_images(&database, config)?;
+        // The paths of the images in their order
+        let paths = (0..image_data.urls.len())
+            .map(|position| image_data.urls[image_data.get_image_index(position)].get_path())
+            .collect::<Vec<_>>();
+        let allowed_positions = (0..paths.len())
+            .map(|offset| (image_data.current_index + offset) % paths.len())
+            .filter(|position| !blocked_images.contains(&paths[*position]))
+            .collect::<Vec<_>>();
+        let Some(position) = allowed_positions
+            .iter()
+            .find(|position| !recent_images.contains(&paths[**position]))
+            .or(allowed_positions.first())
+            .copied()
+        else {
+            image_data.needs_downloading = true;
+            image_data.store()?;
+            return Err("All the online images are blocked".into());
+        };
+        image_data.current_index = position;
 
         // Use the current online image
-        let current_image = image_data.urls[image_data.current_index].clone();
-        current_image.download()?;
+        let current_image = image_data.urls[image_data.get_image_index(position)].clone();
 
-        // Increment the current index and store it
+        // Increment the current index and store it before downloading,
+        // so that an image that can't be downloaded is skipped the next time
         image_data.current_index += 1;
         debug!("Current index: {}", image_data.current_index);
         image_data.store()?;
 
+        current_image.download(get_wallpaper_size(config))?;
+
         Ok(Box::new(current_image))
     }
 
     fn get_path(&self) -> PathBuf {
-        Paths::downloaded_pictures_dir().join(format!("unsplash_{}.jpg", self.id))
+        // The error was already reported when the online images were loaded from the database
+        Paths::downloaded_pictures_dir()
+            .map_or_else(|_| PathBuf::new(), Path::to_path_buf)
+            .join(format!("unsplash_{}.jpg", self.id))
     }
 
     fn get_description(&self) -> String {
         self.description.clone()
     }
+
+    fn get_source(&self) -> String {
+        format!("https://unsplash.com/photos/{}", self.id)
+    }
+
+    fn get_author(&self) -> Option<String> {
+        Some(self.photographer.clone()).filter(|photographer| !photographer.is_empty())
+    }
 }
 
-impl From<&Value> for OnlineImage {
-    fn from(image: &Value) -> Self {
+#[derive(Deserialize)]
+/// The URLs of a photo of the Unsplash API.
+struct UnsplashUrls {
+    /// The original image, which can be cropped with the parameters of the URL.
+    raw: String,
+}
+
+#[derive(Deserialize)]
+/// The author of a photo of the Unsplash API.
+struct UnsplashUser {
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+/// A photo in the responses of the Unsplash API and of the lfnewtab API, which returns them unchanged.
+/// Only the ID and the URL are required, the other fields are often `null`.
+pub(crate) struct UnsplashPhoto {
+    id: String,
+    urls: UnsplashUrls,
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    alt_description: Option<String>,
+    #[serde(default)]
+    user: Option<UnsplashUser>,
+}
+
+impl From<UnsplashPhoto> for OnlineImage {
+    fn from(photo: UnsplashPhoto) -> Self {
         Self {
-            id: image["id"].as_str().unwrap_or_default().to_string(),
-            url: image["urls"]["raw"]
-                .as_str()
-                .unwrap_or_default()
-                .to_string(),
-            date: image["created_at"]
-                .as_str()
-                .and_then(|date| chrono::DateTime::parse_from_rfc3339(date).ok())
-                .map(|date| date.to_utc()),
-            description: image["alt_description"]
-                .as_str()
-                .unwrap_or_default()
-                .to_string(),
+            id: photo.id,
+            url: photo.urls.raw,
+            date: photo.created_at,
+            description: photo.alt_description.unwrap_or_default(),
+            photographer: photo.user.and_then(|user| user.name).unwrap_or_default(),
         }
     }
 }
 
+/// The number of times an online image is downloaded before giving up if it is unusable.
+const DOWNLOAD_ATTEMPTS: u32 = 2;
+
 impl OnlineImage {
-    /// Download an [`OnlineImage`] to its destination file if needed.
+    /// Returns the path of the partially downloaded image.
+    pub(crate) fn get_part_path(&self) -> PathBuf {
+        self.get_path().with_extension("jpg.part")
+    }
+
+    /// Returns the URL of the image cropped to the given wallpaper size.
     ///
     /// # Errors
-    /// Fails if the URL can't be edited or if the destination file can't be written to.
-    pub(crate) fn download(&self) -> Result<(), Box<dyn Error>> {
-        let image_path = self.get_path();
-        if image_path.exists() {
-            debug!("Image already exists: {:?}", image_path);
-            return Ok(());
-        }
-
+    /// Fails if the URL of the image is invalid.
+    fn get_download_url(&self, wallpaper_size: (u32, u32)) -> Result<Url, Box<dyn Error>> {
         let mut image_url = Url::parse(&self.url)?;
         // Keep only the ixid parameter
         let ixid = image_url
@@ -267,64 +478,296 @@ impl OnlineImage {
         if let Some(value) = ixid {
             image_url.query_pairs_mut().append_pair("ixid", &value);
         }
-        let screen_dimensions = get_screen_size();
         image_url
             .query_pairs_mut()
             .append_pair("fm", "jpg")
             .append_pair("q", "85")
-            .append_pair("w", &screen_dimensions.0.to_string())
-            .append_pair("h", &screen_dimensions.1.to_string())
+            .append_pair("w", &wallpaper_size.0.to_string())
+            .append_pair("h", &wallpaper_size.1.to_string())
             .append_pair("fit", "crop")
             .append_pair("crop", "faces,edges");
+        Ok(image_url)
+    }
 
-        let image_response = ureq::get(image_url.to_string()).call()?;
+    /// Downloads the image in its partial file, resuming the previous download if there is one.
+    /// Nothing is downloaded if the server says that the partial file is complete.
+    ///
+    /// # Errors
+    /// Fails if the image can't be downloaded or if the partial file can't be written to.
+    fn download_part(&self, image_url: &Url) -> Result<(), Box<dyn Error>> {
+        let part_path = self.get_part_path();
+        let downloaded = metadata(&part_path).map_or(0, |metadata| metadata.len());
+        let mut request = http::get(image_url.as_str())
+            .config()
+            .timeout_global(Some(http::DOWNLOAD_TIMEOUT))
+            .build();
+        if downloaded > 0 {
+            debug!("Resuming the download of {part_path:?} after {downloaded} bytes");
+            request = request.header("Range", format!("bytes={downloaded}-"));
+        }
+        let image_response = match request.call() {
+            Ok(image_response) => image_response,
+            // The partial file is probably complete: it is checked by the caller
+            // and only downloaded again if it can't be decoded
+            Err(ureq::Error::StatusCode(416)) if downloaded > 0 => {
+                debug!("The partial download {part_path:?} is already complete");
+                return Ok(());
+            }
+            Err(err) => return Err(err.into()),
+        };
+        // The server may ignore the range and send the whole image
+        let resume = downloaded > 0 && image_response.status().as_u16() == 206;
+
+        let mut part_file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resume)
+            .truncate(!resume)
+            .open(&part_path)?;
+        copy(
+            &mut http::throttle(image_response.into_body().as_reader()),
+            &mut part_file,
+        )?;
+        Ok(())
+    }
 
-        let mut image_file = File::create(image_path)?;
-        copy(&mut image_response.into_body().as_reader(), &mut image_file)?;
+    /// Download an [`OnlineImage`] to its destination file if needed,
+    /// cropped to the given wallpaper size.
+    ///
+    /// The image is downloaded in a separate file so that an interrupted download
+    /// is never used and is resumed on the next run. It is only kept if it can be decoded
+    /// and is large enough, otherwise it is downloaded again.
+    ///
+    /// # Errors
+    /// Fails if the URL can't be edited, if the destination file can't be written to
+    /// or if the downloaded image is still unusable after [`DOWNLOAD_ATTEMPTS`] attempts.
+    pub(crate) fn download(&self, wallpaper_size: (u32, u32)) -> Result<(), Box<dyn Error>> {
+        let _phase = timing::phase("download");
+        let image_path = self.get_path();
+        if image_path.exists() {
+            // The images downloaded by older versions may be error pages or incomplete files
+            match check_downloaded_image(&image_path, wallpaper_size, false) {
+                Ok(()) => {
+                    debug!("Image already exists: {:?}", image_path);
+                    return Ok(());
+                }
+                Err(err) => {
+                    warn!("Downloading {image_path:?} again: {err}");
+                    remove_file(&image_path)?;
+                }
+            }
+        }
 
-        Ok(())
+        let image_url = self.get_download_url(wallpaper_size)?;
+        let part_path = self.get_part_path();
+        let mut attempt = 1;
+        loop {
+            self.download_part(&image_url)?;
+            match check_downloaded_image(&part_path, wallpaper_size, true) {
+                Ok(()) => {
+                    dedupe::store(&part_path, &image_path)?;
+                    return Ok(());
+                }
+                Err(err) if attempt < DOWNLOAD_ATTEMPTS => {
+                    warn!("The downloaded image {image_path:?} is unusable, downloading it again: {err}");
+                    remove_file(&part_path)?;
+                    attempt += 1;
+                }
+                Err(err) => {
+                    remove_file(&part_path)?;
+                    return Err(
+                        format!("The downloaded image {image_path:?} is unusable: {err}").into(),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Checks that a downloaded image can be read and is at least as large as the wallpaper.
+/// The whole image is decoded if `decode` is `true`, otherwise only its header is read.
+///
+/// # Errors
+/// Fails if the image can't be read or if it is too small.
+fn check_downloaded_image(
+    path: &Path,
+    wallpaper_size: (u32, u32),
+    decode: bool,
+) -> Result<(), Box<dyn Error>> {
+    let reader = ImageReader::open(path)?.with_guessed_format()?;
+    let (width, height) = if decode {
+        let img = reader.decode()?;
+        (img.width(), img.height())
+    } else {
+        reader.into_dimensions()?
+    };
+    if width < wallpaper_size.0 || height < wallpaper_size.1 {
+        return Err(format!(
+            "the image ({width}x{height}) is smaller than the wallpaper ({}x{})",
+            wallpaper_size.0, wallpaper_size.1
+        )
+        .into());
     }
+    Ok(())
 }
 
-/// Returns `true` if the file is an image.
+/// Returns `true` if the file is an image. The extension is case-insensitive (e.g. `IMG_0001.JPG`).
 pub(crate) fn is_image(path: &Path) -> bool {
+    let Some(extension) = path.extension().and_then(OsStr::to_str) else {
+        return false;
+    };
     ["jpg", "jpeg", "png", "gif", "bmp", "tiff", "webp"]
-        .map(OsStr::new)
-        .contains(&path.extension().unwrap_or_default())
+        .iter()
+        .chain(decoders::EXTENSIONS)
+        .any(|image_extension| image_extension.eq_ignore_ascii_case(extension))
+}
+
+/// Returns the contents of the caption file of an image (`photo.jpg.txt` or `photo.md`),
+/// or `None` if there is no such file or if it is empty.
+fn read_sidecar_caption(path: &Path) -> Option<String> {
+    let mut txt_path = path.as_os_str().to_owned();
+    txt_path.push(".txt");
+    [PathBuf::from(txt_path), path.with_extension("md")]
+        .iter()
+        .find_map(|sidecar_path| {
+            let caption = read_to_string(sidecar_path).ok()?;
+            debug!("Found caption file {sidecar_path:?}");
+            Some(caption.trim().to_string())
+        })
+        .filter(|caption| !caption.is_empty())
 }
 
 /// Opens an image file and rotates it according to its EXIF metadata.
 ///
 /// # Errors
 /// Fails if the image can't be opened or if its orientation can't be determined.
-fn open_image(path: &Path) -> Result<DynamicImage, Box<dyn Error>> {
+pub(crate) fn open_image(path: &Path) -> Result<DynamicImage, Box<dyn Error>> {
+    if let Some(image) = decoders::open(path) {
+        return image;
+    }
+
     // Rotate the image according to its EXIF metadata
-    let mut decoder = ImageReader::open(path)?.into_decoder()?;
+    let mut decoder = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()?;
     let orientation = decoder.orientation()?;
     let mut image = DynamicImage::from_decoder(decoder)?;
     image.apply_orientation(orientation);
     Ok(image)
 }
 
-/// Returns `true` if the image is too vertical for the current screen size.
+/// Returns `true` if the image has more pixels than the `max_decoded_megapixels` of the config.
+pub(crate) fn exceeds_decoded_size(config: &Config, (width, height): (u32, u32)) -> bool {
+    config.max_decoded_megapixels > 0
+        && u64::from(width) * u64::from(height)
+            > u64::from(config.max_decoded_megapixels) * 1_000_000
+}
+
+/// Returns `true` if the file is a JPEG image, which can be downscaled while it is decoded.
+fn is_jpeg(path: &Path) -> bool {
+    ImageReader::open(path)
+        .and_then(ImageReader::with_guessed_format)
+        .is_ok_and(|reader| reader.format() == Some(ImageFormat::Jpeg))
+}
+
+/// Opens an image file for a wallpaper of the given size and rotates it according to its EXIF metadata.
 ///
-/// If the image size can't be determined, it returns `false`.
-fn is_too_vertical(path: &Path) -> bool {
-    #[expect(clippy::cast_precision_loss)]
-    if let Ok(img) = open_image(path) {
-        debug!("Opened image {:?}", path);
-        let dimensions = img.dimensions();
-        debug!("Image dimensions: {:?}", dimensions);
-        let screen_size = get_screen_size();
-        debug!("Screen size: {:?}", screen_size);
-
-        let ret = (dimensions.1 as f32 / dimensions.0 as f32)
-            / (screen_size.1 as f32 / screen_size.0 as f32)
-            > 1.5;
-        debug!("Result: {}", ret);
-        ret
-    } else {
-        debug!("Couldn't open image {:?}", path);
-        false
+/// An image with more pixels than `max_decoded_megapixels` isn't decoded at its full size,
+/// so that a huge panorama doesn't take gigabytes of memory: a JPEG image is downscaled
+/// while it is decoded (but not under the size of the wallpaper) and the other images are rejected.
+///
+/// # Errors
+/// Fails if the image can't be opened, if it is too large or if its orientation can't be determined.
+pub(crate) fn open_image_for(
+    path: &Path,
+    config: &Config,
+    (width, height): (u32, u32),
+) -> Result<DynamicImage, Box<dyn Error>> {
+    let (image_width, image_height) = get_image_dimensions(path)?;
+    if !exceeds_decoded_size(config, (image_width, image_height)) {
+        return open_image(path);
+    }
+    if !is_jpeg(path) {
+        warn!("Rejecting {path:?} ({image_width}x{image_height}), it has more pixels than max_decoded_megapixels");
+        return Err(format!(
+            "The image has more than {} megapixels (max_decoded_megapixels)",
+            config.max_decoded_megapixels
+        )
+        .into());
     }
+
+    // The smallest size with the proportions of the image that covers the wallpaper
+    let factor = f64::max(
+        f64::from(width) / f64::from(image_width),
+        f64::from(height) / f64::from(image_height),
+    );
+    let orientation = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()?
+        .orientation()?;
+    let mut image = decoders::open_scaled_jpeg(path, factor)?;
+    image.apply_orientation(orientation);
+    info!(
+        "Downscaled {path:?} from {image_width}x{image_height} to {}x{} while decoding it",
+        image.width(),
+        image.height()
+    );
+    Ok(image)
+}
+
+/// Returns the dimensions of an image once rotated according to its EXIF metadata,
+/// reading only its header.
+///
+/// # Errors
+/// Fails if the image header can't be read or if its orientation can't be determined.
+pub(crate) fn get_image_dimensions(path: &Path) -> Result<(u32, u32), Box<dyn Error>> {
+    if let Some(dimensions) = decoders::get_dimensions(path) {
+        return dimensions;
+    }
+
+    let mut decoder = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()?;
+    let (width, height) = decoder.dimensions();
+    Ok(match decoder.orientation()? {
+        Orientation::Rotate90
+        | Orientation::Rotate270
+        | Orientation::Rotate90FlipH
+        | Orientation::Rotate270FlipH => (height, width),
+        _ => (width, height),
+    })
+}
+
+/// Returns the format of an image if it can be set as a wallpaper of the given size as is:
+/// it is a JPEG or PNG image of this size that doesn't need to be rotated.
+/// Only its header is read.
+pub(crate) fn get_ready_format(path: &Path, size: (u32, u32)) -> Option<ImageFormat> {
+    let reader = ImageReader::open(path)
+        .and_then(ImageReader::with_guessed_format)
+        .ok()?;
+    let format = reader
+        .format()
+        .filter(|format| matches!(format, ImageFormat::Jpeg | ImageFormat::Png))?;
+    let mut decoder = reader.into_decoder().ok()?;
+    (decoder.dimensions() == size
+        && decoder
+            .orientation()
+            .is_ok_and(|orientation| orientation == Orientation::NoTransforms))
+    .then_some(format)
+}
+
+/// Returns `true` if an image with the given dimensions is too vertical for the given wallpaper size.
+#[expect(clippy::cast_precision_loss)]
+fn is_too_vertical(dimensions: (u32, u32), screen_size: (u32, u32)) -> bool {
+    (dimensions.1 as f32 / dimensions.0 as f32) / (screen_size.1 as f32 / screen_size.0 as f32)
+        > 1.5
+}
+
+/// Returns `true` if an image with the given dimensions is not wide enough to span
+/// the given wallpaper size (for example across several monitors) without being cropped too much.
+#[expect(clippy::cast_precision_loss)]
+fn is_too_narrow(dimensions: (u32, u32), wallpaper_size: (u32, u32)) -> bool {
+    (dimensions.0 as f32 / dimensions.1 as f32)
+        / (wallpaper_size.0 as f32 / wallpaper_size.1 as f32)
+        < 0.75
 }
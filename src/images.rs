@@ -1,33 +1,86 @@
-use ab_glyph::FontRef;
 use ab_glyph::PxScale;
-use image::imageops::blur;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::fast_blur;
+use image::imageops::{self, FilterType};
 use image::DynamicImage;
 use image::GenericImageView;
+use image::ImageFormat;
 use image::Rgba;
 use image::RgbaImage;
-use imageproc::drawing::{draw_text_mut, text_size as get_text_size};
-use log::info;
-use std::env;
+use log::{debug, info};
 use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::config::{Config, DimRegion, LabelPosition};
+use crate::fonts::FontStack;
+use crate::resize::resize_to_fill;
+use crate::screen_size::{get_bounding_box, scale_size, Monitor};
+use crate::smart_crop::smart_resize_to_fill;
+
+/// The standard deviation of the blur of the shadow of the text, in pixels.
+const SHADOW_BLUR: f32 = 5.0;
+
+/// The opacity of the box drawn behind the text with the `box` background.
+const BOX_OPACITY: f32 = 0.5;
+
+/// The opacity of the color drawn over the blurred background with the `blur` background.
+const BLUR_TINT_OPACITY: f32 = 0.2;
+
+/// How the text is drawn on the wallpaper.
+pub(crate) struct TextStyle {
+    /// The color of the text, `None` to use black or white depending on the background.
+    pub(crate) color: Option<Rgba<u8>>,
+    pub(crate) outline_color: Rgba<u8>,
+    /// The opacity of the text and of its background, between 0 and 1.
+    pub(crate) opacity: f32,
+    /// What is drawn behind the text: `none` (only a shadow), `box` or `blur`.
+    pub(crate) background: String,
+    /// The space between the text and the borders of the image, in pixels.
+    pub(crate) margin: u32,
+}
+
+impl TextStyle {
+    /// Returns the style of the labels set in the config, for a monitor with the given scaling factor.
+    ///
+    /// # Errors
+    /// Fails if a color is malformed.
+    pub(crate) fn from_config(config: &Config, scale: f32) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            color: if config.label_color == "auto" {
+                None
+            } else {
+                Some(parse_color(&config.label_color)?)
+            },
+            outline_color: parse_color(&config.label_outline_color)?,
+            opacity: config.label_opacity.clamp(0.0, 1.0),
+            background: config.label_background.clone(),
+            margin: scale_size(config.label_margin, scale),
+        })
+    }
+}
 
 /// Writes text on an image.
-///
-/// # Errors
-/// Fails if the font can't be loaded.
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
 pub(crate) fn write_text_on_image(
     img: &mut DynamicImage,
+    fonts: &FontStack,
+    style: &TextStyle,
     text: &str,
     font_size: u32,
-    label_position: &str,
-) -> Result<(), Box<dyn Error>> {
-    if label_position == "none" {
-        return Ok(());
+    label_position: LabelPosition,
+) {
+    if label_position == LabelPosition::Hidden {
+        return;
     }
     info!("Writing text on image...");
 
-    let font_data = include_bytes!(concat!(env!("OUT_DIR"), "/Montserrat-Bold.ttf"));
-    let font = FontRef::try_from_slice(font_data)?;
-
     let scale = PxScale {
         x: font_size as f32,
         y: font_size as f32,
@@ -37,82 +90,220 @@ pub(crate) fn write_text_on_image(
 
     let mut image_buffer = img.to_rgba8();
 
-    // Calculate text size
-    let text_size = get_text_size(scale, &font, text);
-    let (x, y) = match label_position {
-        "center" => (
-            (width as i32 - text_size.0 as i32) / 2,
-            (height as i32 - text_size.1 as i32) / 2,
-        ),
-        "top_right" => (width as i32 - text_size.0 as i32 - 10, 10),
-        "bottom_left" => (10, height as i32 - text_size.1 as i32 - 10),
-        "bottom_right" => (
-            width as i32 - text_size.0 as i32 - 10,
-            height as i32 - text_size.1 as i32 - 10,
-        ),
-        // top_left
-        _ => (10, 10),
-    };
+    // Calculate the size of each line and of the whole text
+    let line_height = scale.y as i32 + 5;
+    let line_widths: Vec<i32> = text
+        .lines()
+        .map(|line| fonts.text_size(scale, line).0 as i32)
+        .collect();
+    let text_size = (
+        line_widths.iter().copied().max().unwrap_or(0),
+        (line_widths.len() as i32 * line_height - 5).max(0),
+    );
+    let (x, y) = get_text_position(
+        label_position,
+        (width as i32, height as i32),
+        text_size,
+        style.margin as i32,
+    );
 
-    // Create a shadow image with the text
-    let mut shadow_image = RgbaImage::new(width, height);
-    for (i, line) in text.lines().enumerate() {
-        let line_width = get_text_size(scale, &font, line).0;
-        let line_x = match label_position {
-            "center" => ((width - line_width as u32) / 2) as i32,
-            "top_right" | "bottom_right" => width as i32 - line_width as i32 - 10,
-            _ => x,
-        };
-        let line_y = y + i as i32 * (scale.y as i32 + 5);
-        draw_text_mut(
-            &mut shadow_image,
-            Rgba([0, 0, 0, 255]),
-            line_x,
-            line_y,
-            scale,
-            &font,
-            line,
-        );
+    // Position and width of each line
+    let lines: Vec<(&str, i32, i32, i32)> = text
+        .lines()
+        .zip(line_widths)
+        .enumerate()
+        .map(|(i, (line, line_width))| {
+            let line_x = match label_position {
+                LabelPosition::Center => (width as i32 - line_width) / 2,
+                LabelPosition::TopRight | LabelPosition::BottomRight => {
+                    x + text_size.0 - line_width
+                }
+                _ => x,
+            };
+            let line_y = y + i as i32 * line_height;
+            (line, line_x, line_y, line_width)
+        })
+        .collect();
+
+    // Bounding box of the text with some padding, inside the image
+    let padding = font_size as i32 / 4;
+    let left = lines.iter().map(|line| line.1).min().unwrap_or(x) - padding;
+    let right = lines.iter().map(|line| line.1 + line.3).max().unwrap_or(x) + padding;
+    let top = y - padding;
+    let bottom = lines.last().map_or(y, |line| line.2 + scale.y as i32) + padding;
+    let (left, top) = (left.max(0) as u32, top.max(0) as u32);
+    let (right, bottom) = (
+        right.clamp(0, width as i32) as u32,
+        bottom.clamp(0, height as i32) as u32,
+    );
+    if right <= left || bottom <= top {
+        debug!("The text is outside of the image");
+        return;
     }
+    let text_box = (left, top, right - left, bottom - top);
 
-    // Apply blur to the shadow image
-    let shadow_image = blur(&shadow_image, 5.0);
+    // Keep the original pixels to blend the text with them
+    let original = (style.opacity < 1.0).then(|| crop_rect(&image_buffer, text_box));
 
-    // Overlay the shadow image onto the original image
-    for y in 0..height {
-        for x in 0..width {
-            let shadow_pixel = shadow_image.get_pixel(x, y);
-            if shadow_pixel[3] > 0 {
-                let original_pixel = image_buffer.get_pixel_mut(x, y);
-                *original_pixel = blend(original_pixel, shadow_pixel);
-            }
+    let background_color = |opacity: f32| {
+        let [r, g, b, _] = style.outline_color.0;
+        Rgba([r, g, b, (opacity * 255.0) as u8])
+    };
+    match style.background.as_str() {
+        "box" => fill_rect(&mut image_buffer, text_box, background_color(BOX_OPACITY)),
+        "blur" => {
+            let region = DynamicImage::ImageRgba8(crop_rect(&image_buffer, text_box));
+            let blurred = region.fast_blur(font_size as f32 / 2.0).to_rgba8();
+            imageops::replace(
+                &mut image_buffer,
+                &blurred,
+                i64::from(text_box.0),
+                i64::from(text_box.1),
+            );
+            fill_rect(
+                &mut image_buffer,
+                text_box,
+                background_color(BLUR_TINT_OPACITY),
+            );
         }
+        _ => draw_shadow(&mut image_buffer, fonts, &lines, scale),
     }
 
+    // Use black text on bright backgrounds and white text on dark backgrounds
+    let (color, outline_color) = style.color.map_or_else(
+        || {
+            if get_mean_luminance(&image_buffer, text_box) > 0.5 {
+                (Rgba([0, 0, 0, 255]), Rgba([255, 255, 255, 255]))
+            } else {
+                (Rgba([255, 255, 255, 255]), Rgba([0, 0, 0, 255]))
+            }
+        },
+        |color| (color, style.outline_color),
+    );
+
     // Draw the original text on top of the shadow with an outline
-    for (i, line) in text.lines().enumerate() {
-        let line_width = get_text_size(scale, &font, line).0;
-        let line_x = match label_position {
-            "center" => ((width - line_width as u32) / 2) as i32,
-            "top_right" | "bottom_right" => width as i32 - line_width as i32 - 10,
-            _ => x,
-        };
-        let line_y = y + i as i32 * (scale.y as i32 + 5);
+    for (line, line_x, line_y, _) in &lines {
         draw_text_with_outline(
             &mut image_buffer,
-            Rgba([255, 255, 255, 255]),
-            Rgba([0, 0, 0, 255]),
-            line_x,
-            line_y,
+            color,
+            outline_color,
+            *line_x,
+            *line_y,
             scale,
-            &font,
+            fonts,
             line,
             1,
         );
     }
 
+    if let Some(original) = original {
+        for (x, y, original_pixel) in original.enumerate_pixels() {
+            let pixel = image_buffer.get_pixel_mut(text_box.0 + x, text_box.1 + y);
+            let [r, g, b, _] = pixel.0;
+            *pixel = blend(
+                original_pixel,
+                &Rgba([r, g, b, (style.opacity * 255.0) as u8]),
+            );
+        }
+    }
+
     *img = DynamicImage::ImageRgba8(image_buffer);
-    Ok(())
+}
+
+/// Returns the position of the top left corner of a text of the given size.
+///
+/// Coordinates are either in pixels from the top left corner of the image (`120,80`)
+/// or percentages of the free space around the text (`50%,100%` is at the bottom center).
+fn get_text_position(
+    label_position: LabelPosition,
+    (width, height): (i32, i32),
+    (text_width, text_height): (i32, i32),
+    margin: i32,
+) -> (i32, i32) {
+    let right = width - text_width - margin;
+    let bottom = height - text_height - margin;
+    match label_position {
+        LabelPosition::Center => ((width - text_width) / 2, (height - text_height) / 2),
+        LabelPosition::TopLeft | LabelPosition::Hidden => (margin, margin),
+        LabelPosition::TopRight => (right, margin),
+        LabelPosition::BottomLeft => (margin, bottom),
+        LabelPosition::BottomRight => (right, bottom),
+        LabelPosition::Coordinates(x, y) => (
+            x.resolve(width - text_width),
+            y.resolve(height - text_height),
+        ),
+    }
+}
+
+/// Draws a blurred black shadow of the lines of text on the image.
+///
+/// Only the area around the text is blurred, so that it stays fast on large images.
+#[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn draw_shadow(
+    image_buffer: &mut RgbaImage,
+    fonts: &FontStack,
+    lines: &[(&str, i32, i32, i32)],
+    scale: PxScale,
+) {
+    // The blur spreads the shadow around the text
+    let spread = (SHADOW_BLUR * 3.0).ceil() as i32;
+    let left = lines.iter().map(|line| line.1).min().unwrap_or(0) - spread;
+    let top = lines.iter().map(|line| line.2).min().unwrap_or(0) - spread;
+    let right = lines.iter().map(|line| line.1 + line.3).max().unwrap_or(0) + spread;
+    let bottom = lines
+        .iter()
+        .map(|line| line.2 + scale.y.ceil() as i32)
+        .max()
+        .unwrap_or(0)
+        + spread;
+
+    // Create a shadow image with the text
+    let mut shadow_image = RgbaImage::new((right - left) as u32, (bottom - top) as u32);
+    for (line, line_x, line_y, _) in lines {
+        fonts.draw_text_mut(
+            &mut shadow_image,
+            Rgba([0, 0, 0, 255]),
+            line_x - left,
+            line_y - top,
+            scale,
+            line,
+        );
+    }
+
+    // Blur the shadow and blend it onto the original image
+    let shadow_image = fast_blur(&shadow_image, SHADOW_BLUR);
+    imageops::overlay(image_buffer, &shadow_image, i64::from(left), i64::from(top));
+}
+
+/// Returns a copy of a rectangle `(x, y, width, height)` of the image.
+fn crop_rect(image_buffer: &RgbaImage, rect: (u32, u32, u32, u32)) -> RgbaImage {
+    imageops::crop_imm(image_buffer, rect.0, rect.1, rect.2, rect.3).to_image()
+}
+
+/// Blends a rectangle `(x, y, width, height)` of the given color over the image.
+fn fill_rect(image_buffer: &mut RgbaImage, rect: (u32, u32, u32, u32), color: Rgba<u8>) {
+    for y in rect.1..rect.1 + rect.3 {
+        for x in rect.0..rect.0 + rect.2 {
+            let pixel = image_buffer.get_pixel_mut(x, y);
+            *pixel = blend(pixel, &color);
+        }
+    }
+}
+
+/// Returns the mean luminance (between 0 and 1) of a rectangle `(x, y, width, height)` of the image.
+#[expect(clippy::cast_precision_loss)]
+fn get_mean_luminance(image_buffer: &RgbaImage, rect: (u32, u32, u32, u32)) -> f32 {
+    let region = imageops::crop_imm(image_buffer, rect.0, rect.1, rect.2, rect.3);
+    let total: f32 = region
+        .pixels()
+        .map(|(_, _, pixel)| {
+            (0.299 * f32::from(pixel[0])
+                + 0.587 * f32::from(pixel[1])
+                + 0.114 * f32::from(pixel[2]))
+                / 255.0
+        })
+        .sum();
+    total / (u64::from(rect.2) * u64::from(rect.3)).max(1) as f32
 }
 
 pub(crate) fn blend(base: &Rgba<u8>, overlay: &Rgba<u8>) -> Rgba<u8> {
@@ -134,7 +325,7 @@ pub(crate) fn draw_text_with_outline(
     x: i32,
     y: i32,
     scale: PxScale,
-    font: &FontRef,
+    fonts: &FontStack,
     text: &str,
     outline_width: i32,
 ) {
@@ -142,11 +333,273 @@ pub(crate) fn draw_text_with_outline(
     for dy in -outline_width..=outline_width {
         for dx in -outline_width..=outline_width {
             if dx != 0 || dy != 0 {
-                draw_text_mut(image, outline_color, x + dx, y + dy, scale, font, text);
+                fonts.draw_text_mut(image, outline_color, x + dx, y + dy, scale, text);
             }
         }
     }
 
     // Draw text
-    draw_text_mut(image, color, x, y, scale, font, text);
+    fonts.draw_text_mut(image, color, x, y, scale, text);
+}
+
+/// Resizes an image to the given size according to the fit mode:
+/// - `fill`: the image fills the whole size and is cropped if needed
+///   (around its most detailed part if `smart_crop` is `true`);
+/// - `fit`: the whole image is visible, with black bars if needed;
+/// - `stretch`: the image is stretched to the size;
+/// - `center`: the image is centered without being resized;
+/// - `blur_fill`: the whole image is visible, over a blurred copy of itself that fills the size.
+pub(crate) fn fit_image(
+    img: &DynamicImage,
+    width: u32,
+    height: u32,
+    fit_mode: &str,
+    smart_crop: bool,
+) -> DynamicImage {
+    debug!("Fitting image in {width}x{height} with mode {fit_mode:?}");
+    match fit_mode {
+        "fit" => {
+            let fitted = img.resize(width, height, FilterType::Lanczos3);
+            center_on(RgbaImage::new(width, height), &fitted)
+        }
+        "stretch" => img.resize_exact(width, height, FilterType::Lanczos3),
+        "center" => center_on(RgbaImage::new(width, height), img),
+        "blur_fill" => {
+            // Blurring a small copy is much faster and looks the same once upscaled
+            let background = img
+                .resize_to_fill(
+                    (width / 8).max(1),
+                    (height / 8).max(1),
+                    FilterType::Triangle,
+                )
+                .fast_blur(4.0)
+                .resize_exact(width, height, FilterType::Triangle)
+                .to_rgba8();
+            let fitted = img.resize(width, height, FilterType::Lanczos3);
+            center_on(background, &fitted)
+        }
+        // fill
+        _ if smart_crop => smart_resize_to_fill(img, width, height),
+        _ => resize_to_fill(img, width, height),
+    }
+}
+
+/// Draws an image centered on a background and returns the result.
+fn center_on(mut background: RgbaImage, img: &DynamicImage) -> DynamicImage {
+    let x = (i64::from(background.width()) - i64::from(img.width())) / 2;
+    let y = (i64::from(background.height()) - i64::from(img.height())) / 2;
+    imageops::overlay(&mut background, &img.to_rgba8(), x, y);
+    DynamicImage::ImageRgba8(background)
+}
+
+/// Resizes an image to the bounding box of all the monitors according to the fit mode
+/// and keeps only the slices that are visible on a monitor.
+pub(crate) fn span_across_monitors(
+    img: &DynamicImage,
+    monitors: &[Monitor],
+    fit_mode: &str,
+    smart_crop: bool,
+) -> DynamicImage {
+    let (left, top, width, height) = get_bounding_box(monitors);
+    info!(
+        "Spanning image across {} monitors ({width}x{height})",
+        monitors.len()
+    );
+
+    let filled = fit_image(img, width, height, fit_mode, smart_crop);
+
+    // The parts of the bounding box that are not covered by a monitor stay black
+    let mut canvas = RgbaImage::new(width, height);
+    for monitor in monitors {
+        let (x, y) = get_monitor_offset(monitor, left, top);
+        let slice = filled.crop_imm(x, y, monitor.width, monitor.height);
+        imageops::replace(&mut canvas, &slice.to_rgba8(), i64::from(x), i64::from(y));
+    }
+
+    DynamicImage::ImageRgba8(canvas)
+}
+
+/// Returns the offset of a monitor relative to the top left corner of the bounding box.
+#[expect(clippy::cast_sign_loss)]
+pub(crate) fn get_monitor_offset(monitor: &Monitor, left: i32, top: i32) -> (u32, u32) {
+    ((monitor.x - left) as u32, (monitor.y - top) as u32)
+}
+
+/// Changes the brightness and the contrast of an image (negative values darken it).
+pub(crate) fn dim(img: &DynamicImage, brightness: i32, contrast: f32) -> DynamicImage {
+    debug!("Dimming image (brightness {brightness}, contrast {contrast})");
+    let mut ret = if brightness == 0 {
+        img.clone()
+    } else {
+        img.brighten(brightness)
+    };
+    if contrast != 0.0 {
+        ret = ret.adjust_contrast(contrast);
+    }
+    ret
+}
+
+/// Darkens and blurs the regions in each screen of the image, given as `(x, y, width, height)`
+/// (the whole image unless it spans several monitors).
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+pub(crate) fn dim_regions(
+    img: &mut DynamicImage,
+    regions: &[DimRegion],
+    screens: &[(u32, u32, u32, u32)],
+) {
+    let percent_of = |value: f32, size: u32| (value / 100.0 * size as f32).round() as u32;
+    for &(screen_x, screen_y, screen_width, screen_height) in screens {
+        for region in regions {
+            let x = screen_x + percent_of(region.x, screen_width);
+            let y = screen_y + percent_of(region.y, screen_height);
+            let width = percent_of(region.width, screen_width).min(screen_x + screen_width - x);
+            let height = percent_of(region.height, screen_height).min(screen_y + screen_height - y);
+            if width == 0 || height == 0 {
+                continue;
+            }
+            debug!(
+                "Dimming the region {width}x{height} at {x},{y} (darken {}, blur {})",
+                region.darken, region.blur
+            );
+
+            let mut part = img.crop_imm(x, y, width, height);
+            if region.blur > 0.0 {
+                part = part.fast_blur(region.blur);
+            }
+            if region.darken > 0.0 {
+                let factor = 1.0 - region.darken.clamp(0.0, 1.0);
+                let mut buffer = part.to_rgba8();
+                for pixel in buffer.pixels_mut() {
+                    for channel in &mut pixel.0[..3] {
+                        *channel = (f32::from(*channel) * factor).round() as u8;
+                    }
+                }
+                part = DynamicImage::ImageRgba8(buffer);
+            }
+            imageops::replace(img, &part, i64::from(x), i64::from(y));
+        }
+    }
+}
+
+/// Returns the format of the output image set in the config (PNG by default).
+pub(crate) fn get_output_format(config: &Config) -> ImageFormat {
+    match ImageFormat::from_extension(&config.output_format) {
+        Some(format @ (ImageFormat::Jpeg | ImageFormat::WebP)) => format,
+        _ => ImageFormat::Png,
+    }
+}
+
+/// Saves an image in the given format. JPEG images are saved with the given quality
+/// (between 1 and 100) and WebP images are lossless.
+///
+/// # Errors
+/// Fails if the file can't be written or if the image can't be encoded.
+pub(crate) fn save_image(
+    img: &DynamicImage,
+    path: &Path,
+    format: ImageFormat,
+    quality: u8,
+) -> Result<(), Box<dyn Error>> {
+    if format == ImageFormat::Jpeg {
+        let writer = BufWriter::new(File::create(path)?);
+        // JPEG doesn't support transparency
+        DynamicImage::ImageRgb8(img.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(writer, quality.clamp(1, 100)))?;
+    } else {
+        img.save_with_format(path, format)?;
+    }
+    Ok(())
+}
+
+/// Parses a color written as `#RRGGBB` or `#RRGGBBAA`.
+///
+/// # Errors
+/// Fails if the color is malformed.
+pub(crate) fn parse_color(color: &str) -> Result<Rgba<u8>, Box<dyn Error>> {
+    let hex = color.trim().trim_start_matches('#');
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return Err(format!("Invalid color {color:?}, expected #RRGGBB or #RRGGBBAA").into());
+    }
+    let component = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16);
+    Ok(Rgba([
+        component(0)?,
+        component(2)?,
+        component(4)?,
+        if hex.len() == 8 { component(6)? } else { 255 },
+    ]))
+}
+
+/// Applies the color filter, tint, vignette and blur set in the config to the whole image.
+///
+/// # Errors
+/// Fails if the tint color is malformed.
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+pub(crate) fn apply_filters(img: &mut DynamicImage, config: &Config) -> Result<(), Box<dyn Error>> {
+    if config.blur_radius > 0.0 {
+        debug!("Blurring image with radius {}", config.blur_radius);
+        *img = img.fast_blur(config.blur_radius);
+    }
+
+    let tint = if config.tint_color.is_empty() {
+        None
+    } else {
+        Some(parse_color(&config.tint_color)?)
+    };
+    if config.color_filter == "none" && tint.is_none() && config.vignette <= 0.0 {
+        return Ok(());
+    }
+    debug!(
+        "Applying filter {:?}, tint {:?} and vignette {}",
+        config.color_filter, tint, config.vignette
+    );
+
+    let mut image_buffer = img.to_rgba8();
+    let (width, height) = image_buffer.dimensions();
+    let (center_x, center_y) = (width as f32 / 2.0, height as f32 / 2.0);
+    let max_distance = center_x.hypot(center_y);
+
+    for (x, y, pixel) in image_buffer.enumerate_pixels_mut() {
+        let [mut r, mut g, mut b, a] = pixel.0.map(f32::from);
+
+        match config.color_filter.as_str() {
+            "grayscale" => {
+                let luminance = 0.299 * r + 0.587 * g + 0.114 * b;
+                (r, g, b) = (luminance, luminance, luminance);
+            }
+            "sepia" => {
+                (r, g, b) = (
+                    0.393 * r + 0.769 * g + 0.189 * b,
+                    0.349 * r + 0.686 * g + 0.168 * b,
+                    0.272 * r + 0.534 * g + 0.131 * b,
+                );
+            }
+            _ => {}
+        }
+
+        if let Some(tint) = tint {
+            let opacity = config.tint_opacity.clamp(0.0, 1.0) * f32::from(tint[3]) / 255.0;
+            r = r * (1.0 - opacity) + f32::from(tint[0]) * opacity;
+            g = g * (1.0 - opacity) + f32::from(tint[1]) * opacity;
+            b = b * (1.0 - opacity) + f32::from(tint[2]) * opacity;
+        }
+
+        if config.vignette > 0.0 {
+            // 0 at the center, 1 in the corners
+            let distance = (x as f32 - center_x).hypot(y as f32 - center_y) / max_distance;
+            let factor = 1.0 - config.vignette.min(1.0) * distance * distance;
+            (r, g, b) = (r * factor, g * factor, b * factor);
+        }
+
+        *pixel = Rgba([r, g, b, a].map(|value| value.clamp(0.0, 255.0) as u8));
+    }
+
+    *img = DynamicImage::ImageRgba8(image_buffer);
 }
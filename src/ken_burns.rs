@@ -0,0 +1,181 @@
+//! Looped videos with a slow pan and zoom over the photo (Ken Burns effect),
+//! for the desktops that support video wallpapers.
+use image::DynamicImage;
+use log::{debug, info};
+use rand::Rng;
+use std::env;
+use std::error::Error;
+use std::f32::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::{Config, KenBurnsConfig};
+use crate::images::fit_image;
+use crate::paths::Paths;
+use crate::resize::resize_cropped;
+
+/// The name of the video wallpaper.
+const VIDEO_NAME: &str = "ken_burns.mp4";
+
+/// Renders the frames of the pan and zoom over the image in a directory,
+/// as `frame_00000.png`, `frame_00001.png`... Returns the number of frames.
+///
+/// The zoom goes forth and back so that the video can be looped without a jump.
+///
+/// # Errors
+/// Fails if a frame can't be saved.
+fn render_frames(
+    img: &DynamicImage,
+    size: (u32, u32),
+    config: &Config,
+    ken_burns: &KenBurnsConfig,
+    frames_dir: &Path,
+) -> Result<u32, Box<dyn Error>> {
+    let zoom = ken_burns.zoom.max(1.0);
+    #[expect(clippy::cast_precision_loss)]
+    let (width, height) = (size.0 as f32, size.1 as f32);
+    // The image is resized once at the largest size, each frame is a part of it
+    #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let (big_width, big_height) = ((width * zoom) as u32, (height * zoom) as u32);
+    let big = fit_image(
+        img,
+        big_width,
+        big_height,
+        &config.fit_mode,
+        config.smart_crop,
+    );
+
+    // The point that stays at the same place while zooming
+    let mut rng = rand::rng();
+    let anchor: (f32, f32) = (rng.random(), rng.random());
+
+    fs::create_dir_all(frames_dir)?;
+    let frame_count = (ken_burns.duration * ken_burns.fps).max(1);
+    for index in 0..frame_count {
+        #[expect(clippy::cast_precision_loss)]
+        let time = index as f32 / frame_count as f32;
+        let progress = (1.0 - (2.0 * PI * time).cos()) / 2.0;
+        let scale = 1.0 + (zoom - 1.0) * progress;
+
+        let (crop_width, crop_height) = (width * zoom / scale, height * zoom / scale);
+        #[expect(clippy::cast_precision_loss)]
+        let (x, y) = (
+            (big.width() as f32 - crop_width) * anchor.0,
+            (big.height() as f32 - crop_height) * anchor.1,
+        );
+        #[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let frame = resize_cropped(
+            &big,
+            (x as u32, y as u32, crop_width as u32, crop_height as u32),
+            size.0,
+            size.1,
+        );
+        frame
+            .to_rgb8()
+            .save(frames_dir.join(format!("frame_{index:05}.png")))?;
+    }
+    debug!("Rendered {frame_count} frames in {frames_dir:?}");
+    Ok(frame_count)
+}
+
+/// Encodes the frames rendered by [`render_frames`] in an MP4 video with `ffmpeg`.
+///
+/// # Errors
+/// Fails if `ffmpeg` can't be called or if the encoding fails.
+fn encode_video(frames_dir: &Path, fps: u32, output: &Path) -> Result<(), Box<dyn Error>> {
+    let result = Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-framerate",
+            &fps.to_string(),
+            "-i",
+        ])
+        .arg(frames_dir.join("frame_%05d.png"))
+        .args(["-c:v", "libx264", "-pix_fmt", "yuv420p"])
+        .arg(output)
+        .output()
+        .map_err(|err| format!("Could not encode the video using ffmpeg: {err}"))?;
+    if !result.status.success() {
+        return Err(format!(
+            "Could not encode the video: {}",
+            String::from_utf8_lossy(&result.stderr)
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Makes a looped video with a pan and zoom over the image and puts it in the output directory
+/// (e.g. a folder watched by Wallpaper Engine). Sets it as the background on KDE.
+/// Returns the path of the video.
+///
+/// # Errors
+/// Fails if the frames can't be rendered or encoded or if the video can't be set as the background.
+pub(crate) fn make_video(
+    img: &DynamicImage,
+    size: (u32, u32),
+    config: &Config,
+    ken_burns: &KenBurnsConfig,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let frames_dir = Paths::temp_dir()?.join("ken_burns_frames");
+    if frames_dir.exists() {
+        fs::remove_dir_all(&frames_dir)?;
+    }
+    info!("Rendering the Ken Burns frames...");
+    render_frames(img, size, config, ken_burns, &frames_dir)?;
+
+    let output_dir = if ken_burns.output_dir.is_empty() {
+        Paths::temp_dir()?.to_path_buf()
+    } else {
+        Paths::expand_user(&ken_burns.output_dir)
+    };
+    fs::create_dir_all(&output_dir)?;
+    let video_path = output_dir.join(VIDEO_NAME);
+    info!("Encoding the Ken Burns video in {video_path:?}...");
+    encode_video(&frames_dir, ken_burns.fps, &video_path)?;
+    fs::remove_dir_all(&frames_dir)?;
+
+    #[cfg(target_os = "linux")]
+    if env::var("XDG_CURRENT_DESKTOP").is_ok_and(|desktop| desktop.contains("KDE")) {
+        set_kde_video_wallpaper(&video_path)?;
+    }
+
+    Ok(video_path)
+}
+
+/// Sets a video as the background of all the KDE desktops with the Smart Video Wallpaper plugin.
+///
+/// # Errors
+/// Fails if the Plasma shell can't be called.
+#[cfg(target_os = "linux")]
+fn set_kde_video_wallpaper(video_path: &Path) -> Result<(), Box<dyn Error>> {
+    debug!("Setting the video wallpaper on KDE");
+    let script = format!(
+        "desktops().forEach(desktop => {{\
+            desktop.wallpaperPlugin = 'smartvideowallpaper';\
+            desktop.currentConfigGroup = ['Wallpaper', 'smartvideowallpaper', 'General'];\
+            desktop.writeConfig('VideoWallpaperBackgroundVideo', 'file://{}');\
+        }});",
+        video_path.to_string_lossy().replace('\'', "\\'")
+    );
+    let result = Command::new("qdbus")
+        .args([
+            "org.kde.plasmashell",
+            "/PlasmaShell",
+            "org.kde.PlasmaShell.evaluateScript",
+            &script,
+        ])
+        .output()
+        .map_err(|err| format!("Could not set the video wallpaper using qdbus: {err}"))?;
+    if !result.status.success() {
+        return Err(format!(
+            "Could not set the video wallpaper: {}",
+            String::from_utf8_lossy(&result.stderr)
+        )
+        .into());
+    }
+    Ok(())
+}
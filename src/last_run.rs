@@ -0,0 +1,148 @@
+//! The status of the last run, written to `last_run.json` and shown with the `status` command,
+//! so that a scheduled task that keeps failing in the background can be noticed.
+//! A healthchecks.io-style URL can also be pinged after every run.
+use chrono::{DateTime, Local, Utc};
+use log::{debug, error, warn};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::http;
+use crate::i18n::tr;
+use crate::paths::Paths;
+use crate::{NoImagesError, UnreadableImageError};
+
+/// The maximum duration of the request to the health check URL.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The status of the last run.
+#[derive(Deserialize, Serialize)]
+struct LastRun {
+    time: DateTime<Utc>,
+    success: bool,
+    /// The kind of the error (`no_images`, `io`, `network`, `image` or `other`).
+    error_kind: Option<String>,
+    /// The error message.
+    error: Option<String>,
+    /// The image set as the wallpaper, `None` if the wallpaper wasn't changed.
+    wallpaper: Option<PathBuf>,
+}
+
+/// Returns the kind of an error, to group the failures without reading the messages.
+fn get_error_kind(err: &(dyn Error + 'static)) -> &'static str {
+    if err.is::<NoImagesError>() {
+        "no_images"
+    } else if err.is::<io::Error>() {
+        "io"
+    } else if err.is::<ureq::Error>() {
+        "network"
+    } else if err.is::<image::ImageError>() || err.is::<UnreadableImageError>() {
+        "image"
+    } else {
+        "other"
+    }
+}
+
+/// Pings the `healthcheck_url` of the config: the URL itself after a successful run,
+/// and the URL followed by `/fail` with the error message after a failure.
+fn ping_healthcheck(error: Option<&str>) {
+    let Some(url) = Config::read_unvalidated()
+        .map(|config| config.healthcheck_url)
+        .filter(|url| !url.is_empty())
+    else {
+        return;
+    };
+    let url = match error {
+        Some(_) => format!("{}/fail", url.trim_end_matches('/')),
+        None => url,
+    };
+    debug!("Pinging the health check URL {url}");
+    let result = http::post(&url)
+        .config()
+        .timeout_global(Some(TIMEOUT))
+        .build()
+        .send(error.unwrap_or_default());
+    if let Err(err) = result {
+        warn!("Couldn't ping the health check URL: {err}");
+    }
+}
+
+/// Writes the status of the last run in `last_run.json`.
+///
+/// # Errors
+/// Fails if the file can't be written.
+fn write(last_run: &LastRun) -> Result<(), Box<dyn Error>> {
+    fs::write(
+        Paths::last_run_file()?,
+        serde_json::to_string_pretty(last_run)?,
+    )?;
+    Ok(())
+}
+
+/// Writes the status of the last run and pings the health check URL.
+fn record(last_run: &LastRun) {
+    if let Err(err) = write(last_run) {
+        error!("Couldn't write the status of the last run: {err}");
+    }
+    ping_healthcheck(last_run.error.as_deref());
+}
+
+/// Records a successful run, with the image set as the wallpaper if it was changed.
+pub(crate) fn record_success(wallpaper: Option<&Path>) {
+    record(&LastRun {
+        time: Utc::now(),
+        success: true,
+        error_kind: None,
+        error: None,
+        wallpaper: wallpaper.map(Path::to_path_buf),
+    });
+}
+
+/// Records a failed run.
+pub(crate) fn record_failure(err: &(dyn Error + 'static)) {
+    record(&LastRun {
+        time: Utc::now(),
+        success: false,
+        error_kind: Some(get_error_kind(err).to_string()),
+        error: Some(err.to_string()),
+        wallpaper: None,
+    });
+}
+
+/// Shows the status of the last run.
+///
+/// # Errors
+/// Fails if the status file is malformed.
+pub(crate) fn show_status() -> Result<(), Box<dyn Error>> {
+    let Ok(content) = fs::read_to_string(Paths::last_run_file()?) else {
+        println!("{}", tr!("status-never-run"));
+        return Ok(());
+    };
+    let last_run: LastRun = serde_json::from_str(&content)?;
+    let time = last_run
+        .time
+        .with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M:%S");
+    if last_run.success {
+        println!("{}", tr!("status-success", time = time));
+    } else {
+        println!("{}", tr!("status-failure", time = time));
+        println!(
+            "{}",
+            tr!(
+                "status-error",
+                kind = last_run.error_kind.unwrap_or_default(),
+                error = last_run.error.unwrap_or_default()
+            )
+        );
+    }
+    match last_run.wallpaper {
+        Some(wallpaper) => println!("{}", tr!("status-wallpaper", path = wallpaper.display())),
+        None => println!("{}", tr!("status-unchanged")),
+    }
+    Ok(())
+}
@@ -0,0 +1,748 @@
+//! The core of the wallpaper changer: the selection of the local and online images,
+//! their composition and the backends that set them as the wallpaper.
+//!
+//! It can be embedded in other programs (e.g. bars or launchers):
+//!
+//! ```no_run
+//! use wallpaper_changer_core::{Config, WallpaperEngine};
+//!
+//! let mut engine = WallpaperEngine::new(Config::load()?)?;
+//! let images = engine.change()?;
+//! println!("Chosen image: {}", images[0].display());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use config::LabelPosition;
+use database::{Database, ImageList};
+use fonts::FontStack;
+use image::{DynamicImage, ImageFormat};
+use image_list::ImageData;
+use image_structs::{get_ready_format, open_image, open_image_for, Image};
+use log::info;
+use log::{debug, error, warn};
+use paths::Paths;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use screen_size::{
+    get_bounding_box, get_monitors, get_primary_scale, get_wallpaper_size, get_wallpaper_size_for,
+    scale_size, Monitor,
+};
+use set_background::BackgroundStyle;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// The entry points of the program and of its tests, which aren't part of the API
+#[doc(hidden)]
+pub use cli::{run_command, run_program};
+pub use config::Config;
+
+#[derive(Debug)]
+/// An error that is raised when no images are available.
+struct NoImagesError;
+
+impl fmt::Display for NoImagesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "No images available locally or online")
+    }
+}
+
+impl Error for NoImagesError {}
+
+#[derive(Debug)]
+/// An error that is raised when the chosen image can't be decoded (e.g. a corrupt file).
+struct UnreadableImageError {
+    path: PathBuf,
+    source: Box<dyn Error>,
+}
+
+impl fmt::Display for UnreadableImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Couldn't read the image {:?}: {}",
+            self.path, self.source
+        )
+    }
+}
+
+impl Error for UnreadableImageError {}
+
+/// The number of images that are tried when the chosen ones can't be decoded.
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Changes the wallpaper with a configuration, like the program does.
+///
+/// The state of the online images, the history and the caches are shared with the program.
+pub struct WallpaperEngine {
+    config: Config,
+    image_data: ImageData,
+}
+
+impl WallpaperEngine {
+    /// Makes an engine that uses the given configuration.
+    ///
+    /// # Errors
+    /// Fails if the state of the online images can't be loaded.
+    pub fn new(config: Config) -> Result<Self, Box<dyn Error>> {
+        i18n::set_language(&config.language);
+        Ok(Self {
+            config,
+            image_data: ImageData::load()?,
+        })
+    }
+
+    /// Chooses new images, sets them as the wallpaper and returns the paths of the chosen images,
+    /// not of the composed wallpaper (only one unless a slideshow is made).
+    ///
+    /// # Errors
+    /// Fails if no image can be chosen or if it can't be composed, saved or set as the wallpaper.
+    pub fn change(&mut self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let images = change_wallpaper(&self.config, &mut self.image_data)?;
+        Ok(images.iter().map(|image| image.get_path()).collect())
+    }
+}
+
+/// Returns the most recent wallpaper generated by the program.
+///
+/// # Errors
+/// Fails if no wallpaper has been generated yet.
+fn get_last_wallpaper() -> Result<PathBuf, Box<dyn Error>> {
+    Ok(find_last_wallpaper(None)?.ok_or("No wallpaper has been generated yet")?)
+}
+
+/// Returns the most recent wallpaper generated by the program other than the given one
+/// and the prepared next wallpaper, or `None` if there is none.
+///
+/// # Errors
+/// Fails if the temporary directory can't be read.
+fn find_last_wallpaper(except: Option<&Path>) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    let next = prefetch::get_background();
+    Ok(fs::read_dir(Paths::temp_dir()?)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("background_"))
+                && Some(path.as_path()) != except
+                && Some(path) != next.as_ref()
+        })
+        .max_by_key(|path| {
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        }))
+}
+
+/// Selects random images, sets them as the background and downloads the next online images.
+/// Returns the selected images (only one unless a slideshow is made).
+///
+/// # Errors
+/// Fails if no image can be selected or if it can't be composed, saved or set as the background.
+fn change_wallpaper(
+    config: &Config,
+    image_data: &mut ImageData,
+) -> Result<Vec<Box<dyn Image>>, Box<dyn Error>> {
+    let start = timing::start();
+    let config = &resolve_config(config);
+    let mut rng = get_rng(config);
+
+    let (images, output_paths) = select_and_set(config, image_data, &mut rng)?;
+
+    // Make a video wallpaper from the first image
+    if let Some(ken_burns) = &config.ken_burns {
+        let size = get_wallpaper_size(config);
+        let video = open_image_for(&images[0].get_path(), config, size)
+            .and_then(|img| ken_burns::make_video(&img, size, config, ken_burns));
+        match video {
+            Ok(path) => info!("Saved the Ken Burns video in {path:?}"),
+            Err(err) => error!("Couldn't make the Ken Burns video: {err}"),
+        }
+    }
+
+    hooks::run_post_change(config, images[0].as_ref(), &output_paths[0]);
+    if let Some(announce) = &config.announce {
+        announce::announce_change(announce, images[0].as_ref(), &output_paths[0]);
+    }
+
+    if let Err(err) = catch_up::record_change() {
+        error!("Couldn't store the time of the change: {err}");
+    }
+    if let Err(err) = Database::open().and_then(|mut database| database.add_to_history(&images)) {
+        error!("Couldn't add the wallpaper to the history: {err}");
+    }
+    if config.notifications {
+        if let Err(err) = notification::show_notification(images[0].as_ref()) {
+            error!("Couldn't show the notification: {err}");
+        }
+    }
+    if let Err(err) = accessibility::write_description(images[0].as_ref()) {
+        error!("Couldn't write the description of the wallpaper: {err}");
+    }
+    if config.speak_description {
+        if let Err(err) = accessibility::speak_description(images[0].as_ref()) {
+            error!("Couldn't read the description of the wallpaper aloud: {err}");
+        }
+    }
+
+    // Find old background images and delete them
+    let phase = timing::phase("cleanup");
+    image_data.delete_old_images(&output_paths)?;
+    let mut kept_paths = images
+        .iter()
+        .map(|image| image.get_path())
+        .collect::<Vec<_>>();
+    // The online images that weren't shown yet (e.g. the ones of the prefetch command) are kept
+    kept_paths.extend(image_data.get_unshown_paths());
+    image_list::enforce_cache_limits(config, &kept_paths)?;
+    drop(phase);
+
+    // Download all the other images
+    if config.online_probability > 0.0 {
+        debug!("Downloading all other images");
+        image_data.download_all_images(get_wallpaper_size(config))?;
+    }
+
+    timing::log_summary(start);
+
+    // Compose the next wallpaper now so that the next run only has to set it
+    if config.prefetch_next {
+        if let Err(err) = prefetch::prepare(config, image_data, &mut rng) {
+            error!("Couldn't prepare the next wallpaper: {err}");
+        }
+    }
+    Ok(images)
+}
+
+/// Returns the config with the pictures and the search terms of the time of day
+/// and of the dark mode, and without the online images on a metered connection.
+fn resolve_config(config: &Config) -> Config {
+    let mut config = config.resolve();
+
+    // Don't use the network on a metered connection (e.g. when tethering)
+    if config.online_probability > 0.0
+        && !config.use_metered_connection
+        && skip_rules::is_metered_connection()
+    {
+        info!("Using only local images because the connection is metered");
+        config.online_probability = 0.0;
+    }
+    config
+}
+
+/// Returns the random number generator that chooses the images, with the seed of the config
+/// or a random one.
+fn get_rng(config: &Config) -> StdRng {
+    // The seed is logged so that the choice of the images can be reproduced with --seed
+    let seed = config.seed.unwrap_or_else(|| rand::rng().random());
+    debug!("Random seed: {seed}");
+    StdRng::seed_from_u64(seed)
+}
+
+/// Selects random images and sets them as the background. If an image can't be decoded,
+/// it is added to the blocklist (with `block_unreadable_images`) and other images are chosen.
+/// Returns the selected images and the paths of the saved backgrounds.
+///
+/// # Errors
+/// Fails if no image can be selected or if it can't be composed, saved or set as the background.
+fn select_and_set(
+    config: &Config,
+    image_data: &mut ImageData,
+    rng: &mut StdRng,
+) -> Result<(Vec<Box<dyn Image>>, Vec<PathBuf>), Box<dyn Error>> {
+    let count = match &config.slideshow {
+        Some(slideshow) if slideshow::SUPPORTED => slideshow.images.max(1),
+        Some(_) => {
+            warn!("Slideshows are not supported on this platform, using a single image");
+            1
+        }
+        None => 1,
+    };
+    let mut attempt = 1;
+    loop {
+        // Select random images (local or online)
+        let phase = timing::phase("select");
+        // The image prepared by the last run is used first
+        let prepared = (config.prefetch_next && count == 1 && attempt == 1)
+            .then(|| prefetch::take(config))
+            .flatten();
+        let images = match prepared {
+            Some(image) => vec![image],
+            None => (0..count)
+                .map(|_| image_list::select_random_image(config, image_data, rng))
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+        drop(phase);
+
+        hooks::run_pre_change(config, images[0].as_ref());
+        let err = match set_wallpaper(config, &images) {
+            Ok(output_paths) => return Ok((images, output_paths)),
+            Err(err) => err,
+        };
+        let Some(unreadable) = err.downcast_ref::<UnreadableImageError>() else {
+            return Err(err);
+        };
+        if attempt == MAX_ATTEMPTS {
+            return Err(err);
+        }
+        error!("{unreadable}, choosing another image");
+        if config.block_unreadable_images {
+            let source = images
+                .iter()
+                .find(|image| image.get_path() == unreadable.path)
+                .map_or_else(
+                    || unreadable.path.to_string_lossy().to_string(),
+                    |image| image.get_source(),
+                );
+            if let Err(err) = Database::open().and_then(|database| {
+                database.add_to_list(ImageList::Blocklist, &unreadable.path, &source)
+            }) {
+                error!("Couldn't add the image to the blocklist: {err}");
+            }
+        }
+        attempt += 1;
+    }
+}
+
+/// Sets the image as the background, or a slideshow of the images if there are several of them.
+/// Returns the paths of the saved backgrounds.
+///
+/// # Errors
+/// Fails if an image can't be composed or saved or if the background can't be set.
+fn set_wallpaper(
+    config: &Config,
+    images: &[Box<dyn Image>],
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let (Some(slideshow), [_, _, ..]) = (&config.slideshow, images) else {
+        let image = images.first().ok_or(NoImagesError)?;
+        return Ok(vec![compose_and_set(config, image.as_ref())?]);
+    };
+
+    let monitors = get_monitors();
+    let mut output_paths = Vec::new();
+    let mut first_palette = None;
+    for image in images {
+        let (output_path, palette) = compose_background(config, image.as_ref(), &monitors)?;
+        if output_paths.is_empty() {
+            first_palette = palette;
+        }
+        output_paths.push(output_path);
+    }
+
+    let slideshow_path = Paths::temp_dir()?.join("slideshow.xml");
+    info!("Writing the slideshow in {slideshow_path:?}");
+    slideshow::write_slideshow(&output_paths, slideshow, &slideshow_path)?;
+
+    // The slideshow starts with the first background
+    set_composed_background(
+        config,
+        &monitors,
+        &slideshow_path,
+        &output_paths[0],
+        first_palette,
+    )?;
+
+    Ok(output_paths)
+}
+
+/// Resizes the image for the current monitors, writes its description on it,
+/// saves it and sets it as the background. Returns the path of the saved background.
+///
+/// If the same composition was already made, the saved background is reused.
+///
+/// # Errors
+/// Fails if the image can't be opened, labeled, saved or set as the background.
+fn compose_and_set(config: &Config, image: &dyn Image) -> Result<PathBuf, Box<dyn Error>> {
+    // Query the monitors again, they may have changed since the last run
+    let monitors = get_monitors();
+    let (output_path, palette) = compose_background(config, image, &monitors)?;
+    set_composed_background(config, &monitors, &output_path, &output_path, palette)?;
+    Ok(output_path)
+}
+
+/// Resizes the image for the monitors, writes its description on it and saves it.
+/// Returns the path of the saved background and its palette if it was computed.
+///
+/// If the same composition was already made, the saved background is reused.
+///
+/// # Errors
+/// Fails if the image can't be opened, labeled or saved.
+fn compose_background(
+    config: &Config,
+    image: &dyn Image,
+    monitors: &[Monitor],
+) -> Result<(PathBuf, Option<palette::Palette>), Box<dyn Error>> {
+    let dark_mode = theme::is_dark_mode();
+
+    // The image is copied as is if it doesn't need to be modified
+    let original_format = get_original_format(config, image, monitors, dark_mode);
+    let output_format = original_format.unwrap_or_else(|| images::get_output_format(config));
+
+    // Path of the modified image
+    let cache_key = get_cache_key(config, image, monitors, dark_mode);
+    let output_path = Paths::temp_dir()?
+        .join(cache_key.map_or_else(
+            || {
+                format!(
+                    "background_{}",
+                    chrono::Local::now().format("%Y-%m-%d_%H-%M-%S-%3f")
+                )
+            },
+            |key| format!("background_{key:016x}"),
+        ))
+        .with_extension(output_format.extensions_str()[0]);
+
+    let palette = if cache_key.is_some() && output_path.exists() {
+        info!("Reusing the background {output_path:?}");
+        // Mark it as recently used so that it isn't deleted
+        File::options()
+            .append(true)
+            .open(&output_path)?
+            .set_modified(SystemTime::now())?;
+        None
+    } else {
+        let palette = if original_format.is_some() {
+            info!("The image doesn't need to be modified, copying it to {output_path:?}");
+            fs::create_dir_all(Paths::temp_dir()?)?;
+            fs::copy(image.get_path(), &output_path)?;
+            None
+        } else {
+            compose(
+                config,
+                image,
+                monitors,
+                dark_mode,
+                &output_path,
+                output_format,
+            )?
+        };
+        // Remember where the wallpaper comes from
+        if let Err(err) = provenance::write(&output_path, image) {
+            error!("Couldn't write the provenance of the wallpaper: {err}");
+        }
+        palette
+    };
+
+    Ok((output_path, palette))
+}
+
+/// Sets the file (a background or a slideshow) as the background
+/// and applies the palette of the background image.
+///
+/// # Errors
+/// Fails if the background can't be set or if the palette can't be stored.
+fn set_composed_background(
+    config: &Config,
+    monitors: &[Monitor],
+    path: &Path,
+    background_path: &Path,
+    palette: Option<palette::Palette>,
+) -> Result<(), Box<dyn Error>> {
+    // The colors of a reused or copied background are extracted from the file
+    let palette = match palette {
+        None if config.palette || config.set_accent_color || config.set_panel_color => Some(
+            palette::Palette::from_image(&open_image(background_path)?, background_path),
+        ),
+        palette => palette,
+    };
+
+    // Set the image as the background
+    debug!("Setting background");
+    let phase = timing::phase("set");
+    let spanning = config.span_monitors && monitors.len() > 1;
+    let style = if spanning {
+        BackgroundStyle::Span
+    } else {
+        BackgroundStyle::Fill
+    };
+    // Fade from the previous wallpaper (not with a slideshow, which has its own transitions)
+    if let Some(cross_fade) = config
+        .cross_fade
+        .as_ref()
+        .filter(|_| path == background_path)
+    {
+        if !cross_fade::SUPPORTED {
+            debug!("The cross-fade isn't supported on this platform");
+        } else if let Ok(Some(previous)) = find_last_wallpaper(Some(background_path)) {
+            if let Err(err) = cross_fade::play(&previous, background_path, cross_fade, style) {
+                error!("Couldn't show the cross-fade: {err}");
+            }
+        }
+    }
+    set_background::set_background(path, style)?;
+    drop(phase);
+
+    if let Some(palette) = palette {
+        if config.palette {
+            palette.store(config)?;
+        }
+        if config.set_accent_color {
+            if let Err(err) = accent_color::set_accent_color(palette.dominant) {
+                error!("Couldn't set the accent color: {err}");
+            }
+        }
+        if config.set_panel_color {
+            if let Err(err) = accent_color::set_panel_color(palette::mute(palette.dominant)) {
+                error!("Couldn't set the panel color: {err}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the format of the image if it can be used as the background without being modified:
+/// nothing is written on it, no filter is applied and it already has the size of the wallpaper.
+fn get_original_format(
+    config: &Config,
+    image: &dyn Image,
+    monitors: &[Monitor],
+    dark_mode: bool,
+) -> Option<ImageFormat> {
+    let unmodified = config.label_position == LabelPosition::Hidden
+        && config.clock.is_none()
+        && config.date.is_none()
+        && config.calendar.is_none()
+        && config.system_info.is_none()
+        && config.color_filter == "none"
+        && config.tint_color.is_empty()
+        && config.vignette <= 0.0
+        && config.blur_radius <= 0.0
+        && config.dim_regions.is_empty()
+        && (!dark_mode || (config.dark_brightness == 0 && config.dark_contrast == 0.0));
+    if !unmodified {
+        return None;
+    }
+    get_ready_format(&image.get_path(), get_wallpaper_size_for(config, monitors))
+}
+
+/// Returns `true` if widgets that change over time (e.g. the clock) are written on the wallpaper.
+fn has_live_widgets(config: &Config) -> bool {
+    config.clock.is_some()
+        || config.date.is_some()
+        || config.calendar.is_some()
+        || config.system_info.is_some()
+}
+
+/// Returns a key that identifies the composition of the image with the current settings,
+/// or `None` if it can't be reused because the widgets change over time.
+fn get_cache_key(
+    config: &Config,
+    image: &dyn Image,
+    monitors: &[Monitor],
+    dark_mode: bool,
+) -> Option<u64> {
+    if has_live_widgets(config) {
+        return None;
+    }
+
+    let path = image.get_path();
+    let modified = fs::metadata(&path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    modified.hash(&mut hasher);
+    image.get_description().hash(&mut hasher);
+    dark_mode.hash(&mut hasher);
+    // The monitors and the config contain floats, which can't be hashed directly
+    format!("{monitors:?}").hash(&mut hasher);
+    format!("{config:?}").hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// Resizes the image for the monitors, applies the filters, writes its description on it
+/// and saves it in the given format. Returns the palette of the background if it is needed.
+///
+/// # Errors
+/// Fails if the image can't be opened, labeled or saved.
+fn compose(
+    config: &Config,
+    image: &dyn Image,
+    monitors: &[Monitor],
+    dark_mode: bool,
+    output_path: &Path,
+    output_format: ImageFormat,
+) -> Result<Option<palette::Palette>, Box<dyn Error>> {
+    let spanning = config.span_monitors && monitors.len() > 1;
+    let wallpaper_size = get_wallpaper_size_for(config, monitors);
+
+    // Load the image
+    let phase = timing::phase("decode");
+    let path = image.get_path();
+    let img = open_image_for(&path, config, wallpaper_size)
+        .map_err(|source| UnreadableImageError { path, source })?;
+    drop(phase);
+
+    // Scale the text like the rest of the desktop on HiDPI monitors
+    let scale = get_primary_scale(monitors);
+
+    let phase = timing::phase("resize");
+    let mut background = if spanning {
+        // Span the image across all the monitors
+        images::span_across_monitors(&img, monitors, &config.fit_mode, config.smart_crop)
+    } else {
+        // Resize the background to the size of the largest monitor
+        images::fit_image(
+            &img,
+            wallpaper_size.0,
+            wallpaper_size.1,
+            &config.fit_mode,
+            config.smart_crop,
+        )
+    };
+    drop(phase);
+
+    // Apply the color filters
+    let phase = timing::phase("filter");
+    images::apply_filters(&mut background, config)?;
+
+    // Dim the background so that it isn't too bright with a dark theme
+    if dark_mode {
+        background = images::dim(&background, config.dark_brightness, config.dark_contrast);
+    }
+
+    // Keep the icons and the taskbar readable on each monitor
+    if !config.dim_regions.is_empty() {
+        let screens = if spanning {
+            let (left, top, _, _) = get_bounding_box(monitors);
+            monitors
+                .iter()
+                .map(|monitor| {
+                    let (x, y) = images::get_monitor_offset(monitor, left, top);
+                    (x, y, monitor.width, monitor.height)
+                })
+                .collect()
+        } else {
+            vec![(0, 0, background.width(), background.height())]
+        };
+        images::dim_regions(&mut background, &config.dim_regions, &screens);
+    }
+    drop(phase);
+
+    // Extract the colors of the background before writing on it
+    let palette = (config.palette || config.set_accent_color || config.set_panel_color)
+        .then(|| palette::Palette::from_image(&background, output_path));
+
+    // Write the filename, the date and the widgets on the image
+    // (on the chosen monitor or on the primary monitor when spanning)
+    let phase = timing::phase("label");
+    if spanning {
+        let (left, top, _, _) = get_bounding_box(monitors);
+        let label_monitor = config
+            .label_monitor
+            .and_then(|index| monitors.get(index))
+            .or_else(|| monitors.iter().find(|monitor| monitor.primary))
+            .unwrap_or(&monitors[0]);
+        let (x, y) = images::get_monitor_offset(label_monitor, left, top);
+        let mut slice = background.crop_imm(x, y, label_monitor.width, label_monitor.height);
+        draw_overlays(&mut slice, config, image, label_monitor.scale)?;
+        image::imageops::replace(&mut background, &slice, i64::from(x), i64::from(y));
+    } else {
+        draw_overlays(&mut background, config, image, scale)?;
+    }
+    drop(phase);
+
+    // Save the modified image, creating the parent directory if needed
+    let _phase = timing::phase("save");
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    info!("Saving image in {output_path:?}...");
+    images::save_image(
+        &background,
+        output_path,
+        output_format,
+        config.output_quality,
+    )?;
+
+    Ok(palette)
+}
+
+/// Writes the description of the image and the widgets on the background.
+///
+/// # Errors
+/// Fails if the font can't be loaded or if a label color is malformed.
+fn draw_overlays(
+    background: &mut DynamicImage,
+    config: &Config,
+    image: &dyn Image,
+    scale: f32,
+) -> Result<(), Box<dyn Error>> {
+    let fonts = FontStack::load(config)?;
+    let style = images::TextStyle::from_config(config, scale)?;
+    images::write_text_on_image(
+        background,
+        &fonts,
+        &style,
+        &image.get_description(),
+        scale_size(config.font_size, scale),
+        config.label_position,
+    );
+    widgets::draw_widgets(background, config, &fonts, &style, scale);
+    Ok(())
+}
+
+mod accent_color;
+mod accessibility;
+mod add_scheduled_task;
+mod announce;
+mod batch;
+mod calendar;
+mod catch_up;
+mod circuit_breaker;
+mod cli;
+mod config;
+mod content_filter;
+mod control;
+mod cross_fade;
+mod daemon;
+mod database;
+mod date_format;
+mod decoders;
+mod dedupe;
+mod exif_data;
+mod favorites_backup;
+mod feed;
+mod fonts;
+mod gallery;
+mod hooks;
+mod http;
+mod i18n;
+mod image_list;
+mod image_structs;
+mod images;
+mod ken_burns;
+mod last_run;
+mod local_index;
+mod local_terms;
+mod location;
+mod logging;
+mod notification;
+mod offline;
+mod palette;
+mod paths;
+mod pause;
+mod pin;
+mod prefetch;
+mod provenance;
+mod resize;
+mod screen_size;
+mod set_background;
+mod settings_bundle;
+mod skip_rules;
+mod slideshow;
+mod smart_crop;
+mod solar;
+mod system_info;
+mod theme;
+mod timing;
+#[cfg(feature = "tray")]
+mod tray;
+#[cfg(feature = "video")]
+mod video_frames;
+mod weights;
+mod widgets;
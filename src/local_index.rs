@@ -0,0 +1,248 @@
+//! The index of the local images, stored in the database and updated incrementally:
+//! only the directories whose modification time changed are listed again,
+//! and the daemon updates it as soon as the pictures folders change.
+use chrono::{DateTime, TimeDelta, Utc};
+use log::{debug, error, info};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+use crate::config::Config;
+use crate::database::Database;
+use crate::image_structs::is_image;
+use crate::paths::Paths;
+
+/// The interval between two full indexings of a pictures folder, in case a change was missed
+/// (e.g. on network drives where the modification times aren't reliable).
+const FULL_INDEXING_INTERVAL: TimeDelta = TimeDelta::days(7);
+
+/// The images of a directory of a pictures folder, without the ones of its subdirectories.
+pub(crate) struct DirectoryScan {
+    /// The path of the directory, relative to the pictures folder.
+    pub(crate) dir: PathBuf,
+    pub(crate) modified: DateTime<Utc>,
+    /// The file names of the images.
+    pub(crate) files: Vec<String>,
+}
+
+/// Returns the modification time of a file or a directory.
+///
+/// # Errors
+/// Fails if the file doesn't exist or if its modification time is unknown.
+fn get_modified(path: &Path) -> Result<DateTime<Utc>, Box<dyn Error>> {
+    Ok(DateTime::from(fs::metadata(path)?.modified()?))
+}
+
+/// Lists the images of a directory of a pictures folder and returns its subdirectories.
+///
+/// # Errors
+/// Fails if the directory can't be read.
+fn scan_directory(
+    folder: &Path,
+    dir: &Path,
+) -> Result<(DirectoryScan, Vec<PathBuf>), Box<dyn Error>> {
+    let path = folder.join(dir);
+    let modified = get_modified(&path)?;
+    let mut files = Vec::new();
+    let mut subdirs = Vec::new();
+    for entry in fs::read_dir(&path)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_dir() {
+            subdirs.push(dir.join(entry.file_name()));
+        } else if path.is_file() && is_image(&path) {
+            files.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    let scan = DirectoryScan {
+        dir: dir.to_path_buf(),
+        modified,
+        files,
+    };
+    Ok((scan, subdirs))
+}
+
+/// Lists the images of a directory of a pictures folder and of all its subdirectories,
+/// except the ones that are already indexed.
+///
+/// # Errors
+/// Fails if a directory can't be read.
+fn scan_recursively(
+    folder: &Path,
+    dir: &Path,
+    known_dirs: &HashSet<PathBuf>,
+    scans: &mut Vec<DirectoryScan>,
+) -> Result<(), Box<dyn Error>> {
+    let (scan, subdirs) = scan_directory(folder, dir)?;
+    scans.push(scan);
+    for subdir in subdirs {
+        if !known_dirs.contains(&subdir) {
+            scan_recursively(folder, &subdir, known_dirs, scans)?;
+        }
+    }
+    Ok(())
+}
+
+/// Lists the given directories of a pictures folder again, and the new subdirectories.
+/// The directories that don't exist anymore are removed from the index with their subdirectories.
+///
+/// # Errors
+/// Fails if a directory can't be read or if the index can't be updated.
+fn rescan(
+    database: &mut Database,
+    folder: &Path,
+    dirs: &[PathBuf],
+    known_dirs: &HashSet<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let mut scans = Vec::new();
+    let mut removed_dirs = Vec::new();
+    for dir in dirs {
+        removed_dirs.extend(
+            known_dirs
+                .iter()
+                .filter(|known_dir| known_dir.starts_with(dir) && !folder.join(known_dir).is_dir())
+                .cloned(),
+        );
+        if folder.join(dir).is_dir() {
+            scan_recursively(folder, dir, known_dirs, &mut scans)?;
+        }
+    }
+    if scans.is_empty() && removed_dirs.is_empty() {
+        return Ok(());
+    }
+    debug!(
+        "Updating the index of {folder:?}: {} directories listed, {} removed",
+        scans.len(),
+        removed_dirs.len()
+    );
+    database.update_local_index(folder, false, &scans, &removed_dirs)
+}
+
+/// Updates the index of a pictures folder. Only the directories that were modified are listed again,
+/// unless the folder wasn't indexed recently.
+///
+/// # Errors
+/// Fails if a directory can't be read or if the index can't be updated.
+pub(crate) fn update(database: &mut Database, folder: &Path) -> Result<(), Box<dyn Error>> {
+    let index = database
+        .get_local_dirs(folder)?
+        .filter(|(indexed_at, _)| Utc::now() - *indexed_at < FULL_INDEXING_INTERVAL);
+    let Some((_, dirs)) = index else {
+        info!("Indexing the pictures in {folder:?}");
+        let mut scans = Vec::new();
+        scan_recursively(folder, Path::new(""), &HashSet::new(), &mut scans)?;
+        return database.update_local_index(folder, true, &scans, &[]);
+    };
+
+    let modified_dirs = dirs
+        .iter()
+        .filter(|(dir, modified)| get_modified(&folder.join(dir)).ok() != Some(*modified))
+        .map(|(dir, _)| dir.clone())
+        .collect::<Vec<_>>();
+    let known_dirs = dirs.into_iter().map(|(dir, _)| dir).collect();
+    rescan(database, folder, &modified_dirs, &known_dirs)
+}
+
+/// Returns the pictures folders used with the config (the normal one, the dark one
+/// and the ones of the themes and of the times of day).
+pub(crate) fn get_pictures_folders(config: &Config) -> Vec<PathBuf> {
+    let mut folders = vec![&config.pictures_folder, &config.dark_pictures_folder];
+    folders.extend(
+        config
+            .themes
+            .values()
+            .chain(config.time_of_day.values())
+            .map(|sources| &sources.pictures_folder),
+    );
+    let mut folders = folders
+        .into_iter()
+        .filter(|folder| !folder.as_os_str().is_empty())
+        .map(|folder| Paths::expand_user(&folder.to_string_lossy()))
+        .collect::<Vec<_>>();
+    folders.sort();
+    folders.dedup();
+    folders
+}
+
+/// Watches the pictures folders and updates their index when they change.
+pub(crate) struct PicturesWatcher {
+    /// The events are received as long as the watcher is kept.
+    _watcher: RecommendedWatcher,
+    receiver: Receiver<notify::Result<Event>>,
+    folders: Vec<PathBuf>,
+}
+
+impl PicturesWatcher {
+    /// Starts watching the pictures folders of the config.
+    ///
+    /// # Errors
+    /// Fails if the watcher can't be created. The folders that can't be watched are ignored.
+    pub(crate) fn start(config: &Config) -> Result<Self, Box<dyn Error>> {
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        let folders = get_pictures_folders(config)
+            .into_iter()
+            .filter(
+                |folder| match watcher.watch(folder, RecursiveMode::Recursive) {
+                    Ok(()) => true,
+                    Err(err) => {
+                        error!("Couldn't watch the pictures folder {folder:?}: {err}");
+                        false
+                    }
+                },
+            )
+            .collect();
+        Ok(Self {
+            _watcher: watcher,
+            receiver,
+            folders,
+        })
+    }
+
+    /// Updates the index of the directories that changed since the last call.
+    pub(crate) fn apply_changes(&self) {
+        // The directories whose list of files changed, relative to their pictures folder
+        let mut changed_dirs: HashMap<&Path, HashSet<PathBuf>> = HashMap::new();
+        for event in self.receiver.try_iter().filter_map(Result::ok) {
+            if event.kind.is_access() {
+                continue;
+            }
+            for path in &event.paths {
+                let Some(folder) = self.folders.iter().find(|folder| path.starts_with(folder))
+                else {
+                    continue;
+                };
+                if let Some(dir) = path
+                    .parent()
+                    .and_then(|parent| parent.strip_prefix(folder).ok())
+                {
+                    changed_dirs
+                        .entry(folder.as_path())
+                        .or_default()
+                        .insert(dir.to_path_buf());
+                }
+            }
+        }
+        if changed_dirs.is_empty() {
+            return;
+        }
+
+        let result = Database::open().and_then(|mut database| {
+            for (folder, dirs) in changed_dirs {
+                // The folders that aren't indexed yet are indexed when they are used
+                if let Some((_, known_dirs)) = database.get_local_dirs(folder)? {
+                    let dirs = dirs.into_iter().collect::<Vec<_>>();
+                    let known_dirs = known_dirs.into_iter().map(|(dir, _)| dir).collect();
+                    rescan(&mut database, folder, &dirs, &known_dirs)?;
+                }
+            }
+            Ok(())
+        });
+        if let Err(err) = result {
+            error!("Couldn't update the index of the pictures: {err}");
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! The placeholders of the search terms that depend on where the computer is:
+//! `{season}`, `{local_terrain}` (`sea`, `mountains`, `hills` or `countryside`)
+//! and `{weather}` (`sunny`, `cloudy`, `fog`, `rain`, `snow` or `storm`),
+//! so that e.g. `"{season} {local_terrain}"` finds snowy mountains in the Alps in winter.
+//!
+//! The location comes from the config or from the IP address, and the terrain and the weather
+//! from Open-Meteo. They are cached, and the placeholders that can't be resolved
+//! (e.g. offline without a cache) are removed from the term.
+use chrono::{DateTime, Duration, Local, Utc};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+
+use crate::config::{get_season, Config};
+use crate::http;
+use crate::location::get_location;
+use crate::paths::Paths;
+
+/// The URL of the elevation API.
+const ELEVATION_URL: &str = "https://api.open-meteo.com/v1/elevation";
+
+/// The URL of the weather API.
+const WEATHER_URL: &str = "https://api.open-meteo.com/v1/forecast";
+
+/// How long the weather is kept.
+const WEATHER_CACHE_DURATION: Duration = Duration::hours(1);
+
+/// The distance between the location and the points around it where the elevation is checked,
+/// in degrees of latitude (about 20 km).
+const NEIGHBOR_DISTANCE: f64 = 0.2;
+
+#[derive(Deserialize, Serialize)]
+/// The weather at a location.
+struct CachedWeather {
+    location: String,
+    weather: String,
+    date: DateTime<Utc>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+/// The terrains and the weather obtained from Open-Meteo, stored on disk.
+struct Cache {
+    /// The terrains of the locations, which don't change.
+    terrains: BTreeMap<String, String>,
+    weather: Option<CachedWeather>,
+}
+
+impl Cache {
+    /// Reads the cache, or returns an empty cache if it doesn't exist or is invalid.
+    fn read() -> Self {
+        Paths::local_terms_cache_file()
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the cache.
+    fn write(&self) {
+        if let Err(err) = Paths::local_terms_cache_file()
+            .and_then(|path| Ok(fs::write(path, serde_json::to_string(self)?)?))
+        {
+            debug!("Couldn't cache the terrain and the weather: {err}");
+        }
+    }
+}
+
+/// Returns the key of a location in the cache, rounded to about 10 km.
+fn get_location_key((latitude, longitude): (f64, f64)) -> String {
+    format!("{latitude:.1},{longitude:.1}")
+}
+
+/// Returns the terrain from the elevation of a location and of the points around it:
+/// the sea is at an elevation of 0.
+fn classify_terrain(elevation: f64, neighbors: &[f64]) -> &'static str {
+    let highest = neighbors.iter().copied().fold(elevation, f64::max);
+    let lowest = neighbors.iter().copied().fold(elevation, f64::min);
+    if elevation < 200.0 && lowest <= 0.0 {
+        "sea"
+    } else if elevation >= 1000.0 || highest >= 1500.0 {
+        "mountains"
+    } else if elevation >= 400.0 || highest - lowest >= 300.0 {
+        "hills"
+    } else {
+        "countryside"
+    }
+}
+
+/// Returns the terrain of a location from the elevations of Open-Meteo.
+///
+/// # Errors
+/// Fails if the elevation API can't be contacted or if its response can't be decoded.
+fn get_terrain_from_elevation(
+    (latitude, longitude): (f64, f64),
+) -> Result<&'static str, Box<dyn Error>> {
+    #[derive(Deserialize)]
+    struct Response {
+        elevation: Vec<f64>,
+    }
+
+    // The location and 8 points around it (the degrees of longitude are shorter far from the equator)
+    let longitude_distance = NEIGHBOR_DISTANCE / latitude.to_radians().cos().max(0.1);
+    let mut latitudes = Vec::new();
+    let mut longitudes = Vec::new();
+    for (row, column) in [
+        (0.0, 0.0),
+        (1.0, 0.0),
+        (1.0, 1.0),
+        (0.0, 1.0),
+        (-1.0, 1.0),
+        (-1.0, 0.0),
+        (-1.0, -1.0),
+        (0.0, -1.0),
+        (1.0, -1.0),
+    ] {
+        latitudes.push(format!("{:.3}", latitude + row * NEIGHBOR_DISTANCE));
+        longitudes.push(format!("{:.3}", longitude + column * longitude_distance));
+    }
+    let url = format!(
+        "{ELEVATION_URL}?latitude={}&longitude={}",
+        latitudes.join(","),
+        longitudes.join(",")
+    );
+    let response = http::get(&url).call()?;
+    let response: Response = serde_json::from_reader(response.into_body().as_reader())?;
+    let [elevation, neighbors @ ..] = response.elevation.as_slice() else {
+        return Err("The elevation API didn't return any elevation".into());
+    };
+    Ok(classify_terrain(*elevation, neighbors))
+}
+
+/// Returns the terrain of a location, from the cache if it was already obtained.
+fn get_terrain(cache: &mut Cache, location: (f64, f64)) -> Option<String> {
+    let key = get_location_key(location);
+    if let Some(terrain) = cache.terrains.get(&key) {
+        return Some(terrain.clone());
+    }
+    match get_terrain_from_elevation(location) {
+        Ok(terrain) => {
+            info!("Terrain of the location: {terrain}");
+            cache.terrains.insert(key, terrain.to_string());
+            cache.write();
+            Some(terrain.to_string())
+        }
+        Err(err) => {
+            debug!("Couldn't get the terrain of the location: {err}");
+            None
+        }
+    }
+}
+
+/// Returns the current weather at a location from Open-Meteo.
+///
+/// # Errors
+/// Fails if the weather API can't be contacted or if its response can't be decoded.
+fn get_weather_from_api((latitude, longitude): (f64, f64)) -> Result<&'static str, Box<dyn Error>> {
+    #[derive(Deserialize)]
+    struct Current {
+        weather_code: u8,
+    }
+    #[derive(Deserialize)]
+    struct Response {
+        current: Current,
+    }
+
+    let url = format!(
+        "{WEATHER_URL}?latitude={latitude:.2}&longitude={longitude:.2}&current=weather_code"
+    );
+    let response = http::get(&url).call()?;
+    let response: Response = serde_json::from_reader(response.into_body().as_reader())?;
+    // The WMO weather codes
+    Ok(match response.current.weather_code {
+        0 | 1 => "sunny",
+        45 | 48 => "fog",
+        51..=67 | 80..=82 => "rain",
+        71..=77 | 85 | 86 => "snow",
+        95..=99 => "storm",
+        _ => "cloudy",
+    })
+}
+
+/// Returns the current weather at a location, from the cache if it is recent enough.
+fn get_weather(cache: &mut Cache, location: (f64, f64)) -> Option<String> {
+    let key = get_location_key(location);
+    let cached = cache
+        .weather
+        .as_ref()
+        .filter(|weather| weather.location == key);
+    if let Some(weather) =
+        cached.filter(|weather| Utc::now() - weather.date < WEATHER_CACHE_DURATION)
+    {
+        return Some(weather.weather.clone());
+    }
+    match get_weather_from_api(location) {
+        Ok(weather) => {
+            info!("Current weather: {weather}");
+            cache.weather = Some(CachedWeather {
+                location: key,
+                weather: weather.to_string(),
+                date: Utc::now(),
+            });
+            cache.write();
+            Some(weather.to_string())
+        }
+        Err(err) => {
+            // An outdated weather is better than nothing
+            debug!("Couldn't get the weather: {err}");
+            cached.map(|weather| weather.weather.clone())
+        }
+    }
+}
+
+/// Replaces the `{season}`, `{local_terrain}` and `{weather}` placeholders of a search term.
+/// The placeholders that can't be resolved are removed.
+pub(crate) fn expand(config: &Config, term: &str) -> String {
+    if !term.contains('{') {
+        return term.to_string();
+    }
+    let location = get_location(config);
+    let mut cache = Cache::read();
+    let mut expanded = term.replace(
+        "{season}",
+        get_season(Local::now(), location.map(|(latitude, _)| latitude)),
+    );
+    if expanded.contains("{local_terrain}") {
+        let terrain = location.and_then(|location| get_terrain(&mut cache, location));
+        expanded = expanded.replace("{local_terrain}", &terrain.unwrap_or_default());
+    }
+    if expanded.contains("{weather}") {
+        let weather = location.and_then(|location| get_weather(&mut cache, location));
+        expanded = expanded.replace("{weather}", &weather.unwrap_or_default());
+    }
+    let expanded = expanded.split_whitespace().collect::<Vec<_>>().join(" ");
+    info!("Search term {term:?} expanded to {expanded:?}");
+    expanded
+}
@@ -0,0 +1,54 @@
+# The messages shown to the user, in English.
+# The messages that aren't translated in another language are shown in English.
+
+## Notifications
+
+notification-title = New wallpaper
+notification-block = Block this image
+photo-by = Photo by { $author }
+source = Source: { $source }
+
+## Commands
+
+compose-saved = Saved the wallpaper in { $path }
+feed-saved = Saved { $count } wallpapers in { $dir }
+filtered-count = { $count } images are never chosen because of filtered_folders
+filtered-image = { $path } (folder { $folder })
+gallery-saved = Saved the gallery in { $path }
+history-empty = No wallpaper has been shown yet
+list-added-blocklist = Added { $path } to the blocklist
+list-added-favorites = Added { $path } to the favorites
+list-missing-blocklist = { $path } isn't in the blocklist
+list-missing-favorites = { $path } isn't in the favorites
+list-removed-blocklist = Removed { $path } from the blocklist
+list-removed-favorites = Removed { $path } from the favorites
+pause-resumed = Wallpaper changes resumed
+pause-until = Wallpaper changes paused until { $end }
+pause-until-resume = Wallpaper changes paused until the resume command
+pin-removed = Wallpaper unpinned
+pin-until = Wallpaper pinned until { $end }
+pin-until-unpin = Wallpaper pinned until the unpin command
+prefetch-done = Downloaded { $count } images, they are shown before new images are downloaded
+register-cron = Added the script to the crontab
+register-systemd = Enabled the timer { $timer }
+register-systemd-system = Enabled the timer { $timer } for all the users, from their next logon
+register-updated = Updating the scheduled task that ran { $path }
+register-windows = Created the task { $task }
+schedule-command = Command: { $command }
+schedule-moved = The task runs { $old } instead of { $new }, run register again to update it
+schedule-not-registered = Not registered as a scheduled task
+schedule-registered = Registered as a { $kind }
+schedule-schedule = Schedule: { $schedule }
+settings-config-backup = Saved the previous config in { $path }
+settings-exported = Exported the config, { $favorites } favorites, { $blocked } blocked images and { $history } wallpapers of the history to { $path }
+settings-imported = Imported the settings exported on { $date }
+status-error = Error ({ $kind }): { $error }
+status-failure = Last run: { $time }, failed
+status-never-run = The program hasn't run yet
+status-success = Last run: { $time }, successful
+status-unchanged = The wallpaper wasn't changed
+status-wallpaper = Wallpaper: { $path }
+unregister-cron = Removed the script from the crontab
+unregister-systemd = Removed the timer { $timer }
+unregister-windows = Deleted the task { $task }
+whatis-no-provenance = No provenance information found
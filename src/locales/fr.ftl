@@ -0,0 +1,53 @@
+# Les messages affichés à l'utilisateur, en français.
+
+## Notifications
+
+notification-title = Nouveau fond d'écran
+notification-block = Bloquer cette image
+photo-by = Photo de { $author }
+source = Source : { $source }
+
+## Commandes
+
+compose-saved = Fond d'écran enregistré dans { $path }
+feed-saved = { $count } fonds d'écran enregistrés dans { $dir }
+filtered-count = { $count } images ne sont jamais choisies à cause de filtered_folders
+filtered-image = { $path } (dossier { $folder })
+gallery-saved = Galerie enregistrée dans { $path }
+history-empty = Aucun fond d'écran n'a encore été affiché
+list-added-blocklist = { $path } ajouté à la liste de blocage
+list-added-favorites = { $path } ajouté aux favoris
+list-missing-blocklist = { $path } n'est pas dans la liste de blocage
+list-missing-favorites = { $path } n'est pas dans les favoris
+list-removed-blocklist = { $path } retiré de la liste de blocage
+list-removed-favorites = { $path } retiré des favoris
+pause-resumed = Changements de fond d'écran repris
+pause-until = Changements de fond d'écran en pause jusqu'au { $end }
+pause-until-resume = Changements de fond d'écran en pause jusqu'à la commande resume
+pin-removed = Fond d'écran détaché
+pin-until = Fond d'écran épinglé jusqu'au { $end }
+pin-until-unpin = Fond d'écran épinglé jusqu'à la commande unpin
+prefetch-done = { $count } images téléchargées, elles seront affichées avant le téléchargement de nouvelles images
+register-cron = Script ajouté à la crontab
+register-systemd = Minuteur { $timer } activé
+register-systemd-system = Minuteur { $timer } activé pour tous les utilisateurs, à partir de leur prochaine connexion
+register-updated = Mise à jour de la tâche planifiée qui lançait { $path }
+register-windows = Tâche { $task } créée
+schedule-command = Commande : { $command }
+schedule-moved = La tâche lance { $old } au lieu de { $new }, relancez register pour la mettre à jour
+schedule-not-registered = Non enregistré comme tâche planifiée
+schedule-registered = Enregistré comme { $kind }
+schedule-schedule = Planification : { $schedule }
+settings-config-backup = Configuration précédente enregistrée dans { $path }
+settings-exported = Configuration, { $favorites } favoris, { $blocked } images bloquées et { $history } fonds d'écran de l'historique exportés dans { $path }
+settings-imported = Paramètres exportés le { $date } importés
+status-error = Erreur ({ $kind }) : { $error }
+status-failure = Dernier lancement : { $time }, échec
+status-never-run = Le programme n'a pas encore été lancé
+status-success = Dernier lancement : { $time }, réussi
+status-unchanged = Le fond d'écran n'a pas été changé
+status-wallpaper = Fond d'écran : { $path }
+unregister-cron = Script retiré de la crontab
+unregister-systemd = Minuteur { $timer } supprimé
+unregister-windows = Tâche { $task } supprimée
+whatis-no-provenance = Aucune information de provenance trouvée
@@ -0,0 +1,94 @@
+//! Utility functions to get the approximate location of the computer.
+use chrono::{DateTime, Duration, Utc};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+
+use crate::config::Config;
+use crate::http;
+use crate::paths::Paths;
+
+/// The URL of the IP geolocation service.
+const GEOLOCATION_URL: &str = "http://ip-api.com/json/?fields=status,lat,lon";
+
+/// How long the location obtained from the IP address is kept.
+const CACHE_DURATION: Duration = Duration::days(1);
+
+#[derive(Deserialize, Serialize)]
+/// The location obtained from the IP address, stored on disk.
+struct CachedLocation {
+    latitude: f64,
+    longitude: f64,
+    date: DateTime<Utc>,
+}
+
+/// Returns the location (latitude, longitude) of the computer.
+///
+/// It is taken from the config if it is set, otherwise it is obtained from the IP address
+/// and cached for a day. If it can't be determined, it returns `None`.
+pub(crate) fn get_location(config: &Config) -> Option<(f64, f64)> {
+    if let (Some(latitude), Some(longitude)) = (config.latitude, config.longitude) {
+        return Some((latitude, longitude));
+    }
+
+    let cache_path = Paths::location_cache_file()
+        .inspect_err(|err| debug!("Couldn't use the location cache: {err}"))
+        .ok();
+    let cached = cache_path
+        .and_then(|cache_path| fs::File::open(cache_path).ok())
+        .and_then(|file| serde_json::from_reader::<_, CachedLocation>(file).ok());
+    if let Some(ref location) = cached {
+        if Utc::now() - location.date < CACHE_DURATION {
+            debug!("Using the cached location");
+            return Some((location.latitude, location.longitude));
+        }
+    }
+
+    match get_location_from_ip() {
+        Ok(location) => {
+            info!("Location obtained from the IP address: {:?}", location);
+            let cached = CachedLocation {
+                latitude: location.0,
+                longitude: location.1,
+                date: Utc::now(),
+            };
+            if let Some(cache_path) = cache_path {
+                if let Err(err) = fs::File::create(cache_path)
+                    .map_err(Box::<dyn Error>::from)
+                    .and_then(|file| Ok(serde_json::to_writer(file, &cached)?))
+                {
+                    debug!("Couldn't cache the location: {err}");
+                }
+            }
+            Some(location)
+        }
+        Err(err) => {
+            // An outdated location is better than nothing
+            debug!("Couldn't get the location from the IP address: {err}");
+            cached.map(|location| (location.latitude, location.longitude))
+        }
+    }
+}
+
+/// Returns the approximate location (latitude, longitude) of the IP address.
+///
+/// # Errors
+/// Fails if the geolocation service can't be contacted or if its response can't be decoded.
+fn get_location_from_ip() -> Result<(f64, f64), Box<dyn Error>> {
+    #[derive(Deserialize)]
+    struct Response {
+        status: String,
+        #[serde(default)]
+        lat: f64,
+        #[serde(default)]
+        lon: f64,
+    }
+
+    let response = http::get(GEOLOCATION_URL).call()?;
+    let response: Response = serde_json::from_reader(response.into_body().as_reader())?;
+    if response.status != "success" {
+        return Err(format!("Geolocation failed with status {:?}", response.status).into());
+    }
+    Ok((response.lat, response.lon))
+}
@@ -0,0 +1,115 @@
+//! The loggers that write the messages to the console, to the log files and to Sentry.
+use chrono::Local;
+use ftail::channels::console::ConsoleLogger;
+use ftail::channels::daily_file::DailyFileLogger;
+use log::{error, LevelFilter, Log, Metadata, Record};
+#[cfg(feature = "sentry")]
+use sentry_log::LogFilter;
+use serde_json::json;
+use std::error::Error;
+use std::io::{self, Write};
+
+use crate::config::LoggingConfig;
+use crate::paths::Paths;
+
+/// The levels from the least to the most verbose.
+const LEVELS: [LevelFilter; 6] = [
+    LevelFilter::Off,
+    LevelFilter::Error,
+    LevelFilter::Warn,
+    LevelFilter::Info,
+    LevelFilter::Debug,
+    LevelFilter::Trace,
+];
+
+/// Shows the messages in the console as JSON objects, one per line.
+struct JsonConsoleLogger {
+    level_filter: LevelFilter,
+}
+
+impl Log for JsonConsoleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_filter
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = json!({
+            "time": Local::now().to_rfc3339(),
+            "level": record.level().as_str(),
+            "target": record.target(),
+            "message": record.args().to_string(),
+        });
+        println!("{message}");
+    }
+
+    fn flush(&self) {
+        io::stdout().flush().ok();
+    }
+}
+
+/// Returns the level `verbosity` steps more verbose than `level` (less verbose if it is negative).
+fn shift_level(level: LevelFilter, verbosity: i8) -> LevelFilter {
+    let index = LEVELS.iter().position(|other| *other == level).unwrap_or(3);
+    let index = index.saturating_add_signed(isize::from(verbosity));
+    LEVELS[index.min(LEVELS.len() - 1)]
+}
+
+/// Sets up the loggers of the config. The console shows `verbosity` levels more than configured.
+///
+/// # Errors
+/// Fails if a logger is already set up. The log files are skipped if they can't be created.
+pub(crate) fn init(config: &LoggingConfig, verbosity: i8) -> Result<(), Box<dyn Error>> {
+    let console_level = shift_level(config.console_level, verbosity);
+    let mut loggers: Vec<Box<dyn Log>> = Vec::new();
+
+    if config.console_json {
+        loggers.push(Box::new(JsonConsoleLogger {
+            level_filter: console_level,
+        }));
+    } else {
+        loggers.push(Box::new(ConsoleLogger::new(ftail::Config {
+            level_filter: console_level,
+            ..Default::default()
+        })));
+    }
+
+    // The error is logged in the console once the loggers are set up
+    let mut file_error = None;
+    if config.file {
+        let file_logger = Paths::logs_dir().and_then(|logs_dir| {
+            Ok(DailyFileLogger::new(
+                &logs_dir.to_string_lossy(),
+                ftail::Config {
+                    level_filter: config.file_level,
+                    retention_days: Some(config.retention_days),
+                    ..Default::default()
+                },
+            )?)
+        });
+        match file_logger {
+            Ok(file_logger) => loggers.push(Box::new(file_logger)),
+            Err(err) => file_error = Some(err),
+        }
+    }
+
+    #[cfg(feature = "sentry")]
+    loggers.push(Box::new(sentry_log::SentryLogger::new().filter(
+        |md| match md.level() {
+            log::Level::Error => LogFilter::Exception,
+            _ => LogFilter::Breadcrumb,
+        },
+    )));
+
+    log::set_boxed_logger(Box::new(multi_log::MultiLogger::new(loggers)))?;
+
+    log::set_max_level(LevelFilter::Trace);
+
+    if let Some(err) = file_error {
+        error!("Couldn't write the logs to a file: {err}");
+    }
+
+    Ok(())
+}
@@ -1,33 +1,20 @@
 //! A program that automatically changes the wallpaper,
 //! choosing a local or online image.
-use add_scheduled_task::{register_task, unregister_task};
-use compile_dotenv::compile_env;
-use config::Config;
-use ftail::channels::console::ConsoleLogger;
-use ftail::channels::daily_file::DailyFileLogger;
-use image::imageops::FilterType;
-use log::info;
-use log::{debug, error, LevelFilter};
-use paths::Paths;
-use screen_size::get_screen_size;
-use sentry_log::LogFilter;
+// On Windows, the program has no console window so that the scheduled tasks don't flash one
+// (the console of the terminal is attached when it is run from one)
+#![cfg_attr(target_os = "windows", windows_subsystem = "windows")]
+use log::error;
+#[cfg(feature = "sentry")]
+use log::warn;
 use std::env;
 use std::error::Error;
-use std::fmt;
-use std::fs;
 
-#[derive(Debug)]
-/// An error that is raised when no images are available.
-struct NoImagesError;
-
-impl fmt::Display for NoImagesError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "No images available locally or online")
-    }
+/// The variables read when the program was built, see `build.rs`.
+#[cfg_attr(not(feature = "sentry"), expect(dead_code))]
+mod build_env {
+    include!(concat!(env!("OUT_DIR"), "/build_env.rs"));
 }
 
-impl Error for NoImagesError {}
-
 /// The real entry point for the program.
 fn main() {
     match real_main() {
@@ -41,134 +28,59 @@ fn main() {
 /// # Errors
 /// The program can fail for a number of reasons.
 fn real_main() -> Result<(), Box<dyn Error>> {
-    log_panics::init();
-
-    // Initialize the logger
-    let logger1 = ConsoleLogger::new(ftail::Config {
-        level_filter: LevelFilter::Info,
-        ..Default::default()
-    });
+    // The messages must be shown in the terminal, if there is one
+    #[cfg(target_os = "windows")]
+    attach_console();
 
-    let logger2 = DailyFileLogger::new(
-        &Paths::logs_dir().to_string_lossy(),
-        ftail::Config {
-            level_filter: LevelFilter::Debug,
-            retention_days: Some(7),
-            ..Default::default()
-        },
-    )?;
+    log_panics::init();
 
-    let logger3 = sentry_log::SentryLogger::new().filter(|md| match md.level() {
-        log::Level::Error => LogFilter::Exception,
-        _ => LogFilter::Breadcrumb,
-    });
+    #[cfg(feature = "sentry")]
+    let start_telemetry = init_sentry;
+    #[cfg(not(feature = "sentry"))]
+    let start_telemetry = || ();
 
-    log::set_boxed_logger(Box::new(multi_log::MultiLogger::new(vec![
-        Box::new(logger1),
-        Box::new(logger2),
-        Box::new(logger3),
-    ])))?;
+    wallpaper_changer_core::run_program(env::args().skip(1).collect(), start_telemetry)
+}
 
-    log::set_max_level(LevelFilter::Trace);
+/// Writes the output of the program in the console of the terminal that started it, if any,
+/// since a program of the Windows subsystem doesn't get a console.
+///
+/// The terminal doesn't wait for the program, so the output may be written after its prompt.
+#[cfg(target_os = "windows")]
+fn attach_console() {
+    /// The identifier that designates the console of the parent process.
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // It fails when the program isn't started from a terminal, which is expected
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
 
-    let dsn = compile_env!("SENTRY_DSN");
-    let _guard = sentry::init((
+/// Starts reporting the errors to Sentry, until the returned guard is dropped.
+///
+/// The DSN is the `SENTRY_DSN` variable set when the program was built, or when it runs if it wasn't
+/// set at build time. Without a DSN, nothing is reported and `None` is returned.
+#[cfg(feature = "sentry")]
+fn init_sentry() -> Option<sentry::ClientInitGuard> {
+    let dsn = build_env::SENTRY_DSN
+        .filter(|dsn| !dsn.is_empty())
+        .map(str::to_string)
+        .or_else(|| env::var("SENTRY_DSN").ok().filter(|dsn| !dsn.is_empty()));
+    let Some(dsn) = dsn else {
+        warn!(
+            "The errors aren't reported because SENTRY_DSN isn't set (at build time or at runtime)"
+        );
+        return None;
+    };
+    Some(sentry::init((
         dsn,
         sentry::ClientOptions {
             release: sentry::release_name!(),
             traces_sample_rate: 0.1,
             ..Default::default()
         },
-    ));
-
-    // if the first argument is register, register a scheduled task
-    if env::args().nth(1).is_some_and(|arg| arg == "register") {
-        debug!("Found register argument, registering scheduled task");
-        return register_task(&env::current_exe()?);
-    }
-
-    // if the first argument is unregister, unregister a scheduled task
-    if env::args().nth(1).is_some_and(|arg| arg == "unregister") {
-        debug!("Found unregister argument, unregistering scheduled task");
-        return unregister_task(&env::current_exe()?);
-    }
-
-    // on Linux
-    #[cfg(target_os = "linux")]
-    {
-        extern "C" {
-            fn getuid() -> u32;
-        }
-        let uid = unsafe { getuid() };
-        debug!("uid is {}", uid);
-        unsafe {
-            env::set_var(
-                "DBUS_SESSION_BUS_ADDRESS",
-                format!("unix:path=/run/user/{uid}/bus"),
-            );
-        }
-        debug!(
-            "Environment variable DBUS_SESSION_BUS_ADDRESS is set to {:?}",
-            env::var("DBUS_SESSION_BUS_ADDRESS")
-        );
-    }
-
-    // Load configuration
-    let config = Config::load()?;
-
-    // Load image data
-    let mut image_data = image_list::ImageData::load()?;
-
-    // Select a random image (local or online)
-    let image = image_list::select_random_image(&config, &mut image_data)?;
-
-    // Load the image
-    let img = image::open(image.get_path())?;
-
-    // Resize the background to fill the screen size
-    let screen_size = get_screen_size();
-    let mut background = img.resize_to_fill(screen_size.0, screen_size.1, FilterType::Lanczos3);
-
-    // Write the filename and date on the image
-    images::write_text_on_image(
-        &mut background,
-        &image.get_description(),
-        config.font_size,
-        &config.label_position,
-    )?;
-
-    // Save the modified image
-    let output_path = Paths::temp_dir().join(format!(
-        "background_{}.png",
-        chrono::Local::now().format("%Y-%m-%d_%H-%M-%S")
-    ));
-    // Create the parent directory if needed
-    if let Some(parent) = output_path.parent() {
-        fs::create_dir_all(parent)?;
-    }
-    info!("Saving image in {output_path:?}...");
-    background.save(&output_path)?;
-
-    // Set the image as the background
-    debug!("Setting background");
-    set_background::set_background(&output_path)?;
-
-    // Find old background images and delete them
-    image_data.delete_old_images(&output_path)?;
-
-    // Download all the other images
-    debug!("Downloading all other images");
-    image_data.download_all_images()?;
-
-    Ok(())
+    )))
 }
-
-mod add_scheduled_task;
-mod config;
-mod date_format;
-mod image_list;
-mod image_structs;
-mod images;
-mod paths;
-mod screen_size;
-mod set_background;
@@ -0,0 +1,71 @@
+//! Utility functions to show the notifications on Linux.
+use log::error;
+use notify_rust::{Notification, Timeout};
+use std::error::Error;
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::{block_image, get_block_label, get_body, get_title};
+use crate::image_structs::Image;
+
+/// The identifier of the action that blocks the image.
+const BLOCK_ACTION: &str = "block";
+
+/// How long the notifications are shown.
+const NOTIFICATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The threads that wait for a click on the buttons of the notifications.
+static PENDING_ACTIONS: Mutex<Vec<JoinHandle<()>>> = Mutex::new(Vec::new());
+
+/// Shows a notification for the image, with a button that blocks it.
+///
+/// # Errors
+/// Fails if the list of the notification threads is poisoned. The errors of the notification server
+/// are logged by the thread that shows the notification.
+pub(crate) fn show_notification(image: &dyn Image) -> Result<(), Box<dyn Error>> {
+    let path = image.get_path();
+    let source = image.get_source();
+    let body = get_body(image);
+    let (title, block_label) = (get_title(), get_block_label());
+    // The notification is shown in a thread because waiting for its actions blocks until it is closed
+    let thread = thread::spawn(move || {
+        let result = Notification::new()
+            .appname("Wallpaper Changer")
+            .summary(&title)
+            .body(&body)
+            .icon(&path.to_string_lossy())
+            .action(BLOCK_ACTION, &block_label)
+            .timeout(Timeout::Milliseconds(
+                u32::try_from(NOTIFICATION_TIMEOUT.as_millis()).unwrap_or(u32::MAX),
+            ))
+            .show();
+        match result {
+            Ok(handle) => handle.wait_for_action(|action| {
+                if action == BLOCK_ACTION {
+                    block_image(&path, &source);
+                }
+            }),
+            Err(err) => error!("Couldn't show the notification: {err}"),
+        }
+    });
+    PENDING_ACTIONS
+        .lock()
+        .map_err(|_| "The notification threads are poisoned")?
+        .push(thread);
+    Ok(())
+}
+
+/// Waits until the notifications are closed (at most a little longer than they are shown),
+/// so that their buttons still work when the program is about to exit.
+pub(crate) fn wait_for_actions() {
+    let Ok(mut pending_actions) = PENDING_ACTIONS.lock() else {
+        return;
+    };
+    // Some notification servers keep the notifications with buttons until they are dismissed
+    let deadline = Instant::now() + NOTIFICATION_TIMEOUT * 2;
+    while pending_actions.iter().any(|thread| !thread.is_finished()) && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(100));
+    }
+    pending_actions.retain(|thread| !thread.is_finished());
+}
@@ -0,0 +1,52 @@
+//! Desktop notifications shown when the wallpaper changes,
+//! with the description of the image and a button to block it.
+use log::{error, info};
+use std::path::Path;
+
+use crate::database::{Database, ImageList};
+use crate::i18n::tr;
+use crate::image_structs::Image;
+
+#[cfg(target_os = "linux")]
+mod linux;
+
+#[cfg(target_os = "linux")]
+pub(crate) use linux::{show_notification, wait_for_actions};
+
+#[cfg(target_os = "windows")]
+mod windows;
+
+#[cfg(target_os = "windows")]
+pub(crate) use windows::{show_notification, wait_for_actions};
+
+/// Returns the title of the notifications.
+pub(crate) fn get_title() -> String {
+    tr!("notification-title")
+}
+
+/// Returns the label of the button that blocks the image.
+fn get_block_label() -> String {
+    tr!("notification-block")
+}
+
+/// Returns the text of the notification of an image: its description and its author.
+pub(crate) fn get_body(image: &dyn Image) -> String {
+    let mut body = image.get_description().trim().to_string();
+    if let Some(author) = image.get_author() {
+        if !body.is_empty() {
+            body.push('\n');
+        }
+        body.push_str(&tr!("photo-by", author = author));
+    }
+    body
+}
+
+/// Adds an image to the blocklist when the button of its notification is clicked.
+fn block_image(path: &Path, source: &str) {
+    match Database::open()
+        .and_then(|database| database.add_to_list(ImageList::Blocklist, path, source))
+    {
+        Ok(()) => info!("Added {path:?} to the blocklist"),
+        Err(err) => error!("Couldn't add {path:?} to the blocklist: {err}"),
+    }
+}
@@ -0,0 +1,108 @@
+//! Utility functions to show the notifications on Windows, as toasts.
+use log::debug;
+use std::env;
+use std::error::Error;
+use std::process::Command;
+
+use super::{get_block_label, get_body, get_title};
+use crate::image_structs::Image;
+use crate::paths::Paths;
+use crate::slideshow::escape_xml;
+
+/// The identifier of PowerShell, which shows the toasts.
+const APP_ID: &str =
+    r"{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe";
+
+/// Shows the toast given in the `TOAST_XML` environment variable.
+const SHOW_TOAST_SCRIPT: &str = "\
+    [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] | Out-Null; \
+    [Windows.Data.Xml.Dom.XmlDocument, Windows.Data.Xml.Dom.XmlDocument, ContentType = WindowsRuntime] | Out-Null; \
+    $xml = New-Object Windows.Data.Xml.Dom.XmlDocument; \
+    $xml.LoadXml($env:TOAST_XML); \
+    $toast = [Windows.UI.Notifications.ToastNotification]::new($xml); \
+    [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier($env:TOAST_APP_ID).Show($toast)";
+
+/// Sets a string value of a registry key, or its default value if `name` is `None`.
+///
+/// # Errors
+/// Fails if `reg` can't be called or if it fails.
+fn set_registry_value(key: &str, name: Option<&str>, value: &str) -> Result<(), Box<dyn Error>> {
+    let mut command = Command::new("reg");
+    command.args(["add", key]);
+    match name {
+        Some(name) => command.args(["/v", name]),
+        None => command.arg("/ve"),
+    };
+    let output = command.args(["/d", value, "/f"]).output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "Could not set registry value {key}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Registers the URL scheme opened by the button of the toasts, which runs the `block` command
+/// with the current profile, and returns its name.
+///
+/// # Errors
+/// Fails if the registry values can't be set.
+fn register_url_scheme() -> Result<String, Box<dyn Error>> {
+    // The URL schemes can't contain underscores, which can't be in the profiles
+    let scheme = match Paths::profile() {
+        Some(profile) => format!("wallpaper-changer-rs-{}", profile.replace('_', ".")),
+        None => "wallpaper-changer-rs".to_string(),
+    };
+    let mut command = format!("\"{}\"", env::current_exe()?.display());
+    if let Some(profile) = Paths::profile() {
+        command.push_str(&format!(" --profile {profile}"));
+    }
+    command.push_str(" block");
+    debug!("Registering the URL scheme {scheme:?} to run {command:?}");
+
+    let key = format!(r"HKCU\Software\Classes\{scheme}");
+    set_registry_value(&key, None, &format!("URL:{scheme}"))?;
+    set_registry_value(&key, Some("URL Protocol"), "")?;
+    set_registry_value(&format!(r"{key}\shell\open\command"), None, &command)?;
+    Ok(scheme)
+}
+
+/// Shows a toast for the image, with a button that blocks it.
+///
+/// The button blocks the last wallpaper, which is the image of the toast
+/// unless the wallpaper has changed since.
+///
+/// # Errors
+/// Fails if the URL scheme can't be registered or if PowerShell can't be called.
+pub(crate) fn show_notification(image: &dyn Image) -> Result<(), Box<dyn Error>> {
+    let scheme = register_url_scheme()?;
+    let xml = format!(
+        "<toast><visual><binding template=\"ToastGeneric\">\
+         <text>{}</text><text>{}</text><image placement=\"hero\" src=\"{}\"/>\
+         </binding></visual><actions>\
+         <action content=\"{}\" activationType=\"protocol\" arguments=\"{scheme}:block\"/>\
+         </actions></toast>",
+        escape_xml(&get_title()),
+        escape_xml(&get_body(image)),
+        escape_xml(&image.get_path().to_string_lossy()),
+        escape_xml(&get_block_label()),
+    );
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-Command", SHOW_TOAST_SCRIPT])
+        .env("TOAST_XML", xml)
+        .env("TOAST_APP_ID", APP_ID)
+        .output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "Could not show the notification: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Does nothing: the button of the toasts works after the program has exited.
+pub(crate) const fn wait_for_actions() {}
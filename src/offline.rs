@@ -0,0 +1,76 @@
+//! The large batch of online images downloaded ahead of an offline period (e.g. a flight)
+//! with the `prefetch` command. It replaces the current batch of online images,
+//! so its images are shown one after the other, and they aren't deleted from the cache
+//! until they are shown.
+use log::{error, info, warn};
+use std::error::Error;
+
+use crate::config::Config;
+use crate::get_rng;
+use crate::i18n::tr;
+use crate::image_list::{download_pictures, ImageData};
+use crate::image_structs::{Image, OnlineImage};
+use crate::screen_size::get_wallpaper_size;
+
+/// The maximum number of images that Unsplash returns for a request.
+const MAX_IMAGES_PER_REQUEST: u32 = 30;
+
+/// Downloads `count` online images (fewer if the API doesn't return enough new images)
+/// with their descriptions and authors, and makes them the current batch.
+///
+/// # Errors
+/// Fails if no image can be downloaded or if the image data can't be stored.
+pub(crate) fn prefetch(config: &Config, count: u32) -> Result<(), Box<dyn Error>> {
+    let mut config = config.resolve();
+    let mut rng = get_rng(&config);
+    let wallpaper_size = get_wallpaper_size(&config);
+
+    let mut images: Vec<OnlineImage> = Vec::new();
+    loop {
+        let missing = count.saturating_sub(u32::try_from(images.len()).unwrap_or(u32::MAX));
+        if missing == 0 {
+            break;
+        }
+        config.images_per_download = missing.min(MAX_IMAGES_PER_REQUEST);
+        let batch = match download_pictures(&config, &mut rng) {
+            Ok(batch) => batch,
+            // Keep the images that are already downloaded (e.g. when the rate limit is reached)
+            Err(err) if !images.is_empty() => {
+                warn!("Stopping after {} images: {err}", images.len());
+                break;
+            }
+            Err(err) => return Err(err),
+        };
+        let new_images = batch
+            .into_iter()
+            .filter(|image| images.iter().all(|other| other.id != image.id))
+            .collect::<Vec<_>>();
+        if new_images.is_empty() {
+            warn!(
+                "No new images are returned, stopping after {} images",
+                images.len()
+            );
+            break;
+        }
+        for image in new_images {
+            match image.download(wallpaper_size) {
+                Ok(()) => images.push(image),
+                Err(err) => error!("Couldn't download {:?}: {err}", image.get_source()),
+            }
+        }
+        info!("{} of {count} images downloaded", images.len());
+    }
+    if images.is_empty() {
+        return Err("No image could be downloaded".into());
+    }
+
+    // The images of the previous batch are deleted at the next change
+    let downloaded = images.len();
+    let image_data = ImageData {
+        urls: images,
+        ..Default::default()
+    };
+    image_data.store()?;
+    println!("{}", tr!("prefetch-done", count = downloaded));
+    Ok(())
+}
@@ -0,0 +1,264 @@
+//! Utility functions to extract the dominant colors of the wallpaper
+//! and write them in pywal-style files.
+use image::imageops::FilterType;
+use image::DynamicImage;
+use image::Rgb;
+use log::{debug, error, info};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use crate::config::Config;
+use crate::paths::Paths;
+
+/// The size of the copy of the image used to extract the colors.
+const ANALYSIS_SIZE: u32 = 64;
+
+/// The number of colors extracted from the image.
+const COLOR_COUNT: usize = 8;
+
+/// The number of iterations of the k-means algorithm.
+const ITERATIONS: usize = 10;
+
+#[derive(Clone, Debug, Serialize)]
+/// The colors of a wallpaper, in the format used by pywal.
+pub(crate) struct Palette {
+    pub(crate) wallpaper: String,
+    pub(crate) special: BTreeMap<String, String>,
+    pub(crate) colors: BTreeMap<String, String>,
+    /// The most common color of the image.
+    #[serde(skip)]
+    pub(crate) dominant: Rgb<u8>,
+}
+
+impl Palette {
+    /// Extracts the palette of an image saved as `wallpaper`.
+    pub(crate) fn from_image(img: &DynamicImage, wallpaper: &Path) -> Self {
+        let pixels = img
+            .resize(ANALYSIS_SIZE, ANALYSIS_SIZE, FilterType::Triangle)
+            .to_rgb8()
+            .pixels()
+            .map(|pixel| pixel.0.map(f32::from))
+            .collect::<Vec<_>>();
+
+        let mut clusters = k_means(&pixels, COLOR_COUNT);
+        let dominant = clusters
+            .iter()
+            .max_by_key(|(_, count)| *count)
+            .map_or(Rgb([0, 0, 0]), |(color, _)| *color);
+
+        // Sort the colors from the darkest to the lightest, like pywal
+        clusters.sort_by(|a, b| luminance(a.0).total_cmp(&luminance(b.0)));
+        let mut colors = clusters
+            .into_iter()
+            .map(|(color, _)| color)
+            .collect::<Vec<_>>();
+        // Repeat the last color if there are not enough different colors in the image
+        while colors.len() < COLOR_COUNT {
+            colors.push(colors.last().copied().unwrap_or(Rgb([0, 0, 0])));
+        }
+        // The colors 8 to 15 are lighter versions of the colors 0 to 7
+        let lighter = colors
+            .iter()
+            .map(|color| lighten(*color, 0.25))
+            .collect::<Vec<_>>();
+        colors.extend(lighter);
+
+        let background = to_hex(colors[0]);
+        let foreground = to_hex(colors[COLOR_COUNT - 1]);
+        debug!("Palette: {:?}", colors);
+
+        Self {
+            wallpaper: wallpaper.to_string_lossy().to_string(),
+            special: BTreeMap::from([
+                ("background".to_string(), background),
+                ("foreground".to_string(), foreground.clone()),
+                ("cursor".to_string(), foreground),
+            ]),
+            colors: colors
+                .into_iter()
+                .enumerate()
+                .map(|(i, color)| (format!("color{i}"), to_hex(color)))
+                .collect(),
+            dominant,
+        }
+    }
+
+    /// Writes the palette in `palette.json`, fills the templates set in the config
+    /// and runs the reload command.
+    ///
+    /// # Errors
+    /// Fails if the palette file can't be written.
+    pub(crate) fn store(&self, config: &Config) -> Result<(), Box<dyn Error>> {
+        let palette_path = Paths::palette_file()?;
+        debug!("Storing palette to {:?}", palette_path);
+        serde_json::to_writer_pretty(fs::File::create(palette_path)?, self)?;
+
+        for template in &config.palette_templates {
+            if let Err(err) = self.fill_template(&template.template, &template.output) {
+                error!("Couldn't fill the template {:?}: {err}", template.template);
+            }
+        }
+
+        if !config.palette_reload_command.is_empty() {
+            info!("Running {:?}", config.palette_reload_command);
+            if let Err(err) = run_shell_command(&config.palette_reload_command, &[]) {
+                error!("Couldn't run the reload command: {err}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces `{color0}`...`{color15}`, `{background}`, `{foreground}`, `{cursor}` and `{wallpaper}`
+    /// in the template file and writes the result to the output file.
+    /// `{color0.strip}` gives the color without the `#`.
+    ///
+    /// # Errors
+    /// Fails if the template can't be read or if the output can't be written.
+    fn fill_template(&self, template: &str, output: &str) -> Result<(), Box<dyn Error>> {
+        let mut contents = fs::read_to_string(Paths::expand_user(template))?;
+        for (name, color) in self.special.iter().chain(&self.colors) {
+            contents = contents
+                .replace(&format!("{{{name}.strip}}"), color.trim_start_matches('#'))
+                .replace(&format!("{{{name}}}"), color);
+        }
+        contents = contents.replace("{wallpaper}", &self.wallpaper);
+
+        let output = Paths::expand_user(output);
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        debug!("Writing template to {:?}", output);
+        fs::write(output, contents)?;
+        Ok(())
+    }
+}
+
+/// Groups the pixels in `count` clusters and returns the average color of each cluster
+/// with its number of pixels.
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+fn k_means(pixels: &[[f32; 3]], count: usize) -> Vec<(Rgb<u8>, usize)> {
+    if pixels.is_empty() {
+        return Vec::new();
+    }
+
+    // Start with colors spread over the luminance range so the result is deterministic
+    let mut sorted = pixels.to_vec();
+    sorted.sort_by(|a, b| luminance_f32(*a).total_cmp(&luminance_f32(*b)));
+    let mut centers = (0..count)
+        .map(|i| sorted[(i * 2 + 1) * sorted.len() / (count * 2)])
+        .collect::<Vec<_>>();
+
+    let mut assignments = vec![0; pixels.len()];
+    for _ in 0..ITERATIONS {
+        // Assign each pixel to the nearest center
+        for (pixel, assignment) in pixels.iter().zip(assignments.iter_mut()) {
+            *assignment = centers
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| distance(pixel, a).total_cmp(&distance(pixel, b)))
+                .map_or(0, |(i, _)| i);
+        }
+
+        // Move each center to the average of its pixels
+        let mut sums = vec![[0.0_f32; 3]; count];
+        let mut counts = vec![0_usize; count];
+        for (pixel, &assignment) in pixels.iter().zip(&assignments) {
+            for (sum, value) in sums[assignment].iter_mut().zip(pixel) {
+                *sum += value;
+            }
+            counts[assignment] += 1;
+        }
+        for ((center, sum), &cluster_count) in centers.iter_mut().zip(&sums).zip(&counts) {
+            if cluster_count > 0 {
+                *center = sum.map(|value| value / cluster_count as f32);
+            }
+        }
+    }
+
+    let mut counts = vec![0_usize; count];
+    for &assignment in &assignments {
+        counts[assignment] += 1;
+    }
+    centers
+        .into_iter()
+        .zip(counts)
+        .filter(|(_, cluster_count)| *cluster_count > 0)
+        .map(|(center, cluster_count)| {
+            (
+                Rgb(center.map(|value| value.round().clamp(0.0, 255.0) as u8)),
+                cluster_count,
+            )
+        })
+        .collect()
+}
+
+/// Returns the squared distance between two colors.
+fn distance(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+/// Returns the perceived luminance of a color, between 0 and 255.
+fn luminance_f32(color: [f32; 3]) -> f32 {
+    0.299 * color[0] + 0.587 * color[1] + 0.114 * color[2]
+}
+
+/// Returns the perceived luminance of a color, between 0 and 255.
+pub(crate) fn luminance(color: Rgb<u8>) -> f32 {
+    luminance_f32(color.0.map(f32::from))
+}
+
+/// Mixes a color with white.
+#[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub(crate) fn lighten(color: Rgb<u8>, amount: f32) -> Rgb<u8> {
+    Rgb(color
+        .0
+        .map(|value| (f32::from(value) + (255.0 - f32::from(value)) * amount).round() as u8))
+}
+
+/// Returns a dark and less saturated shade of a color, for the large areas like the panels.
+#[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub(crate) fn mute(color: Rgb<u8>) -> Rgb<u8> {
+    let gray = luminance(color);
+    Rgb(color
+        .0
+        .map(|value| ((f32::from(value) + gray) / 2.0 * 0.4).round() as u8))
+}
+
+/// Returns a color as `#rrggbb`.
+pub(crate) fn to_hex(color: Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+/// Runs a command with the shell of the operating system and the given environment variables.
+///
+/// # Errors
+/// Fails if the command can't be started or if it fails.
+pub(crate) fn run_shell_command(
+    command: &str,
+    variables: &[(String, String)],
+) -> Result<(), Box<dyn Error>> {
+    #[cfg(target_os = "windows")]
+    let status = Command::new("cmd")
+        .args(["/C", command])
+        .envs(variables.iter().cloned())
+        .status()?;
+    #[cfg(not(target_os = "windows"))]
+    let status = Command::new("sh")
+        .args(["-c", command])
+        .envs(variables.iter().cloned())
+        .status()?;
+
+    if !status.success() {
+        return Err(format!("The command {command:?} failed with {status}").into());
+    }
+    Ok(())
+}
@@ -1,21 +1,25 @@
 //! Utility functions to get files and folders accessed by the program.
+//!
+//! On Linux, the files are separated according to the XDG Base Directory specification
+//! (the config, the data, the state such as the logs, and the caches such as the downloaded
+//! pictures), so that the backups can skip the caches. The other platforms use a single directory.
+use log::{error, info};
 use std::error::Error;
 use std::fs;
+use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::OnceLock;
 
 /// A macro to create a function that returns a file path and creates its parent directory if it doesn't exist.
 macro_rules! file {
-    ($name:ident, $path:expr) => {
-        pub(crate) fn $name() -> &'static Path {
+    ($name:ident, $kind:ident, $path:expr) => {
+        pub(crate) fn $name() -> Result<&'static Path, Box<dyn Error>> {
             static $name: OnceLock<&'static Path> = OnceLock::new();
-            $name.get_or_init(|| {
-                let ret = Self::base_dir().join($path);
-                Self::create_file_parent_if_needed(&ret).expect(concat!(
-                    "Could not create the parent directory for ",
-                    stringify!($name)
-                ));
-                Box::leak(ret.into_boxed_path())
+            get_or_try_init(&$name, || {
+                let ret = Self::kind_dir(Kind::$kind)?.join($path);
+                Self::create_file_parent_if_needed(&ret)?;
+                Ok(ret)
             })
         }
     };
@@ -23,49 +27,267 @@ macro_rules! file {
 
 /// A macro to define a function that returns a directory path and creates it if it doesn't exist.
 macro_rules! dir {
-    ($name:ident, $path:expr) => {
-        pub(crate) fn $name() -> &'static Path {
+    ($name:ident, $kind:ident, $path:expr) => {
+        pub(crate) fn $name() -> Result<&'static Path, Box<dyn Error>> {
             static $name: OnceLock<&'static Path> = OnceLock::new();
-            $name.get_or_init(|| {
-                let ret = Self::base_dir().join($path);
-                Self::create_dir_if_needed(&ret).expect(concat!(
-                    "Could not create the directory for ",
-                    stringify!($name)
-                ));
-                Box::leak(ret.into_boxed_path())
+            get_or_try_init(&$name, || {
+                let ret = Self::kind_dir(Kind::$kind)?.join($path);
+                Self::create_dir_if_needed(&ret)?;
+                Ok(ret)
             })
         }
     };
 }
 
+/// The profile chosen with `--profile`.
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Whether the system-wide config is used (`--system`).
+static SYSTEM: AtomicBool = AtomicBool::new(false);
+
+/// The kinds of files, stored in different directories on Linux.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// The config files.
+    Config,
+    /// The data that can't be recreated (the database with the favorites and the history).
+    Data,
+    /// The state that is kept between the runs but isn't worth a backup (e.g. the logs).
+    State,
+    /// The files that can be deleted (e.g. the downloaded pictures).
+    Cache,
+}
+
+/// The files and directories that were stored in the local data directory before the files
+/// were separated, with their new kind. They are moved when their directory is first used.
+const MOVED_ENTRIES: [(&str, Kind); 8] = [
+    ("logs", Kind::State),
+    ("last_change.txt", Kind::State),
+    ("last_run.json", Kind::State),
+    ("palette.json", Kind::State),
+    ("paused_until.txt", Kind::State),
+    ("pictures", Kind::Cache),
+    ("tmp", Kind::Cache),
+    ("location.json", Kind::Cache),
+];
+
+/// Returns the directory of the platform for a kind of files.
+#[cfg(target_os = "linux")]
+fn get_platform_dir(kind: Kind) -> Option<PathBuf> {
+    match kind {
+        Kind::Config => dirs::config_dir(),
+        Kind::Data => dirs::data_local_dir(),
+        Kind::State => dirs::state_dir(),
+        Kind::Cache => dirs::cache_dir(),
+    }
+}
+
+/// Returns the directory of the platform for a kind of files.
+#[cfg(not(target_os = "linux"))]
+fn get_platform_dir(_kind: Kind) -> Option<PathBuf> {
+    dirs::data_local_dir()
+}
+
+/// Returns the directory of the system-wide config, managed by the administrators.
+#[cfg(target_os = "linux")]
+fn get_system_config_dir() -> PathBuf {
+    PathBuf::from("/etc/wallpaper-changer-rs")
+}
+
+/// Returns the directory of the system-wide config, managed by the administrators.
+#[cfg(target_os = "windows")]
+fn get_system_config_dir() -> PathBuf {
+    use std::env;
+
+    env::var_os("ProgramData")
+        .map_or_else(|| PathBuf::from(r"C:\ProgramData"), PathBuf::from)
+        .join("wallpaper-changer-rs")
+}
+
+/// Returns the directory of the system-wide config, managed by the administrators.
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn get_system_config_dir() -> PathBuf {
+    PathBuf::from("/Library/Application Support/wallpaper-changer-rs")
+}
+
+/// Returns the path stored in a cell, or makes it and stores it if it can be made.
+/// The path is made again the next time if it fails.
+///
+/// # Errors
+/// Fails if the path can't be made.
+fn get_or_try_init(
+    cell: &OnceLock<&'static Path>,
+    make: impl FnOnce() -> Result<PathBuf, Box<dyn Error>>,
+) -> Result<&'static Path, Box<dyn Error>> {
+    if let Some(path) = cell.get() {
+        return Ok(path);
+    }
+    let path = make()?;
+    Ok(cell.get_or_init(|| Box::leak(path.into_boxed_path())))
+}
+
+/// Moves a file or a directory to its new location if it hasn't been moved yet.
+fn move_entry(old_path: &Path, new_path: &Path) {
+    if old_path == new_path || !old_path.exists() || new_path.exists() {
+        return;
+    }
+    info!("Moving {old_path:?} to {new_path:?}");
+    let result = Paths::create_file_parent_if_needed(new_path)
+        .and_then(|()| fs::rename(old_path, new_path).map_err(Into::into));
+    if let Err(err) = result {
+        error!("Couldn't move {old_path:?} to {new_path:?}: {err}");
+    }
+}
+
 pub(crate) struct Paths;
 
 #[expect(non_upper_case_globals)]
 impl Paths {
-    /// Returns the local data directory.
+    /// Sets the profile, which has its own config file and data directory.
+    ///
+    /// It must be called before any path is used.
+    ///
+    /// # Errors
+    /// Fails if the name isn't made of letters, digits, `-` and `_` or if a profile was already set.
+    pub(crate) fn set_profile(name: &str) -> Result<(), Box<dyn Error>> {
+        if name.is_empty()
+            || !name.chars().all(|character| {
+                character.is_ascii_alphanumeric() || matches!(character, '-' | '_')
+            })
+        {
+            return Err(format!("Invalid profile name {name:?}").into());
+        }
+        PROFILE
+            .set(name.to_string())
+            .map_err(|_| "The profile was already set")?;
+        Ok(())
+    }
+
+    /// Returns the current profile, if one was set.
+    pub(crate) fn profile() -> Option<&'static str> {
+        PROFILE.get().map(String::as_str)
+    }
+
+    /// Uses the system-wide config file, shared by all the users, instead of the config
+    /// of the user. The data, the state and the caches stay in the directories of the user.
+    ///
+    /// It must be called before any path is used.
+    pub(crate) fn set_system() {
+        SYSTEM.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the system-wide config is used.
+    pub(crate) fn is_system() -> bool {
+        SYSTEM.load(Ordering::Relaxed)
+    }
+
+    /// Returns the directory of the program for a kind of files, shared by all the profiles.
+    ///
+    /// # Errors
+    /// Fails if the directories of the user are unknown.
+    fn root_dir(kind: Kind) -> Result<PathBuf, Box<dyn Error>> {
+        let dir = get_platform_dir(kind).or_else(dirs::data_local_dir).ok_or(
+            "Couldn't find the local data directory, check that the HOME environment variable \
+             (LOCALAPPDATA on Windows) is set",
+        )?;
+        Ok(dir.join("wallpaper-changer-rs"))
+    }
+
+    /// Returns the directory of the current profile in a directory of the program.
+    fn profile_dir(root_dir: &Path) -> PathBuf {
+        match Self::profile() {
+            Some(profile) => root_dir.join("profiles").join(profile),
+            None => root_dir.to_path_buf(),
+        }
+    }
+
+    /// Returns the directory where all the files were stored before they were separated.
+    ///
+    /// # Errors
+    /// Fails if the directories of the user are unknown.
+    pub(crate) fn legacy_base_dir() -> Result<PathBuf, Box<dyn Error>> {
+        Ok(Self::profile_dir(&Self::root_dir(Kind::Data)?))
+    }
+
+    /// Returns the directory of the current profile for a kind of files,
+    /// after moving its files from the local data directory.
+    ///
+    /// The value is cached across multiple runs.
+    ///
+    /// # Errors
+    /// Fails if the directories of the user are unknown.
+    fn kind_dir(kind: Kind) -> Result<&'static Path, Box<dyn Error>> {
+        static KIND_DIRS: [OnceLock<&'static Path>; 4] = [const { OnceLock::new() }; 4];
+        get_or_try_init(&KIND_DIRS[kind as usize], || {
+            let dir = Self::profile_dir(&Self::root_dir(kind)?);
+            let legacy_dir = Self::legacy_base_dir()?;
+            for (name, _) in MOVED_ENTRIES
+                .iter()
+                .filter(|(_, entry_kind)| *entry_kind == kind)
+            {
+                move_entry(&legacy_dir.join(name), &dir.join(name));
+            }
+            Ok(dir)
+        })
+    }
+
+    /// Returns the local data directory of the current profile.
+    ///
+    /// # Errors
+    /// Fails if the directories of the user are unknown.
+    pub(crate) fn base_dir() -> Result<&'static Path, Box<dyn Error>> {
+        Self::kind_dir(Kind::Data)
+    }
+
+    /// Returns the path of the config file: `config.toml`, or `config.<profile>.toml` for a profile,
+    /// in the system-wide config directory in system mode (which is never created by the program).
     ///
     /// The value is cached across multiple runs.
-    pub(crate) fn base_dir() -> &'static Path {
-        static BASE_DIR: OnceLock<&'static Path> = OnceLock::new();
-        BASE_DIR.get_or_init(|| {
-            Box::leak(
-                dirs::data_local_dir()
-                    .expect("Could not find the local data directory")
-                    .join("wallpaper-changer-rs")
-                    .into_boxed_path(),
-            )
+    ///
+    /// # Errors
+    /// Fails if the directories of the user are unknown or if the config directory can't be created.
+    pub(crate) fn config_file() -> Result<&'static Path, Box<dyn Error>> {
+        static CONFIG_FILE: OnceLock<&'static Path> = OnceLock::new();
+        get_or_try_init(&CONFIG_FILE, || {
+            let name = Self::profile().map_or_else(
+                || "config.toml".to_string(),
+                |profile| format!("config.{profile}.toml"),
+            );
+            if Self::is_system() {
+                return Ok(get_system_config_dir().join(name));
+            }
+            let ret = Self::root_dir(Kind::Config)?.join(&name);
+            move_entry(&Self::root_dir(Kind::Data)?.join(&name), &ret);
+            Self::create_file_parent_if_needed(&ret)?;
+            Ok(ret)
         })
     }
 
-    /// Create a directory if it doesn't exist. Returns the directory path.
+    /// Returns the path of the policy file, `policy.toml` in the system-wide config directory:
+    /// its options are set by the administrators and override the config of all the users.
+    pub(crate) fn policy_file() -> PathBuf {
+        get_system_config_dir().join("policy.toml")
+    }
+
+    /// Create a directory if it doesn't exist.
     ///
     /// # Errors
-    /// Fails if the directory can't be created.
+    /// Fails with the path of the directory and a hint to fix the problem if it can't be created.
     fn create_dir_if_needed(dir: &Path) -> Result<(), Box<dyn Error>> {
-        if !dir.exists() {
-            fs::create_dir_all(dir)?;
+        if dir.exists() {
+            return Ok(());
         }
-        Ok(())
+        fs::create_dir_all(dir).map_err(|err| {
+            let hint = match err.kind() {
+                ErrorKind::PermissionDenied => {
+                    "check that you are allowed to write in it and in its parent directories"
+                }
+                ErrorKind::ReadOnlyFilesystem => "the disk is mounted as read-only",
+                ErrorKind::StorageFull => "the disk is full",
+                _ => "check that its parent directories exist and aren't files",
+            };
+            format!("Couldn't create the directory {dir:?}: {err} ({hint})").into()
+        })
     }
 
     /// Create the parent directory of a file if it doesn't exist.
@@ -79,19 +301,29 @@ impl Paths {
         Ok(())
     }
 
-    dir!(logs_dir, "logs");
-    dir!(downloaded_pictures_dir, "pictures");
-    dir!(path_cache_dir, "path_cache");
-    dir!(temp_dir, "tmp");
+    dir!(logs_dir, State, "logs");
+    dir!(downloaded_pictures_dir, Cache, "pictures");
+    dir!(gallery_thumbnails_dir, Cache, "thumbnails");
+    dir!(temp_dir, Cache, "tmp");
 
-    file!(config_file, "config.toml");
-    file!(image_data_path, "image_data.json");
-    file!(crontab_temp_file, "tmp/crontab");
+    file!(current_wallpaper_file, Data, "current_wallpaper.txt");
+    file!(database_file, Data, "wallpaper_changer.db");
+    file!(gallery_file, Cache, "gallery.html");
+    file!(last_change_file, State, "last_change.txt");
+    file!(last_run_file, State, "last_run.json");
+    file!(local_terms_cache_file, Cache, "local_terms.json");
+    file!(location_cache_file, Cache, "location.json");
+    file!(next_wallpaper_file, State, "next_wallpaper.json");
+    file!(palette_file, State, "palette.json");
+    file!(pause_file, State, "paused_until.txt");
+    file!(pin_file, State, "pinned_until.txt");
+    file!(crontab_temp_file, Cache, "tmp/crontab");
 
-    /// Returns the path where the pictures list for the given directory is stored.
-    pub(crate) fn get_path_cache_file_path(name: &Path) -> PathBuf {
-        Self::path_cache_dir()
-            .join(name.to_string_lossy().replace(['\\', '/'], "_"))
-            .clone()
+    /// Expands a leading `~` in a path to the home directory.
+    pub(crate) fn expand_user(path: &str) -> PathBuf {
+        match (path.strip_prefix('~'), dirs::home_dir()) {
+            (Some(rest), Some(home)) => home.join(rest.trim_start_matches(['/', '\\'])),
+            _ => PathBuf::from(path),
+        }
     }
 }
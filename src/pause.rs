@@ -0,0 +1,160 @@
+//! Pauses of the wallpaper changes, stored in a file so that the scheduled runs see them.
+use chrono::{DateTime, Local, TimeDelta};
+use log::debug;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::i18n::tr;
+use crate::paths::Paths;
+
+/// Parses a duration in minutes (`30m`), hours (`2h`) or days (`1d`).
+///
+/// # Errors
+/// Fails if the duration is malformed.
+fn parse_duration(duration: &str) -> Result<TimeDelta, Box<dyn Error>> {
+    let delta = [
+        ('m', TimeDelta::try_minutes as fn(i64) -> Option<TimeDelta>),
+        ('h', TimeDelta::try_hours),
+        ('d', TimeDelta::try_days),
+    ]
+    .into_iter()
+    .find_map(|(unit, to_delta)| {
+        let value = duration.strip_suffix(unit)?.parse::<i64>().ok()?;
+        to_delta(value).filter(|_| value > 0)
+    });
+    delta.ok_or_else(|| {
+        format!(
+            "The duration must be a number of minutes, hours or days (e.g. 30m, 2h, 1d); \
+             found {duration:?}"
+        )
+        .into()
+    })
+}
+
+/// A state that lasts until a command ends it or until a given time (a pause or a pin),
+/// stored in a file of the state directory so that the scheduled runs see it.
+pub(crate) struct TimedState {
+    /// The name of the state in the logs.
+    pub(crate) name: &'static str,
+    /// Returns the file where the end of the state is stored.
+    pub(crate) file: fn() -> Result<&'static Path, Box<dyn Error>>,
+    /// The content of the file when the state lasts until it is ended by a command.
+    pub(crate) until_ended: &'static str,
+}
+
+impl TimedState {
+    /// Starts the state until it is ended by a command, or for the duration given
+    /// with `--for` in the arguments (e.g. `--for 2h`).
+    /// Returns the end of the state, or `None` if it lasts until it is ended.
+    ///
+    /// # Errors
+    /// Fails with the `usage` if the arguments are invalid, or if the file can't be written.
+    pub(crate) fn start(
+        &self,
+        args: &[String],
+        usage: &str,
+    ) -> Result<Option<DateTime<Local>>, Box<dyn Error>> {
+        let (content, end) = match args {
+            [] => (self.until_ended.to_string(), None),
+            [option, duration] if option == "--for" => {
+                let end = Local::now() + parse_duration(duration)?;
+                (end.to_rfc3339(), Some(end))
+            }
+            _ => return Err(usage.into()),
+        };
+        fs::write((self.file)()?, content)?;
+        Ok(end)
+    }
+
+    /// Ends the state.
+    ///
+    /// # Errors
+    /// Fails if the file can't be removed.
+    pub(crate) fn end(&self) -> Result<(), Box<dyn Error>> {
+        let file = (self.file)()?;
+        if file.exists() {
+            fs::remove_file(file)?;
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the state is active. An expired state is removed.
+    pub(crate) fn is_active(&self) -> bool {
+        let Ok(file) = (self.file)() else {
+            return false;
+        };
+        let Ok(content) = fs::read_to_string(file) else {
+            return false;
+        };
+        let content = content.trim();
+        if content == self.until_ended {
+            return true;
+        }
+        match DateTime::parse_from_rfc3339(content) {
+            Ok(end) if end > Local::now() => true,
+            result => {
+                if let Err(err) = result {
+                    debug!("Invalid end of the {}, removing it: {err}", self.name);
+                }
+                // The state has expired
+                let _ = fs::remove_file(file);
+                false
+            }
+        }
+    }
+}
+
+/// The pause of the wallpaper changes.
+const PAUSE: TimedState = TimedState {
+    name: "pause",
+    file: Paths::pause_file,
+    until_ended: "until resume",
+};
+
+/// Pauses the wallpaper changes until the `resume` command, or for the duration given
+/// with `--for` in the arguments (e.g. `--for 2h`).
+///
+/// # Errors
+/// Fails if the arguments are invalid or if the pause file can't be written.
+pub(crate) fn pause(args: &[String]) -> Result<(), Box<dyn Error>> {
+    match PAUSE.start(args, "Usage: pause [--for DURATION]")? {
+        Some(end) => println!("{}", tr!("pause-until", end = end.format("%Y-%m-%d %H:%M"))),
+        None => println!("{}", tr!("pause-until-resume")),
+    }
+    Ok(())
+}
+
+/// Resumes the wallpaper changes.
+///
+/// # Errors
+/// Fails if the pause file can't be removed.
+pub(crate) fn resume() -> Result<(), Box<dyn Error>> {
+    PAUSE.end()?;
+    println!("{}", tr!("pause-resumed"));
+    Ok(())
+}
+
+/// Returns `true` if the wallpaper changes are paused. An expired pause is removed.
+pub(crate) fn is_paused() -> bool {
+    PAUSE.is_active()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("30m").unwrap(), TimeDelta::minutes(30));
+        assert_eq!(parse_duration("2h").unwrap(), TimeDelta::hours(2));
+        assert_eq!(parse_duration("1d").unwrap(), TimeDelta::days(1));
+    }
+
+    #[test]
+    fn invalid_durations() {
+        for duration in ["", "30", "h", "0m", "-2h", "2w", "1.5h"] {
+            assert!(parse_duration(duration).is_err(), "{duration:?}");
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! Pins of the current wallpaper, stored in a file so that the scheduled runs keep it
+//! until it is unpinned or the pin expires.
+use std::error::Error;
+
+use crate::i18n::tr;
+use crate::paths::Paths;
+use crate::pause::TimedState;
+
+/// The pin of the current wallpaper.
+const PIN: TimedState = TimedState {
+    name: "pin",
+    file: Paths::pin_file,
+    until_ended: "until unpin",
+};
+
+/// Pins the current wallpaper until the `unpin` command, or for the duration given
+/// with `--for` in the arguments (e.g. `--for 2h`).
+///
+/// # Errors
+/// Fails if the arguments are invalid or if the pin file can't be written.
+pub(crate) fn pin(args: &[String]) -> Result<(), Box<dyn Error>> {
+    match PIN.start(args, "Usage: pin [--for DURATION]")? {
+        Some(end) => println!("{}", tr!("pin-until", end = end.format("%Y-%m-%d %H:%M"))),
+        None => println!("{}", tr!("pin-until-unpin")),
+    }
+    Ok(())
+}
+
+/// Unpins the wallpaper.
+///
+/// # Errors
+/// Fails if the pin file can't be removed.
+pub(crate) fn unpin() -> Result<(), Box<dyn Error>> {
+    PIN.end()?;
+    println!("{}", tr!("pin-removed"));
+    Ok(())
+}
+
+/// Returns `true` if the wallpaper is pinned. An expired pin is removed.
+pub(crate) fn is_pinned() -> bool {
+    PIN.is_active()
+}
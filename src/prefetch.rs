@@ -0,0 +1,128 @@
+//! The next wallpaper, chosen and composed at the end of a run so that the next run
+//! only has to set it as the background. It is stored in `next_wallpaper.json` with the settings
+//! it was composed with, and it is discarded if they change (e.g. the config or the resolution).
+use log::{debug, info};
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::image_list::{self, ImageData};
+use crate::image_structs::{Image, LocalImage, OnlineImage};
+use crate::paths::Paths;
+use crate::screen_size::{get_monitors, Monitor};
+use crate::{compose_background, has_live_widgets, theme};
+
+/// The image chosen for the next run.
+#[derive(Deserialize, Serialize)]
+enum NextImage {
+    Local(PathBuf),
+    Online(OnlineImage),
+}
+
+/// The next wallpaper.
+#[derive(Deserialize, Serialize)]
+struct NextWallpaper {
+    image: NextImage,
+    /// The composed background, which is reused by the next run.
+    background: PathBuf,
+    /// The hash of the settings of the composition, see [`get_settings_key`].
+    settings: u64,
+}
+
+/// Returns a hash of the config, the monitors and the theme, which change the composition.
+fn get_settings_key(config: &Config, monitors: &[Monitor], dark_mode: bool) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    dark_mode.hash(&mut hasher);
+    // The monitors and the config contain floats, which can't be hashed directly
+    format!("{monitors:?}").hash(&mut hasher);
+    format!("{config:?}").hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Reads the next wallpaper, or returns `None` if there is none.
+fn read() -> Option<NextWallpaper> {
+    let content = fs::read_to_string(Paths::next_wallpaper_file().ok()?).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(next) => Some(next),
+        Err(err) => {
+            debug!("Invalid next wallpaper: {err}");
+            None
+        }
+    }
+}
+
+/// Returns the background composed for the next run, which isn't the current wallpaper yet.
+pub(crate) fn get_background() -> Option<PathBuf> {
+    read().map(|next| next.background)
+}
+
+/// Chooses the next image and composes it for the current monitors.
+///
+/// Nothing is prepared for a slideshow or with widgets that change over time,
+/// because their backgrounds can't be reused.
+///
+/// # Errors
+/// Fails if no image can be chosen, if it can't be composed or if it can't be stored.
+pub(crate) fn prepare(
+    config: &Config,
+    image_data: &mut ImageData,
+    rng: &mut StdRng,
+) -> Result<(), Box<dyn Error>> {
+    if config.slideshow.is_some() || has_live_widgets(config) {
+        debug!("Not preparing the next wallpaper, it couldn't be reused");
+        return Ok(());
+    }
+    let monitors = get_monitors();
+    let dark_mode = theme::is_dark_mode();
+
+    let image = image_list::select_random_image(config, image_data, rng)?;
+    let path = image.get_path();
+    info!("Preparing the next wallpaper from {path:?}");
+    let (background, _) = compose_background(config, image.as_ref(), &monitors)?;
+
+    // The online images are stored with their description and their author
+    let image = image_data
+        .urls
+        .iter()
+        .find(|online| online.get_path() == path)
+        .map_or(NextImage::Local(path), |online| {
+            NextImage::Online(online.clone())
+        });
+    let next = NextWallpaper {
+        image,
+        background,
+        settings: get_settings_key(config, &monitors, dark_mode),
+    };
+    fs::write(
+        Paths::next_wallpaper_file()?,
+        serde_json::to_string_pretty(&next)?,
+    )?;
+    Ok(())
+}
+
+/// Returns the image prepared for this run, or `None` if there is none or if the settings
+/// have changed since it was composed. It is returned only once.
+pub(crate) fn take(config: &Config) -> Option<Box<dyn Image>> {
+    let next = read()?;
+    if let Ok(path) = Paths::next_wallpaper_file() {
+        let _ = fs::remove_file(path);
+    }
+    if next.settings != get_settings_key(config, &get_monitors(), theme::is_dark_mode()) {
+        info!("The settings have changed, discarding the prepared wallpaper");
+        return None;
+    }
+    let image: Box<dyn Image> = match next.image {
+        NextImage::Local(path) => Box::new(LocalImage::with_config(path, config)),
+        NextImage::Online(image) => Box::new(image),
+    };
+    if !image.get_path().exists() || !next.background.exists() {
+        info!("The prepared wallpaper was deleted, choosing another image");
+        return None;
+    }
+    info!("Using the prepared wallpaper {:?}", image.get_path());
+    Some(image)
+}
@@ -0,0 +1,218 @@
+//! Information about the origin of a wallpaper, stored in the generated image file
+//! (in PNG text chunks or in JPEG EXIF fields).
+use chrono::Local;
+use exif::experimental::Writer;
+use exif::{Field, In, Reader, Tag, Value};
+use log::debug;
+use std::error::Error;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::image_structs::Image;
+
+/// The signature at the start of all PNG files.
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// The signature at the start of all JPEG files.
+const JPEG_SIGNATURE: &[u8] = b"\xff\xd8";
+
+/// The PNG keywords and the matching EXIF tags used to store the provenance.
+const FIELDS: [(&str, Tag); 4] = [
+    ("Source", Tag::ImageDescription),
+    ("Author", Tag::Artist),
+    ("Creation Time", Tag::DateTime),
+    ("Software", Tag::Software),
+];
+
+/// Returns the provenance of an image made from the given source, as `(PNG keyword, value)` pairs.
+fn get_fields(source: &str, author: Option<String>) -> Vec<(&'static str, String)> {
+    let now = Local::now();
+    [
+        source.to_string(),
+        author.unwrap_or_default(),
+        now.format("%Y-%m-%d %H:%M:%S").to_string(),
+        format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+    ]
+    .into_iter()
+    .zip(FIELDS)
+    .filter(|(value, _)| !value.is_empty())
+    .map(|(value, (keyword, _))| (keyword, value))
+    .collect()
+}
+
+/// Writes the source, the author and the generation date of the image
+/// in the PNG or JPEG file of the wallpaper.
+///
+/// # Errors
+/// Fails if the file can't be read or written.
+pub(crate) fn write(path: &Path, image: &dyn Image) -> Result<(), Box<dyn Error>> {
+    write_source(path, &image.get_source(), image.get_author())
+}
+
+/// Writes the given source and author and the generation date in a PNG or JPEG file
+/// (e.g. a frame extracted from a video).
+///
+/// # Errors
+/// Fails if the file can't be read or written.
+pub(crate) fn write_source(
+    path: &Path,
+    source: &str,
+    author: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    let fields = get_fields(source, author);
+    let data = fs::read(path)?;
+    let data = if data.starts_with(PNG_SIGNATURE) {
+        insert_png_text(&data, &fields)
+    } else if data.starts_with(JPEG_SIGNATURE) {
+        insert_jpeg_exif(&data, &fields)?
+    } else {
+        debug!("Can't write the provenance in {path:?}, only PNG and JPEG are supported");
+        return Ok(());
+    };
+    fs::write(path, data)?;
+    Ok(())
+}
+
+/// Reads the provenance stored in a wallpaper, as `(keyword, value)` pairs.
+///
+/// # Errors
+/// Fails if the file can't be read or if its EXIF metadata is malformed.
+pub(crate) fn read(path: &Path) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let data = fs::read(path)?;
+    if data.starts_with(PNG_SIGNATURE) {
+        return Ok(read_png_text(&data));
+    }
+
+    let exif = Reader::new().read_from_container(&mut Cursor::new(data))?;
+    Ok(FIELDS
+        .iter()
+        .filter_map(|(keyword, tag)| {
+            let field = exif.get_field(*tag, In::PRIMARY)?;
+            let Value::Ascii(values) = &field.value else {
+                return None;
+            };
+            let value = String::from_utf8_lossy(values.first()?).to_string();
+            Some(((*keyword).to_string(), value))
+        })
+        .collect())
+}
+
+/// Returns a copy of a PNG file with `iTXt` chunks (UTF-8 text) inserted after its header.
+fn insert_png_text(data: &[u8], fields: &[(&str, String)]) -> Vec<u8> {
+    // The IHDR chunk (length, type, 13 bytes of data and CRC) comes right after the signature
+    let header_end = PNG_SIGNATURE.len() + 4 + 4 + 13 + 4;
+    let mut output = data[..header_end].to_vec();
+    for (keyword, value) in fields {
+        // Keyword, compression flag and method, empty language tag and translated keyword
+        let mut chunk_data = keyword.as_bytes().to_vec();
+        chunk_data.extend_from_slice(&[0, 0, 0, 0, 0]);
+        chunk_data.extend_from_slice(value.as_bytes());
+
+        #[expect(clippy::cast_possible_truncation)]
+        output.extend_from_slice(&(chunk_data.len() as u32).to_be_bytes());
+        let type_and_data = [b"iTXt".as_slice(), &chunk_data].concat();
+        output.extend_from_slice(&type_and_data);
+        output.extend_from_slice(&crc32(&type_and_data).to_be_bytes());
+    }
+    output.extend_from_slice(&data[header_end..]);
+    output
+}
+
+/// Returns the `tEXt` and uncompressed `iTXt` chunks of a PNG file as `(keyword, value)` pairs.
+fn read_png_text(data: &[u8]) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    let mut position = PNG_SIGNATURE.len();
+    while position + 8 <= data.len() {
+        let length = u32::from_be_bytes([
+            data[position],
+            data[position + 1],
+            data[position + 2],
+            data[position + 3],
+        ]) as usize;
+        let chunk_type = &data[position + 4..position + 8];
+        let Some(chunk_data) = data.get(position + 8..position + 8 + length) else {
+            break;
+        };
+        let text = match chunk_type {
+            b"tEXt" => chunk_data
+                .iter()
+                .position(|byte| *byte == 0)
+                .map(|end| (&chunk_data[..end], &chunk_data[end + 1..])),
+            b"iTXt" => chunk_data
+                .iter()
+                .position(|byte| *byte == 0)
+                // Only uncompressed text without language tag and translated keyword
+                .filter(|end| chunk_data.get(end + 1..end + 5) == Some([0, 0, 0, 0].as_slice()))
+                .map(|end| (&chunk_data[..end], &chunk_data[end + 5..])),
+            _ => None,
+        };
+        if let Some((keyword, value)) = text {
+            fields.push((
+                String::from_utf8_lossy(keyword).to_string(),
+                String::from_utf8_lossy(value).to_string(),
+            ));
+        }
+        // Length, type, data and CRC
+        position += 4 + 4 + length + 4;
+    }
+    fields
+}
+
+/// Returns a copy of a JPEG file with an EXIF segment inserted after its signature.
+///
+/// # Errors
+/// Fails if the EXIF data can't be encoded.
+fn insert_jpeg_exif(data: &[u8], fields: &[(&str, String)]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let exif_fields: Vec<Field> = fields
+        .iter()
+        .filter_map(|(keyword, value)| {
+            let (_, tag) = FIELDS.iter().find(|(name, _)| name == keyword)?;
+            // EXIF dates use colons
+            let value = if *tag == Tag::DateTime {
+                value.replace('-', ":")
+            } else {
+                value.clone()
+            };
+            Some(Field {
+                tag: *tag,
+                ifd_num: In::PRIMARY,
+                value: Value::Ascii(vec![value.into_bytes()]),
+            })
+        })
+        .collect();
+
+    let mut writer = Writer::new();
+    for field in &exif_fields {
+        writer.push_field(field);
+    }
+    let mut tiff = Cursor::new(Vec::new());
+    writer.write(&mut tiff, false)?;
+    let tiff = tiff.into_inner();
+
+    // APP1 marker, length (including itself), EXIF header and TIFF data
+    let segment_length = u16::try_from(2 + 6 + tiff.len())?;
+    let mut output = JPEG_SIGNATURE.to_vec();
+    output.extend_from_slice(b"\xff\xe1");
+    output.extend_from_slice(&segment_length.to_be_bytes());
+    output.extend_from_slice(b"Exif\0\0");
+    output.extend_from_slice(&tiff);
+    output.extend_from_slice(&data[JPEG_SIGNATURE.len()..]);
+    Ok(output)
+}
+
+/// Computes the CRC-32 checksum used in PNG chunks.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFF_u32;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
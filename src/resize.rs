@@ -0,0 +1,56 @@
+//! Fast image resizing that uses the SIMD instructions of the processor.
+use fast_image_resize::{FilterType, ResizeAlg, ResizeOptions, Resizer};
+use image::imageops;
+use image::DynamicImage;
+use log::debug;
+
+/// Resizes an image to fill the given size, cropping its center if needed.
+pub(crate) fn resize_to_fill(img: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+    resize(img, width, height, None)
+}
+
+/// Crops an image to the rectangle `(x, y, width, height)` and resizes it to the given size.
+pub(crate) fn resize_cropped(
+    img: &DynamicImage,
+    crop: (u32, u32, u32, u32),
+    width: u32,
+    height: u32,
+) -> DynamicImage {
+    resize(img, width, height, Some(crop))
+}
+
+/// Resizes an image with a Lanczos3 filter, after cropping it to the given rectangle
+/// or to its center with the aspect ratio of the size.
+///
+/// If the pixel type isn't supported by `fast_image_resize`, the slower `image` functions are used.
+fn resize(
+    img: &DynamicImage,
+    width: u32,
+    height: u32,
+    crop: Option<(u32, u32, u32, u32)>,
+) -> DynamicImage {
+    let options = ResizeOptions::new().resize_alg(ResizeAlg::Convolution(FilterType::Lanczos3));
+    let options = match crop {
+        Some((x, y, crop_width, crop_height)) => options.crop(
+            f64::from(x),
+            f64::from(y),
+            f64::from(crop_width),
+            f64::from(crop_height),
+        ),
+        None => options.fit_into_destination(Some((0.5, 0.5))),
+    };
+
+    let mut resized = DynamicImage::new(width, height, img.color());
+    match Resizer::new().resize(img, &mut resized, &options) {
+        Ok(()) => resized,
+        Err(err) => {
+            debug!("Couldn't resize the image quickly ({err}), using the slow path");
+            match crop {
+                Some((x, y, crop_width, crop_height)) => img
+                    .crop_imm(x, y, crop_width, crop_height)
+                    .resize_exact(width, height, imageops::FilterType::Lanczos3),
+                None => img.resize_to_fill(width, height, imageops::FilterType::Lanczos3),
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Utility functions to get the monitors on Linux.
+use log::debug;
+use std::env;
+use std::error::Error;
+use std::process::Command;
+
+use super::Monitor;
+
+/// Returns the monitors reported by `xrandr` as they are, without querying the scaling factor.
+///
+/// # Errors
+/// Fails if `xrandr` can't be called.
+pub(crate) fn get_monitor_geometry() -> Result<Vec<Monitor>, Box<dyn Error>> {
+    let output = Command::new("xrandr")
+        .arg("--listmonitors")
+        .output()
+        .map_err(|err| format!("Could not list monitors using xrandr: {err}"))?;
+
+    // The first line is "Monitors: N"
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .skip(1)
+        .filter_map(parse_monitor_line)
+        .collect())
+}
+
+/// Returns all the monitors reported by `xrandr`, in physical pixels.
+///
+/// # Errors
+/// Fails if `xrandr` can't be called.
+pub(crate) fn get_monitors() -> Result<Vec<Monitor>, Box<dyn Error>> {
+    let monitors = get_monitor_geometry()?;
+
+    let scale = get_scale_factor().unwrap_or(1.0);
+    debug!("Scale factor: {}", scale);
+
+    // On Wayland, XWayland reports the logical size of the monitors
+    let logical = env::var_os("WAYLAND_DISPLAY").is_some();
+    debug!("Logical monitor sizes: {}", logical);
+
+    Ok(monitors
+        .into_iter()
+        .map(|monitor| {
+            let monitor = Monitor { scale, ..monitor };
+            if logical {
+                monitor.to_physical()
+            } else {
+                monitor
+            }
+        })
+        .collect())
+}
+
+/// Parses a line like ` 0: +*DP-1 2560/597x1440/336+0+0  DP-1` into a [`Monitor`].
+fn parse_monitor_line(line: &str) -> Option<Monitor> {
+    let mut parts = line.split_whitespace();
+    let name = parts.nth(1)?;
+    let geometry = parts.next()?;
+
+    let (size, position) = geometry.split_once('+')?;
+    let (x, y) = position.split_once('+')?;
+    let (width, height) = size.split_once('x')?;
+
+    Some(Monitor {
+        x: x.parse().ok()?,
+        y: y.parse().ok()?,
+        width: width.split('/').next()?.parse().ok()?,
+        height: height.split('/').next()?.parse().ok()?,
+        primary: name.contains('*'),
+        scale: 1.0,
+    })
+}
+
+/// Returns the scaling factor of the desktop, or `None` if it can't be determined.
+///
+/// It is read from `GDK_SCALE` or from the Cinnamon / GNOME settings.
+fn get_scale_factor() -> Option<f32> {
+    if let Some(scale) = env::var("GDK_SCALE")
+        .ok()
+        .and_then(|scale| scale.parse().ok())
+    {
+        return Some(scale);
+    }
+
+    for schema in [
+        "org.cinnamon.desktop.interface",
+        "org.gnome.desktop.interface",
+    ] {
+        let Ok(output) = Command::new("gsettings")
+            .args(["get", schema, "scaling-factor"])
+            .output()
+        else {
+            continue;
+        };
+        // The output looks like "uint32 2", 0 means automatic
+        let scale = String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .last()
+            .and_then(|scale| scale.parse::<u16>().ok())
+            .unwrap_or_default();
+        if scale > 0 {
+            return Some(f32::from(scale));
+        }
+    }
+
+    None
+}
@@ -0,0 +1,168 @@
+//! Utility functions to get the screen size.
+use log::debug;
+use screen_size::get_primary_screen_size;
+
+use crate::config::Config;
+
+#[cfg(target_os = "linux")]
+mod linux;
+
+#[cfg(target_os = "windows")]
+mod windows;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// A monitor connected to the computer, in virtual desktop coordinates (physical pixels).
+pub(crate) struct Monitor {
+    pub(crate) x: i32,
+    pub(crate) y: i32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) primary: bool,
+    /// The scaling factor of the monitor (2.0 on a 200% scaled display).
+    pub(crate) scale: f32,
+}
+
+impl Monitor {
+    /// Converts a monitor whose geometry is in logical pixels to physical pixels.
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    pub(crate) fn to_physical(self) -> Self {
+        Self {
+            x: (self.x as f32 * self.scale).round() as i32,
+            y: (self.y as f32 * self.scale).round() as i32,
+            width: (self.width as f32 * self.scale).round() as u32,
+            height: (self.height as f32 * self.scale).round() as u32,
+            ..self
+        }
+    }
+}
+
+/// Returns the primary screen size as reported by the `screen_size` crate,
+/// or 1920x1080 if it can't be determined.
+fn get_fallback_screen_size() -> (u32, u32) {
+    let tmp = get_primary_screen_size().unwrap_or((1920, 1080));
+    #[expect(clippy::cast_possible_truncation)]
+    (tmp.0 as u32, tmp.1 as u32)
+}
+
+/// Returns all the monitors connected to the computer.
+///
+/// If they can't be determined, it returns a single monitor with the primary screen size.
+pub(crate) fn get_monitors() -> Vec<Monitor> {
+    #[cfg(target_os = "linux")]
+    let monitors = linux::get_monitors();
+    #[cfg(target_os = "windows")]
+    let monitors = windows::get_monitors();
+
+    match monitors {
+        Ok(monitors) if !monitors.is_empty() => {
+            debug!("Found monitors: {:?}", monitors);
+            monitors
+        }
+        Ok(_) => {
+            debug!("No monitors found, using the primary screen size");
+            vec![fallback_monitor()]
+        }
+        Err(err) => {
+            debug!("Couldn't get the monitors ({err}), using the primary screen size");
+            vec![fallback_monitor()]
+        }
+    }
+}
+
+/// Returns the monitors connected to the computer without their scaling factor,
+/// which is cheaper than [`get_monitors`] and enough to notice a display configuration change.
+///
+/// It returns an empty list if they can't be determined.
+pub(crate) fn get_monitor_geometry() -> Vec<Monitor> {
+    // The scaling factor is cheap to get on Windows and changes the physical sizes anyway
+    #[cfg(target_os = "linux")]
+    let monitors = linux::get_monitor_geometry();
+    #[cfg(target_os = "windows")]
+    let monitors = windows::get_monitors();
+
+    monitors.unwrap_or_default()
+}
+
+/// Returns a monitor at the origin that has the fallback screen size.
+fn fallback_monitor() -> Monitor {
+    let (width, height) = get_fallback_screen_size();
+    Monitor {
+        x: 0,
+        y: 0,
+        width,
+        height,
+        primary: true,
+        scale: 1.0,
+    }
+}
+
+/// Returns the size of the largest monitor, so that an image
+/// of this size looks sharp on every monitor.
+pub(crate) fn get_largest_monitor_size(monitors: &[Monitor]) -> (u32, u32) {
+    monitors
+        .iter()
+        .map(|monitor| (monitor.width, monitor.height))
+        .max_by_key(|(width, height)| u64::from(*width) * u64::from(*height))
+        .unwrap_or_else(get_fallback_screen_size)
+}
+
+/// Returns the scaling factor of the primary monitor.
+pub(crate) fn get_primary_scale(monitors: &[Monitor]) -> f32 {
+    monitors
+        .iter()
+        .find(|monitor| monitor.primary)
+        .or_else(|| monitors.first())
+        .map_or(1.0, |monitor| monitor.scale)
+}
+
+/// Scales a size in logical pixels (e.g. a font size) to physical pixels.
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+pub(crate) fn scale_size(size: u32, scale: f32) -> u32 {
+    (size as f32 * scale).round() as u32
+}
+
+/// Returns the bounding box `(x, y, width, height)` of all the given monitors.
+pub(crate) fn get_bounding_box(monitors: &[Monitor]) -> (i32, i32, u32, u32) {
+    let left = monitors.iter().map(|monitor| monitor.x).min().unwrap_or(0);
+    let top = monitors.iter().map(|monitor| monitor.y).min().unwrap_or(0);
+    #[expect(clippy::cast_possible_wrap)]
+    let right = monitors
+        .iter()
+        .map(|monitor| monitor.x + monitor.width as i32)
+        .max()
+        .unwrap_or(0);
+    #[expect(clippy::cast_possible_wrap)]
+    let bottom = monitors
+        .iter()
+        .map(|monitor| monitor.y + monitor.height as i32)
+        .max()
+        .unwrap_or(0);
+    #[expect(clippy::cast_sign_loss)]
+    (left, top, (right - left) as u32, (bottom - top) as u32)
+}
+
+/// Returns the size of the wallpaper that must be generated for the given monitors.
+///
+/// This is the bounding box of all the monitors when spanning is enabled,
+/// and the size of the largest monitor otherwise.
+pub(crate) fn get_wallpaper_size_for(config: &Config, monitors: &[Monitor]) -> (u32, u32) {
+    if config.span_monitors && monitors.len() > 1 {
+        let (_, _, width, height) = get_bounding_box(monitors);
+        (width, height)
+    } else {
+        get_largest_monitor_size(monitors)
+    }
+}
+
+/// Returns the size of the wallpaper that must be generated for the current monitors.
+pub(crate) fn get_wallpaper_size(config: &Config) -> (u32, u32) {
+    get_wallpaper_size_for(config, &get_monitors())
+}
@@ -0,0 +1,117 @@
+//! Utility functions to get the monitors on Windows.
+use std::error::Error;
+use std::ffi::c_void;
+use std::io;
+use std::mem::size_of;
+use std::ptr;
+use std::sync::Once;
+
+use super::Monitor;
+
+#[repr(C)]
+#[derive(Default)]
+struct Rect {
+    left: i32,
+    top: i32,
+    right: i32,
+    bottom: i32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct MonitorInfo {
+    cb_size: u32,
+    rc_monitor: Rect,
+    rc_work: Rect,
+    dw_flags: u32,
+}
+
+type MonitorEnumProc = unsafe extern "system" fn(*mut c_void, *mut c_void, *mut Rect, isize) -> i32;
+
+extern "system" {
+    fn EnumDisplayMonitors(
+        hdc: *mut c_void,
+        lprcClip: *const Rect,
+        lpfnEnum: MonitorEnumProc,
+        dwData: isize,
+    ) -> i32;
+    fn GetMonitorInfoW(hMonitor: *mut c_void, lpmi: *mut MonitorInfo) -> i32;
+    fn SetProcessDpiAwarenessContext(value: isize) -> i32;
+}
+
+#[link(name = "shcore")]
+extern "system" {
+    fn GetDpiForMonitor(hmonitor: *mut c_void, dpiType: u32, dpiX: *mut u32, dpiY: *mut u32)
+        -> i32;
+}
+
+const MONITORINFOF_PRIMARY: u32 = 0x01;
+const DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2: isize = -4;
+const MDT_EFFECTIVE_DPI: u32 = 0;
+const DEFAULT_DPI: f32 = 96.0;
+
+/// Adds the monitor to the `Vec<Monitor>` pointed by `data`.
+unsafe extern "system" fn add_monitor(
+    monitor: *mut c_void,
+    _hdc: *mut c_void,
+    _rect: *mut Rect,
+    data: isize,
+) -> i32 {
+    let monitors = &mut *(data as *mut Vec<Monitor>);
+    let mut info = MonitorInfo {
+        cb_size: size_of::<MonitorInfo>() as u32,
+        ..Default::default()
+    };
+    if GetMonitorInfoW(monitor, &mut info) != 0 {
+        let mut dpi_x = 0;
+        let mut dpi_y = 0;
+        let scale = if GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) == 0 {
+            dpi_x as f32 / DEFAULT_DPI
+        } else {
+            1.0
+        };
+        monitors.push(Monitor {
+            x: info.rc_monitor.left,
+            y: info.rc_monitor.top,
+            width: (info.rc_monitor.right - info.rc_monitor.left) as u32,
+            height: (info.rc_monitor.bottom - info.rc_monitor.top) as u32,
+            primary: info.dw_flags & MONITORINFOF_PRIMARY != 0,
+            scale,
+        });
+    }
+    // Continue the enumeration
+    1
+}
+
+/// Returns all the monitors reported by `EnumDisplayMonitors`, in physical pixels.
+///
+/// # Errors
+/// Fails if the monitors can't be enumerated.
+pub(crate) fn get_monitors() -> Result<Vec<Monitor>, Box<dyn Error>> {
+    // Without DPI awareness, Windows reports scaled (logical) monitor sizes
+    static DPI_AWARENESS: Once = Once::new();
+    DPI_AWARENESS.call_once(|| unsafe {
+        SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
+    });
+
+    let mut monitors: Vec<Monitor> = Vec::new();
+
+    let result = unsafe {
+        EnumDisplayMonitors(
+            ptr::null_mut(),
+            ptr::null(),
+            add_monitor,
+            &mut monitors as *mut Vec<Monitor> as isize,
+        )
+    };
+
+    if result == 0 {
+        return Err(format!(
+            "Could not enumerate monitors: {}",
+            io::Error::last_os_error()
+        )
+        .into());
+    }
+
+    Ok(monitors)
+}
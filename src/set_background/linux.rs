@@ -3,6 +3,8 @@ use std::error::Error;
 use std::path::Path;
 use std::process::Command;
 
+use super::BackgroundStyle;
+
 extern "C" {
     fn getuid() -> u32;
 }
@@ -11,23 +13,36 @@ extern "C" {
 ///
 /// # Errors
 /// Fails if the call to `gsettings` fails.
-pub(crate) fn set_background(image_path: &Path) -> Result<(), Box<dyn Error>> {
+pub(crate) fn set_background(
+    image_path: &Path,
+    style: BackgroundStyle,
+) -> Result<(), Box<dyn Error>> {
     info!("Setting background...");
     let uid = unsafe { getuid() };
     debug!("uid is {}", uid);
-    Command::new("gsettings")
-        .env(
-            "DBUS_SESSION_BUS_ADDRESS",
-            format!("unix:path=/run/user/{uid}/bus"),
-        )
-        .args([
-            "set",
-            "org.cinnamon.desktop.background",
+
+    let picture_options = match style {
+        BackgroundStyle::Fill => "zoom",
+        BackgroundStyle::Span => "spanned",
+    };
+    debug!("Picture options: {}", picture_options);
+
+    for (key, value) in [
+        ("picture-options", picture_options.to_string()),
+        (
             "picture-uri",
-            &format!("file://{}", image_path.to_string_lossy()),
-        ])
-        .output()
-        .map_err(|err| format!("Could not set background using gsettings: {err}"))?;
+            format!("file://{}", image_path.to_string_lossy()),
+        ),
+    ] {
+        Command::new("gsettings")
+            .env(
+                "DBUS_SESSION_BUS_ADDRESS",
+                format!("unix:path=/run/user/{uid}/bus"),
+            )
+            .args(["set", "org.cinnamon.desktop.background", key, &value])
+            .output()
+            .map_err(|err| format!("Could not set background using gsettings: {err}"))?;
+    }
 
     Ok(())
 }
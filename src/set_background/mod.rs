@@ -9,3 +9,12 @@ mod windows;
 
 #[cfg(target_os = "windows")]
 pub(crate) use windows::set_background;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// How the desktop environment should place the background image.
+pub(crate) enum BackgroundStyle {
+    /// The image fills each monitor.
+    Fill,
+    /// The image spans across all the monitors.
+    Span,
+}
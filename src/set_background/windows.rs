@@ -3,6 +3,9 @@ use std::ffi::OsStr;
 use std::io;
 use std::os::windows::ffi::OsStrExt;
 use std::path::Path;
+use std::process::Command;
+
+use super::BackgroundStyle;
 
 extern "system" {
     fn SystemParametersInfoW(uiAction: u32, uiParam: u32, pvParam: *const u16, fWinIni: u32)
@@ -13,11 +16,52 @@ const SPI_SETDESKWALLPAPER: u32 = 0x0014;
 const SPIF_UPDATEINIFILE: u32 = 0x01;
 const SPIF_SENDCHANGE: u32 = 0x02;
 
+/// Sets a string value in the `HKEY_CURRENT_USER\Control Panel\Desktop` registry key.
+///
+/// # Errors
+/// Fails if `reg` can't be called or if it fails.
+fn set_desktop_registry_value(name: &str, value: &str) -> Result<(), Box<dyn Error>> {
+    let output = Command::new("reg")
+        .args([
+            "add",
+            r"HKCU\Control Panel\Desktop",
+            "/v",
+            name,
+            "/t",
+            "REG_SZ",
+            "/d",
+            value,
+            "/f",
+        ])
+        .output()?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Could not set registry value {name}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
 /// Set the desktop background on Windows.
 ///
 /// # Errors
 /// Fails if the registry key cannot be set or if the system parameters cannot be updated.
-pub(crate) fn set_background(image_path: &Path) -> Result<(), Box<dyn Error>> {
+pub(crate) fn set_background(
+    image_path: &Path,
+    style: BackgroundStyle,
+) -> Result<(), Box<dyn Error>> {
+    // The style is read when the wallpaper is changed, so it must be set before
+    let wallpaper_style = match style {
+        BackgroundStyle::Fill => "10",
+        BackgroundStyle::Span => "22",
+    };
+    set_desktop_registry_value("WallpaperStyle", wallpaper_style)?;
+    set_desktop_registry_value("TileWallpaper", "0")?;
+
     let image_path_wide: Vec<u16> = OsStr::new(image_path)
         .encode_wide()
         .chain(Some(0).into_iter())
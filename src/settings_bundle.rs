@@ -0,0 +1,159 @@
+//! The settings bundle: a single JSON file with the config, the favorites, the blocklist
+//! and the history, to move a setup to another computer. The caches aren't included.
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::database::{Database, HistoryEntry, ImageList, ListEntry};
+use crate::i18n::tr;
+use crate::paths::Paths;
+
+/// The version of the format of the bundles, to migrate the bundles made by older versions.
+const BUNDLE_VERSION: u64 = 1;
+
+/// The settings of the program.
+#[derive(Deserialize, Serialize)]
+struct Bundle {
+    version: u64,
+    exported_at: DateTime<Utc>,
+    /// The home directory of the computer where the bundle was made,
+    /// replaced by the new one in the paths when it is imported.
+    home_dir: Option<PathBuf>,
+    /// The content of the config file, `None` if there was no config file.
+    config: Option<String>,
+    favorites: Vec<ListEntry>,
+    blocklist: Vec<ListEntry>,
+    history: Vec<HistoryEntry>,
+}
+
+/// Exports the settings to a bundle.
+///
+/// # Errors
+/// Fails if the config or the database can't be read or if the bundle can't be written.
+pub(crate) fn export(path: &Path) -> Result<(), Box<dyn Error>> {
+    let database = Database::open()?;
+    let config_path = Paths::config_file()?;
+    let bundle = Bundle {
+        version: BUNDLE_VERSION,
+        exported_at: Utc::now(),
+        home_dir: dirs::home_dir(),
+        config: config_path
+            .exists()
+            .then(|| fs::read_to_string(config_path))
+            .transpose()?,
+        favorites: database.get_list_entries(ImageList::Favorites)?,
+        blocklist: database.get_list_entries(ImageList::Blocklist)?,
+        history: database.get_history(u32::MAX)?,
+    };
+    fs::write(path, serde_json::to_string_pretty(&bundle)?)?;
+    println!(
+        "{}",
+        tr!(
+            "settings-exported",
+            favorites = bundle.favorites.len(),
+            blocked = bundle.blocklist.len(),
+            history = bundle.history.len(),
+            path = path.display()
+        )
+    );
+    Ok(())
+}
+
+/// Converts a bundle made by the current or an older version of the program.
+///
+/// # Errors
+/// Fails if the file isn't a bundle or if it was made by a newer version of the program.
+fn migrate(bundle: Value) -> Result<Bundle, Box<dyn Error>> {
+    let version = bundle
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or("The file isn't a settings bundle")?;
+    if version > BUNDLE_VERSION {
+        return Err(format!(
+            "The settings bundle was made by a newer version of the program (version {version})"
+        )
+        .into());
+    }
+    // The changes of the format are applied here, from the oldest to the newest
+    Ok(serde_json::from_value(bundle)?)
+}
+
+/// Returns the path in the new home directory if it is in the old one.
+fn move_to_home(path: &Path, old_home: &Path, new_home: &Path) -> PathBuf {
+    path.strip_prefix(old_home)
+        .map_or_else(|_| path.to_path_buf(), |rest| new_home.join(rest))
+}
+
+/// Moves the paths of the images from the home directory of the computer where the bundle
+/// was made to the current one (the URLs of the online images are kept).
+fn move_paths(bundle: &mut Bundle) {
+    let (Some(old_home), Some(new_home)) = (bundle.home_dir.take(), dirs::home_dir()) else {
+        return;
+    };
+    if old_home == new_home {
+        return;
+    }
+    info!("Moving the paths from {old_home:?} to {new_home:?}");
+    let move_source = |source: &str| {
+        move_to_home(Path::new(source), &old_home, &new_home)
+            .to_string_lossy()
+            .to_string()
+    };
+    for entry in bundle.favorites.iter_mut().chain(&mut bundle.blocklist) {
+        entry.path = move_to_home(&entry.path, &old_home, &new_home);
+        entry.source = move_source(&entry.source);
+    }
+    for entry in &mut bundle.history {
+        entry.path = move_to_home(&entry.path, &old_home, &new_home);
+        entry.source = move_source(&entry.source);
+    }
+}
+
+/// Imports the settings of a bundle. The images are added to the lists and to the history,
+/// and the config replaces the current one, which is kept in `config.toml.bak`.
+///
+/// # Errors
+/// Fails if the bundle can't be read, if it is invalid or if the settings can't be written.
+pub(crate) fn import(path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut bundle = migrate(serde_json::from_str(&fs::read_to_string(path)?)?)?;
+
+    // Check the config before changing anything
+    if let Some(config) = &bundle.config {
+        toml::from_str::<Config>(config)
+            .map_err(|err| format!("Invalid config in the settings bundle: {err}"))?
+            .validate()?;
+    }
+    move_paths(&mut bundle);
+
+    let lists = [
+        (ImageList::Favorites, bundle.favorites),
+        (ImageList::Blocklist, bundle.blocklist),
+    ];
+    Database::open()?.import_settings(&lists, &bundle.history)?;
+
+    if let Some(config) = bundle.config {
+        let config_path = Paths::config_file()?;
+        if config_path.exists() {
+            let backup_path = config_path.with_extension("toml.bak");
+            fs::copy(config_path, &backup_path)?;
+            println!(
+                "{}",
+                tr!("settings-config-backup", path = backup_path.display())
+            );
+        }
+        fs::write(config_path, config)?;
+    }
+    println!(
+        "{}",
+        tr!(
+            "settings-imported",
+            date = bundle.exported_at.format("%Y-%m-%d %H:%M")
+        )
+    );
+    Ok(())
+}
@@ -0,0 +1,96 @@
+//! Utility functions to check the skip rules on Linux.
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+/// Runs a command and returns its output.
+///
+/// # Errors
+/// Fails if the command can't be called or if it fails.
+fn run_command(program: &str, args: &[&str]) -> Result<String, Box<dyn Error>> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|err| format!("Could not call {program}: {err}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Returns `true` if the active window is fullscreen.
+///
+/// # Errors
+/// Fails if `xprop` can't be called.
+pub(crate) fn is_fullscreen_app_running() -> Result<bool, Box<dyn Error>> {
+    // The output is like "_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007"
+    let active_window = run_command("xprop", &["-root", "_NET_ACTIVE_WINDOW"])?;
+    let window_id = active_window
+        .split_whitespace()
+        .last()
+        .ok_or("No active window")?;
+    if window_id == "0x0" {
+        return Ok(false);
+    }
+    let state = run_command("xprop", &["-id", window_id, "_NET_WM_STATE"])?;
+    Ok(state.contains("_NET_WM_STATE_FULLSCREEN"))
+}
+
+/// Returns `true` if the computer runs on battery.
+///
+/// # Errors
+/// Fails if the power supplies can't be listed.
+pub(crate) fn is_on_battery() -> Result<bool, Box<dyn Error>> {
+    let mut discharging = false;
+    for entry in fs::read_dir("/sys/class/power_supply")? {
+        let path = entry?.path();
+        let read = |name: &str| {
+            fs::read_to_string(path.join(name))
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        };
+        match read("type").as_str() {
+            "Mains" if read("online") == "1" => return Ok(false),
+            "Battery" if read("status") == "Discharging" => discharging = true,
+            _ => {}
+        }
+    }
+    Ok(discharging)
+}
+
+/// Returns `true` if NetworkManager considers the connection as metered.
+///
+/// # Errors
+/// Fails if NetworkManager can't be called.
+pub(crate) fn is_metered_connection() -> Result<bool, Box<dyn Error>> {
+    // The output is like "(<uint32 4>,)"
+    let output = run_command(
+        "gdbus",
+        &[
+            "call",
+            "--system",
+            "--dest",
+            "org.freedesktop.NetworkManager",
+            "--object-path",
+            "/org/freedesktop/NetworkManager",
+            "--method",
+            "org.freedesktop.DBus.Properties.Get",
+            "org.freedesktop.NetworkManager",
+            "Metered",
+        ],
+    )?;
+    let metered: u32 = output
+        .split("uint32")
+        .nth(1)
+        .and_then(|rest| rest.split('>').next())
+        .ok_or("Unexpected output of NetworkManager")?
+        .trim()
+        .parse()?;
+    // 1 is "yes" and 3 is "guess yes"
+    Ok(matches!(metered, 1 | 3))
+}
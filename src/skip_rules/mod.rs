@@ -0,0 +1,91 @@
+//! Rules that prevent the wallpaper from changing in some conditions,
+//! e.g. during a presentation, on battery or at night.
+use chrono::{Local, NaiveTime};
+use log::debug;
+use std::error::Error;
+
+use crate::config::Config;
+
+#[cfg(target_os = "linux")]
+mod linux;
+
+#[cfg(target_os = "linux")]
+use linux as platform;
+
+#[cfg(target_os = "windows")]
+mod windows;
+
+#[cfg(target_os = "windows")]
+use windows as platform;
+
+/// Parses a time range like `22:00-07:00`.
+pub(crate) fn parse_time_range(range: &str) -> Option<(NaiveTime, NaiveTime)> {
+    let (start, end) = range.split_once('-')?;
+    Some((
+        NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?,
+        NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?,
+    ))
+}
+
+/// Returns `true` if the time is in the range, which can go past midnight.
+pub(crate) fn is_between(time: NaiveTime, (start, end): (NaiveTime, NaiveTime)) -> bool {
+    if start <= end {
+        start <= time && time < end
+    } else {
+        time >= start || time < end
+    }
+}
+
+/// Returns `true` if the rule is `fullscreen_app`, `on_battery`, `metered_connection`
+/// or `between HH:MM-HH:MM`.
+pub(crate) fn is_valid_rule(rule: &str) -> bool {
+    matches!(rule, "fullscreen_app" | "on_battery" | "metered_connection")
+        || rule
+            .strip_prefix("between ")
+            .and_then(parse_time_range)
+            .is_some()
+}
+
+/// Returns `true` if the condition of the rule is met.
+///
+/// # Errors
+/// Fails if the rule is unknown or if the condition can't be checked.
+fn is_met(rule: &str) -> Result<bool, Box<dyn Error>> {
+    match rule {
+        "fullscreen_app" => platform::is_fullscreen_app_running(),
+        "on_battery" => platform::is_on_battery(),
+        "metered_connection" => platform::is_metered_connection(),
+        _ => {
+            let range = rule
+                .strip_prefix("between ")
+                .and_then(parse_time_range)
+                .ok_or_else(|| format!("Unknown skip rule {rule:?}"))?;
+            Ok(is_between(Local::now().time(), range))
+        }
+    }
+}
+
+/// Returns the first rule of `skip_when` whose condition is met, if the wallpaper must not be changed.
+///
+/// The rules whose condition can't be checked are ignored.
+pub(crate) fn get_matching_rule(config: &Config) -> Option<&str> {
+    config
+        .skip_when
+        .iter()
+        .map(String::as_str)
+        .find(|rule| match is_met(rule) {
+            Ok(met) => met,
+            Err(err) => {
+                debug!("Couldn't check the skip rule {rule:?}: {err}");
+                false
+            }
+        })
+}
+
+/// Returns `true` if the Internet connection is metered, `false` if it can't be checked.
+pub(crate) fn is_metered_connection() -> bool {
+    platform::is_metered_connection().unwrap_or_else(|err| {
+        debug!("Couldn't check if the connection is metered: {err}");
+        false
+    })
+}
@@ -0,0 +1,86 @@
+//! Utility functions to check the skip rules on Windows.
+use std::error::Error;
+use std::io;
+use std::process::Command;
+
+#[repr(C)]
+#[derive(Default)]
+#[expect(dead_code)]
+/// The power status filled by `GetSystemPowerStatus`.
+struct SystemPowerStatus {
+    ac_line_status: u8,
+    battery_flag: u8,
+    battery_life_percent: u8,
+    system_status_flag: u8,
+    battery_life_time: u32,
+    battery_full_life_time: u32,
+}
+
+extern "system" {
+    fn GetSystemPowerStatus(lpSystemPowerStatus: *mut SystemPowerStatus) -> i32;
+}
+
+#[link(name = "shell32")]
+extern "system" {
+    fn SHQueryUserNotificationState(pquns: *mut i32) -> i32;
+}
+
+/// A fullscreen application is running.
+const QUNS_BUSY: i32 = 2;
+/// A fullscreen Direct3D application is running.
+const QUNS_RUNNING_D3D_FULL_SCREEN: i32 = 3;
+/// The presentation mode is enabled.
+const QUNS_PRESENTATION_MODE: i32 = 4;
+
+/// Returns `true` if a fullscreen application or a presentation is running.
+///
+/// # Errors
+/// Fails if the notification state can't be queried.
+pub(crate) fn is_fullscreen_app_running() -> Result<bool, Box<dyn Error>> {
+    let mut state = 0;
+    let result = unsafe { SHQueryUserNotificationState(&mut state) };
+    if result != 0 {
+        return Err(format!("Could not query the notification state: {result:#x}").into());
+    }
+    Ok(matches!(
+        state,
+        QUNS_BUSY | QUNS_RUNNING_D3D_FULL_SCREEN | QUNS_PRESENTATION_MODE
+    ))
+}
+
+/// Returns `true` if the computer runs on battery.
+///
+/// # Errors
+/// Fails if the power status can't be read.
+pub(crate) fn is_on_battery() -> Result<bool, Box<dyn Error>> {
+    let mut status = SystemPowerStatus::default();
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+        return Err(Box::new(io::Error::last_os_error()));
+    }
+    Ok(status.ac_line_status == 0)
+}
+
+/// Returns `true` if the Internet connection is metered.
+///
+/// # Errors
+/// Fails if PowerShell can't be called.
+pub(crate) fn is_metered_connection() -> Result<bool, Box<dyn Error>> {
+    let output = Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            "[Windows.Networking.Connectivity.NetworkInformation, Windows.Networking.Connectivity, \
+             ContentType=WindowsRuntime]::GetInternetConnectionProfile().GetConnectionCost().NetworkCostType",
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "Could not get the connection cost: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+    // "Unrestricted", "Fixed" or "Variable"
+    let cost_type = String::from_utf8_lossy(&output.stdout);
+    Ok(matches!(cost_type.trim(), "Fixed" | "Variable"))
+}
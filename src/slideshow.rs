@@ -0,0 +1,69 @@
+//! Slideshows in the GNOME background XML format, which cross-fade between the wallpapers.
+use chrono::{Datelike, Local, Timelike};
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::SlideshowConfig;
+
+/// Whether the desktop environment can show a slideshow
+/// (GNOME and Cinnamon read the XML file given as the background).
+pub(crate) const SUPPORTED: bool = cfg!(target_os = "linux");
+
+/// Escapes the characters that have a special meaning in XML.
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Returns the definition of a slideshow that shows each background in turn
+/// and goes back to the first one at the end.
+fn get_slideshow_xml(backgrounds: &[PathBuf], config: &SlideshowConfig) -> String {
+    let now = Local::now();
+    let mut xml = String::from("<background>\n");
+    // The slideshow starts now, with the first background
+    let _ = write!(
+        xml,
+        "  <starttime>\n    <year>{}</year>\n    <month>{}</month>\n    <day>{}</day>\n    \
+         <hour>{}</hour>\n    <minute>{}</minute>\n    <second>{}</second>\n  </starttime>\n",
+        now.year(),
+        now.month(),
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second(),
+    );
+
+    for (index, background) in backgrounds.iter().enumerate() {
+        let file = escape_xml(&background.to_string_lossy());
+        let next = escape_xml(&backgrounds[(index + 1) % backgrounds.len()].to_string_lossy());
+        let _ = write!(
+            xml,
+            "  <static>\n    <duration>{}.0</duration>\n    <file>{file}</file>\n  </static>\n  \
+             <transition type=\"overlay\">\n    <duration>{}.0</duration>\n    \
+             <from>{file}</from>\n    <to>{next}</to>\n  </transition>\n",
+            config.duration, config.transition,
+        );
+    }
+
+    xml.push_str("</background>\n");
+    xml
+}
+
+/// Writes the definition of a slideshow of the backgrounds in the given file.
+///
+/// # Errors
+/// Fails if the file can't be written.
+pub(crate) fn write_slideshow(
+    backgrounds: &[PathBuf],
+    config: &SlideshowConfig,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, get_slideshow_xml(backgrounds, config))?;
+    Ok(())
+}
@@ -0,0 +1,108 @@
+//! Content-aware cropping that keeps the most detailed part of an image.
+use image::imageops::FilterType;
+use image::DynamicImage;
+use image::GenericImageView;
+use imageproc::gradients::sobel_gradients;
+use log::debug;
+
+use crate::resize::resize_cropped;
+
+/// The size of the longest side of the copy used to find the interesting part of the image.
+const ANALYSIS_SIZE: u32 = 256;
+
+/// How much a crop far from the center is penalized (0 = not at all, 1 = fully).
+const CENTER_BIAS: f64 = 0.2;
+
+/// Resizes an image to fill the given size, cropping it around its most detailed part
+/// (the one with the most edges) instead of its center.
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+pub(crate) fn smart_resize_to_fill(img: &DynamicImage, width: u32, height: u32) -> DynamicImage {
+    let (img_width, img_height) = img.dimensions();
+
+    // Size of the crop in the original image, with the aspect ratio of the target size
+    let horizontal =
+        u64::from(img_width) * u64::from(height) > u64::from(width) * u64::from(img_height);
+    let (crop_width, crop_height) = if horizontal {
+        (
+            (u64::from(img_height) * u64::from(width) / u64::from(height)) as u32,
+            img_height,
+        )
+    } else {
+        (
+            img_width,
+            (u64::from(img_width) * u64::from(height) / u64::from(width)) as u32,
+        )
+    };
+    let (crop_width, crop_height) = (crop_width.max(1), crop_height.max(1));
+
+    // Analyze a small grayscale copy of the image
+    let scale = (f64::from(ANALYSIS_SIZE) / f64::from(img_width.max(img_height))).min(1.0);
+    let small_width = ((f64::from(img_width) * scale).round() as u32).max(1);
+    let small_height = ((f64::from(img_height) * scale).round() as u32).max(1);
+    let small = img
+        .resize_exact(small_width, small_height, FilterType::Triangle)
+        .to_luma8();
+    let energy = sobel_gradients(&small);
+
+    // Energy of each column (or row) of the image
+    let profile: Vec<u64> = if horizontal {
+        (0..small_width)
+            .map(|x| {
+                (0..small_height)
+                    .map(|y| u64::from(energy.get_pixel(x, y)[0]))
+                    .sum()
+            })
+            .collect()
+    } else {
+        (0..small_height)
+            .map(|y| {
+                (0..small_width)
+                    .map(|x| u64::from(energy.get_pixel(x, y)[0]))
+                    .sum()
+            })
+            .collect()
+    };
+
+    let crop_length = if horizontal { crop_width } else { crop_height };
+    let window = ((f64::from(crop_length) * scale).round() as usize).clamp(1, profile.len());
+    let best_start = find_best_window(&profile, window);
+
+    // Convert the position back to the original image
+    let img_length = if horizontal { img_width } else { img_height };
+    let offset = ((best_start as f64 / scale).round() as u32).min(img_length - crop_length);
+    let (x, y) = if horizontal { (offset, 0) } else { (0, offset) };
+    debug!("Smart crop of {crop_width}x{crop_height} at ({x}, {y})");
+
+    resize_cropped(img, (x, y, crop_width, crop_height), width, height)
+}
+
+/// Returns the start of the window of the given length that has the most energy,
+/// slightly favoring centered windows.
+#[expect(clippy::cast_precision_loss)]
+fn find_best_window(profile: &[u64], window: usize) -> usize {
+    let last_start = profile.len() - window;
+    if last_start == 0 {
+        return 0;
+    }
+
+    let mut sum: u64 = profile[..window].iter().sum();
+    let mut best_start = 0;
+    let mut best_score = f64::MIN;
+    for start in 0..=last_start {
+        if start > 0 {
+            sum = sum - profile[start - 1] + profile[start + window - 1];
+        }
+        // 0 for a centered window, 1 for a window on the border
+        let distance = (start as f64 - last_start as f64 / 2.0).abs() / (last_start as f64 / 2.0);
+        let score = sum as f64 * (1.0 - CENTER_BIAS * distance);
+        if score > best_score {
+            best_score = score;
+            best_start = start;
+        }
+    }
+    best_start
+}
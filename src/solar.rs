@@ -0,0 +1,81 @@
+//! Utility functions to compute the position of the sun.
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use std::f64::consts::PI;
+
+/// The elevation of the sun (in degrees) under which it is considered night.
+const TWILIGHT_ELEVATION: f64 = -6.0;
+
+/// The elevation of the sun (in degrees) above which it is considered day.
+const DAY_ELEVATION: f64 = 6.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A period of the day, based on the position of the sun.
+pub(crate) enum TimeOfDay {
+    Sunrise,
+    Day,
+    Sunset,
+    Night,
+}
+
+impl TimeOfDay {
+    /// Returns the period of the day at the given date and location (latitude, longitude).
+    pub(crate) fn at(date: DateTime<Utc>, location: (f64, f64)) -> Self {
+        let (elevation, morning) = get_sun_position(date, location);
+        if elevation < TWILIGHT_ELEVATION {
+            Self::Night
+        } else if elevation > DAY_ELEVATION {
+            Self::Day
+        } else if morning {
+            Self::Sunrise
+        } else {
+            Self::Sunset
+        }
+    }
+
+    /// Returns the name of the period, as used in the config.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Sunrise => "sunrise",
+            Self::Day => "day",
+            Self::Sunset => "sunset",
+            Self::Night => "night",
+        }
+    }
+}
+
+/// Returns the elevation of the sun in degrees at the given date and location
+/// (latitude, longitude), and `true` if it is before solar noon.
+///
+/// This uses the NOAA approximation, which is precise to a few minutes.
+fn get_sun_position(date: DateTime<Utc>, (latitude, longitude): (f64, f64)) -> (f64, bool) {
+    let hour = f64::from(date.hour()) + f64::from(date.minute()) / 60.0;
+
+    // Fractional year in radians
+    let gamma = 2.0 * PI / 365.0 * (f64::from(date.ordinal() - 1) + (hour - 12.0) / 24.0);
+
+    // Equation of time (in minutes) and solar declination (in radians)
+    let equation_of_time = 229.18
+        * (0.000_075 + 0.001_868 * gamma.cos()
+            - 0.032_077 * gamma.sin()
+            - 0.014_615 * (2.0 * gamma).cos()
+            - 0.040_849 * (2.0 * gamma).sin());
+    let declination = 0.006_918 - 0.399_912 * gamma.cos() + 0.070_257 * gamma.sin()
+        - 0.006_758 * (2.0 * gamma).cos()
+        + 0.000_907 * (2.0 * gamma).sin()
+        - 0.002_697 * (3.0 * gamma).cos()
+        + 0.001_48 * (3.0 * gamma).sin();
+
+    // True solar time (in minutes) and hour angle (in degrees)
+    let solar_time = hour * 60.0 + equation_of_time + 4.0 * longitude;
+    let hour_angle = solar_time / 4.0 - 180.0;
+
+    let latitude = latitude.to_radians();
+    let cos_zenith = latitude.sin() * declination.sin()
+        + latitude.cos() * declination.cos() * hour_angle.to_radians().cos();
+    let elevation = 90.0 - cos_zenith.clamp(-1.0, 1.0).acos().to_degrees();
+
+    // The hour angle can go beyond ±180° near midnight
+    let morning = hour_angle.rem_euclid(360.0) > 180.0;
+
+    (elevation, morning)
+}
@@ -0,0 +1,63 @@
+//! Utility functions to get information about the computer on Linux.
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+use std::time::Duration;
+
+use super::DiskUsage;
+
+/// Returns the hostname of the computer.
+///
+/// # Errors
+/// Fails if `/proc/sys/kernel/hostname` can't be read.
+pub(crate) fn get_hostname() -> Result<String, Box<dyn Error>> {
+    Ok(fs::read_to_string("/proc/sys/kernel/hostname")?
+        .trim()
+        .to_string())
+}
+
+/// Returns the time since the computer was started.
+///
+/// # Errors
+/// Fails if `/proc/uptime` can't be read or parsed.
+pub(crate) fn get_uptime() -> Result<Duration, Box<dyn Error>> {
+    // The file contains "<uptime> <idle time>" in seconds
+    let contents = fs::read_to_string("/proc/uptime")?;
+    let uptime: f64 = contents
+        .split_whitespace()
+        .next()
+        .ok_or("Empty /proc/uptime")?
+        .parse()?;
+    Ok(Duration::from_secs_f64(uptime))
+}
+
+/// Returns the usage of the root and home partitions.
+///
+/// # Errors
+/// Fails if `df` can't be called.
+pub(crate) fn get_disks() -> Result<Vec<DiskUsage>, Box<dyn Error>> {
+    let home = dirs::home_dir().unwrap_or_default();
+    let output = Command::new("df")
+        .args(["-P", "-k", "/"])
+        .arg(&home)
+        .output()?;
+
+    // Skip the header: "Filesystem 1024-blocks Used Available Capacity Mounted on"
+    let mut disks: Vec<DiskUsage> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines().skip(1) {
+        let parts = line.split_whitespace().collect::<Vec<_>>();
+        let [_, total, used, _, _, mount_point, ..] = parts[..] else {
+            continue;
+        };
+        // The home directory is often on the root partition
+        if disks.iter().any(|disk| disk.name == mount_point) {
+            continue;
+        }
+        disks.push(DiskUsage {
+            name: mount_point.to_string(),
+            used: used.parse::<u64>()? * 1024,
+            total: total.parse::<u64>()? * 1024,
+        });
+    }
+    Ok(disks)
+}
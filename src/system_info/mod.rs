@@ -0,0 +1,100 @@
+//! Utility functions to get information about the computer.
+use log::debug;
+use std::fmt::Write;
+use std::net::{IpAddr, UdpSocket};
+use std::time::Duration;
+
+#[cfg(target_os = "linux")]
+mod linux;
+
+#[cfg(target_os = "linux")]
+use linux::{get_disks, get_hostname, get_uptime};
+
+#[cfg(target_os = "windows")]
+mod windows;
+
+#[cfg(target_os = "windows")]
+use windows::{get_disks, get_hostname, get_uptime};
+
+#[derive(Clone, Debug)]
+/// The usage of a disk.
+pub(crate) struct DiskUsage {
+    pub(crate) name: String,
+    pub(crate) used: u64,
+    pub(crate) total: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+/// Information about the computer. The values that can't be determined are empty.
+pub(crate) struct SystemInfo {
+    pub(crate) hostname: Option<String>,
+    pub(crate) uptime: Option<Duration>,
+    pub(crate) disks: Vec<DiskUsage>,
+    pub(crate) ip_address: Option<IpAddr>,
+}
+
+impl SystemInfo {
+    /// Gets the information about the computer.
+    pub(crate) fn collect() -> Self {
+        let ret = Self {
+            hostname: get_hostname().ok(),
+            uptime: get_uptime().ok(),
+            disks: get_disks().unwrap_or_default(),
+            ip_address: get_ip_address(),
+        };
+        debug!("System info: {:?}", ret);
+        ret
+    }
+
+    /// Returns the information as text, with one value per line.
+    #[expect(clippy::cast_precision_loss)]
+    pub(crate) fn to_text(&self) -> String {
+        const GIGABYTE: f64 = 1024.0 * 1024.0 * 1024.0;
+
+        let mut lines = Vec::new();
+        if let Some(hostname) = &self.hostname {
+            lines.push(hostname.clone());
+        }
+        if let Some(uptime) = self.uptime {
+            lines.push(format!("Uptime: {}", format_duration(uptime)));
+        }
+        for disk in &self.disks {
+            let mut line = format!(
+                "{}: {:.0} / {:.0} GB",
+                disk.name,
+                disk.used as f64 / GIGABYTE,
+                disk.total as f64 / GIGABYTE
+            );
+            if disk.total > 0 {
+                let _ = write!(line, " ({}%)", disk.used * 100 / disk.total);
+            }
+            lines.push(line);
+        }
+        if let Some(ip_address) = self.ip_address {
+            lines.push(format!("IP: {ip_address}"));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Formats a duration as `1d 2h 3m`.
+fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        format!("{days}d {hours}h {minutes}m")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+/// Returns the local IP address used to access the internet.
+///
+/// No packet is sent: connecting a UDP socket only selects the route.
+fn get_ip_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("1.1.1.1:80").ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
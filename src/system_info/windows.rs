@@ -0,0 +1,69 @@
+//! Utility functions to get information about the computer on Windows.
+use std::env;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::io;
+use std::os::windows::ffi::OsStrExt;
+use std::ptr;
+use std::time::Duration;
+
+use super::DiskUsage;
+
+extern "system" {
+    fn GetTickCount64() -> u64;
+    fn GetDiskFreeSpaceExW(
+        lpDirectoryName: *const u16,
+        lpFreeBytesAvailableToCaller: *mut u64,
+        lpTotalNumberOfBytes: *mut u64,
+        lpTotalNumberOfFreeBytes: *mut u64,
+    ) -> i32;
+}
+
+/// Returns the hostname of the computer.
+///
+/// # Errors
+/// Fails if the `COMPUTERNAME` environment variable isn't set.
+pub(crate) fn get_hostname() -> Result<String, Box<dyn Error>> {
+    Ok(env::var("COMPUTERNAME")?)
+}
+
+/// Returns the time since the computer was started.
+///
+/// It returns a [`Result`] to match the other platforms.
+///
+/// # Errors
+/// This function doesn't fail.
+#[expect(clippy::unnecessary_wraps)]
+pub(crate) fn get_uptime() -> Result<Duration, Box<dyn Error>> {
+    Ok(Duration::from_millis(unsafe { GetTickCount64() }))
+}
+
+/// Returns the usage of the system drive.
+///
+/// # Errors
+/// Fails if the free space can't be determined.
+pub(crate) fn get_disks() -> Result<Vec<DiskUsage>, Box<dyn Error>> {
+    let drive = env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string()) + "\\";
+    let drive_wide: Vec<u16> = OsStr::new(&drive)
+        .encode_wide()
+        .chain(Some(0).into_iter())
+        .collect();
+
+    let mut total = 0;
+    let mut free = 0;
+    let result =
+        unsafe { GetDiskFreeSpaceExW(drive_wide.as_ptr(), ptr::null_mut(), &mut total, &mut free) };
+    if result == 0 {
+        return Err(format!(
+            "Could not get the free space of {drive}: {}",
+            io::Error::last_os_error()
+        )
+        .into());
+    }
+
+    Ok(vec![DiskUsage {
+        name: drive,
+        used: total - free,
+        total,
+    }])
+}
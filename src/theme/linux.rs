@@ -0,0 +1,43 @@
+//! Utility functions to get the theme on Linux.
+use std::error::Error;
+use std::process::Command;
+
+/// Returns the value of a `gsettings` key, without the quotes.
+///
+/// # Errors
+/// Fails if `gsettings` can't be called or if the key doesn't exist.
+fn get_setting(schema: &str, key: &str) -> Result<String, Box<dyn Error>> {
+    let output = Command::new("gsettings")
+        .args(["get", schema, key])
+        .output()
+        .map_err(|err| format!("Could not get {schema} {key} using gsettings: {err}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Could not get {schema} {key}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .trim_matches('\'')
+        .to_string())
+}
+
+/// Returns `true` if the desktop uses a dark theme.
+///
+/// # Errors
+/// Fails if neither the color scheme nor the GTK theme can be read.
+pub(crate) fn is_dark_mode() -> Result<bool, Box<dyn Error>> {
+    // GNOME 42+ has an explicit setting
+    if let Ok(color_scheme) = get_setting("org.gnome.desktop.interface", "color-scheme") {
+        if color_scheme == "prefer-dark" {
+            return Ok(true);
+        }
+    }
+
+    // Otherwise, look at the name of the GTK theme (e.g. "Mint-Y-Dark")
+    let theme = get_setting("org.cinnamon.desktop.interface", "gtk-theme")
+        .or_else(|_| get_setting("org.gnome.desktop.interface", "gtk-theme"))?;
+    Ok(theme.to_lowercase().contains("dark"))
+}
@@ -0,0 +1,29 @@
+//! Utility functions to get the theme of the operating system.
+use log::debug;
+
+#[cfg(target_os = "linux")]
+mod linux;
+
+#[cfg(target_os = "windows")]
+mod windows;
+
+/// Returns `true` if the operating system uses a dark theme.
+///
+/// If the theme can't be determined, it returns `false`.
+pub(crate) fn is_dark_mode() -> bool {
+    #[cfg(target_os = "linux")]
+    let ret = linux::is_dark_mode();
+    #[cfg(target_os = "windows")]
+    let ret = windows::is_dark_mode();
+
+    match ret {
+        Ok(dark_mode) => {
+            debug!("Dark mode: {}", dark_mode);
+            dark_mode
+        }
+        Err(err) => {
+            debug!("Couldn't determine the theme: {err}");
+            false
+        }
+    }
+}
@@ -0,0 +1,35 @@
+//! Utility functions to get the theme on Windows.
+use std::error::Error;
+use std::process::Command;
+
+/// Returns `true` if the apps use the dark theme.
+///
+/// # Errors
+/// Fails if `reg` can't be called or if the registry value doesn't exist.
+pub(crate) fn is_dark_mode() -> Result<bool, Box<dyn Error>> {
+    let output = Command::new("reg")
+        .args([
+            "query",
+            r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+            "/v",
+            "AppsUseLightTheme",
+        ])
+        .output()?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Could not read the theme from the registry: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+
+    // The output contains a line like "AppsUseLightTheme    REG_DWORD    0x0"
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let value = stdout
+        .lines()
+        .find(|line| line.contains("AppsUseLightTheme"))
+        .and_then(|line| line.split_whitespace().last())
+        .ok_or("AppsUseLightTheme not found in the registry")?;
+    Ok(value == "0x0")
+}
@@ -0,0 +1,63 @@
+//! The durations of the phases of a wallpaper change (selection, download, decoding...),
+//! summarized at the end of the change to find which one is slow.
+//!
+//! The phases are kept per thread, so that a change isn't mixed up with the downloads
+//! or the changes running at the same time in other threads (e.g. in the daemon).
+use log::{debug, info};
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+thread_local! {
+    /// The phases measured in this thread since the start of the change, with their total durations.
+    static PHASES: RefCell<Vec<(&'static str, Duration)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A phase of a wallpaper change, measured until it is dropped.
+pub(crate) struct Phase {
+    name: &'static str,
+    start: Instant,
+}
+
+impl Drop for Phase {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        debug!("The {} phase took {elapsed:?}", self.name);
+        PHASES.with_borrow_mut(|phases| {
+            match phases.iter_mut().find(|(name, _)| *name == self.name) {
+                Some((_, total)) => *total += elapsed,
+                None => phases.push((self.name, elapsed)),
+            }
+        });
+    }
+}
+
+/// Starts measuring a phase. A phase can contain other phases (e.g. a download during the selection)
+/// and its durations are added up if it happens several times.
+pub(crate) fn phase(name: &'static str) -> Phase {
+    Phase {
+        name,
+        start: Instant::now(),
+    }
+}
+
+/// Forgets the phases of the previous changes in this thread and returns the start of the change.
+pub(crate) fn start() -> Instant {
+    PHASES.with_borrow_mut(Vec::clear);
+    Instant::now()
+}
+
+/// Logs the duration of the change that started at `start` in this thread
+/// and of each of its phases.
+pub(crate) fn log_summary(start: Instant) {
+    let summary = PHASES.with_borrow_mut(|phases| {
+        phases
+            .drain(..)
+            .map(|(name, duration)| format!("{name} {:.2}s", duration.as_secs_f64()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    });
+    info!(
+        "Wallpaper changed in {:.2}s ({summary})",
+        start.elapsed().as_secs_f64()
+    );
+}
@@ -0,0 +1,254 @@
+//! An icon in the system tray with a menu to change or pause the wallpaper
+//! and open the current image or the config file (with the `tray` feature).
+use log::{error, info};
+use std::error::Error;
+#[cfg(target_os = "linux")]
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::mpsc::{self, Sender};
+use tray_item::{IconSource, TrayItem};
+
+use crate::config::Config;
+use crate::image_list::ImageData;
+use crate::paths::Paths;
+use crate::{change_wallpaper, get_last_wallpaper, pause, provenance};
+
+/// An entry of the menu.
+#[derive(Clone, Copy)]
+enum Action {
+    /// Change the wallpaper now.
+    Next,
+    /// Pause the wallpaper changes until they are resumed.
+    Pause,
+    /// Resume the wallpaper changes.
+    Resume,
+    /// Open the image of the current wallpaper.
+    OpenCurrentImage,
+    /// Open the config file.
+    OpenSettings,
+    /// Remove the icon.
+    Quit,
+}
+
+/// Returns the icon of the tray.
+///
+/// # Errors
+/// Fails if the icon can't be loaded.
+#[cfg(target_os = "linux")]
+#[expect(clippy::unnecessary_wraps)]
+fn get_icon() -> Result<IconSource, Box<dyn Error>> {
+    // The name of an icon of the icon theme
+    Ok(IconSource::Resource("preferences-desktop-wallpaper"))
+}
+
+/// Returns the icon of the tray.
+///
+/// # Errors
+/// Fails if the icon can't be loaded.
+#[cfg(target_os = "windows")]
+fn get_icon() -> Result<IconSource, Box<dyn Error>> {
+    /// The identifier of the default application icon.
+    const IDI_APPLICATION: usize = 32512;
+    #[link(name = "user32")]
+    extern "system" {
+        fn LoadIconW(hInstance: isize, lpIconName: usize) -> isize;
+    }
+    let icon = unsafe { LoadIconW(0, IDI_APPLICATION) };
+    if icon == 0 {
+        return Err("Could not load the icon of the tray".into());
+    }
+    Ok(IconSource::RawIcon(icon as _))
+}
+
+/// Opens a file or a URL with the default application.
+///
+/// # Errors
+/// Fails if the opener can't be started.
+fn open(target: &str) -> Result<(), Box<dyn Error>> {
+    #[cfg(target_os = "linux")]
+    Command::new("xdg-open").arg(target).spawn()?;
+    // The empty argument is the title of the window
+    #[cfg(target_os = "windows")]
+    Command::new("cmd")
+        .args(["/C", "start", "", target])
+        .spawn()?;
+    Ok(())
+}
+
+/// Runs an action of the menu.
+///
+/// # Errors
+/// Fails if the action fails.
+fn run_action(action: Action) -> Result<(), Box<dyn Error>> {
+    match action {
+        Action::Next => {
+            let config = Config::load()?;
+            let mut image_data = ImageData::load()?;
+            change_wallpaper(&config, &mut image_data)?;
+        }
+        Action::Pause => pause::pause(&[])?,
+        Action::Resume => pause::resume()?,
+        Action::OpenCurrentImage => {
+            let wallpaper = get_last_wallpaper()?;
+            // The source is the path or the URL of the original image
+            let source = provenance::read(&wallpaper)?
+                .into_iter()
+                .find(|(keyword, _)| keyword == "Source")
+                .map_or_else(
+                    || wallpaper.to_string_lossy().to_string(),
+                    |(_, value)| value,
+                );
+            open(&source)?;
+        }
+        Action::OpenSettings => open(&Paths::config_file()?.to_string_lossy())?,
+        Action::Quit => {}
+    }
+    Ok(())
+}
+
+/// Adds an entry to the menu that sends the action when it is clicked.
+///
+/// # Errors
+/// Fails if the entry can't be added.
+fn add_entry(
+    tray: &mut TrayItem,
+    label: &str,
+    action: Action,
+    sender: &Sender<Action>,
+) -> Result<(), Box<dyn Error>> {
+    let sender = sender.clone();
+    tray.add_menu_item(label, move || {
+        let _ = sender.send(action);
+    })?;
+    Ok(())
+}
+
+/// Shows the icon in the system tray and runs the actions of its menu until "Quit" is clicked.
+///
+/// The actions run in the main thread, with the same code as the command line.
+///
+/// # Errors
+/// Fails if the icon can't be created.
+pub(crate) fn run() -> Result<(), Box<dyn Error>> {
+    let (sender, receiver) = mpsc::channel();
+    let mut tray = TrayItem::new("Wallpaper changer", get_icon()?)?;
+    for (label, action) in [
+        ("Next wallpaper", Action::Next),
+        ("Pause", Action::Pause),
+        ("Resume", Action::Resume),
+        ("Open current image", Action::OpenCurrentImage),
+        ("Settings file", Action::OpenSettings),
+        ("Quit", Action::Quit),
+    ] {
+        add_entry(&mut tray, label, action, &sender)?;
+    }
+    info!("Tray icon shown");
+
+    for action in receiver {
+        if matches!(action, Action::Quit) {
+            break;
+        }
+        if let Err(err) = run_action(action) {
+            error!("Error: {err}");
+        }
+    }
+    Ok(())
+}
+
+/// Returns the arguments that show the tray icon with the current profile.
+fn get_tray_arguments() -> String {
+    match Paths::profile() {
+        Some(profile) => format!("--profile {profile} tray"),
+        None => "tray".to_string(),
+    }
+}
+
+/// Returns the name of the autostart entry of the current profile.
+fn get_autostart_name() -> String {
+    match Paths::profile() {
+        Some(profile) => format!("wallpaper-changer-rs-tray-{profile}"),
+        None => "wallpaper-changer-rs-tray".to_string(),
+    }
+}
+
+/// Shows the tray icon when the user logs in (`enabled`) or stops showing it,
+/// with a desktop entry in the autostart directory.
+///
+/// # Errors
+/// Fails if the desktop entry can't be written or removed.
+#[cfg(target_os = "linux")]
+pub(crate) fn set_autostart(script_path: &Path, enabled: bool) -> Result<(), Box<dyn Error>> {
+    let path = dirs::config_dir()
+        .ok_or("Could not find the config directory")?
+        .join("autostart")
+        .join(format!("{}.desktop", get_autostart_name()));
+    if !enabled {
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+        info!("The tray icon won't be shown at login");
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(
+        &path,
+        format!(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name=Wallpaper changer\n\
+             Exec=\"{}\" {}\n\
+             Icon=preferences-desktop-wallpaper\n\
+             X-GNOME-Autostart-enabled=true\n",
+            script_path.to_string_lossy(),
+            get_tray_arguments()
+        ),
+    )?;
+    info!("The tray icon will be shown at login");
+    Ok(())
+}
+
+/// Shows the tray icon when the user logs in (`enabled`) or stops showing it,
+/// with a value in the `Run` registry key.
+///
+/// # Errors
+/// Fails if `reg` can't be called or if it fails.
+#[cfg(target_os = "windows")]
+pub(crate) fn set_autostart(script_path: &Path, enabled: bool) -> Result<(), Box<dyn Error>> {
+    /// The registry key of the programs run at login.
+    const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+    let name = get_autostart_name();
+    let command = format!(
+        "\"{}\" {}",
+        script_path.to_string_lossy(),
+        get_tray_arguments()
+    );
+    let output = if enabled {
+        Command::new("reg")
+            .args([
+                "add", RUN_KEY, "/v", &name, "/t", "REG_SZ", "/d", &command, "/f",
+            ])
+            .output()?
+    } else {
+        Command::new("reg")
+            .args(["delete", RUN_KEY, "/v", &name, "/f"])
+            .output()?
+    };
+    // Deleting a value that doesn't exist fails
+    if enabled && !output.status.success() {
+        return Err(format!(
+            "Could not change the autostart entry: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+    if enabled {
+        info!("The tray icon will be shown at login");
+    } else {
+        info!("The tray icon won't be shown at login");
+    }
+    Ok(())
+}
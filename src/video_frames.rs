@@ -0,0 +1,179 @@
+//! Random frames of the videos of the pictures folder, extracted with `ffmpeg`.
+//!
+//! The frames are kept in the downloaded pictures folder, with the video and the position
+//! of the frame as their provenance, so that the videos aren't read each time they are chosen.
+use log::{debug, info};
+use rand::seq::IteratorRandom;
+use rand::Rng;
+use std::error::Error;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::paths::Paths;
+use crate::provenance;
+
+/// The number of frames that are extracted from each video.
+/// Once there are that many, one of them is chosen instead of extracting a new one.
+const FRAMES_PER_VIDEO: usize = 5;
+
+/// Returns the folder of the extracted frames.
+///
+/// # Errors
+/// Fails if the folder can't be created.
+fn get_frames_dir() -> Result<PathBuf, Box<dyn Error>> {
+    let frames_dir = Paths::downloaded_pictures_dir()?.join("video_frames");
+    fs::create_dir_all(&frames_dir)?;
+    Ok(frames_dir)
+}
+
+/// Returns the start of the names of the frames of a video (its name and a hash of its path).
+fn get_frame_prefix(video: &Path) -> String {
+    let mut hasher = DefaultHasher::new();
+    video.hash(&mut hasher);
+    format!(
+        "{}-{:016x}-",
+        video.file_stem().unwrap_or_default().to_string_lossy(),
+        hasher.finish()
+    )
+}
+
+/// Returns the frames that were extracted from a video.
+/// The frames that are older than the video are deleted.
+///
+/// # Errors
+/// Fails if the video or the folder of the frames can't be read.
+fn get_cached_frames(video: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let video_modified = fs::metadata(video)?.modified()?;
+    let prefix = get_frame_prefix(video);
+    let mut frames = Vec::new();
+    for entry in fs::read_dir(get_frames_dir()?)? {
+        let entry = entry?;
+        if !entry.file_name().to_string_lossy().starts_with(&prefix) {
+            continue;
+        }
+        if entry.metadata()?.modified()? < video_modified {
+            debug!("Removing the outdated frame {:?}", entry.path());
+            fs::remove_file(entry.path())?;
+        } else {
+            frames.push(entry.path());
+        }
+    }
+    Ok(frames)
+}
+
+/// Returns the duration of a video with `ffprobe`.
+///
+/// # Errors
+/// Fails if `ffprobe` can't be called or if the video can't be read.
+fn get_duration(video: &Path) -> Result<Duration, Box<dyn Error>> {
+    let result = Command::new("ffprobe")
+        .args([
+            "-loglevel",
+            "error",
+            "-show_entries",
+            "format=duration",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+        ])
+        .arg(video)
+        .output()
+        .map_err(|err| format!("Could not read the video using ffprobe: {err}"))?;
+    if !result.status.success() {
+        return Err(format!(
+            "Could not read the video: {}",
+            String::from_utf8_lossy(&result.stderr)
+        )
+        .into());
+    }
+    let seconds: f64 = String::from_utf8_lossy(&result.stdout).trim().parse()?;
+    Ok(Duration::try_from_secs_f64(seconds)?)
+}
+
+/// Formats a position in a video as `HH:MM:SS`.
+fn format_position(position: Duration) -> String {
+    let seconds = position.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Extracts the frame at a random position of a video with `ffmpeg` and returns its path.
+///
+/// # Errors
+/// Fails if `ffmpeg` can't be called or if the frame can't be extracted.
+fn extract_random_frame(video: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    // The start and the end of the videos are often blurry or black
+    let position = get_duration(video)?.mul_f64(rand::rng().random_range(0.05..0.95));
+    let frame = get_frames_dir()?.join(format!(
+        "{}{}.jpg",
+        get_frame_prefix(video),
+        position.as_millis()
+    ));
+    info!(
+        "Extracting the frame at {} of {video:?}",
+        format_position(position)
+    );
+    let result = Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-ss",
+            &format!("{:.3}", position.as_secs_f64()),
+            "-i",
+        ])
+        .arg(video)
+        .args(["-frames:v", "1", "-q:v", "2"])
+        .arg(&frame)
+        .output()
+        .map_err(|err| format!("Could not extract a frame using ffmpeg: {err}"))?;
+    if !result.status.success() || !frame.exists() {
+        return Err(format!(
+            "Could not extract a frame of the video: {}",
+            String::from_utf8_lossy(&result.stderr)
+        )
+        .into());
+    }
+
+    let source = format!(
+        "{} at {}",
+        video.to_string_lossy(),
+        format_position(position)
+    );
+    provenance::write_source(&frame, &source, None)?;
+    Ok(frame)
+}
+
+/// Returns the path of a random frame of a video. A new frame is extracted
+/// until [`FRAMES_PER_VIDEO`] of them are kept, then one of them is chosen.
+///
+/// # Errors
+/// Fails if the video can't be read or if the frame can't be extracted.
+pub(crate) fn get_random_frame(video: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let frames = get_cached_frames(video)?;
+    if frames.len() >= FRAMES_PER_VIDEO {
+        if let Some(frame) = frames.into_iter().choose(&mut rand::rng()) {
+            debug!("Using the extracted frame {frame:?}");
+            return Ok(frame);
+        }
+    }
+    extract_random_frame(video)
+}
+
+/// Returns the path of a frame of a video, extracting one only if there is none yet
+/// (e.g. to get the dimensions of the video).
+///
+/// # Errors
+/// Fails if the video can't be read or if the frame can't be extracted.
+pub(crate) fn get_any_frame(video: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    match get_cached_frames(video)?.into_iter().next() {
+        Some(frame) => Ok(frame),
+        None => extract_random_frame(video),
+    }
+}
@@ -0,0 +1,112 @@
+//! The weights of the local images, so that the best rated photos and some folders
+//! are chosen more often than the others.
+use log::{debug, error};
+use rand::Rng;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::exif_data::ExifData;
+use crate::paths::Paths;
+
+/// Reads the ratings file: a TOML file with the number of stars of the images,
+/// like `"Holidays/IMG_0001.jpg" = 5`, the paths being relative to the pictures folder.
+///
+/// # Errors
+/// Fails if the file can't be read or if it is malformed.
+fn read_ratings(path: &Path, pictures_dir: &Path) -> Result<HashMap<PathBuf, u8>, Box<dyn Error>> {
+    let ratings: HashMap<String, u8> = toml::from_str(&fs::read_to_string(path)?)?;
+    Ok(ratings
+        .into_iter()
+        .map(|(image, rating)| (pictures_dir.join(image), rating))
+        .collect())
+}
+
+/// The weights of the local images of a pictures folder.
+pub(crate) struct Weights {
+    pictures_dir: PathBuf,
+    /// The weights of the folders, relative to the pictures folder.
+    folder_weights: Vec<(PathBuf, f64)>,
+    /// The weights of the images with 1 to 5 stars, empty to ignore the ratings.
+    rating_weights: Vec<f64>,
+    /// The ratings of the ratings file.
+    ratings: HashMap<PathBuf, u8>,
+    /// The largest weight an image can have.
+    max_weight: f64,
+}
+
+impl Weights {
+    /// Returns the weights of the config for the images of a pictures folder.
+    pub(crate) fn new(config: &Config, pictures_dir: &Path) -> Self {
+        let ratings = if config.ratings_file.is_empty() {
+            HashMap::new()
+        } else {
+            read_ratings(&Paths::expand_user(&config.ratings_file), pictures_dir).unwrap_or_else(
+                |err| {
+                    error!("Couldn't read the ratings file: {err}");
+                    HashMap::new()
+                },
+            )
+        };
+        let folder_weights = config
+            .folder_weights
+            .iter()
+            .map(|(folder, weight)| (PathBuf::from(folder), *weight))
+            .collect::<Vec<_>>();
+        // The images that aren't rated or outside the weighted folders have a weight of 1
+        let max_weight = folder_weights
+            .iter()
+            .map(|(_, weight)| *weight)
+            .fold(1.0, f64::max)
+            * config.rating_weights.iter().copied().fold(1.0, f64::max);
+        Self {
+            pictures_dir: pictures_dir.to_path_buf(),
+            folder_weights,
+            rating_weights: config.rating_weights.clone(),
+            ratings,
+            max_weight,
+        }
+    }
+
+    /// Returns the weight of the most specific folder of `folder_weights` that contains the image.
+    fn get_folder_weight(&self, path: &Path) -> f64 {
+        let Ok(relative_path) = path.strip_prefix(&self.pictures_dir) else {
+            return 1.0;
+        };
+        self.folder_weights
+            .iter()
+            .filter(|(folder, _)| relative_path.starts_with(folder))
+            .max_by_key(|(folder, _)| folder.components().count())
+            .map_or(1.0, |(_, weight)| *weight)
+    }
+
+    /// Returns the weight of the rating of the image (from the ratings file or its EXIF metadata).
+    fn get_rating_weight(&self, path: &Path) -> f64 {
+        if self.rating_weights.is_empty() {
+            return 1.0;
+        }
+        let rating = self
+            .ratings
+            .get(path)
+            .copied()
+            .or_else(|| ExifData::read(path).ok()?.rating);
+        // 0 star means that the image isn't rated
+        match rating {
+            Some(stars @ 1..=5) => self.rating_weights[usize::from(stars - 1)],
+            _ => 1.0,
+        }
+    }
+
+    /// Returns `true` if a randomly chosen image is kept, with a probability proportional
+    /// to its weight, so that the images are chosen according to their weights.
+    pub(crate) fn accept(&self, path: &Path, rng: &mut impl Rng) -> bool {
+        if self.folder_weights.is_empty() && self.rating_weights.is_empty() {
+            return true;
+        }
+        let weight = self.get_folder_weight(path) * self.get_rating_weight(path);
+        debug!("Weight of {path:?}: {weight}");
+        rng.random::<f64>() * self.max_weight < weight
+    }
+}
@@ -0,0 +1,98 @@
+//! Widgets drawn on the wallpaper: a clock, the date, the next events of a calendar
+//! and information about the computer.
+use chrono::Local;
+use image::DynamicImage;
+use log::{debug, error};
+
+use crate::calendar::get_next_events;
+use crate::config::Config;
+use crate::date_format::format_date;
+use crate::fonts::FontStack;
+use crate::images::{write_text_on_image, TextStyle};
+use crate::paths::Paths;
+use crate::screen_size::scale_size;
+use crate::system_info::SystemInfo;
+
+/// Draws the enabled widgets on the image.
+pub(crate) fn draw_widgets(
+    img: &mut DynamicImage,
+    config: &Config,
+    fonts: &FontStack,
+    style: &TextStyle,
+    scale: f32,
+) {
+    let now = Local::now();
+
+    if let Some(clock) = &config.clock {
+        debug!("Drawing the clock");
+        let text = now.format(&clock.format).to_string();
+        write_text_on_image(
+            img,
+            fonts,
+            style,
+            &text,
+            scale_size(clock.font_size, scale),
+            clock.position,
+        );
+    }
+
+    if let Some(date) = &config.date {
+        debug!("Drawing the date");
+        let text = if date.format.is_empty() {
+            format_date(now)
+        } else {
+            now.format(&date.format).to_string()
+        };
+        write_text_on_image(
+            img,
+            fonts,
+            style,
+            &text,
+            scale_size(date.font_size, scale),
+            date.position,
+        );
+    }
+
+    if let Some(calendar) = &config.calendar {
+        debug!("Drawing the calendar");
+        let path = Paths::expand_user(&calendar.ics_file);
+        match get_next_events(&path, now, calendar.events) {
+            Ok(events) if !events.is_empty() => {
+                let text = events
+                    .iter()
+                    .map(|event| {
+                        let date = if event.all_day {
+                            event.start.format(&calendar.all_day_format)
+                        } else {
+                            event.start.format(&calendar.format)
+                        };
+                        format!("{date}  {}", event.summary)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                write_text_on_image(
+                    img,
+                    fonts,
+                    style,
+                    &text,
+                    scale_size(calendar.font_size, scale),
+                    calendar.position,
+                );
+            }
+            Ok(_) => debug!("No upcoming events"),
+            Err(err) => error!("Couldn't read the calendar {path:?}: {err}"),
+        }
+    }
+
+    if let Some(system_info) = &config.system_info {
+        debug!("Drawing the system information");
+        write_text_on_image(
+            img,
+            fonts,
+            style,
+            &SystemInfo::collect().to_text(),
+            scale_size(system_info.font_size, scale),
+            system_info.position,
+        );
+    }
+}
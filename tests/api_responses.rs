@@ -0,0 +1,89 @@
+//! The decoding of the photos returned by the API given in `api_url`.
+#![cfg(target_os = "linux")]
+mod common;
+
+use common::{get_pictures_dir, photo, run, setup, MockResponse, MockServer};
+use rusqlite::Connection;
+use serde_json::json;
+
+#[test]
+fn decodes_the_photos_and_skips_the_unexpected_ones() {
+    let server = MockServer::start(|server_url, request_url| {
+        if request_url.starts_with("/photos/random") {
+            MockResponse::json(
+                200,
+                &[],
+                &json!([
+                    photo(server_url, "complete"),
+                    // The optional fields are often null
+                    {
+                        "id": "minimal",
+                        "created_at": null,
+                        "alt_description": null,
+                        "urls": {"raw": format!("{server_url}images/minimal")},
+                        "user": null,
+                    },
+                    // A photo without a URL can't be used
+                    {"id": "broken", "user": {"name": "Nobody"}},
+                ]),
+            )
+        } else if request_url.starts_with("/images/complete") {
+            MockResponse::image(request_url, 100)
+        } else {
+            MockResponse::image(request_url, 200)
+        }
+    });
+    let home = setup(&server);
+
+    run(&["prefetch", "--count", "2"]).expect("The prefetch failed");
+    // The broken photo is skipped instead of asking for more photos
+    let api_requests = server
+        .requests()
+        .into_iter()
+        .filter(|request| request.starts_with("/photos/"))
+        .collect::<Vec<_>>();
+    assert_eq!(api_requests, ["/photos/random?count=2"]);
+
+    let database =
+        Connection::open(home.join(".local/share/wallpaper-changer-rs/wallpaper_changer.db"))
+            .expect("Couldn't open the database");
+    let mut statement = database
+        .prepare(
+            "SELECT id, url, date IS NOT NULL, description, photographer
+             FROM online_images ORDER BY position",
+        )
+        .expect("Couldn't read the online images");
+    let images = statement
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, bool>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })
+        .expect("Couldn't read the online images")
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Couldn't read the online images");
+    assert_eq!(
+        images,
+        [
+            (
+                "complete".to_string(),
+                format!("{}images/complete?ixid=abc&ixlib=rb-4.0.3", server.url),
+                true,
+                "Description of complete".to_string(),
+                "Author of complete".to_string(),
+            ),
+            (
+                "minimal".to_string(),
+                format!("{}images/minimal", server.url),
+                false,
+                String::new(),
+                String::new(),
+            ),
+        ]
+    );
+    assert!(!get_pictures_dir(&home).join("unsplash_broken.jpg").exists());
+}
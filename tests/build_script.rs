@@ -0,0 +1,6 @@
+//! Runs the tests of the build script, which Cargo doesn't run by itself.
+
+// Only the tests of the build script are used
+#[expect(dead_code)]
+#[path = "../build.rs"]
+mod build;
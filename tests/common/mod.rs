@@ -0,0 +1,176 @@
+//! A local server that imitates the Unsplash API and a temporary home directory,
+//! so that the tests of the online images don't use the network or the files of the user.
+use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
+use serde_json::{json, Value};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tiny_http::{Header, Response, Server};
+use url::Url;
+use wallpaper_changer_core::run_command;
+
+/// A response of the mock server.
+pub struct MockResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl MockResponse {
+    /// Returns a JSON response with some headers.
+    pub fn json(status: u16, headers: &[(&str, &str)], body: &Value) -> Self {
+        Self {
+            status,
+            headers: [("Content-Type", "application/json")]
+                .iter()
+                .chain(headers)
+                .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+                .collect(),
+            body: body.to_string().into_bytes(),
+        }
+    }
+
+    /// Returns a JPEG image of the size asked in the `w` and `h` parameters of the URL,
+    /// like the Unsplash CDN does. The `shade` makes the images of different photos different.
+    pub fn image(request_url: &str, shade: u8) -> Self {
+        let url = Url::parse(&format!("http://localhost{request_url}")).expect("Invalid URL");
+        let get_size = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .and_then(|(_, value)| value.parse().ok())
+                .expect("The size of the image isn't in the URL")
+        };
+        let image = RgbImage::from_pixel(get_size("w"), get_size("h"), Rgb([shade; 3]));
+        let mut body = Vec::new();
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut Cursor::new(&mut body), ImageFormat::Jpeg)
+            .expect("Couldn't encode the image");
+        Self {
+            status: 200,
+            headers: vec![("Content-Type".to_string(), "image/jpeg".to_string())],
+            body,
+        }
+    }
+}
+
+/// A mock server running in the background until the end of the test.
+pub struct MockServer {
+    /// The URL of the server, used as the `api_url` of the config.
+    pub url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    /// Starts a server that answers each request with the handler, which is given the URL
+    /// of the server and the request (its path with its parameters).
+    pub fn start(handler: impl Fn(&str, &str) -> MockResponse + Send + 'static) -> Self {
+        let server = Server::http("127.0.0.1:0").expect("Couldn't start the mock server");
+        let address = server
+            .server_addr()
+            .to_ip()
+            .expect("The mock server has no IP address");
+        let url = format!("http://{address}/");
+        let server_url = url.clone();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded_requests = Arc::clone(&requests);
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let request_url = request.url().to_string();
+                recorded_requests
+                    .lock()
+                    .expect("The requests are poisoned")
+                    .push(request_url.clone());
+                let response = handler(&server_url, &request_url);
+                let mut reply =
+                    Response::from_data(response.body).with_status_code(response.status);
+                for (name, value) in response.headers {
+                    reply.add_header(
+                        Header::from_bytes(name.as_bytes(), value.as_bytes())
+                            .expect("Invalid header"),
+                    );
+                }
+                let _ = request.respond(reply);
+            }
+        });
+        Self { url, requests }
+    }
+
+    /// Returns the requests received by the server (their path with their parameters).
+    pub fn requests(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .expect("The requests are poisoned")
+            .clone()
+    }
+}
+
+/// Returns a photo of the Unsplash API whose image is served by the mock server at `/images/{id}`.
+pub fn photo(server_url: &str, id: &str) -> Value {
+    json!({
+        "id": id,
+        "created_at": "2024-05-03T11:00:28-04:00",
+        "alt_description": format!("Description of {id}"),
+        "urls": {
+            "raw": format!("{server_url}images/{id}?ixid=abc&ixlib=rb-4.0.3"),
+            "full": format!("{server_url}images/{id}?q=85"),
+        },
+        "user": {"name": format!("Author of {id}")},
+    })
+}
+
+/// Makes an empty home directory for the program with a config that uses the mock API,
+/// and returns it. It must be called once per test binary, before the program uses any path,
+/// since the paths are computed only once.
+pub fn setup(server: &MockServer) -> PathBuf {
+    let name = env::current_exe()
+        .ok()
+        .and_then(|path| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+        })
+        .unwrap_or_default();
+    let home = env::temp_dir().join(format!("wallpaper-changer-{name}-{}", process::id()));
+    let _ = fs::remove_dir_all(&home);
+    for variable in [
+        "XDG_CONFIG_HOME",
+        "XDG_DATA_HOME",
+        "XDG_STATE_HOME",
+        "XDG_CACHE_HOME",
+        "HTTP_PROXY",
+        "HTTPS_PROXY",
+        "http_proxy",
+        "https_proxy",
+    ] {
+        env::remove_var(variable);
+    }
+    env::set_var("HOME", &home);
+    let config_dir = home.join(".config").join("wallpaper-changer-rs");
+    fs::create_dir_all(&config_dir).expect("Couldn't create the config directory");
+    fs::write(
+        config_dir.join("config.toml"),
+        format!("api_url = {:?}\n", server.url),
+    )
+    .expect("Couldn't write the config");
+    home
+}
+
+/// Returns the directory of the downloaded pictures in a home directory made by [`setup`].
+pub fn get_pictures_dir(home: &Path) -> PathBuf {
+    home.join(".cache")
+        .join("wallpaper-changer-rs")
+        .join("pictures")
+}
+
+/// Runs a command of the program.
+///
+/// # Errors
+/// Fails if the command fails.
+pub fn run(args: &[&str]) -> Result<(), Box<dyn Error>> {
+    let args = args.iter().map(ToString::to_string).collect::<Vec<_>>();
+    run_command(&args)
+}
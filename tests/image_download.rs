@@ -0,0 +1,69 @@
+//! The download of the online images at the size of the wallpaper.
+#![cfg(target_os = "linux")]
+mod common;
+
+use common::{get_pictures_dir, photo, run, setup, MockResponse, MockServer};
+use image::ImageReader;
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
+use url::Url;
+
+#[test]
+fn downloads_the_images_at_the_size_of_the_wallpaper() {
+    let broken_once = AtomicBool::new(false);
+    let server = MockServer::start(move |server_url, request_url| {
+        if request_url.starts_with("/photos/random") {
+            MockResponse::json(
+                200,
+                &[],
+                &json!([photo(server_url, "first"), photo(server_url, "second")]),
+            )
+        } else if request_url.starts_with("/images/second")
+            && !broken_once.swap(true, Ordering::Relaxed)
+        {
+            // An error page instead of the image, which must be downloaded again
+            MockResponse::json(200, &[], &json!({"errors": ["Internal error"]}))
+        } else if request_url.starts_with("/images/first") {
+            MockResponse::image(request_url, 50)
+        } else {
+            MockResponse::image(request_url, 150)
+        }
+    });
+    let home = setup(&server);
+
+    run(&["prefetch", "--count", "2"]).expect("The prefetch failed");
+
+    let image_requests = server
+        .requests()
+        .into_iter()
+        .filter(|request| request.starts_with("/images/"))
+        .collect::<Vec<_>>();
+    assert_eq!(image_requests.len(), 3, "{image_requests:?}");
+    let pictures_dir = get_pictures_dir(&home);
+    for id in ["first", "second"] {
+        let request = image_requests
+            .iter()
+            .rfind(|request| request.starts_with(&format!("/images/{id}?")))
+            .unwrap();
+        let url = Url::parse(&format!("http://localhost{request}")).unwrap();
+        let parameters = url.query_pairs().into_owned().collect::<Vec<_>>();
+        // Only the ixid parameter of the raw URL is kept, and the image is cropped
+        assert!(parameters.contains(&("ixid".to_string(), "abc".to_string())));
+        assert!(!parameters.iter().any(|(key, _)| key == "ixlib"));
+        assert!(parameters.contains(&("fit".to_string(), "crop".to_string())));
+        let get_size = |name: &str| -> u32 {
+            parameters
+                .iter()
+                .find(|(key, _)| key == name)
+                .unwrap()
+                .1
+                .parse()
+                .unwrap()
+        };
+
+        let path = pictures_dir.join(format!("unsplash_{id}.jpg"));
+        let dimensions = ImageReader::open(&path).unwrap().into_dimensions().unwrap();
+        assert_eq!(dimensions, (get_size("w"), get_size("h")));
+        assert!(!path.with_extension("jpg.part").exists());
+    }
+}
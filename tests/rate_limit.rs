@@ -0,0 +1,59 @@
+//! The rate limits and the errors of the API given in `api_url`.
+#![cfg(target_os = "linux")]
+mod common;
+
+use common::{get_pictures_dir, photo, run, setup, MockResponse, MockServer};
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[test]
+fn stops_when_the_api_limits_the_requests() {
+    let calls = AtomicUsize::new(0);
+    let server = MockServer::start(move |server_url, request_url| {
+        if request_url.starts_with("/images/") {
+            return MockResponse::image(request_url, 100);
+        }
+        match calls.fetch_add(1, Ordering::Relaxed) {
+            // Most services answer 429 with the time to wait
+            0 => MockResponse::json(
+                429,
+                &[("Retry-After", "120")],
+                &json!({"errors": ["Too many requests"]}),
+            ),
+            // Unsplash answers 403 when there are no remaining requests
+            1 => MockResponse::json(
+                403,
+                &[("X-Ratelimit-Remaining", "0")],
+                &json!({"errors": ["Rate Limit Exceeded"]}),
+            ),
+            // Otherwise a 403 is an error of the API, e.g. an invalid API key
+            2 => MockResponse::json(
+                403,
+                &[("X-Ratelimit-Remaining", "49")],
+                &json!({"errors": ["The access token is invalid"]}),
+            ),
+            _ => MockResponse::json(200, &[], &json!([photo(server_url, "after")])),
+        }
+    });
+    let home = setup(&server);
+
+    for _ in 0..2 {
+        let err = run(&["prefetch", "--count", "1"]).unwrap_err();
+        assert!(err.to_string().contains("rate limit was reached"), "{err}");
+    }
+    let err = run(&["prefetch", "--count", "1"]).unwrap_err();
+    assert!(
+        err.to_string().contains("The access token is invalid"),
+        "{err}"
+    );
+    assert!(!get_pictures_dir(&home).join("unsplash_after.jpg").exists());
+
+    run(&["prefetch", "--count", "1"]).expect("The prefetch failed after the rate limit");
+    assert!(get_pictures_dir(&home).join("unsplash_after.jpg").exists());
+    let api_requests = server
+        .requests()
+        .iter()
+        .filter(|request| request.starts_with("/photos/"))
+        .count();
+    assert_eq!(api_requests, 4);
+}